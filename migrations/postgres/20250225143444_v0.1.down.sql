@@ -0,0 +1,4 @@
+drop table Jobs;
+drop table Evaluations;
+drop table Jobsets;
+drop table Projects;
@@ -0,0 +1,50 @@
+create table Projects (
+    id serial not null,
+    name varchar(255) not null,
+    description varchar(255) not null,
+
+    primary key (id)
+);
+
+create table Jobsets (
+    id serial not null,
+    project_id int not null,
+    flake text not null,
+    name varchar(255) not null,
+    description varchar(255) not null,
+    last_evaluated timestamptz,
+    last_checked timestamptz,
+    check_interval int not null,
+    evaluation_took int, -- seconds
+    state text, -- JobsetState
+    error_message text, -- Eval error messages
+
+    primary key (id),
+    foreign key (project_id)
+        references Projects(id)
+        on delete cascade
+);
+
+create table Evaluations (
+    id serial not null,
+    jobset_id int not null,
+
+    primary key (id),
+    foreign key (jobset_id)
+        references Jobsets(id)
+        on delete cascade
+);
+
+create table Jobs (
+    id serial not null,
+    evaluation_id int not null,
+    attribute_name text not null, -- name of attribute in hydraJobs. like: "systems.main" or "systems.wattson"
+    derivation_path text not null,
+    state text,
+    finished timestamptz,
+
+    primary key (id),
+    foreign key (evaluation_id)
+        references Evaluations(id)
+        on delete cascade
+);
@@ -0,0 +1,8 @@
+create table QueueRequests (
+    id serial not null,
+    kind text not null, -- QueueRequestKind
+    jobset_id int,
+    created timestamptz not null,
+
+    primary key (id)
+);
@@ -0,0 +1,8 @@
+create table Leases (
+    name text not null,
+    holder text not null, -- instance id of the current holder
+    token bigint not null, -- fencing token, incremented on every takeover
+    expires timestamptz not null,
+
+    primary key (name)
+);
@@ -0,0 +1 @@
+-- the removed rows can not be restored
@@ -0,0 +1,16 @@
+alter table Projects add column binary_cache boolean not null default false;
+
+-- the closure of the outputs of every successful job, these may be served from the binary cache
+create table JobPaths (
+    job_id int not null,
+    hash text not null, -- hash part of the store path
+    path text not null,
+    output boolean not null, -- whether the path is an output of the job itself
+
+    primary key (job_id, path),
+    foreign key (job_id)
+        references Jobs(id)
+        on delete cascade
+);
+
+create index JobPaths_hash on JobPaths (hash);
@@ -0,0 +1,29 @@
+-- external binary caches the outputs of a project's jobs are copied to
+create table Caches (
+    id serial not null,
+    project_id int not null,
+    url text not null, -- nix store url, like file:///srv/cache, s3://bucket?endpoint=localhost:9000 or ssh-ng://host
+
+    primary key (id),
+    foreign key (project_id)
+        references Projects(id)
+        on delete cascade
+);
+
+create table Uploads (
+    id serial not null,
+    job_id int not null,
+    cache_id int not null,
+    state text not null, -- UploadState
+    attempts int not null,
+    error_message text,
+    finished timestamptz,
+
+    primary key (id),
+    foreign key (job_id)
+        references Jobs(id)
+        on delete cascade,
+    foreign key (cache_id)
+        references Caches(id)
+        on delete cascade
+);
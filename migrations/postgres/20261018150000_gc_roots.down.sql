@@ -0,0 +1,3 @@
+alter table Jobs drop column keep;
+
+alter table Jobsets drop column keep_evaluations;
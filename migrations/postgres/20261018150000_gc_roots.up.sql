@@ -0,0 +1,5 @@
+-- the outputs of the last keep_evaluations evaluations of a jobset are rooted against garbage collection
+alter table Jobsets add column keep_evaluations int not null default 3;
+
+-- kept jobs are rooted no matter how old their evaluation is
+alter table Jobs add column keep boolean not null default false;
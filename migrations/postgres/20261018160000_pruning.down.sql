@@ -0,0 +1,6 @@
+drop table Prunings;
+
+alter table Evaluations drop column created;
+
+alter table Jobsets drop column max_evaluation_age;
+alter table Jobsets drop column max_evaluations;
@@ -0,0 +1,25 @@
+-- evaluations beyond max_evaluations or older than max_evaluation_age days are pruned, 0 disables the limit
+alter table Jobsets add column max_evaluations int not null default 0;
+alter table Jobsets add column max_evaluation_age int not null default 0;
+
+alter table Evaluations add column created timestamptz;
+
+-- evaluations from before this migration are dated by their first finished job
+update Evaluations set created = (
+    select min(finished) from Jobs where Jobs.evaluation_id = Evaluations.id
+);
+
+-- one row per pruning run which removed something
+create table Prunings (
+    id serial not null,
+    started timestamptz not null,
+    finished timestamptz not null,
+    evaluations int not null,
+    jobs int not null,
+    logs int not null,
+    log_bytes bigint not null, -- size of the removed build logs
+    db_bytes_before bigint not null,
+    db_bytes_after bigint not null,
+
+    primary key (id)
+);
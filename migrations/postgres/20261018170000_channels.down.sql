@@ -0,0 +1,7 @@
+drop table ChannelRevisions;
+
+alter table Jobsets drop column channel_gate;
+alter table Jobsets drop column channel;
+
+alter table Evaluations drop column revision;
+alter table Evaluations drop column flake;
@@ -0,0 +1,3 @@
+drop table Constituents;
+
+alter table Jobs drop column aggregate;
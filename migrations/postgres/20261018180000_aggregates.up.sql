@@ -0,0 +1,17 @@
+-- Hydra style aggregate jobs, which only succeed if all of their constituents do
+alter table Jobs add column aggregate boolean not null default false;
+
+create table Constituents (
+    aggregate_id int not null,
+    constituent_id int not null,
+
+    primary key (aggregate_id, constituent_id),
+    foreign key (aggregate_id)
+        references Jobs(id)
+        on delete cascade,
+    foreign key (constituent_id)
+        references Jobs(id)
+        on delete cascade
+);
+
+create index Constituents_constituent on Constituents (constituent_id);
@@ -0,0 +1,2 @@
+-- the job a RestartJob request is about
+alter table QueueRequests add column job_id int;
@@ -0,0 +1,17 @@
+-- pushes trigger the jobset at the pushed revision
+alter table QueueRequests add column revision text;
+
+-- every webhook delivery, to debug why a push did or did not trigger something
+create table WebhookDeliveries (
+    id serial not null,
+    forge text not null, -- GitHub, Gitea or GitLab
+    event text,
+    received timestamptz not null,
+    repository text,
+    branch text,
+    revision text,
+    outcome text not null, -- Triggered, Ignored or Rejected
+    message text not null,
+
+    primary key (id)
+);
@@ -0,0 +1,40 @@
+-- where the events of a project or of one of its jobsets are sent to
+create table Notifiers (
+    id serial not null,
+    project_id int not null,
+    jobset_id int, -- only events of this jobset, or of every jobset of the project if null
+    kind text not null, -- Webhook
+    target text not null, -- what the kind delivers to, like the url of a webhook
+    events text not null, -- comma separated names of the events which are sent
+    created timestamptz not null,
+
+    primary key (id),
+    foreign key (project_id)
+        references Projects(id)
+        on delete cascade,
+    foreign key (jobset_id)
+        references Jobsets(id)
+        on delete cascade
+);
+
+create index Notifiers_project on Notifiers (project_id);
+
+-- every notification through every notifier, retried a few times if it fails
+create table NotificationDeliveries (
+    id serial not null,
+    notifier_id int not null,
+    event text not null,
+    payload text not null, -- the notification as json
+    state text not null, -- Queued, Sending, Retrying, Delivered or Failed
+    attempts int not null,
+    error_message text,
+    created timestamptz not null,
+    finished timestamptz,
+
+    primary key (id),
+    foreign key (notifier_id)
+        references Notifiers(id)
+        on delete cascade
+);
+
+create index NotificationDeliveries_notifier on NotificationDeliveries (notifier_id);
@@ -0,0 +1,22 @@
+-- every commit status reported to a forge, retried a few times if it fails
+create table StatusDeliveries (
+    id serial not null,
+    jobset_id int not null,
+    revision text not null,
+    status text not null, -- Pending, Success or Failure
+    context text not null, -- tells the statuses of several jobsets of the same commit apart
+    description text not null,
+    target_url text not null,
+    state text not null, -- Queued, Sending, Retrying, Delivered or Failed
+    attempts int not null,
+    error_message text,
+    created timestamptz not null,
+    finished timestamptz,
+
+    primary key (id),
+    foreign key (jobset_id)
+        references Jobsets(id)
+        on delete cascade
+);
+
+create index StatusDeliveries_jobset on StatusDeliveries (jobset_id);
@@ -0,0 +1,8 @@
+create table QueueRequests (
+    id integer not null,
+    kind text not null, -- QueueRequestKind
+    jobset_id int,
+    created date not null,
+
+    primary key (id)
+);
@@ -0,0 +1,8 @@
+create table Leases (
+    name text not null,
+    holder text not null, -- instance id of the current holder
+    token int not null, -- fencing token, incremented on every takeover
+    expires date not null,
+
+    primary key (name)
+);
@@ -0,0 +1,5 @@
+-- foreign keys were not enforced on sqlite before, so deletes left rows behind
+delete from QueueRequests where jobset_id is not null and jobset_id not in (select id from Jobsets);
+delete from Jobsets where project_id not in (select id from Projects);
+delete from Evaluations where jobset_id not in (select id from Jobsets);
+delete from Jobs where evaluation_id not in (select id from Evaluations);
@@ -0,0 +1,3 @@
+drop table JobPaths;
+
+alter table Projects drop column binary_cache;
@@ -0,0 +1,3 @@
+drop table Uploads;
+
+drop table Caches;
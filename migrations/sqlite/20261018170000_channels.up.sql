@@ -0,0 +1,26 @@
+-- what exactly was evaluated, channels are built from it
+alter table Evaluations add column flake text; -- locked flake reference
+alter table Evaluations add column revision text; -- git revision of the flake, if it has one
+
+alter table Jobsets add column channel boolean not null default false;
+alter table Jobsets add column channel_gate text; -- attribute which has to succeed for a release, all jobs if null
+
+-- published channel releases, the newest one of a jobset is the channel head
+create table ChannelRevisions (
+    id integer not null,
+    jobset_id int not null,
+    evaluation_id int, -- null once the evaluation was pruned
+    name text not null, -- also the directory in <data dir>/channels/<jobset id>
+    revision text,
+    created date not null,
+
+    primary key (id),
+    foreign key (jobset_id)
+        references Jobsets(id)
+        on delete cascade,
+    foreign key (evaluation_id)
+        references Evaluations(id)
+        on delete set null
+);
+
+create unique index ChannelRevisions_evaluation on ChannelRevisions (evaluation_id);
@@ -0,0 +1,19 @@
+-- files a job lists in $out/nix-support/hydra-build-products, offered for download
+create table BuildProducts (
+    id integer not null,
+    job_id int not null,
+    type text not null, -- like file or doc
+    subtype text not null, -- like iso or manual
+    path text not null, -- store path of the file or directory
+    name text not null, -- file name of the path
+    default_path text, -- what to serve for a directory, relative to it
+    file_size bigint, -- null for directories
+    sha256 text, -- hex, null for directories
+
+    primary key (id),
+    foreign key (job_id)
+        references Jobs(id)
+        on delete cascade
+);
+
+create index BuildProducts_job on BuildProducts (job_id);
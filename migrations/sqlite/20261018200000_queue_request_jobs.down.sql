@@ -0,0 +1 @@
+alter table QueueRequests drop column job_id;
@@ -0,0 +1,3 @@
+drop table Sessions;
+
+drop table Users;
@@ -0,0 +1,26 @@
+-- local accounts, only they may change projects and jobsets
+create table Users (
+    id integer not null,
+    name text not null,
+    password_hash text not null, -- argon2 in the PHC string format
+    admin boolean not null default false,
+    created date not null,
+
+    primary key (id)
+);
+
+create unique index Users_name on Users (name);
+
+create table Sessions (
+    id text not null, -- sha256 of the token in the session cookie
+    user_id int not null,
+    created date not null,
+    expires date not null,
+
+    primary key (id),
+    foreign key (user_id)
+        references Users(id)
+        on delete cascade
+);
+
+create index Sessions_user on Sessions (user_id);
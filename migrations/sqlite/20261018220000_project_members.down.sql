@@ -0,0 +1,3 @@
+drop table ProjectMembers;
+
+alter table Projects drop column private;
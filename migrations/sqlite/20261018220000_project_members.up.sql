@@ -0,0 +1,18 @@
+-- private projects are only shown to their members and admins
+alter table Projects add column private boolean not null default false;
+
+create table ProjectMembers (
+    project_id int not null,
+    user_id int not null,
+    role text not null, -- Owner, Maintainer or Viewer
+
+    primary key (project_id, user_id),
+    foreign key (project_id)
+        references Projects(id)
+        on delete cascade,
+    foreign key (user_id)
+        references Users(id)
+        on delete cascade
+);
+
+create index ProjectMembers_user on ProjectMembers (user_id);
@@ -0,0 +1,23 @@
+-- tokens for scripts, sent as `Authorization: Bearer <token>`
+create table ApiTokens (
+    id integer not null,
+    user_id int not null, -- the token acts as this user
+    project_id int, -- only this project, or every project of the user if null
+    name text not null,
+    token_hash text not null, -- sha256 of the token
+    scope text not null, -- Read, Trigger or Admin
+    created date not null,
+    expires date,
+    last_used date,
+
+    primary key (id),
+    foreign key (user_id)
+        references Users(id)
+        on delete cascade,
+    foreign key (project_id)
+        references Projects(id)
+        on delete cascade
+);
+
+create unique index ApiTokens_hash on ApiTokens (token_hash);
+create index ApiTokens_user on ApiTokens (user_id);
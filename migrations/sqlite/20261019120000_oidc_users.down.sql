@@ -0,0 +1,3 @@
+drop index Users_oidc_subject;
+
+alter table Users drop column oidc_subject;
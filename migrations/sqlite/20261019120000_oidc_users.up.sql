@@ -0,0 +1,4 @@
+-- accounts created on the first login through the OpenID Connect provider
+alter table Users add column oidc_subject text;
+
+create unique index Users_oidc_subject on Users (oidc_subject);
@@ -0,0 +1,3 @@
+drop table WebhookDeliveries;
+
+alter table QueueRequests drop column revision;
@@ -93,6 +93,8 @@ fn HomePage() -> impl IntoView {
     //    <button on:click=on_click>"Click Me: " {count}</button>
     //}
 
+    let user = OnceResource::new(routes::get_current_user());
+
     view! {
         <Router>
             <nav> // navbar
@@ -101,23 +103,35 @@ fn HomePage() -> impl IntoView {
                     <div class=app_style::entries>
                         <a href="jobsets">Jobsets</a>
                         <a href="dashboard">Dashboard</a>
-                        <div class="dropdown">
-                            <div class="title">
-                                <span>Admin</span>
-                                <svg xmlns="http://www.w3.org/2000/svg" width="16" height="16" fill="currentColor" class="bi bi-caret-down" viewBox="0 0 16 16">
-                                  <path d="M3.204 5h9.592L8 10.481zm-.753.659 4.796 5.48a1 1 0 0 0 1.506 0l4.796-5.48c.566-.647.106-1.659-.753-1.659H3.204a1 1 0 0 0-.753 1.659"/>
-                                </svg>
-                            </div>
-                            <div class="dropdown_content">
-                                <div class="dropdown_group">
-                                    <a href="/create-project">Create Project</a>
-                                    <a href="/blub-blub">Blub blub</a>
-                                </div>
-                                <div class="dropdown_group">
-                                    <a href="/somethingelse">Something else</a>
-                                </div>
-                            </div>
-                        </div>
+                        <a href="/binary-cache">Binary cache</a>
+                        <Suspense fallback=|| ()>
+                            {move || {
+                                let admin = matches!(user.get(), Some(Ok(Some(user))) if user.admin);
+
+                                admin.then(|| view! {
+                                    <div class="dropdown">
+                                        <div class="title">
+                                            <span>Admin</span>
+                                            <svg xmlns="http://www.w3.org/2000/svg" width="16" height="16" fill="currentColor" class="bi bi-caret-down" viewBox="0 0 16 16">
+                                              <path d="M3.204 5h9.592L8 10.481zm-.753.659 4.796 5.48a1 1 0 0 0 1.506 0l4.796-5.48c.566-.647.106-1.659-.753-1.659H3.204a1 1 0 0 0-.753 1.659"/>
+                                            </svg>
+                                        </div>
+                                        <div class="dropdown_content">
+                                            <div class="dropdown_group">
+                                                <a href="/create-project">Create Project</a>
+                                                <a href="/admin/pruning">Pruning</a>
+                                                <a href="/admin/webhooks">Webhooks</a>
+                                                <a href="/blub-blub">Blub blub</a>
+                                            </div>
+                                            <div class="dropdown_group">
+                                                <a href="/somethingelse">Something else</a>
+                                            </div>
+                                        </div>
+                                    </div>
+                                })
+                            }}
+                        </Suspense>
+                        <routes::UserMenu user/>
                     </div>
                 </div>
             </nav>
@@ -129,11 +143,18 @@ fn HomePage() -> impl IntoView {
                     //</ParentRoute>
                     <Route path=path!("/") view=routes::Home/>
                     <Route path=path!("/create-project") view=routes::project::CreateProject/>
+                    <Route path=path!("/binary-cache") view=routes::BinaryCache/>
+                    <Route path=path!("/admin/pruning") view=routes::Pruning/>
+                    <Route path=path!("/admin/webhooks") view=routes::Webhooks/>
+                    <Route path=path!("/login") view=routes::Login/>
+                    <Route path=path!("/settings") view=routes::Settings/>
                     <Route path=path!("/project/:proj-id") view=routes::project::Project/>
                     <Route path=path!("/project/:proj-id/edit") view=routes::project::EditProject/>
                     <Route path=path!("/project/:proj-id/create-jobset") view=routes::jobset::CreateJobset/>
                     <Route path=path!("/project/:proj-id/jobset/:jobset-id") view=routes::jobset::Jobset/>
                     <Route path=path!("/project/:proj-id/jobset/:jobset-id/edit") view=routes::jobset::EditJobset/>
+                    <Route path=path!("/project/:proj-id/jobset/:jobset-id/channel") view=routes::jobset::JobsetChannel/>
+                    <Route path=path!("/project/:proj-id/jobset/:jobset-id/job/:job-id") view=routes::job::Job/>
                 </Routes>
             </main>
         </Router>
@@ -0,0 +1,144 @@
+use core::{error, fmt};
+
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Debug)]
+pub struct CtlError {
+    error: String,
+}
+
+impl CtlError {
+    pub fn new(error: String) -> Self {
+        CtlError { error }
+    }
+}
+
+impl fmt::Display for CtlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl error::Error for CtlError {}
+
+impl From<reqwest::Error> for CtlError {
+    fn from(e: reqwest::Error) -> Self {
+        CtlError::new(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for CtlError {
+    fn from(e: serde_json::Error) -> Self {
+        CtlError::new(format!("Unexpected response: {}", e))
+    }
+}
+
+pub type CtlResult<T> = Result<T, CtlError>;
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: String,
+}
+
+/// Talks to the `/api/v1` json api of a ladon server
+pub struct Client {
+    http: reqwest::Client,
+    base: String,
+    token: Option<String>,
+}
+
+impl Client {
+    pub fn new(url: &str, token: Option<String>) -> Self {
+        Client {
+            http: reqwest::Client::new(),
+            base: format!("{}/api/v1", url.trim_end_matches('/')),
+            token,
+        }
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self.http.request(method, format!("{}{}", self.base, path));
+
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// Turns error responses into their message
+    async fn check(response: Response) -> CtlResult<Response> {
+        let status = response.status();
+
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let text = response.text().await.unwrap_or_default();
+
+        let message = serde_json::from_str::<ErrorBody>(&text)
+            .map(|body| body.error)
+            .unwrap_or(text);
+
+        Err(CtlError::new(match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                format!("{} (is --token or LADON_TOKEN set?)", message)
+            }
+            _ => format!("{}: {}", status, message),
+        }))
+    }
+
+    async fn send(&self, request: RequestBuilder) -> CtlResult<Value> {
+        let response = Client::check(request.send().await?).await?;
+
+        Ok(response.json::<Value>().await?)
+    }
+
+    pub async fn get(&self, path: &str) -> CtlResult<Value> {
+        self.send(self.request(Method::GET, path)).await
+    }
+
+    pub async fn get_query<Q: Serialize>(&self, path: &str, query: &Q) -> CtlResult<Value> {
+        self.send(self.request(Method::GET, path).query(query))
+            .await
+    }
+
+    pub async fn post(&self, path: &str) -> CtlResult<Value> {
+        self.send(self.request(Method::POST, path)).await
+    }
+
+    pub async fn post_json(&self, path: &str, body: &Value) -> CtlResult<Value> {
+        self.send(self.request(Method::POST, path).json(body)).await
+    }
+
+    pub async fn put_json(&self, path: &str, body: &Value) -> CtlResult<Value> {
+        self.send(self.request(Method::PUT, path).json(body)).await
+    }
+
+    pub async fn delete(&self, path: &str) -> CtlResult<Value> {
+        self.send(self.request(Method::DELETE, path)).await
+    }
+
+    /// The part of the job's build log after `offset`, None if there is no log yet
+    pub async fn get_log(&self, job_id: i32, offset: u64) -> CtlResult<Option<Vec<u8>>> {
+        let response = self
+            .request(Method::GET, &format!("/jobs/{}/log", job_id))
+            .query(&[("offset", offset)])
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let response = Client::check(response).await?;
+
+        Ok(Some(response.bytes().await?.to_vec()))
+    }
+}
+
+/// Parses a response into the type the table is printed from
+pub fn parse<T: DeserializeOwned>(value: Value) -> CtlResult<T> {
+    Ok(serde_json::from_value(value)?)
+}
@@ -0,0 +1,633 @@
+use std::{io::Write, path::Path, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::Value;
+
+use crate::{
+    client::{parse, Client, CtlError, CtlResult},
+    table::{print_fields, Table},
+};
+
+/// How often a followed build log is polled
+const FOLLOW_INTERVAL: Duration = Duration::from_secs(2);
+/// The most items the api returns at once
+const MAX_LIMIT: i64 = 500;
+
+#[derive(Deserialize)]
+struct Page<T> {
+    items: Vec<T>,
+    total: i64,
+    offset: i64,
+}
+
+#[derive(Deserialize)]
+struct Project {
+    id: i32,
+    name: String,
+    description: String,
+    binary_cache: bool,
+    #[serde(default)]
+    private: bool,
+}
+
+#[derive(Deserialize)]
+struct Jobset {
+    id: i32,
+    name: String,
+    flake: String,
+    description: String,
+    check_interval: i32,
+    channel: bool,
+    last_evaluated: Option<DateTime<Utc>>,
+    state: Option<String>,
+    error_message: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Evaluation {
+    id: i32,
+    jobset_id: i32,
+    created: Option<DateTime<Utc>>,
+    flake: Option<String>,
+    revision: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Job {
+    id: i32,
+    evaluation_id: i32,
+    attribute_name: String,
+    derivation_path: String,
+    state: String,
+    finished: Option<DateTime<Utc>>,
+    keep: bool,
+    aggregate: bool,
+}
+
+impl Job {
+    fn is_finished(&self) -> bool {
+        matches!(self.state.as_str(), "Done" | "Failed")
+    }
+}
+
+#[derive(Deserialize)]
+struct QueueRequest {
+    id: i32,
+    kind: String,
+    jobset_id: Option<i32>,
+    job_id: Option<i32>,
+    created: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+struct Queue {
+    requests: Vec<QueueRequest>,
+    builds: Vec<Job>,
+}
+
+fn time(time: Option<DateTime<Utc>>) -> String {
+    time.map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn text(text: Option<String>) -> String {
+    text.unwrap_or_else(|| "-".to_string())
+}
+
+/// Prints the response as json, or as what `human` makes of it
+fn output<T: DeserializeOwned>(value: Value, json: bool, human: impl FnOnce(T)) -> CtlResult<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(&value)?);
+        return Ok(());
+    }
+
+    human(parse(value)?);
+
+    Ok(())
+}
+
+fn print_page<T>(page: &Page<T>) {
+    let shown = page.offset + page.items.len() as i64;
+
+    if shown < page.total {
+        println!(
+            "({} more, continue with --offset {})",
+            page.total - shown,
+            shown
+        );
+    }
+}
+
+/// Reads a project or jobset from a json or toml file, `-` reads json from stdin
+pub fn read_input(path: &Path) -> CtlResult<Value> {
+    if path == Path::new("-") {
+        return Ok(serde_json::from_reader(std::io::stdin())?);
+    }
+
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| CtlError::new(format!("Failed to read {}: {}", path.display(), e)))?;
+
+    let is_toml = path.extension().map(|ext| ext == "toml").unwrap_or(false);
+
+    if is_toml {
+        return toml::from_str(&content)
+            .map_err(|e| CtlError::new(format!("Invalid toml in {}: {}", path.display(), e)));
+    }
+
+    serde_json::from_str(&content)
+        .map_err(|e| CtlError::new(format!("Invalid json in {}: {}", path.display(), e)))
+}
+
+/// Changes the fields of `current` which are set in `changes`
+fn merge(mut current: Value, changes: Value) -> CtlResult<Value> {
+    let Value::Object(changes) = changes else {
+        return Err(CtlError::new("The input has to be an object".to_string()));
+    };
+
+    if let Value::Object(current) = &mut current {
+        current.extend(changes);
+    }
+
+    Ok(current)
+}
+
+fn print_projects(page: Page<Project>) {
+    let mut table = Table::new(&["id", "name", "binary cache", "private", "description"]);
+
+    for project in page.items.iter() {
+        table.add(vec![
+            project.id.to_string(),
+            project.name.clone(),
+            project.binary_cache.to_string(),
+            project.private.to_string(),
+            project.description.clone(),
+        ]);
+    }
+
+    table.print();
+    print_page(&page);
+}
+
+fn print_project(project: Project) {
+    print_fields(&[
+        ("id", project.id.to_string()),
+        ("name", project.name),
+        ("description", project.description),
+        ("binary cache", project.binary_cache.to_string()),
+        ("private", project.private.to_string()),
+    ]);
+}
+
+fn print_jobsets(page: Page<Jobset>) {
+    let mut table = Table::new(&["id", "name", "state", "last evaluated", "flake"]);
+
+    for jobset in page.items.iter() {
+        table.add(vec![
+            jobset.id.to_string(),
+            jobset.name.clone(),
+            text(jobset.state.clone()),
+            time(jobset.last_evaluated),
+            jobset.flake.clone(),
+        ]);
+    }
+
+    table.print();
+    print_page(&page);
+}
+
+fn print_jobset(jobset: Jobset) {
+    print_fields(&[
+        ("id", jobset.id.to_string()),
+        ("name", jobset.name),
+        ("description", jobset.description),
+        ("flake", jobset.flake),
+        ("check interval", format!("{}s", jobset.check_interval)),
+        ("channel", jobset.channel.to_string()),
+        ("state", text(jobset.state)),
+        ("last evaluated", time(jobset.last_evaluated)),
+        ("error", text(jobset.error_message)),
+    ]);
+}
+
+fn print_evaluations(page: Page<Evaluation>) {
+    let mut table = Table::new(&["id", "created", "revision", "flake"]);
+
+    for evaluation in page.items.iter() {
+        table.add(vec![
+            evaluation.id.to_string(),
+            time(evaluation.created),
+            text(evaluation.revision.clone()),
+            text(evaluation.flake.clone()),
+        ]);
+    }
+
+    table.print();
+    print_page(&page);
+}
+
+fn print_evaluation(evaluation: Evaluation) {
+    print_fields(&[
+        ("id", evaluation.id.to_string()),
+        ("jobset", evaluation.jobset_id.to_string()),
+        ("created", time(evaluation.created)),
+        ("flake", text(evaluation.flake)),
+        ("revision", text(evaluation.revision)),
+    ]);
+}
+
+fn jobs_table(jobs: &[Job]) -> Table {
+    let mut table = Table::new(&["id", "evaluation", "attribute", "state", "finished"]);
+
+    for job in jobs {
+        table.add(vec![
+            job.id.to_string(),
+            job.evaluation_id.to_string(),
+            job.attribute_name.clone(),
+            job.state.clone(),
+            time(job.finished),
+        ]);
+    }
+
+    table
+}
+
+fn print_jobs(page: Page<Job>) {
+    jobs_table(&page.items).print();
+    print_page(&page);
+}
+
+fn print_job(job: Job) {
+    print_fields(&[
+        ("id", job.id.to_string()),
+        ("evaluation", job.evaluation_id.to_string()),
+        ("attribute", job.attribute_name),
+        ("derivation", job.derivation_path),
+        ("state", job.state),
+        ("finished", time(job.finished)),
+        ("kept", job.keep.to_string()),
+        ("aggregate", job.aggregate.to_string()),
+    ]);
+}
+
+fn print_request(request: QueueRequest) {
+    let target = match (request.jobset_id, request.job_id) {
+        (_, Some(job_id)) => format!("job {}", job_id),
+        (Some(jobset_id), None) => format!("jobset {}", jobset_id),
+        (None, None) => "everything".to_string(),
+    };
+
+    println!("Queued request {}: {} {}", request.id, request.kind, target);
+}
+
+fn print_deletion(report: Value) {
+    let Value::Object(report) = report else {
+        return;
+    };
+
+    let fields: Vec<(&str, String)> = report
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.to_string()))
+        .collect();
+
+    println!("Deleted:");
+    print_fields(&fields);
+}
+
+pub struct Listing {
+    pub offset: i64,
+    pub limit: i64,
+}
+
+impl Listing {
+    fn query(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("offset", self.offset.to_string()),
+            ("limit", self.limit.to_string()),
+        ]
+    }
+}
+
+pub async fn list_projects(
+    client: &Client,
+    json: bool,
+    listing: &Listing,
+    name: Option<String>,
+) -> CtlResult<()> {
+    let mut query = listing.query();
+
+    if let Some(name) = name {
+        query.push(("name", name));
+    }
+
+    let value = client.get_query("/projects", &query).await?;
+
+    output(value, json, print_projects)
+}
+
+pub async fn show_project(client: &Client, json: bool, id: i32) -> CtlResult<()> {
+    let value = client.get(&format!("/projects/{}", id)).await?;
+
+    output(value, json, print_project)
+}
+
+pub async fn create_project(client: &Client, json: bool, input: Value) -> CtlResult<()> {
+    let value = client.post_json("/projects", &input).await?;
+
+    output(value, json, print_project)
+}
+
+pub async fn edit_project(client: &Client, json: bool, id: i32, changes: Value) -> CtlResult<()> {
+    let path = format!("/projects/{}", id);
+
+    let project = merge(client.get(&path).await?, changes)?;
+
+    let value = client.put_json(&path, &project).await?;
+
+    output(value, json, print_project)
+}
+
+pub async fn delete_project(client: &Client, json: bool, id: i32) -> CtlResult<()> {
+    let value = client.delete(&format!("/projects/{}", id)).await?;
+
+    output(value, json, print_deletion)
+}
+
+pub async fn list_jobsets(
+    client: &Client,
+    json: bool,
+    listing: &Listing,
+    project: i32,
+) -> CtlResult<()> {
+    let value = client
+        .get_query(&format!("/projects/{}/jobsets", project), &listing.query())
+        .await?;
+
+    output(value, json, print_jobsets)
+}
+
+pub async fn show_jobset(client: &Client, json: bool, project: i32, id: i32) -> CtlResult<()> {
+    let value = client
+        .get(&format!("/projects/{}/jobsets/{}", project, id))
+        .await?;
+
+    output(value, json, print_jobset)
+}
+
+pub async fn create_jobset(
+    client: &Client,
+    json: bool,
+    project: i32,
+    input: Value,
+) -> CtlResult<()> {
+    let value = client
+        .post_json(&format!("/projects/{}/jobsets", project), &input)
+        .await?;
+
+    output(value, json, print_jobset)
+}
+
+pub async fn edit_jobset(
+    client: &Client,
+    json: bool,
+    project: i32,
+    id: i32,
+    changes: Value,
+) -> CtlResult<()> {
+    let path = format!("/projects/{}/jobsets/{}", project, id);
+
+    let jobset = merge(client.get(&path).await?, changes)?;
+
+    let value = client.put_json(&path, &jobset).await?;
+
+    output(value, json, print_jobset)
+}
+
+pub async fn delete_jobset(client: &Client, json: bool, project: i32, id: i32) -> CtlResult<()> {
+    let value = client
+        .delete(&format!("/projects/{}/jobsets/{}", project, id))
+        .await?;
+
+    output(value, json, print_deletion)
+}
+
+/// `action` is `trigger` or `cancel`
+pub async fn queue_jobset(
+    client: &Client,
+    json: bool,
+    project: i32,
+    id: i32,
+    action: &str,
+) -> CtlResult<()> {
+    let value = client
+        .post(&format!("/projects/{}/jobsets/{}/{}", project, id, action))
+        .await?;
+
+    output(value, json, print_request)
+}
+
+pub async fn list_evaluations(
+    client: &Client,
+    json: bool,
+    listing: &Listing,
+    project: i32,
+    jobset: i32,
+) -> CtlResult<()> {
+    let value = client
+        .get_query(
+            &format!("/projects/{}/jobsets/{}/evaluations", project, jobset),
+            &listing.query(),
+        )
+        .await?;
+
+    output(value, json, print_evaluations)
+}
+
+/// The evaluation and its jobs
+pub async fn show_evaluation(client: &Client, json: bool, id: i32) -> CtlResult<()> {
+    let evaluation = client.get(&format!("/evaluations/{}", id)).await?;
+
+    let jobs = all_jobs(client, id, None).await?;
+
+    if json {
+        let mut evaluation = evaluation;
+        evaluation["jobs"] = Value::Array(jobs);
+        println!("{}", serde_json::to_string_pretty(&evaluation)?);
+        return Ok(());
+    }
+
+    print_evaluation(parse(evaluation)?);
+    println!();
+
+    let jobs: Vec<Job> = jobs.into_iter().map(parse).collect::<CtlResult<_>>()?;
+
+    jobs_table(&jobs).print();
+
+    Ok(())
+}
+
+/// Every job of the evaluation, fetched page by page
+async fn all_jobs(client: &Client, evaluation: i32, state: Option<&str>) -> CtlResult<Vec<Value>> {
+    let mut jobs = Vec::new();
+
+    loop {
+        let mut query = vec![
+            ("offset", jobs.len().to_string()),
+            ("limit", MAX_LIMIT.to_string()),
+        ];
+
+        if let Some(state) = state {
+            query.push(("state", state.to_string()));
+        }
+
+        let page = client
+            .get_query(&format!("/evaluations/{}/jobs", evaluation), &query)
+            .await?;
+
+        let total = page["total"].as_u64().unwrap_or(0) as usize;
+
+        match page["items"].as_array() {
+            Some(items) if !items.is_empty() => jobs.extend(items.iter().cloned()),
+            _ => break,
+        }
+
+        if jobs.len() >= total {
+            break;
+        }
+    }
+
+    Ok(jobs)
+}
+
+pub struct JobListing {
+    pub jobset: Option<i32>,
+    pub state: Option<String>,
+    pub attribute: Option<String>,
+}
+
+pub async fn list_jobs(
+    client: &Client,
+    json: bool,
+    listing: &Listing,
+    filter: JobListing,
+) -> CtlResult<()> {
+    let mut query = listing.query();
+
+    if let Some(jobset) = filter.jobset {
+        query.push(("jobset_id", jobset.to_string()));
+    }
+
+    if let Some(state) = filter.state {
+        query.push(("state", state));
+    }
+
+    if let Some(attribute) = filter.attribute {
+        query.push(("attribute", attribute));
+    }
+
+    let value = client.get_query("/jobs", &query).await?;
+
+    output(value, json, print_jobs)
+}
+
+pub async fn show_job(client: &Client, json: bool, id: i32) -> CtlResult<()> {
+    let value = client.get(&format!("/jobs/{}", id)).await?;
+
+    output(value, json, print_job)
+}
+
+/// Restarts the given jobs and the failed ones of the evaluation
+pub async fn restart_jobs(
+    client: &Client,
+    json: bool,
+    mut ids: Vec<i32>,
+    evaluation: Option<i32>,
+) -> CtlResult<()> {
+    if let Some(evaluation) = evaluation {
+        for job in all_jobs(client, evaluation, Some("Failed")).await? {
+            ids.push(parse::<Job>(job)?.id);
+        }
+    }
+
+    if ids.is_empty() {
+        if !json {
+            println!("Nothing to restart");
+        }
+        return Ok(());
+    }
+
+    let mut requests = Vec::new();
+
+    for id in ids {
+        requests.push(client.post(&format!("/jobs/{}/restart", id)).await?);
+    }
+
+    output(
+        Value::Array(requests),
+        json,
+        |requests: Vec<QueueRequest>| requests.into_iter().for_each(print_request),
+    )
+}
+
+/// Prints the build log, with `follow` until the job finished
+pub async fn job_log(client: &Client, id: i32, follow: bool) -> CtlResult<()> {
+    let mut offset = 0;
+
+    loop {
+        // checked before reading, so everything written until the end is read
+        let finished =
+            follow && parse::<Job>(client.get(&format!("/jobs/{}", id)).await?)?.is_finished();
+
+        let part = client.get_log(id, offset).await?;
+
+        if !follow && part.is_none() {
+            return Err(CtlError::new("The job has no build log".to_string()));
+        }
+
+        let part = part.unwrap_or_default();
+
+        let mut stdout = std::io::stdout();
+
+        stdout
+            .write_all(&part)
+            .and_then(|_| stdout.flush())
+            .map_err(|e| CtlError::new(e.to_string()))?;
+
+        offset += part.len() as u64;
+
+        if !follow || (finished && part.is_empty()) {
+            return Ok(());
+        }
+
+        if part.is_empty() {
+            tokio::time::sleep(FOLLOW_INTERVAL).await;
+        }
+    }
+}
+
+pub async fn show_queue(client: &Client, json: bool) -> CtlResult<()> {
+    let value = client.get("/queue").await?;
+
+    output(value, json, |queue: Queue| {
+        println!("Requests:");
+
+        let mut table = Table::new(&["id", "kind", "jobset", "job", "created"]);
+
+        for request in queue.requests {
+            table.add(vec![
+                request.id.to_string(),
+                request.kind,
+                text(request.jobset_id.map(|id| id.to_string())),
+                text(request.job_id.map(|id| id.to_string())),
+                time(Some(request.created)),
+            ]);
+        }
+
+        table.print();
+
+        println!();
+        println!("Builds:");
+
+        jobs_table(&queue.builds).print();
+    })
+}
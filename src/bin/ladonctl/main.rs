@@ -0,0 +1,282 @@
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand};
+
+use client::{Client, CtlResult};
+use commands::{JobListing, Listing};
+
+mod client;
+mod commands;
+mod table;
+
+/// Command line client for the json api of a ladon server
+#[derive(Parser)]
+#[command(name = "ladonctl")]
+struct Cli {
+    #[arg(
+        long,
+        global = true,
+        env = "LADON_URL",
+        default_value = "http://127.0.0.1:3598",
+        help = "Url of the ladon server"
+    )]
+    url: String,
+    #[arg(
+        long,
+        global = true,
+        env = "LADON_TOKEN",
+        hide_env_values = true,
+        help = "Api token sent with every request"
+    )]
+    token: Option<String>,
+    #[arg(long, global = true, help = "Prints the responses as json")]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Args)]
+struct ListArgs {
+    #[arg(long, default_value_t = 0, help = "How many items to skip")]
+    offset: i64,
+    #[arg(
+        long,
+        default_value_t = 50,
+        help = "How many items to show, at most 500"
+    )]
+    limit: i64,
+}
+
+impl ListArgs {
+    fn listing(&self) -> Listing {
+        Listing {
+            offset: self.offset,
+            limit: self.limit,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Lists, creates and changes projects
+    Projects {
+        #[command(subcommand)]
+        command: ProjectsCommand,
+    },
+    /// Lists, creates, changes, triggers and cancels jobsets
+    Jobsets {
+        #[command(subcommand)]
+        command: JobsetsCommand,
+    },
+    /// Shows evaluations of jobsets
+    Evals {
+        #[command(subcommand)]
+        command: EvalsCommand,
+    },
+    /// Shows and restarts jobs and follows their build logs
+    Jobs {
+        #[command(subcommand)]
+        command: JobsCommand,
+    },
+    /// Shows what the queue runner still has to do
+    Queue,
+}
+
+#[derive(Subcommand)]
+enum ProjectsCommand {
+    List {
+        #[command(flatten)]
+        list: ListArgs,
+        /// Only projects whose name contains this
+        #[arg(long)]
+        name: Option<String>,
+    },
+    Show {
+        id: i32,
+    },
+    /// Creates a project from a json or toml file, `-` reads json from stdin
+    Create {
+        file: PathBuf,
+    },
+    /// Changes the fields of the project which are set in the json or toml file
+    Edit {
+        id: i32,
+        file: PathBuf,
+    },
+    /// Deletes the project with its jobsets, evaluations and builds
+    Delete {
+        id: i32,
+    },
+}
+
+#[derive(Subcommand)]
+enum JobsetsCommand {
+    List {
+        project: i32,
+        #[command(flatten)]
+        list: ListArgs,
+    },
+    Show {
+        project: i32,
+        id: i32,
+    },
+    /// Creates a jobset from a json or toml file, `-` reads json from stdin
+    Create {
+        project: i32,
+        file: PathBuf,
+    },
+    /// Changes the fields of the jobset which are set in the json or toml file
+    Edit {
+        project: i32,
+        id: i32,
+        file: PathBuf,
+    },
+    /// Deletes the jobset with its evaluations and builds
+    Delete {
+        project: i32,
+        id: i32,
+    },
+    /// Evaluates the jobset
+    Trigger {
+        project: i32,
+        id: i32,
+    },
+    /// Stops the evaluation and the unfinished builds of the jobset
+    Cancel {
+        project: i32,
+        id: i32,
+    },
+}
+
+#[derive(Subcommand)]
+enum EvalsCommand {
+    List {
+        project: i32,
+        jobset: i32,
+        #[command(flatten)]
+        list: ListArgs,
+    },
+    /// Shows the evaluation with its jobs
+    Show { id: i32 },
+}
+
+#[derive(Subcommand)]
+enum JobsCommand {
+    List {
+        #[command(flatten)]
+        list: ListArgs,
+        /// Only jobs of the jobset's evaluations
+        #[arg(long)]
+        jobset: Option<i32>,
+        /// ToBeBuilt, Building, Waiting, Done or Failed
+        #[arg(long)]
+        state: Option<String>,
+        /// Only jobs whose attribute contains this
+        #[arg(long)]
+        attribute: Option<String>,
+    },
+    Show {
+        id: i32,
+    },
+    /// Builds failed jobs again
+    Restart {
+        ids: Vec<i32>,
+        /// Also restarts all failed jobs of this evaluation
+        #[arg(long)]
+        evaluation: Option<i32>,
+    },
+    /// Prints the build log of the job
+    Log {
+        id: i32,
+        /// Keeps printing what gets written until the job finished
+        #[arg(short, long)]
+        follow: bool,
+    },
+}
+
+async fn run(cli: Cli) -> CtlResult<()> {
+    let client = Client::new(&cli.url, cli.token);
+    let json = cli.json;
+
+    match cli.command {
+        Command::Projects { command } => match command {
+            ProjectsCommand::List { list, name } => {
+                commands::list_projects(&client, json, &list.listing(), name).await
+            }
+            ProjectsCommand::Show { id } => commands::show_project(&client, json, id).await,
+            ProjectsCommand::Create { file } => {
+                let input = commands::read_input(&file)?;
+                commands::create_project(&client, json, input).await
+            }
+            ProjectsCommand::Edit { id, file } => {
+                let changes = commands::read_input(&file)?;
+                commands::edit_project(&client, json, id, changes).await
+            }
+            ProjectsCommand::Delete { id } => commands::delete_project(&client, json, id).await,
+        },
+        Command::Jobsets { command } => match command {
+            JobsetsCommand::List { project, list } => {
+                commands::list_jobsets(&client, json, &list.listing(), project).await
+            }
+            JobsetsCommand::Show { project, id } => {
+                commands::show_jobset(&client, json, project, id).await
+            }
+            JobsetsCommand::Create { project, file } => {
+                let input = commands::read_input(&file)?;
+                commands::create_jobset(&client, json, project, input).await
+            }
+            JobsetsCommand::Edit { project, id, file } => {
+                let changes = commands::read_input(&file)?;
+                commands::edit_jobset(&client, json, project, id, changes).await
+            }
+            JobsetsCommand::Delete { project, id } => {
+                commands::delete_jobset(&client, json, project, id).await
+            }
+            JobsetsCommand::Trigger { project, id } => {
+                commands::queue_jobset(&client, json, project, id, "trigger").await
+            }
+            JobsetsCommand::Cancel { project, id } => {
+                commands::queue_jobset(&client, json, project, id, "cancel").await
+            }
+        },
+        Command::Evals { command } => match command {
+            EvalsCommand::List {
+                project,
+                jobset,
+                list,
+            } => commands::list_evaluations(&client, json, &list.listing(), project, jobset).await,
+            EvalsCommand::Show { id } => commands::show_evaluation(&client, json, id).await,
+        },
+        Command::Jobs { command } => match command {
+            JobsCommand::List {
+                list,
+                jobset,
+                state,
+                attribute,
+            } => {
+                let filter = JobListing {
+                    jobset,
+                    state,
+                    attribute,
+                };
+                commands::list_jobs(&client, json, &list.listing(), filter).await
+            }
+            JobsCommand::Show { id } => commands::show_job(&client, json, id).await,
+            JobsCommand::Restart { ids, evaluation } => {
+                commands::restart_jobs(&client, json, ids, evaluation).await
+            }
+            JobsCommand::Log { id, follow } => commands::job_log(&client, id, follow).await,
+        },
+        Command::Queue => commands::show_queue(&client, json).await,
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+
+    if let Err(e) = run(cli).await {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,47 @@
+/// Prints rows as columns padded to their widest cell
+pub struct Table {
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(header: &[&str]) -> Self {
+        Table {
+            header: header.iter().map(|cell| cell.to_uppercase()).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, row: Vec<String>) {
+        self.rows.push(row);
+    }
+
+    pub fn print(&self) {
+        let mut widths: Vec<usize> = self.header.iter().map(|cell| cell.len()).collect();
+
+        for row in self.rows.iter() {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        for row in std::iter::once(&self.header).chain(self.rows.iter()) {
+            let line: Vec<String> = row
+                .iter()
+                .zip(widths.iter())
+                .map(|(cell, width)| format!("{:width$}", cell, width = width))
+                .collect();
+
+            println!("{}", line.join("  ").trim_end());
+        }
+    }
+}
+
+/// Prints a single item as `key: value` lines
+pub fn print_fields(fields: &[(&str, String)]) {
+    let width = fields.iter().map(|(key, _)| key.len()).max().unwrap_or(0) + 1;
+
+    for (key, value) in fields {
+        println!("{:width$} {}", format!("{}:", key), value, width = width);
+    }
+}
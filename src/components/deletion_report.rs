@@ -0,0 +1,37 @@
+use leptos::prelude::*;
+
+use crate::{components::go_back::GoBack, models::DeletionReport};
+
+stylance::import_crate_style!(style, "style/components/deletion_report.module.scss");
+
+/// Shows what a delete removed
+#[component]
+pub fn DeletionReportView(
+    report: DeletionReport,
+    what: String,
+    url: String,
+    text: String,
+) -> impl IntoView {
+    let entries = vec![
+        ("Jobsets", report.jobsets),
+        ("Evaluations", report.evaluations),
+        ("Jobs", report.jobs),
+        ("Cancelled evaluations", report.cancelled_evaluations),
+        ("Cancelled builds", report.cancelled_builds),
+        ("Build logs", report.logs),
+        ("GC roots", report.gc_roots),
+    ];
+
+    view! {
+        <div class=style::deletion_report>
+            <p class="success">"Deleted "{what}", removed:"</p>
+            <div class=style::entries>
+                {entries.into_iter().map(|(key, value)| view! {
+                    <p>{key}</p>
+                    <p>{value}</p>
+                }).collect_view()}
+            </div>
+        </div>
+        <GoBack url=url text=text/>
+    }
+}
@@ -1 +1,2 @@
+pub mod deletion_report;
 pub mod go_back;
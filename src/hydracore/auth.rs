@@ -0,0 +1,118 @@
+use core::{error, fmt};
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use crate::models::{Project, ProjectMember, ProjectRole, User};
+
+use super::db::{DBError, DB};
+
+#[derive(Debug)]
+pub struct AuthError {
+    error: String,
+}
+
+impl AuthError {
+    pub fn new(error: String) -> Self {
+        AuthError { error }
+    }
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl error::Error for AuthError {}
+
+/// Hashes the password with argon2 and a random salt, in the PHC string format
+pub fn hash_password(password: &str) -> Result<String, AuthError> {
+    let salt = SaltString::generate(&mut OsRng);
+
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| AuthError::new(e.to_string()))
+}
+
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    let hash = match PasswordHash::new(hash) {
+        Ok(hash) => hash,
+        Err(_) => return false,
+    };
+
+    Argon2::default()
+        .verify_password(password.as_bytes(), &hash)
+        .is_ok()
+}
+
+/// A random token for a session cookie
+pub fn new_token() -> String {
+    let mut bytes = [0u8; 32];
+
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// What the database stores instead of the token, so a leaked database does not leak sessions
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Creates a local account with the password
+pub async fn add_user(db: &DB, name: &str, password: &str, admin: bool) -> Result<User, AuthError> {
+    if name.is_empty() || password.is_empty() {
+        return Err(AuthError::new(
+            "Name and password may not be empty".to_string(),
+        ));
+    }
+
+    let hash = hash_password(password)?;
+
+    let mut user = User::new(name.to_string(), admin);
+
+    user.add_to_db(db, &hash)
+        .await
+        .map_err(|e| AuthError::new(e.to_string()))?;
+
+    Ok(user)
+}
+
+pub async fn get_users(db: &DB) -> Result<Vec<User>, AuthError> {
+    User::get_all(db)
+        .await
+        .map_err(|e| AuthError::new(e.to_string()))
+}
+
+/// The role of the user in the project, admins count as owners of every project
+pub async fn project_role(
+    db: &DB,
+    user: Option<&User>,
+    project_id: i32,
+) -> Result<Option<ProjectRole>, DBError> {
+    match user {
+        None => Ok(None),
+        Some(user) if user.admin => Ok(Some(ProjectRole::Owner)),
+        Some(user) => ProjectMember::get_role(db, project_id, user.id.unwrap()).await,
+    }
+}
+
+/// Everybody may see public projects, private ones only their members
+pub async fn can_view_project(
+    db: &DB,
+    user: Option<&User>,
+    project: &Project,
+) -> Result<bool, DBError> {
+    if !project.private {
+        return Ok(true);
+    }
+
+    Ok(project_role(db, user, project.id.unwrap()).await?.is_some())
+}
@@ -0,0 +1,65 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Layout of the data directory shared by the web frontend and the queue runner
+#[derive(Clone, Debug)]
+pub struct DataDir {
+    path: PathBuf,
+}
+
+impl DataDir {
+    pub fn new(path: PathBuf) -> Self {
+        DataDir { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn logs(&self) -> PathBuf {
+        self.path.join("logs")
+    }
+
+    pub fn build_log(&self, job_id: i32) -> PathBuf {
+        self.logs().join(format!("{}.log", job_id))
+    }
+
+    /// Removes the build log of the job, returns its size or `None` if it had none
+    pub fn remove_build_log(&self, job_id: i32) -> io::Result<Option<u64>> {
+        let log = self.build_log(job_id);
+
+        if !log.exists() {
+            return Ok(None);
+        }
+
+        let size = fs::metadata(&log)?.len();
+
+        fs::remove_file(&log)?;
+
+        Ok(Some(size))
+    }
+
+    pub fn gc_roots(&self) -> PathBuf {
+        self.path.join("gcroots")
+    }
+
+    pub fn keys(&self) -> PathBuf {
+        self.path.join("keys")
+    }
+
+    /// The channel releases of the jobset
+    pub fn channels(&self, jobset_id: i32) -> PathBuf {
+        self.path.join("channels").join(jobset_id.to_string())
+    }
+
+    pub fn channel_revision(&self, jobset_id: i32, name: &str) -> PathBuf {
+        self.channels(jobset_id).join(name)
+    }
+
+    /// The root of the job's outputs, nix adds a suffix for every output after the first one
+    pub fn gc_root(&self, job_id: i32) -> PathBuf {
+        self.gc_roots().join(job_id.to_string())
+    }
+}
@@ -2,15 +2,12 @@ use core::fmt;
 use std::str::FromStr;
 
 use sqlx::{
-    pool::PoolConnection,
-    query, query_as,
+    postgres::{PgConnectOptions, PgPool},
     sqlite::{SqliteConnectOptions, SqlitePool},
-    Sqlite,
+    Postgres, Sqlite,
 };
 use tracing::info;
 
-use crate::models::{Jobset, JobsetID, JobsetState, Project};
-
 fn convert_to_string<T: ToString>(some_option: Option<T>) -> String {
     if some_option.is_some() {
         return some_option.unwrap().to_string();
@@ -33,24 +30,100 @@ impl fmt::Display for DBError {
     }
 }
 
-pub struct DB {
-    pool: SqlitePool,
+/// The connection pool of the configured backend
+#[derive(Clone)]
+pub enum Pool {
+    Sqlite(SqlitePool),
+    Postgres(PgPool),
 }
 
-impl DB {
-    pub async fn new(path: &str) -> Result<Self, DBError> {
-        let path = String::new() + "sqlite://" + path;
-        let opts = SqliteConnectOptions::from_str(&path)
-            .map_err(|e| DBError::new(e.to_string()))?
-            .create_if_missing(true)
-            .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal);
+/// Runs the same sqlx code on whichever backend the database uses.
+/// The body is expanded once per backend, so queries have to be valid on both:
+/// use `$1`, `$2`, ... as placeholders and stick to sql both understand.
+macro_rules! with_db {
+    ($db:expr, |$pool:ident| $body:expr) => {
+        match $db.pool() {
+            $crate::hydracore::Pool::Sqlite($pool) => $body,
+            $crate::hydracore::Pool::Postgres($pool) => $body,
+        }
+    };
+}
+
+pub(crate) use with_db;
+
+/// A running transaction on the configured backend
+pub enum Transaction {
+    Sqlite(sqlx::Transaction<'static, Sqlite>),
+    Postgres(sqlx::Transaction<'static, Postgres>),
+}
+
+impl Transaction {
+    pub async fn commit(self) -> Result<(), DBError> {
+        match self {
+            Transaction::Sqlite(tx) => tx.commit().await,
+            Transaction::Postgres(tx) => tx.commit().await,
+        }
+        .map_err(|e| DBError::new(e.to_string()))
+    }
+}
 
-        info!("Connecting to database: {}", path);
-        let pool = SqlitePool::connect_with(opts).await;
+/// Like `with_db!`, but runs the body on the connection of a transaction
+macro_rules! with_tx {
+    ($tx:expr, |$conn:ident| $body:expr) => {
+        match $tx {
+            $crate::hydracore::Transaction::Sqlite(tx) => {
+                let $conn = &mut **tx;
+                $body
+            }
+            $crate::hydracore::Transaction::Postgres(tx) => {
+                let $conn = &mut **tx;
+                $body
+            }
+        }
+    };
+}
+
+pub(crate) use with_tx;
+
+/// Handle to the database. Cloning it is cheap and every clone uses the same connection pool,
+/// so it can be shared without locking.
+#[derive(Clone)]
+pub struct DB {
+    pool: Pool,
+}
 
-        let db = pool.map_err(|e| DBError::new(e.to_string()))?;
+impl DB {
+    /// Connects to the database, the backend is chosen by the url scheme
+    /// (`sqlite://` or `postgres://`).
+    pub async fn new(url: &str) -> Result<Self, DBError> {
+        info!("Connecting to database: {}", url);
+
+        let pool = if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+            let opts = PgConnectOptions::from_str(url).map_err(|e| DBError::new(e.to_string()))?;
+
+            let pool = PgPool::connect_with(opts)
+                .await
+                .map_err(|e| DBError::new(e.to_string()))?;
+
+            Pool::Postgres(pool)
+        } else if url.starts_with("sqlite:") {
+            let opts = SqliteConnectOptions::from_str(url)
+                .map_err(|e| DBError::new(e.to_string()))?
+                .create_if_missing(true)
+                // the cascading deletes depend on it
+                .foreign_keys(true)
+                .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal);
+
+            let pool = SqlitePool::connect_with(opts)
+                .await
+                .map_err(|e| DBError::new(e.to_string()))?;
+
+            Pool::Sqlite(pool)
+        } else {
+            return Err(DBError::new(format!("Unsupported database url: {}", url)));
+        };
 
-        let db = DB { pool: db };
+        let db = DB { pool };
 
         let setup = db.setup().await;
         if setup.is_some() {
@@ -60,23 +133,75 @@ impl DB {
         Ok(db)
     }
 
-    pub async fn get_conn(&self) -> Result<PoolConnection<Sqlite>, DBError> {
-        self.pool
-            .acquire()
-            .await
-            .map_err(|e| DBError::new(e.to_string()))
+    pub fn pool(&self) -> &Pool {
+        &self.pool
     }
 
-    async fn setup(&self) -> Option<DBError> {
-        let mut conn = self.get_conn().await.unwrap();
+    pub async fn begin(&self) -> Result<Transaction, DBError> {
+        match &self.pool {
+            Pool::Sqlite(pool) => pool.begin().await.map(Transaction::Sqlite),
+            Pool::Postgres(pool) => pool.begin().await.map(Transaction::Postgres),
+        }
+        .map_err(|e| DBError::new(e.to_string()))
+    }
+
+    /// Open connections of the pool and how many of them are idle
+    pub fn pool_stats(&self) -> (u32, usize) {
+        match &self.pool {
+            Pool::Sqlite(pool) => (pool.size(), pool.num_idle()),
+            Pool::Postgres(pool) => (pool.size(), pool.num_idle()),
+        }
+    }
+
+    pub fn is_postgres(&self) -> bool {
+        matches!(self.pool, Pool::Postgres(_))
+    }
 
-        let result = sqlx::migrate!("./migrations")
-            .run(&mut *conn)
-            .await
-            .map_err(|e| DBError::new(e.to_string()));
+    /// Size of the database in bytes
+    pub async fn size(&self) -> Result<i64, DBError> {
+        match &self.pool {
+            Pool::Sqlite(pool) => {
+                sqlx::query_scalar::<_, i64>(
+                    "select page_count * page_size from pragma_page_count(), pragma_page_size()",
+                )
+                .fetch_one(pool)
+                .await
+            }
+            Pool::Postgres(pool) => {
+                sqlx::query_scalar::<_, i64>("select pg_database_size(current_database())")
+                    .fetch_one(pool)
+                    .await
+            }
+        }
+        .map_err(|e| DBError::new(e.to_string()))
+    }
+
+    /// Gives the space of deleted rows back and refreshes the query planner statistics
+    pub async fn vacuum(&self) -> Result<(), DBError> {
+        match &self.pool {
+            Pool::Sqlite(pool) => {
+                sqlx::raw_sql("vacuum; pragma optimize; pragma wal_checkpoint(truncate);")
+                    .execute(pool)
+                    .await
+                    .map(|_| ())
+            }
+            Pool::Postgres(pool) => sqlx::raw_sql("vacuum analyze")
+                .execute(pool)
+                .await
+                .map(|_| ()),
+        }
+        .map_err(|e| DBError::new(e.to_string()))
+    }
+
+    async fn setup(&self) -> Option<DBError> {
+        // both directories have to contain the same migrations
+        let result = match &self.pool {
+            Pool::Sqlite(pool) => sqlx::migrate!("./migrations/sqlite").run(pool).await,
+            Pool::Postgres(pool) => sqlx::migrate!("./migrations/postgres").run(pool).await,
+        };
 
         if result.is_err() {
-            return Some(result.err().unwrap());
+            return Some(DBError::new(result.err().unwrap().to_string()));
         }
 
         info!("Applied database migrations (if necessary)");
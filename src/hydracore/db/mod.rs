@@ -1,3 +1,6 @@
 pub use db::*;
 
 mod db;
+
+#[cfg(test)]
+mod tests;
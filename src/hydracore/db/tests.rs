@@ -0,0 +1,120 @@
+//! Applies the migrations and runs the basic queries on both backends.
+//! Postgres is only tested if LADON_TEST_POSTGRES_URL points to a database, e.g.
+//! `LADON_TEST_POSTGRES_URL=postgres://postgres@127.0.0.1:5432/ladon cargo test --features ssr`
+
+use crate::models::{Jobset, JobsetState, Project};
+
+use super::DB;
+
+async fn crud(db: &DB) {
+    // a postgres database may be shared with earlier runs
+    let name = format!(
+        "smoke-{}-{}",
+        std::process::id(),
+        chrono::Utc::now().timestamp_micros()
+    );
+
+    let mut project = Project {
+        id: None,
+        name: name.clone(),
+        description: "smoke test".to_string(),
+        binary_cache: false,
+        private: false,
+    };
+
+    project.add_to_db(db).await.unwrap();
+
+    let found = Project::get_single(db, project.id.unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.name, name);
+
+    project.description = "changed".to_string();
+    project.private = true;
+    project.update(db).await.unwrap();
+
+    let found = Project::get_by_name(db, &name).await.unwrap().unwrap();
+    assert_eq!(found.description, "changed");
+    assert!(found.private);
+
+    let mut jobset = Jobset {
+        id: None,
+        project_id: project.id.unwrap(),
+        name: "main".to_string(),
+        flake: "github:NixOS/nixpkgs?ref=nixos-unstable".to_string(),
+        description: "smoke test".to_string(),
+        check_interval: 0,
+        keep_evaluations: 1,
+        max_evaluations: 0,
+        max_evaluation_age: 0,
+        channel: false,
+        channel_gate: None,
+        last_checked: None,
+        last_evaluated: None,
+        evaluation_took: None,
+        state: Some(JobsetState::Idle),
+        error_message: None,
+    };
+
+    jobset.add_to_db(db).await.unwrap();
+
+    let jobsets = Jobset::get_all(db, project.id.unwrap()).await.unwrap();
+    assert_eq!(jobsets.len(), 1);
+    assert_eq!(jobsets[0].state, Some(JobsetState::Idle));
+
+    jobset
+        .update_state(db, JobsetState::Evaluating)
+        .await
+        .unwrap();
+
+    let found = Jobset::get_single(db, jobset.id.unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.state, Some(JobsetState::Evaluating));
+
+    project.delete(db).await.unwrap();
+
+    // the jobsets go with their project
+    assert!(Project::get_single(db, project.id.unwrap())
+        .await
+        .unwrap()
+        .is_none());
+    assert!(Jobset::get_single(db, jobset.id.unwrap())
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn sqlite() {
+    let path = std::env::temp_dir().join(format!("ladon-smoke-{}.sqlite", std::process::id()));
+
+    let db = DB::new(&format!("sqlite://{}", path.display()))
+        .await
+        .unwrap();
+
+    crud(&db).await;
+
+    for suffix in ["", "-wal", "-shm"] {
+        _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+    }
+}
+
+#[tokio::test]
+async fn postgres() {
+    let url = match std::env::var("LADON_TEST_POSTGRES_URL") {
+        Ok(url) => url,
+        Err(_) => {
+            eprintln!("LADON_TEST_POSTGRES_URL is not set, skipping");
+            return;
+        }
+    };
+
+    let db = DB::new(&url).await.unwrap();
+
+    assert!(db.is_postgres());
+
+    crud(&db).await;
+}
@@ -0,0 +1,121 @@
+use std::{collections::HashSet, fs};
+
+use tracing::{error, warn};
+
+use crate::models::{DeletionReport, Jobset, Project, QueueRequest, QueueRequestKind};
+
+use super::{
+    data_dir::DataDir,
+    db::{DBError, DB},
+    gc_roots::gc_root_job_id,
+};
+
+/// Deletes the jobset, cancels its evaluation and builds and removes its files.
+pub async fn delete_jobset(
+    db: &DB,
+    data_dir: &DataDir,
+    jobset: &mut Jobset,
+) -> Result<DeletionReport, DBError> {
+    let mut report = jobset.delete(db).await?;
+
+    cleanup(db, data_dir, &mut report).await;
+
+    Ok(report)
+}
+
+/// Deletes the project, cancels the evaluations and builds of its jobsets and removes their files.
+pub async fn delete_project(
+    db: &DB,
+    data_dir: &DataDir,
+    project: &Project,
+) -> Result<DeletionReport, DBError> {
+    let mut report = project.delete(db).await?;
+
+    cleanup(db, data_dir, &mut report).await;
+
+    Ok(report)
+}
+
+async fn cleanup(db: &DB, data_dir: &DataDir, report: &mut DeletionReport) {
+    // the queue runner may live in another process, so it is told through the queue
+    if report.has_active_work() {
+        let mut request = QueueRequest::new(QueueRequestKind::CancelDeleted, None);
+
+        let result = request.add_to_db(db).await;
+
+        if result.is_err() {
+            error!(
+                "Failed to queue cancellation: {}",
+                result.err().unwrap().to_string()
+            );
+        }
+    }
+
+    for job_id in report.job_ids.iter() {
+        match data_dir.remove_build_log(*job_id) {
+            Ok(Some(_)) => report.logs += 1,
+            Ok(None) => (),
+            Err(e) => warn!("Failed to remove build log of job {}: {}", job_id, e),
+        }
+    }
+
+    report.gc_roots = remove_gc_roots(data_dir, &report.job_ids);
+
+    for jobset_id in report.jobset_ids.iter() {
+        let channels = data_dir.channels(*jobset_id);
+
+        if !channels.exists() {
+            continue;
+        }
+
+        let result = fs::remove_dir_all(&channels);
+
+        if result.is_err() {
+            warn!(
+                "Failed to remove channels {}: {}",
+                channels.display(),
+                result.err().unwrap()
+            );
+        }
+    }
+}
+
+/// Removes the gc roots of the jobs, returns how many were removed
+fn remove_gc_roots(data_dir: &DataDir, job_ids: &[i32]) -> i64 {
+    let entries = fs::read_dir(data_dir.gc_roots());
+
+    if entries.is_err() {
+        // no build registered a gc root yet
+        return 0;
+    }
+
+    let job_ids: HashSet<i32> = job_ids.iter().copied().collect();
+
+    let mut removed = 0;
+
+    for entry in entries.unwrap().flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+
+        let id = gc_root_job_id(&name);
+
+        if id.is_none() || !job_ids.contains(&id.unwrap()) {
+            continue;
+        }
+
+        let result = fs::remove_file(entry.path());
+
+        if result.is_err() {
+            warn!(
+                "Failed to remove gc root {}: {}",
+                entry.path().display(),
+                result.err().unwrap()
+            );
+            continue;
+        }
+
+        removed += 1;
+    }
+
+    removed
+}
@@ -0,0 +1,179 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+use tracing::{error, info, warn};
+
+use crate::models::BuildProduct;
+
+use super::super::db::DB;
+
+/// Build products have to be inside the store, a job must not offer arbitrary files for download
+const STORE_DIR: &str = "/nix/store/";
+
+/// One line of `$out/nix-support/hydra-build-products`: `<type> <subtype> <path> [<default path>]`.
+/// The path may be quoted if it contains spaces.
+struct ProductLine {
+    r#type: String,
+    subtype: String,
+    path: String,
+    default_path: Option<String>,
+}
+
+/// Takes the next, possibly quoted, word off the line
+fn next_word(rest: &mut &str) -> Option<String> {
+    *rest = rest.trim_start();
+
+    if rest.is_empty() {
+        return None;
+    }
+
+    let (word, remaining) = match rest.strip_prefix('"') {
+        Some(quoted) => {
+            let end = quoted.find('"')?;
+            (&quoted[..end], &quoted[end + 1..])
+        }
+        None => {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            (&rest[..end], &rest[end..])
+        }
+    };
+
+    *rest = remaining;
+
+    Some(word.to_string())
+}
+
+fn parse_line(line: &str) -> Option<ProductLine> {
+    let mut rest = line.trim();
+
+    let r#type = next_word(&mut rest)?;
+    let subtype = next_word(&mut rest)?;
+    let path = next_word(&mut rest)?;
+    let default_path = next_word(&mut rest);
+
+    Some(ProductLine {
+        r#type,
+        subtype,
+        path,
+        default_path,
+    })
+}
+
+fn hash_file(path: &Path) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+
+    io::copy(&mut file, &mut hasher)?;
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Turns a line into a product, if its path is a file or directory in the store
+fn to_product(job_id: i32, line: ProductLine) -> Result<BuildProduct, String> {
+    // symlinks could point anywhere
+    let path = PathBuf::from(&line.path)
+        .canonicalize()
+        .map_err(|e| format!("{}: {}", line.path, e))?;
+
+    if !path.starts_with(STORE_DIR) {
+        return Err(format!("{} is not in the store", path.display()));
+    }
+
+    let metadata = fs::metadata(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let (file_size, sha256) = match metadata.is_file() {
+        true => (
+            Some(metadata.len() as i64),
+            Some(hash_file(&path).map_err(|e| format!("{}: {}", path.display(), e))?),
+        ),
+        false => (None, None),
+    };
+
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    Ok(BuildProduct {
+        id: None,
+        job_id,
+        r#type: line.r#type,
+        subtype: line.subtype,
+        path: path.to_string_lossy().to_string(),
+        name,
+        default_path: line.default_path,
+        file_size,
+        sha256,
+    })
+}
+
+/// Reads the build products the outputs list, skipping the ones which can not be offered
+fn collect(job_id: i32, outputs: &[String]) -> Vec<BuildProduct> {
+    let mut products = Vec::new();
+
+    for output in outputs {
+        let list = Path::new(output).join("nix-support/hydra-build-products");
+
+        let contents = match fs::read_to_string(&list) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                warn!("Failed to read {}: {}", list.display(), e);
+                continue;
+            }
+        };
+
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            let Some(line) = parse_line(line) else {
+                warn!("Invalid build product line in {}: {}", list.display(), line);
+                continue;
+            };
+
+            match to_product(job_id, line) {
+                Ok(product) => products.push(product),
+                Err(e) => warn!("Skipping build product of job {}: {}", job_id, e),
+            }
+        }
+    }
+
+    products
+}
+
+/// Records the build products of a successful job
+pub async fn add_build_products(db: &DB, job_id: i32, outputs: Vec<String>) {
+    // hashing large images takes a while
+    let products = tokio::task::spawn_blocking(move || collect(job_id, &outputs)).await;
+
+    if products.is_err() {
+        error!(
+            "Failed to collect build products: {}",
+            products.err().unwrap()
+        );
+        return;
+    }
+
+    let mut products = products.unwrap();
+
+    if products.is_empty() {
+        return;
+    }
+
+    let result = BuildProduct::add_many(db, &mut products).await;
+
+    if result.is_err() {
+        error!(
+            "Failed to record build products: {}",
+            result.err().unwrap().to_string()
+        );
+        return;
+    }
+
+    info!(
+        "Recorded {} build products of job {}",
+        products.len(),
+        job_id
+    );
+}
@@ -0,0 +1,238 @@
+use core::{error, fmt};
+use std::{fs, path::Path, process::Stdio};
+
+use tokio::process::Command;
+use tracing::{error, info};
+
+use crate::models::{ChannelRevision, Evaluation, Job, JobState, Jobset, Project};
+
+use super::{
+    super::{
+        data_dir::DataDir,
+        db::{DBError, DB},
+    },
+    nix::flake::FlakeMetadata,
+};
+
+#[derive(Debug)]
+pub struct ChannelError {
+    error: String,
+}
+
+impl ChannelError {
+    pub fn new(error: String) -> Self {
+        ChannelError { error }
+    }
+}
+
+impl fmt::Display for ChannelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl error::Error for ChannelError {}
+
+/// Releases evaluations of jobsets with a channel once they pass the gate
+#[derive(Clone)]
+pub struct ChannelPublisher {
+    db: DB,
+    data_dir: DataDir,
+    /// Where ladon itself is reachable, for the `binary-cache-url` of channels
+    public_url: String,
+}
+
+impl ChannelPublisher {
+    pub fn new(db: DB, data_dir: DataDir, public_url: String) -> Self {
+        ChannelPublisher {
+            db,
+            data_dir,
+            public_url: public_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Called for every successful job, releases its evaluation if that passes the gate now
+    pub async fn on_job_finished(&self, job: &Job) {
+        let result = self.try_release(job.evaluation_id).await;
+
+        if result.is_err() {
+            error!(
+                "Failed to release channel of evaluation {}: {}",
+                job.evaluation_id,
+                result.err().unwrap()
+            );
+        }
+    }
+
+    async fn try_release(&self, evaluation_id: i32) -> Result<(), ChannelError> {
+        let db = &self.db;
+        let db_err = |e: DBError| ChannelError::new(e.to_string());
+
+        let evaluation = Evaluation::get_single(db, evaluation_id)
+            .await
+            .map_err(db_err)?
+            .ok_or(ChannelError::new("Failed to find evaluation".to_string()))?;
+
+        let jobset = Jobset::get_single(db, evaluation.jobset_id)
+            .await
+            .map_err(db_err)?
+            .ok_or(ChannelError::new("Failed to find jobset".to_string()))?;
+
+        if !jobset.channel {
+            return Ok(());
+        }
+
+        // the channel only moves forward, this also skips evaluations which were already released
+        let latest = ChannelRevision::get_latest_evaluation(db, evaluation.jobset_id)
+            .await
+            .map_err(db_err)?;
+
+        if latest.is_some_and(|latest| latest >= evaluation_id) {
+            return Ok(());
+        }
+
+        let jobs = Job::get_by_evaluation(db, evaluation_id)
+            .await
+            .map_err(db_err)?;
+
+        if !passes_gate(&jobs, jobset.channel_gate.as_deref()) {
+            return Ok(());
+        }
+
+        self.release(&jobset, &evaluation).await
+    }
+
+    async fn release(&self, jobset: &Jobset, evaluation: &Evaluation) -> Result<(), ChannelError> {
+        let jobset_id = jobset.id.unwrap();
+        let evaluation_id = evaluation.id.unwrap();
+
+        let flake = evaluation.flake.as_ref().ok_or(ChannelError::new(
+            "The evaluation has no locked flake".to_string(),
+        ))?;
+
+        // fetches the source again if it was garbage collected
+        let metadata = FlakeMetadata::query(flake)
+            .await
+            .map_err(|e| ChannelError::new(e.to_string()))?;
+
+        let name = revision_name(jobset, evaluation_id, evaluation.revision.as_deref());
+
+        let dir = self.data_dir.channel_revision(jobset_id, &name);
+        let tmp_dir = self
+            .data_dir
+            .channel_revision(jobset_id, &format!("{}.tmp", name));
+
+        if tmp_dir.exists() {
+            fs::remove_dir_all(&tmp_dir).map_err(|e| ChannelError::new(e.to_string()))?;
+        }
+
+        fs::create_dir_all(&tmp_dir).map_err(|e| ChannelError::new(e.to_string()))?;
+
+        create_tarball(
+            Path::new(&metadata.path),
+            &name,
+            &tmp_dir.join("nixexprs.tar.xz"),
+        )
+        .await?;
+
+        let project = Project::get_single(&self.db, jobset.project_id)
+            .await
+            .map_err(|e| ChannelError::new(e.to_string()))?;
+
+        let binary_cache_url = match project {
+            Some(project) if project.binary_cache => format!("{}/cache", self.public_url),
+            _ => "https://cache.nixos.org".to_string(),
+        };
+
+        fs::write(tmp_dir.join("binary-cache-url"), binary_cache_url)
+            .map_err(|e| ChannelError::new(e.to_string()))?;
+
+        // left over if recording a previous release of the evaluation failed
+        if dir.exists() {
+            fs::remove_dir_all(&dir).map_err(|e| ChannelError::new(e.to_string()))?;
+        }
+
+        // the files have to be complete before the revision becomes visible
+        fs::rename(&tmp_dir, &dir).map_err(|e| ChannelError::new(e.to_string()))?;
+
+        let mut revision =
+            ChannelRevision::new(jobset_id, evaluation_id, name, evaluation.revision.clone());
+
+        revision
+            .add_to_db(&self.db)
+            .await
+            .map_err(|e| ChannelError::new(e.to_string()))?;
+
+        info!(
+            "Released channel revision {} of jobset {}",
+            revision.name, jobset_id
+        );
+
+        Ok(())
+    }
+}
+
+/// Either the gate attribute or, without one, every job has to be built successfully
+fn passes_gate(jobs: &[Job], gate: Option<&str>) -> bool {
+    let done = |job: &Job| matches!(job.state, JobState::Done);
+
+    match gate {
+        Some(gate) => jobs
+            .iter()
+            .any(|job| job.attribute_name == gate && done(job)),
+        None => !jobs.is_empty() && jobs.iter().all(done),
+    }
+}
+
+/// Like `<jobset>-<evaluation>.<short revision>`, which is also the directory in the tarball
+fn revision_name(jobset: &Jobset, evaluation_id: i32, revision: Option<&str>) -> String {
+    let jobset_name: String = jobset
+        .name
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() || "._-".contains(c) {
+            true => c,
+            false => '-',
+        })
+        .collect();
+
+    match revision {
+        Some(revision) => format!(
+            "{}-{}.{}",
+            jobset_name,
+            evaluation_id,
+            &revision[..revision.len().min(7)]
+        ),
+        None => format!("{}-{}", jobset_name, evaluation_id),
+    }
+}
+
+/// Packs the source into `<name>/` of a tarball, which is what `nix-channel` expects
+async fn create_tarball(source: &Path, name: &str, tarball: &Path) -> Result<(), ChannelError> {
+    let result = Command::new("tar")
+        .arg("--create")
+        .arg("--xz")
+        .arg("--file")
+        .arg(tarball)
+        .arg("--directory")
+        .arg(source)
+        .arg(format!("--transform=s,^\\.,{},", name))
+        .arg("--sort=name")
+        .arg("--owner=0")
+        .arg("--group=0")
+        .arg("--numeric-owner")
+        .arg(".")
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .map_err(|e| ChannelError::new(e.to_string()))?;
+
+    if !result.status.success() {
+        return Err(ChannelError::new(format!(
+            "Failed to create channel tarball: {}",
+            String::from_utf8_lossy(&result.stderr).trim()
+        )));
+    }
+
+    Ok(())
+}
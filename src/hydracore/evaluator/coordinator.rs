@@ -1,270 +1,832 @@
-use std::{process::ExitStatus, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use crate::{
     hydracore::evaluator::nix::drv::DependencyTree,
-    models::{Job, JobDiff, JobState, Jobset, JobsetDiff, JobsetState},
+    models::{Job, JobDiff, JobPath, JobState, Jobset, JobsetDiff, JobsetState},
+    server::config::StatusConfig,
 };
 
 use super::{
-    super::db::DB,
+    super::{data_dir::DataDir, db::DB, gc_roots::GcRoots, metrics::Metrics, pruning::Pruner},
+    build_products::add_build_products,
+    channels::ChannelPublisher,
+    leader::Lease,
     nix::{
         build::{BuildManager, BuildResult},
         drv::DrvBasic,
         eval::{Evaluation, EvaluationError},
+        store::query_closure,
     },
-    notifications::EvalDoneNotification,
+    notifications::{EvalDoneNotification, NotificationDispatcher},
+    statuses::StatusReporter,
+    tasks::TaskRegistry,
+    uploader::Uploader,
 };
 
 use crate::models::Project;
 
 use chrono::{DateTime, Utc};
-use tokio::sync::{
-    mpsc::{self, unbounded_channel, Receiver, Sender, UnboundedReceiver, UnboundedSender},
-    Mutex,
+use tokio::{
+    sync::{
+        mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+        Notify,
+    },
+    task::AbortHandle,
 };
-use tracing::{error, info, trace};
+use tracing::{error, info, warn};
 
+/// Everything the coordinator tasks share. All members are cheap handles,
+/// so every task gets its own copy instead of locking a shared one.
+#[derive(Clone)]
 struct CoordinatorData {
-    db: Arc<Mutex<DB>>,
-    build_manager: Arc<Mutex<BuildManager>>,
+    db: DB,
+    build_manager: BuildManager,
+    lease: Lease,
+    evaluations: TaskRegistry,
+    uploader: Uploader,
+    gc_roots: GcRoots,
+    pruner: Pruner,
+    channels: ChannelPublisher,
+    statuses: StatusReporter,
+    notifications: NotificationDispatcher,
+    metrics: Metrics,
+    /// Jobsets whose evaluation runs or whose results are not stored yet
+    busy_jobsets: Arc<Mutex<HashSet<i32>>>,
+    /// Wakes the queue runner, requests may wait for the jobset
+    evaluation_done: Arc<Notify>,
 }
 
 impl CoordinatorData {
-    pub fn new(db: DB, build_manager: BuildManager) -> Self {
+    pub fn new(
+        db: DB,
+        data_dir: DataDir,
+        build_manager: BuildManager,
+        lease: Lease,
+        public_url: String,
+        statuses: Vec<StatusConfig>,
+        metrics: Metrics,
+    ) -> Self {
         CoordinatorData {
-            db: Arc::new(Mutex::new(db)),
-            build_manager: Arc::new(Mutex::new(build_manager)),
+            statuses: StatusReporter::new(db.clone(), public_url.clone(), statuses),
+            notifications: NotificationDispatcher::new(db.clone(), public_url.clone()),
+            channels: ChannelPublisher::new(db.clone(), data_dir.clone(), public_url),
+            uploader: Uploader::new(db.clone()),
+            gc_roots: GcRoots::new(db.clone(), data_dir.clone()),
+            pruner: Pruner::new(db.clone(), data_dir),
+            db,
+            build_manager,
+            lease,
+            evaluations: TaskRegistry::default(),
+            metrics,
+            busy_jobsets: Arc::new(Mutex::new(HashSet::new())),
+            evaluation_done: Arc::new(Notify::new()),
         }
     }
+
+    /// Nothing is being evaluated or built
+    fn is_idle(&self) -> bool {
+        self.evaluations.ids().is_empty() && self.build_manager.active().is_empty()
+    }
+
+    /// Another evaluation of the jobset may start, the queue runner looks for waiting triggers
+    fn release_jobset(&self, jobset_id: i32) {
+        self.busy_jobsets.lock().unwrap().remove(&jobset_id);
+        self.evaluation_done.notify_one();
+    }
+
+    /// Checks that we are still the leader before writing results
+    async fn holds_lease(&self) -> bool {
+        let result = self.lease.is_held(&self.db).await;
+
+        if result.is_err() {
+            error!(
+                "Failed to check coordinator lease: {}",
+                result.err().unwrap().to_string()
+            );
+            return false;
+        }
+
+        if !result.unwrap() {
+            warn!("Coordinator lease was taken over, discarding result");
+            return false;
+        }
+
+        true
+    }
 }
 
+#[derive(Clone)]
 pub struct Coordinator {
-    data: Arc<Mutex<CoordinatorData>>,
+    data: CoordinatorData,
     eval_tx: Arc<UnboundedSender<EvalDoneNotification>>,
+    /// The tasks handling finished evaluations and builds
+    tasks: Vec<AbortHandle>,
 }
 
 impl Coordinator {
-    pub fn new(db: DB) -> Self {
+    pub fn new(
+        db: DB,
+        data_dir: DataDir,
+        lease: Lease,
+        public_url: String,
+        statuses: Vec<StatusConfig>,
+        metrics: Metrics,
+    ) -> Self {
         let (build_tx, build_rx) = unbounded_channel::<BuildResult>();
-        let data = Arc::new(Mutex::new(CoordinatorData::new(
+        let build_manager = BuildManager::new(
+            build_tx,
+            2,
+            data_dir.clone(),
+            metrics.clone(),
+            lease.holder.clone(),
+        );
+        let data = CoordinatorData::new(
             db,
-            BuildManager::new(build_tx, 2),
-        )));
+            data_dir,
+            build_manager,
+            lease,
+            public_url,
+            statuses,
+            metrics,
+        );
 
         let (eval_tx, eval_rx) = unbounded_channel::<EvalDoneNotification>();
 
         let eval_data = data.clone();
 
-        let _handle = tokio::spawn(async {
+        let eval_handle = tokio::spawn(async {
             Coordinator::on_eval_done(eval_rx, eval_data).await;
         });
 
         let build_data = data.clone();
 
-        tokio::spawn(async move {
+        let build_handle = tokio::spawn(async move {
             Coordinator::on_build_done(build_rx, build_data).await;
         });
 
         Coordinator {
             data,
             eval_tx: Arc::new(eval_tx),
+            tasks: vec![eval_handle.abort_handle(), build_handle.abort_handle()],
+        }
+    }
+
+    pub fn get_db(&self) -> DB {
+        self.data.db.clone()
+    }
+
+    /// Aborts all evaluations, builds and deliveries after the lease was lost.
+    /// The new leader resumes them, so nothing is written back here.
+    pub fn stop(&self) {
+        self.data.evaluations.cancel_all();
+        self.data.build_manager.stop();
+        self.data.uploader.stop();
+        self.data.notifications.stop();
+        self.data.statuses.stop();
+
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+
+    /// Takes over the evaluations and builds a previous coordinator left unfinished.
+    pub async fn resume_unfinished(&self) {
+        let db = &self.data.db;
+
+        let jobsets = Jobset::get_by_state(db, JobsetState::Evaluating)
+            .await
+            .unwrap_or_else(|e| {
+                error!("Failed to get unfinished evaluations: {}", e.to_string());
+                Vec::new()
+            });
+
+        for mut jobset in jobsets {
+            info!("Resuming evaluation of jobset: {}", jobset.id.unwrap());
+
+            let result = jobset.update_state(db, JobsetState::Idle).await;
+
+            if result.is_err() {
+                error!("Failed to reset jobset: {}", result.err().unwrap());
+                continue;
+            }
+
+            let result = self.schedule_jobset(&mut jobset, None).await;
+
+            if result.is_err() {
+                error!("Failed to resume evaluation: {}", result.err().unwrap());
+            }
+        }
+
+        let jobs = Job::get_unfinished(db).await;
+
+        if jobs.is_err() {
+            error!(
+                "Failed to get unfinished jobs: {}",
+                jobs.err().unwrap().to_string()
+            );
+            return;
+        }
+
+        for job in jobs.unwrap() {
+            info!("Resuming build: {}", job.derivation_path);
+
+            self.data
+                .build_manager
+                .queue(job.derivation_path.clone(), job.id.unwrap())
+                .await;
+        }
+
+        self.data.uploader.resume_unfinished().await;
+
+        self.data.notifications.resume_unfinished().await;
+
+        self.data.statuses.check_projects().await;
+        self.data.statuses.resume_unfinished().await;
+
+        // constituents may have finished while nobody was there to settle their aggregates
+        let waiting = Job::get_waiting(db).await.unwrap_or_else(|e| {
+            error!("Failed to get waiting aggregates: {}", e.to_string());
+            Vec::new()
+        });
+
+        for mut aggregate in waiting {
+            if Coordinator::resolve_aggregate(&self.data, &mut aggregate).await {
+                Coordinator::resolve_aggregates(&self.data, aggregate.id.unwrap()).await;
+            }
+        }
+
+        let result = self.data.gc_roots.sync().await;
+
+        if result.is_err() {
+            error!("Failed to sync gc roots: {}", result.err().unwrap());
         }
     }
 
-    pub async fn get_db(&self) -> Arc<Mutex<DB>> {
-        let locked = self.data.lock().await;
-        locked.db.clone()
+    /// Whether the jobset is evaluated right now. Its state in the database may still say
+    /// evaluating after the coordinator that evaluated it went away, so it is not asked.
+    pub fn is_evaluating(&self, jobset_id: i32) -> bool {
+        self.data.busy_jobsets.lock().unwrap().contains(&jobset_id)
     }
 
-    pub async fn schedule_jobset(&mut self, jobset: &mut Jobset) -> Result<(), EvaluationError> {
-        if jobset.state == Some(JobsetState::Evaluating) {
+    /// Resolves once an evaluation was stored, its jobset may be evaluated again
+    pub async fn evaluation_done(&self) {
+        self.data.evaluation_done.notified().await;
+    }
+
+    /// Starts evaluating the jobset, at the revision if a push named one
+    pub async fn schedule_jobset(
+        &self,
+        jobset: &mut Jobset,
+        revision: Option<&str>,
+    ) -> Result<(), EvaluationError> {
+        let jobset_id = jobset.id.unwrap();
+
+        if !self.data.busy_jobsets.lock().unwrap().insert(jobset_id) {
             return Err(EvaluationError::new(
                 "Evaluation already running".to_string(),
             ));
         }
+
+        let result = self.start_evaluation(jobset, revision).await;
+
+        if let Err(e) = &result {
+            self.data.release_jobset(jobset_id);
+
+            let mut diff = JobsetDiff::new();
+            diff.set_state(JobsetState::EvalFailed);
+            diff.set_error_message(e.to_string());
+
+            let update = jobset.update_jobset(&self.data.db, diff).await;
+
+            if update.is_err() {
+                error!(
+                    "Failed to update jobset: {}",
+                    update.err().unwrap().to_string()
+                );
+            }
+        }
+
+        result
+    }
+
+    async fn start_evaluation(
+        &self,
+        jobset: &mut Jobset,
+        revision: Option<&str>,
+    ) -> Result<(), EvaluationError> {
         jobset
-            .update_state(
-                &*self.data.lock().await.db.lock().await,
-                JobsetState::Evaluating,
-            )
+            .update_state(&self.data.db, JobsetState::Evaluating)
             .await
             .map_err(|e| EvaluationError::new(format!("DBError: {}", e.to_string())))?;
 
-        _ = Evaluation::new(self.eval_tx.clone(), &jobset).await?;
+        let handle = Evaluation::new(self.eval_tx.clone(), jobset, revision).await?;
+
+        self.data
+            .evaluations
+            .insert(jobset.id.unwrap(), handle.abort_handle());
+
+        self.data
+            .notifications
+            .on_evaluation_started(jobset, revision)
+            .await;
 
         Ok(())
     }
 
-    async fn on_eval_done(
-        mut receiver: UnboundedReceiver<EvalDoneNotification>,
-        data: Arc<Mutex<CoordinatorData>>,
-    ) {
-        while let Some(mut notification) = receiver.recv().await {
-            info!("Received new evaluation notification");
-            trace!("[lock] Attempting to get lock on data!");
-            let locked = data.lock().await;
-            trace!("[lock] Got lock on data!");
+    /// Stops evaluations and builds whose jobset or job was deleted
+    pub async fn cancel_deleted(&self) {
+        let db = &self.data.db;
 
-            let mut diff = JobsetDiff::new();
-            diff.set_state(JobsetState::EvalFailed);
+        for jobset_id in self.data.evaluations.ids() {
+            let jobset = Jobset::get_single(db, jobset_id).await;
 
-            let start = notification.get_started();
-            let end = notification.get_finished();
+            if let Ok(None) = jobset {
+                info!("Cancelling evaluation of deleted jobset: {}", jobset_id);
+                self.data.evaluations.cancel(jobset_id);
+                self.data.release_jobset(jobset_id);
+            }
+        }
+
+        for job_id in self.data.build_manager.active() {
+            let job = Job::get_single(db, job_id).await;
 
-            let duration = end - start;
+            if let Ok(None) = job {
+                info!("Cancelling build of deleted job: {}", job_id);
+                self.data.build_manager.cancel(job_id);
+            }
+        }
+    }
 
-            diff.set_last_checked(Utc::now());
-            diff.set_last_evaluated(Utc::now());
-            diff.set_evaluation_took(duration.num_seconds() as i32);
+    /// Stops the evaluation and the builds of the jobset, its unfinished jobs count as failed
+    pub async fn cancel_jobset(&self, jobset_id: i32) {
+        let db = &self.data.db;
 
-            trace!("[lock] Attempting to get lock on db");
-            let db = locked.db.lock().await;
-            trace!("[lock] Got lock on db!");
+        if self.data.evaluations.cancel(jobset_id) {
+            info!("Cancelled evaluation of jobset: {}", jobset_id);
+            self.data.release_jobset(jobset_id);
 
-            let jobset = Jobset::get_single(&db, notification.jobset_id()).await;
+            match Jobset::get_single(db, jobset_id).await {
+                Ok(Some(mut jobset)) => {
+                    let result = jobset.update_state(db, JobsetState::Idle).await;
 
-            if jobset.is_err() {
-                error!(
-                    "Failed to get jobset from db: {}",
-                    jobset.err().unwrap().to_string()
-                );
-                continue;
+                    if result.is_err() {
+                        error!("Failed to reset jobset: {}", result.err().unwrap());
+                    }
+                }
+                Ok(None) => (),
+                Err(e) => error!("Failed to get jobset: {}", e.to_string()),
             }
+        }
 
-            let jobset = jobset.unwrap();
+        let jobs = Job::get_unfinished_of_jobset(db, jobset_id).await;
 
-            if jobset.is_none() {
-                error!("Failed to find jobset!");
+        if jobs.is_err() {
+            error!(
+                "Failed to get unfinished jobs of jobset {}: {}",
+                jobset_id,
+                jobs.err().unwrap().to_string()
+            );
+            return;
+        }
+
+        for mut job in jobs.unwrap() {
+            let job_id = job.id.unwrap();
+
+            if self.data.build_manager.cancel(job_id) {
+                info!("Cancelled build: {}", job.derivation_path);
+            }
+
+            let mut diff = JobDiff::new();
+            diff.state = Some(JobState::Failed);
+            diff.finished = Some(Utc::now());
+
+            let result = job.update_job(db, diff).await;
+
+            if result.is_err() {
+                error!("Failed to update job: {}", result.err().unwrap());
                 continue;
             }
 
-            let mut jobset = jobset.unwrap();
+            self.data.statuses.on_job_finished(&job).await;
+        }
+    }
 
-            if !notification.is_successful() {
-                diff.set_error_message(notification.get_err().unwrap().to_string());
+    /// Builds the failed job again
+    pub async fn restart_job(&self, job_id: i32) {
+        let db = &self.data.db;
 
-                let result = jobset.update_jobset(&db, diff).await;
+        let mut job = match Job::get_single(db, job_id).await {
+            Ok(Some(job)) => job,
+            Ok(None) => {
+                warn!("Job {} does not exist anymore", job_id);
+                return;
+            }
+            Err(e) => {
+                error!("Failed to get job: {}", e.to_string());
+                return;
+            }
+        };
 
-                if result.is_err() {
-                    error!(
-                        "Failed to update jobset: {}",
-                        result.err().unwrap().to_string()
-                    );
-                    continue;
-                }
+        match job.restart(db).await {
+            Ok(true) => (),
+            Ok(false) => {
+                warn!("Job {} did not fail, not restarting it", job_id);
+                return;
+            }
+            Err(e) => {
+                error!("Failed to restart job: {}", e.to_string());
+                return;
+            }
+        }
+
+        info!("Restarting build: {}", job.derivation_path);
+
+        self.data.statuses.on_job_restarted(&job).await;
+
+        self.data
+            .build_manager
+            .queue(job.derivation_path.clone(), job_id)
+            .await;
+    }
+
+    /// Prunes old evaluations every `interval`, whenever nothing is being evaluated or built
+    pub async fn run_pruning(&self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
 
+        loop {
+            ticker.tick().await;
+
+            if !self.data.is_idle() || !self.data.holds_lease().await {
                 continue;
             }
 
-            diff.set_state(JobsetState::Idle);
+            let data = self.data.clone();
+
+            let result = self.data.pruner.run(move || data.is_idle()).await;
+
+            if result.is_err() {
+                error!("Failed to prune: {}", result.err().unwrap());
+            }
+        }
+    }
+
+    async fn on_eval_done(
+        mut receiver: UnboundedReceiver<EvalDoneNotification>,
+        data: CoordinatorData,
+    ) {
+        while let Some(notification) = receiver.recv().await {
+            let jobset_id = notification.jobset_id();
+
+            Coordinator::store_evaluation(&data, notification).await;
+
+            // only now another evaluation of the jobset may start
+            data.release_jobset(jobset_id);
+        }
+    }
 
-            let result = jobset.update_jobset(&db, diff).await;
+    async fn store_evaluation(data: &CoordinatorData, mut notification: EvalDoneNotification) {
+        let db = &data.db;
+
+        info!("Received new evaluation notification");
+
+        let mut diff = JobsetDiff::new();
+        diff.set_state(JobsetState::EvalFailed);
+
+        let start = notification.get_started();
+        let end = notification.get_finished();
+
+        let duration = end - start;
+
+        diff.set_last_checked(Utc::now());
+        diff.set_last_evaluated(Utc::now());
+        diff.set_evaluation_took(duration.num_seconds() as i32);
+
+        if !data.holds_lease().await {
+            return;
+        }
+
+        data.metrics.evaluation_finished(
+            notification.jobset_id(),
+            notification.is_successful(),
+            duration.to_std().unwrap_or_default(),
+        );
+
+        let jobset = Jobset::get_single(db, notification.jobset_id()).await;
+
+        if jobset.is_err() {
+            error!(
+                "Failed to get jobset from db: {}",
+                jobset.err().unwrap().to_string()
+            );
+            return;
+        }
+
+        let jobset = jobset.unwrap();
+
+        if jobset.is_none() {
+            error!("Failed to find jobset!");
+            return;
+        }
+
+        let mut jobset = jobset.unwrap();
+
+        if !notification.is_successful() {
+            diff.set_error_message(notification.get_err().unwrap().to_string());
+
+            let result = jobset.update_jobset(db, diff).await;
 
             if result.is_err() {
                 error!(
                     "Failed to update jobset: {}",
                     result.err().unwrap().to_string()
                 );
-                continue;
+                return;
             }
 
-            let mut evaluation = crate::models::Evaluation::new(jobset.id.unwrap());
+            data.statuses
+                .on_evaluation_failed(&jobset, notification.get_revision())
+                .await;
+
+            data.notifications
+                .on_jobset_errored(
+                    &jobset,
+                    notification.get_revision(),
+                    notification.get_err().unwrap(),
+                )
+                .await;
+
+            return;
+        }
+
+        diff.set_state(JobsetState::Idle);
+
+        let result = jobset.update_jobset(db, diff).await;
+
+        if result.is_err() {
+            error!(
+                "Failed to update jobset: {}",
+                result.err().unwrap().to_string()
+            );
+            return;
+        }
+
+        let mut jobs = notification.get_jobs_copy().unwrap();
+
+        // aggregates name their constituents by output path, derivation path or attribute name
+        let mut by_name: HashMap<String, usize> = HashMap::new();
+        let mut aggregates: Vec<(usize, Vec<String>)> = Vec::new();
 
-            let result = evaluation.add_to_db(&db).await;
+        for (index, job) in jobs.iter_mut().enumerate() {
+            by_name.insert(job.derivation_path.clone(), index);
+            by_name.insert(job.attribute_name.clone(), index);
 
+            let result = DrvBasic::get_derivation(&job.derivation_path).await;
             if result.is_err() {
-                error!(
-                    "Failed to add evaluation: {}",
-                    result.err().unwrap().to_string()
-                );
+                error!("Failed to get derivation path: {}", result.err().unwrap());
                 continue;
             }
 
-            let mut jobs = notification.get_jobs_copy().unwrap();
+            let result = result.unwrap();
 
-            for job in jobs.iter_mut() {
-                let result = DrvBasic::get_derivation(&job.derivation_path).await;
-                if result.is_err() {
-                    error!("Failed to get derivation path: {}", result.err().unwrap());
-                    continue;
-                }
+            by_name.insert(result.drv_path.clone(), index);
 
-                let result = result.unwrap();
+            if result.aggregate {
+                job.aggregate = true;
+                aggregates.push((index, result.constituents));
+            }
 
-                job.derivation_path = result.drv_path;
+            job.derivation_path = result.drv_path;
 
-                if job.attribute_name == "" {
-                    job.attribute_name = result.name;
-                }
+            if job.attribute_name.is_empty() {
+                job.attribute_name = result.name;
             }
 
-            for job in jobs.iter_mut() {
-                let result = job.add_to_db(&db).await;
-                if result.is_err() {
-                    error!("Failed to add derivation to db!");
-                    continue;
-                }
+            job.state = JobState::Building;
+        }
 
-                let mut diff = JobDiff::new();
-                diff.state = Some(JobState::Building);
-                let result = job.update_job(&*db, diff).await;
+        if !data.holds_lease().await {
+            return;
+        }
 
-                if result.is_err() {
-                    error!("Failed to update job: {}", result.err().unwrap());
-                    continue;
+        let mut constituents = Vec::new();
+
+        for (aggregate, names) in aggregates {
+            for name in names {
+                match by_name.get(&name) {
+                    Some(index) if *index != aggregate => constituents.push((aggregate, *index)),
+                    Some(_) => (),
+                    None => warn!(
+                        "Constituent {} of {} is not a job of the evaluation",
+                        name, jobs[aggregate].attribute_name
+                    ),
                 }
-
-                locked
-                    .build_manager
-                    .lock()
-                    .await
-                    .queue(job.derivation_path.clone(), job.id.unwrap())
-                    .await;
             }
         }
+
+        let mut evaluation = crate::models::Evaluation::new(jobset.id.unwrap());
+        evaluation.flake = notification.get_flake().map(str::to_string);
+        evaluation.revision = notification.get_revision().map(str::to_string);
+
+        // the evaluation and all of its jobs go in together or not at all
+        let result = evaluation.add_with_jobs(db, &mut jobs, &constituents).await;
+
+        if result.is_err() {
+            error!(
+                "Failed to add evaluation: {}",
+                result.err().unwrap().to_string()
+            );
+            return;
+        }
+
+        for job in jobs.iter() {
+            data.build_manager
+                .queue(job.derivation_path.clone(), job.id.unwrap())
+                .await;
+        }
+
+        data.statuses
+            .on_evaluation_created(evaluation.id.unwrap())
+            .await;
+
+        // older evaluations may have aged out of the jobset's retention policy
+        let result = data.gc_roots.sync().await;
+
+        if result.is_err() {
+            error!("Failed to sync gc roots: {}", result.err().unwrap());
+        }
     }
 
-    async fn on_build_done(
-        mut reciever: UnboundedReceiver<BuildResult>,
-        data: Arc<Mutex<CoordinatorData>>,
-    ) {
+    async fn on_build_done(mut reciever: UnboundedReceiver<BuildResult>, data: CoordinatorData) {
+        let db = &data.db;
+
         info!("Waiting  for build_done messages");
         while let Some(message) = reciever.recv().await {
             info!("Build done: {}", message.path);
 
-            trace!("[lock] Attempts to get data lock");
-            let locked = data.lock().await;
-            trace!("[lock] Got data lock");
+            if !data.holds_lease().await {
+                continue;
+            }
 
-            trace!("[lock] Attempts to get db lock");
-            {
-                let db = locked.db.lock().await;
-                let job = Job::get_single(&*db, message.id).await;
+            let job = Job::get_single(db, message.id).await;
 
-                if job.is_err() {
-                    error!("Failed to get job: {}", job.err().unwrap());
-                    continue;
-                }
+            if job.is_err() {
+                error!("Failed to get job: {}", job.err().unwrap());
+                continue;
+            }
 
-                let job = job.unwrap();
-                if job.is_none() {
-                    error!("Failed to find job!");
-                    continue;
-                }
-                let mut job = job.unwrap();
+            let job = job.unwrap();
+            if job.is_none() {
+                error!("Failed to find job!");
+                continue;
+            }
+            let mut job = job.unwrap();
+
+            match crate::models::Evaluation::get_single(db, job.evaluation_id).await {
+                Ok(Some(evaluation)) => data
+                    .metrics
+                    .build_finished(evaluation.jobset_id, message.successful),
+                Ok(None) => (),
+                Err(e) => error!("Failed to get evaluation of job: {}", e),
+            }
 
-                let mut diff = JobDiff::new();
-                diff.state = Some(JobState::Done);
-                diff.finished = Some(Utc::now());
+            let state = match message.successful {
+                true if job.aggregate => Coordinator::aggregate_state(&data, &job).await,
+                true => JobState::Done,
+                false => JobState::Failed,
+            };
 
-                let result = job.update_job(&*db, diff).await;
+            let mut diff = JobDiff::new();
+            diff.state = Some(state);
+            diff.finished = Some(Utc::now());
+
+            let result = job.update_job(db, diff).await;
+
+            if result.is_err() {
+                error!("Failed to update job: {}", result.err().unwrap());
+                continue;
+            }
+
+            if message.successful {
+                Coordinator::on_build_succeeded(&data, message).await;
+            }
+
+            if matches!(job.state, JobState::Done) {
+                data.channels.on_job_finished(&job).await;
+            }
+
+            Coordinator::resolve_aggregates(&data, job.id.unwrap()).await;
+
+            // after the aggregates, they may be the last jobs of the evaluation
+            data.statuses.on_job_finished(&job).await;
+
+            data.notifications.on_job_finished(&job).await;
+            data.notifications
+                .on_evaluation_progress(job.evaluation_id)
+                .await;
+        }
+    }
+
+    async fn on_build_succeeded(data: &CoordinatorData, message: BuildResult) {
+        let db = &data.db;
+
+        // the binary cache may only serve what is in the closure of a build
+        let closure = query_closure(&message.outputs).await;
+
+        if closure.is_err() {
+            error!(
+                "Failed to get closure of {}: {}",
+                message.path,
+                closure.err().unwrap()
+            );
+            return;
+        }
+
+        let result =
+            JobPath::add_closure(db, message.id, &message.outputs, &closure.unwrap()).await;
+
+        if result.is_err() {
+            error!("Failed to record job paths: {}", result.err().unwrap());
+        }
 
-                if result.is_err() {
-                    error!("Failed to update job: {}", result.err().unwrap());
-                    continue;
+        add_build_products(db, message.id, message.outputs.clone()).await;
+
+        data.gc_roots.add(message.id, &message.outputs).await;
+
+        data.uploader.upload_job(message.id, message.outputs).await;
+    }
+
+    /// What a built aggregate is, depending on its constituents
+    async fn aggregate_state(data: &CoordinatorData, aggregate: &Job) -> JobState {
+        let constituents = Job::get_constituents(&data.db, aggregate.id.unwrap()).await;
+
+        if constituents.is_err() {
+            error!(
+                "Failed to get constituents of {}: {}",
+                aggregate.attribute_name,
+                constituents.err().unwrap()
+            );
+            return JobState::Waiting;
+        }
+
+        Job::aggregate_state(&constituents.unwrap())
+    }
+
+    /// Settles the waiting aggregates of the job now that it finished,
+    /// and in turn the aggregates those are constituents of.
+    async fn resolve_aggregates(data: &CoordinatorData, job_id: i32) {
+        let mut finished = vec![job_id];
+
+        while let Some(job_id) = finished.pop() {
+            let aggregates = Job::get_waiting_aggregates(&data.db, job_id).await;
+
+            if aggregates.is_err() {
+                error!(
+                    "Failed to get aggregates of job {}: {}",
+                    job_id,
+                    aggregates.err().unwrap()
+                );
+                continue;
+            }
+
+            for mut aggregate in aggregates.unwrap() {
+                if Coordinator::resolve_aggregate(data, &mut aggregate).await {
+                    finished.push(aggregate.id.unwrap());
                 }
             }
-            trace!("[lock] Released db lock");
         }
     }
+
+    /// Returns true if the aggregate is not waiting anymore
+    async fn resolve_aggregate(data: &CoordinatorData, aggregate: &mut Job) -> bool {
+        let state = Coordinator::aggregate_state(data, aggregate).await;
+
+        if matches!(state, JobState::Waiting) {
+            return false;
+        }
+
+        info!("Aggregate {} is {:?}", aggregate.attribute_name, state);
+
+        let mut diff = JobDiff::new();
+        diff.state = Some(state);
+        diff.finished = Some(Utc::now());
+
+        let result = aggregate.update_job(&data.db, diff).await;
+
+        if result.is_err() {
+            error!("Failed to update aggregate: {}", result.err().unwrap());
+            return false;
+        }
+
+        if matches!(aggregate.state, JobState::Done) {
+            data.channels.on_job_finished(aggregate).await;
+        }
+
+        data.notifications.on_job_finished(aggregate).await;
+
+        true
+    }
 }
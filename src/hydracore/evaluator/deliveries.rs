@@ -0,0 +1,287 @@
+use std::{
+    fmt::Display,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use tokio::task::AbortHandle;
+use tracing::{error, info, warn};
+
+use crate::models::{DeliveryState, NotificationDelivery, StatusDelivery, Upload, UploadState};
+
+use super::super::db::{DBError, DB};
+
+/// Where a delivery stands, every kind of delivery stores it in its own state
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Progress {
+    /// An attempt is running
+    Sending,
+    /// The last attempt failed, another one follows
+    Retrying,
+    Done,
+    /// Every attempt failed
+    Failed,
+}
+
+/// Something the coordinator sends elsewhere. It is stored, so another coordinator can continue it.
+pub trait Delivery {
+    /// Names the delivery in the log
+    fn describe(&self) -> String;
+
+    fn attempts(&self) -> i32;
+
+    fn set_attempts(&mut self, attempts: i32);
+
+    fn set_progress(&mut self, progress: Progress);
+
+    fn set_error_message(&mut self, error_message: Option<String>);
+
+    fn set_finished(&mut self, finished: DateTime<Utc>);
+
+    async fn update(&self, db: &DB) -> Result<(), DBError>;
+
+    /// A later delivery makes this one pointless, it is not tried again
+    async fn is_replaced(&self, _db: &DB) -> Result<bool, DBError> {
+        Ok(false)
+    }
+}
+
+/// Tries a delivery until an attempt succeeds or it runs out of attempts,
+/// waiting a little longer after every failed attempt.
+#[derive(Clone)]
+pub struct Deliverer {
+    db: DB,
+    max_attempts: i32,
+    retry_delay: Duration,
+    /// The running deliveries, they stop with the coordinator
+    tasks: Arc<Mutex<Vec<AbortHandle>>>,
+}
+
+impl Deliverer {
+    pub fn new(db: DB, max_attempts: i32) -> Self {
+        Deliverer {
+            db,
+            max_attempts,
+            retry_delay: Duration::from_secs(30),
+            tasks: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Runs the delivery in the background, the coordinator goes on meanwhile
+    pub fn spawn<F>(&self, delivery: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let handle = tokio::spawn(delivery);
+
+        let mut tasks = self.tasks.lock().unwrap();
+
+        tasks.retain(|task| !task.is_finished());
+        tasks.push(handle.abort_handle());
+    }
+
+    /// Aborts the running deliveries after the lease was lost.
+    /// They are left unfinished, so the new leader resumes them instead of both sending them.
+    pub fn stop(&self) {
+        for task in self.tasks.lock().unwrap().drain(..) {
+            task.abort();
+        }
+    }
+
+    /// Runs the attempts, continues after the attempts a previous coordinator already made.
+    /// Returns whether the delivery succeeded.
+    pub async fn run<D, F, Fut, E>(&self, delivery: &mut D, mut attempt: F) -> bool
+    where
+        D: Delivery,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<(), E>>,
+        E: Display,
+    {
+        while delivery.attempts() < self.max_attempts {
+            if self.is_replaced(delivery).await {
+                info!("{} was replaced by a later one", delivery.describe());
+                delivery.set_error_message(Some("Replaced by a later delivery".to_string()));
+                delivery.set_finished(Utc::now());
+                self.set_progress(delivery, Progress::Failed).await;
+                return false;
+            }
+
+            delivery.set_attempts(delivery.attempts() + 1);
+
+            self.set_progress(delivery, Progress::Sending).await;
+
+            match attempt().await {
+                Ok(()) => {
+                    info!("{} succeeded", delivery.describe());
+                    delivery.set_error_message(None);
+                    delivery.set_finished(Utc::now());
+                    self.set_progress(delivery, Progress::Done).await;
+                    return true;
+                }
+                Err(e) => {
+                    warn!(
+                        "{} failed (attempt {}): {}",
+                        delivery.describe(),
+                        delivery.attempts(),
+                        e
+                    );
+                    delivery.set_error_message(Some(e.to_string()));
+                }
+            }
+
+            if delivery.attempts() < self.max_attempts {
+                self.set_progress(delivery, Progress::Retrying).await;
+                tokio::time::sleep(self.retry_delay * delivery.attempts() as u32).await;
+            }
+        }
+
+        delivery.set_finished(Utc::now());
+        self.set_progress(delivery, Progress::Failed).await;
+
+        false
+    }
+
+    async fn is_replaced<D: Delivery>(&self, delivery: &D) -> bool {
+        let result = delivery.is_replaced(&self.db).await;
+
+        // better send it once too often than not at all
+        if result.is_err() {
+            error!(
+                "Failed to check {}: {}",
+                delivery.describe(),
+                result.err().unwrap().to_string()
+            );
+            return false;
+        }
+
+        result.unwrap()
+    }
+
+    async fn set_progress<D: Delivery>(&self, delivery: &mut D, progress: Progress) {
+        delivery.set_progress(progress);
+
+        let result = delivery.update(&self.db).await;
+
+        if result.is_err() {
+            error!(
+                "Failed to update {}: {}",
+                delivery.describe(),
+                result.err().unwrap().to_string()
+            );
+        }
+    }
+}
+
+impl Delivery for Upload {
+    fn describe(&self) -> String {
+        format!("Upload of job {} to {}", self.job_id, self.url)
+    }
+
+    fn attempts(&self) -> i32 {
+        self.attempts
+    }
+
+    fn set_attempts(&mut self, attempts: i32) {
+        self.attempts = attempts;
+    }
+
+    fn set_progress(&mut self, progress: Progress) {
+        self.state = match progress {
+            Progress::Sending => UploadState::Uploading,
+            Progress::Retrying => UploadState::Retrying,
+            Progress::Done => UploadState::Done,
+            Progress::Failed => UploadState::Failed,
+        };
+    }
+
+    fn set_error_message(&mut self, error_message: Option<String>) {
+        self.error_message = error_message;
+    }
+
+    fn set_finished(&mut self, finished: DateTime<Utc>) {
+        self.finished = Some(finished);
+    }
+
+    async fn update(&self, db: &DB) -> Result<(), DBError> {
+        Upload::update(self, db).await
+    }
+}
+
+impl Delivery for NotificationDelivery {
+    fn describe(&self) -> String {
+        format!("Delivery of {} to {}", self.event, self.target)
+    }
+
+    fn attempts(&self) -> i32 {
+        self.attempts
+    }
+
+    fn set_attempts(&mut self, attempts: i32) {
+        self.attempts = attempts;
+    }
+
+    fn set_progress(&mut self, progress: Progress) {
+        self.state = match progress {
+            Progress::Sending => DeliveryState::Sending,
+            Progress::Retrying => DeliveryState::Retrying,
+            Progress::Done => DeliveryState::Delivered,
+            Progress::Failed => DeliveryState::Failed,
+        };
+    }
+
+    fn set_error_message(&mut self, error_message: Option<String>) {
+        self.error_message = error_message;
+    }
+
+    fn set_finished(&mut self, finished: DateTime<Utc>) {
+        self.finished = Some(finished);
+    }
+
+    async fn update(&self, db: &DB) -> Result<(), DBError> {
+        NotificationDelivery::update(self, db).await
+    }
+}
+
+impl Delivery for StatusDelivery {
+    fn describe(&self) -> String {
+        format!(
+            "Status of {} at {} ({})",
+            self.context, self.revision, self.status
+        )
+    }
+
+    fn attempts(&self) -> i32 {
+        self.attempts
+    }
+
+    fn set_attempts(&mut self, attempts: i32) {
+        self.attempts = attempts;
+    }
+
+    fn set_progress(&mut self, progress: Progress) {
+        self.state = match progress {
+            Progress::Sending => DeliveryState::Sending,
+            Progress::Retrying => DeliveryState::Retrying,
+            Progress::Done => DeliveryState::Delivered,
+            Progress::Failed => DeliveryState::Failed,
+        };
+    }
+
+    fn set_error_message(&mut self, error_message: Option<String>) {
+        self.error_message = error_message;
+    }
+
+    fn set_finished(&mut self, finished: DateTime<Utc>) {
+        self.finished = Some(finished);
+    }
+
+    async fn update(&self, db: &DB) -> Result<(), DBError> {
+        StatusDelivery::update(self, db).await
+    }
+
+    async fn is_replaced(&self, db: &DB) -> Result<bool, DBError> {
+        StatusDelivery::is_replaced(self, db).await
+    }
+}
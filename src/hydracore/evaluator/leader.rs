@@ -0,0 +1,190 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use sqlx::query_as;
+use tracing::{debug, error, info};
+
+use super::super::db::{with_db, DBError, DB};
+
+/// Name of the lease the scheduler and queue runner run under
+pub const COORDINATOR_LEASE: &str = "coordinator";
+
+/// A lease in the database. Only the holder of a lease may act on it.
+/// The token is incremented every time the lease changes hands and works as a fencing token:
+/// writes of an instance that lost the lease can be recognised by the outdated token.
+#[derive(sqlx::FromRow, Clone, Debug)]
+pub struct Lease {
+    pub name: String,
+    pub holder: String,
+    pub token: i64,
+    pub expires: DateTime<Utc>,
+}
+
+impl Lease {
+    /// Acquires the lease if it is free, expired or already ours.
+    pub async fn try_acquire(
+        db: &DB,
+        name: &str,
+        holder: &str,
+        ttl: Duration,
+    ) -> Result<Option<Lease>, DBError> {
+        let now = Utc::now();
+        let expires = now + ttl;
+
+        let result = with_db!(db, |pool| query_as::<_, Lease>(
+            "
+                insert into Leases
+                    (name, holder, token, expires)
+                values
+                    ($1, $2, 1, $3)
+                on conflict (name) do update
+                    set holder = excluded.holder,
+                        token = Leases.token + 1,
+                        expires = excluded.expires
+                    where Leases.expires < $4 or Leases.holder = excluded.holder
+                returning *
+            ",
+        )
+        .bind(name)
+        .bind(holder)
+        .bind(expires)
+        .bind(now)
+        .fetch_optional(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    /// Extends the lease. Returns false if the lease was taken over in the meantime.
+    pub async fn renew(&mut self, db: &DB, ttl: Duration) -> Result<bool, DBError> {
+        let expires = Utc::now() + ttl;
+
+        let result = with_db!(db, |pool| sqlx::query(
+            "
+                update Leases
+                set expires = $1
+                where name = $2 and holder = $3 and token = $4
+            ",
+        )
+        .bind(expires)
+        .bind(&self.name)
+        .bind(&self.holder)
+        .bind(self.token)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected()))
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        if result != 1 {
+            return Ok(false);
+        }
+
+        self.expires = expires;
+
+        Ok(true)
+    }
+
+    /// Checks the fencing token, has to be called before writing results.
+    pub async fn is_held(&self, db: &DB) -> Result<bool, DBError> {
+        let result: Option<(i64,)> = with_db!(db, |pool| query_as(
+            "
+                select token
+                from Leases
+                where name = $1 and holder = $2 and token = $3
+            ",
+        )
+        .bind(&self.name)
+        .bind(&self.holder)
+        .bind(self.token)
+        .fetch_optional(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result.is_some())
+    }
+}
+
+/// Makes sure only one ladon instance acts as the coordinator at a time.
+pub struct LeaderElection {
+    db: DB,
+    instance_id: String,
+    ttl: Duration,
+}
+
+impl LeaderElection {
+    pub fn new(db: DB, instance_id: String, ttl: Duration) -> Self {
+        LeaderElection {
+            db,
+            instance_id,
+            ttl,
+        }
+    }
+
+    fn heartbeat(&self) -> Duration {
+        self.ttl / 3
+    }
+
+    /// Waits in standby until this instance holds the coordinator lease.
+    pub async fn wait_for_leadership(&self) -> Lease {
+        info!(
+            "Instance '{}' waiting for the coordinator lease",
+            self.instance_id
+        );
+
+        let mut interval = tokio::time::interval(self.heartbeat());
+
+        loop {
+            interval.tick().await;
+
+            let result =
+                Lease::try_acquire(&self.db, COORDINATOR_LEASE, &self.instance_id, self.ttl).await;
+
+            match result {
+                Err(e) => error!("Failed to acquire lease: {}", e.to_string()),
+                Ok(None) => debug!("Coordinator lease is held by another instance"),
+                Ok(Some(lease)) => {
+                    info!(
+                        "Instance '{}' is now the coordinator (token {})",
+                        self.instance_id, lease.token
+                    );
+                    return lease;
+                }
+            }
+        }
+    }
+
+    /// Renews the lease and returns once it is lost.
+    /// Builds and evaluations still running here are taken over by the new leader,
+    /// their results are rejected through the fencing token.
+    pub async fn keep_alive(&self, mut lease: Lease) {
+        let mut interval = tokio::time::interval(self.heartbeat());
+
+        loop {
+            interval.tick().await;
+
+            let result = lease.renew(&self.db, self.ttl).await;
+
+            match result {
+                Ok(true) => {
+                    debug!("Renewed coordinator lease until {}", lease.expires);
+                    continue;
+                }
+                Ok(false) => {
+                    error!("Lost the coordinator lease to another instance");
+                }
+                Err(e) => {
+                    error!("Failed to renew coordinator lease: {}", e.to_string());
+
+                    if Utc::now() < lease.expires {
+                        continue;
+                    }
+
+                    error!("Coordinator lease expired");
+                }
+            }
+
+            return;
+        }
+    }
+}
@@ -1,6 +1,18 @@
 pub use coordinator::*;
+pub use leader::*;
+pub use nix::flake::{split_repository_url, GitSource};
+pub use nix::store::*;
+pub use queue_runner::*;
 
 mod nix;
 
+mod build_products;
+mod channels;
 mod coordinator;
+mod deliveries;
+mod leader;
 mod notifications;
+mod queue_runner;
+mod statuses;
+mod tasks;
+mod uploader;
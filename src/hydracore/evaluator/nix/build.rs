@@ -1,5 +1,5 @@
 use core::{error, fmt};
-use std::{process::Stdio, sync::Arc};
+use std::{fs::File, process::Stdio, sync::Arc, time::Instant};
 
 use tokio::{
     process::Command,
@@ -7,11 +7,13 @@ use tokio::{
         mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
         Mutex, Semaphore,
     },
-    task::JoinHandle,
+    task::{AbortHandle, JoinHandle},
 };
 use tracing::{error, info};
 
-use crate::hydracore::Coordinator;
+use crate::hydracore::{DataDir, Metrics};
+
+use super::super::tasks::TaskRegistry;
 
 #[derive(Debug)]
 pub struct BuildError {
@@ -36,6 +38,8 @@ pub struct BuildResult {
     pub successful: bool,
     pub id: i32,
     pub path: String,
+    /// Output paths, empty if the build failed
+    pub outputs: Vec<String>,
 }
 
 struct QueueItem {
@@ -48,30 +52,103 @@ pub type BuildTx = UnboundedSender<BuildResult>;
 struct BuildSettings {
     max_builders: usize,
     build_tx: BuildTx,
+    data_dir: DataDir,
+    builds: TaskRegistry,
+    metrics: Metrics,
+    /// Which machine the builds run on, for the metrics
+    machine: String,
+}
+
+/// Counts a build as queued and then as running in the metrics,
+/// dropping it also keeps the counts right for cancelled builds.
+struct BuildGauge {
+    metrics: Metrics,
+    machine: String,
+    started: Option<Instant>,
+}
+
+impl BuildGauge {
+    fn new(metrics: Metrics, machine: String) -> Self {
+        metrics.build_queued();
+
+        BuildGauge {
+            metrics,
+            machine,
+            started: None,
+        }
+    }
+
+    fn start(&mut self) {
+        self.metrics.build_dequeued();
+        self.metrics.build_started(&self.machine);
+        self.started = Some(Instant::now());
+    }
+}
+
+impl Drop for BuildGauge {
+    fn drop(&mut self) {
+        match self.started {
+            Some(started) => self.metrics.build_stopped(&self.machine, started.elapsed()),
+            None => self.metrics.build_dequeued(),
+        }
+    }
 }
 
+#[derive(Clone)]
 pub struct BuildManager {
     queue: Arc<UnboundedSender<QueueItem>>,
+    builds: TaskRegistry,
+    consumer: AbortHandle,
 }
 
 impl BuildManager {
-    pub fn new(build_tx: BuildTx, max_builders: usize) -> Self {
+    pub fn new(
+        build_tx: BuildTx,
+        max_builders: usize,
+        data_dir: DataDir,
+        metrics: Metrics,
+        machine: String,
+    ) -> Self {
         let (sender, receiver) = unbounded_channel::<QueueItem>();
 
+        let builds = TaskRegistry::default();
+
         let settings = BuildSettings {
             max_builders,
             build_tx,
+            data_dir,
+            builds: builds.clone(),
+            metrics,
+            machine,
         };
 
-        tokio::spawn(async move {
+        let consumer = tokio::spawn(async move {
             BuildManager::queue_consumer(receiver, settings).await;
         });
 
         BuildManager {
             queue: Arc::new(sender),
+            builds,
+            consumer: consumer.abort_handle(),
         }
     }
 
+    /// Job ids of the builds which are queued or running
+    pub fn active(&self) -> Vec<i32> {
+        self.builds.ids()
+    }
+
+    /// Stops the build of the job, returns false if it wasn't queued or running
+    pub fn cancel(&self, id: i32) -> bool {
+        self.builds.cancel(id)
+    }
+
+    /// Stops taking new builds and aborts the queued and running ones
+    pub fn stop(&self) {
+        self.consumer.abort();
+        self.builds.cancel_all();
+    }
+
     pub async fn queue(&self, path: String, id: i32) {
         self.queue.clone().send(QueueItem { path, drv_id: id });
     }
@@ -81,20 +158,30 @@ impl BuildManager {
         while let Some(item) = receiver.recv().await {
             let semaphore_clone = semaphore.clone();
             let build_tx_clone = settings.build_tx.clone();
-            tokio::spawn(async move {
+            let log = settings.data_dir.build_log(item.drv_id);
+            let id = item.drv_id;
+            let mut gauge = BuildGauge::new(settings.metrics.clone(), settings.machine.clone());
+            let handle = tokio::spawn(async move {
                 let ticket = semaphore_clone.acquire().await.unwrap();
+                gauge.start();
                 info!("Queuing: {}", item.path);
-                let result = BuildManager::realise(&item.path).await;
+                let result = BuildManager::realise(&item.path, &log).await;
                 drop(ticket);
+                drop(gauge);
 
                 let mut message = BuildResult {
                     id: item.drv_id,
                     successful: true,
                     path: item.path,
+                    outputs: Vec::new(),
                 };
-                if result.is_err() {
-                    error!("Failed to realise store path: {}", result.err().unwrap());
-                    message.successful = false;
+
+                match result {
+                    Ok(outputs) => message.outputs = outputs,
+                    Err(e) => {
+                        error!("Failed to realise store path: {}", e);
+                        message.successful = false;
+                    }
                 }
 
                 let result = build_tx_clone.send(message);
@@ -106,32 +193,49 @@ impl BuildManager {
                     )
                 }
             });
+
+            settings.builds.insert(id, handle.abort_handle());
         }
     }
 
-    async fn realise(path: &str) -> Result<(), BuildError> {
-        let mut command = Command::new("nix-store")
+    /// Builds the derivation and returns its output paths.
+    /// The build output of nix goes into the build log.
+    async fn realise(path: &str, log: &std::path::Path) -> Result<Vec<String>, BuildError> {
+        if let Some(dir) = log.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| BuildError::new(e.to_string()))?;
+        }
+
+        let stderr = File::create(log).map_err(|e| BuildError::new(e.to_string()))?;
+
+        let command = Command::new("nix-store")
             .arg("--realise")
             .arg(path)
             .arg("-j")
             .arg("1")
-            .stderr(Stdio::piped())
+            .stderr(Stdio::from(stderr))
             .stdout(Stdio::piped())
+            .kill_on_drop(true)
             .spawn()
             .map_err(|e| BuildError::new(e.to_string()))?;
 
         let result = command
-            .wait()
+            .wait_with_output()
             .await
             .map_err(|e| BuildError::new(e.to_string()))?;
 
-        if !result.success() {
+        if !result.status.success() {
             return Err(BuildError::new(format!(
                 "Failed to realise store path: {}",
                 path
             )));
         }
 
-        Ok(())
+        let outputs = String::from_utf8_lossy(&result.stdout)
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect();
+
+        Ok(outputs)
     }
 }
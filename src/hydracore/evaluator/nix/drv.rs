@@ -80,6 +80,10 @@ async fn run_nix_derivation_show(path: &str) -> Result<(String, String), Derivat
 pub struct DrvBasic {
     pub drv_path: String,
     pub name: String,
+    /// Hydra style aggregate, marked with `_hydraAggregate`
+    pub aggregate: bool,
+    /// Output paths, derivation paths or attribute names of the jobs an aggregate gates on
+    pub constituents: Vec<String>,
 }
 
 impl DrvBasic {
@@ -110,11 +114,26 @@ impl DrvBasic {
 
         let (drv_path, value) = test.remove(0);
 
-        let value = value.get("name").unwrap().as_str().unwrap();
+        let name = value.get("name").unwrap().as_str().unwrap();
+
+        let env = |key: &str| value.get("env").and_then(|env| env.get(key)?.as_str());
+
+        let aggregate = env("_hydraAggregate") == Some("1");
+
+        let constituents = match aggregate {
+            true => env("constituents")
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(str::to_string)
+                .collect(),
+            false => Vec::new(),
+        };
 
         Ok(DrvBasic {
             drv_path,
-            name: value.to_string(),
+            name: name.to_string(),
+            aggregate,
+            constituents,
         })
     }
 }
@@ -166,6 +185,8 @@ impl DrvDepTree {
         let current = DrvBasic {
             drv_path: derivation_path.to_string(),
             name: value.get("name").unwrap().as_str().unwrap().to_string(),
+            aggregate: false,
+            constituents: Vec::new(),
         };
 
         debug!("Done generating build plan for '{}'", derivation_path);
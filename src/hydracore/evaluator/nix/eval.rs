@@ -6,7 +6,7 @@ use std::{
 use axum::Error;
 use chrono::{DateTime, Utc};
 use serde_json::Value;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 use tokio::{
     process::Command,
@@ -16,7 +16,10 @@ use tokio::{
 
 use crate::models::{Job, Jobset, JobsetID};
 
-use super::super::notifications::EvalDoneNotification;
+use super::{
+    super::notifications::EvalDoneNotification,
+    flake::{pin_revision, split_flake_uri, FlakeMetadata},
+};
 
 #[derive(Debug)]
 pub struct EvaluationError {
@@ -47,28 +50,76 @@ impl Evaluation {
     pub async fn new(
         sender: Arc<UnboundedSender<EvalDoneNotification>>,
         jobset: &Jobset,
+        revision: Option<&str>,
     ) -> Result<JoinHandle<()>, EvaluationError> {
         if jobset.id.is_none() {
             return Err(EvaluationError::new("Jobset struct has no id!".to_string()));
         }
         let jobset_id = jobset.id.unwrap();
 
-        info!("Evaluating: {}", jobset.flake);
+        // pushes name the revision they are about, later pushes may have moved the branch already
+        let flake_uri = match revision {
+            Some(revision) => pin_revision(&jobset.flake, revision).unwrap_or_else(|| {
+                warn!(
+                    "Cannot pin {} to revision {}, evaluating the latest one",
+                    jobset.flake, revision
+                );
+                jobset.flake.clone()
+            }),
+            None => jobset.flake.clone(),
+        };
 
-        let process = Command::new("nix")
-            .arg("eval")
-            .arg("--json")
-            .arg("--no-write-lock-file")
-            .arg(&jobset.flake)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .map_err(|e| EvaluationError::new(e.to_string()))?;
+        info!("Evaluating: {}", flake_uri);
 
         let started = Utc::now();
 
         let handle = tokio::spawn(async move {
-            let result = process.wait_with_output().await.unwrap();
+            let mut notification =
+                EvalDoneNotification::new(started, Utc::now(), false, None, None, jobset_id);
+
+            // the flake gets locked first, so the revision is known and channels use the same source
+            let (flake, attribute) = split_flake_uri(&flake_uri);
+
+            let metadata = FlakeMetadata::query(flake).await;
+
+            if metadata.is_err() {
+                notification.set_error(metadata.err().unwrap().to_string());
+
+                let result = sender.send(notification);
+                if result.is_err() {
+                    error!("Failed to send notification");
+                }
+                return;
+            }
+
+            let metadata = metadata.unwrap();
+
+            let locked_uri = match attribute {
+                Some(attribute) => format!("{}#{}", metadata.url, attribute),
+                None => metadata.url.clone(),
+            };
+
+            let process = Command::new("nix")
+                .arg("eval")
+                .arg("--json")
+                .arg("--no-write-lock-file")
+                .arg(&locked_uri)
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn();
+
+            if process.is_err() {
+                notification.set_error(process.err().unwrap().to_string());
+
+                let result = sender.send(notification);
+                if result.is_err() {
+                    error!("Failed to send notification");
+                }
+                return;
+            }
+
+            let result = process.unwrap().wait_with_output().await.unwrap();
             let status = result.status;
 
             let done = Utc::now();
@@ -76,6 +127,9 @@ impl Evaluation {
             let mut notification =
                 EvalDoneNotification::new(started, done, false, None, None, jobset_id);
 
+            // a failed evaluation is still reported for the revision
+            notification.set_flake(metadata.url.clone(), metadata.revision.clone());
+
             if !status.success() {
                 if status.core_dumped() {
                     notification.set_error(format!(
@@ -117,9 +171,11 @@ impl Evaluation {
 
             let derivations = get_derivation_information(&value, jobset_id);
 
-            let notification =
+            let mut notification =
                 EvalDoneNotification::new(started, done, true, None, Some(derivations), jobset_id);
 
+            notification.set_flake(metadata.url, metadata.revision);
+
             let result = sender.send(notification);
             if result.is_err() {
                 error!("Failed to send notification");
@@ -0,0 +1,273 @@
+use std::process::Stdio;
+
+use serde::Deserialize;
+use tokio::process::Command;
+
+use super::eval::EvaluationError;
+
+/// What `nix flake metadata` reports about a flake
+#[derive(Deserialize, Debug, Clone)]
+pub struct FlakeMetadata {
+    /// Locked reference, evaluating it again gives the same result
+    pub url: String,
+    /// Store path of the flake's source
+    pub path: String,
+    /// Git revision, not set for flakes which don't come from git
+    pub revision: Option<String>,
+}
+
+impl FlakeMetadata {
+    pub async fn query(flake: &str) -> Result<Self, EvaluationError> {
+        let result = Command::new("nix")
+            .arg("flake")
+            .arg("metadata")
+            .arg("--json")
+            .arg("--no-write-lock-file")
+            .arg(flake)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .output()
+            .await
+            .map_err(|e| EvaluationError::new(e.to_string()))?;
+
+        if !result.status.success() {
+            return Err(EvaluationError::new(format!(
+                "Failed to get metadata of {}: {}",
+                flake,
+                String::from_utf8_lossy(&result.stderr)
+            )));
+        }
+
+        serde_json::from_slice(&result.stdout).map_err(|e| {
+            EvaluationError::new(format!("Failed to parse metadata of {}: {}", flake, e))
+        })
+    }
+}
+
+/// Splits `<flake>#<attribute>` into the flake and the attribute
+pub fn split_flake_uri(uri: &str) -> (&str, Option<&str>) {
+    match uri.split_once('#') {
+        Some((flake, attribute)) => (flake, Some(attribute)),
+        None => (uri, None),
+    }
+}
+
+/// Where a flake from git lives, to find the jobsets a push belongs to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitSource {
+    /// Host of the forge, lowercase
+    pub host: String,
+    /// Path of the repository on the host, lowercase and without `.git`
+    pub repository: String,
+    /// Branch the flake follows, the default branch if it is not set
+    pub branch: Option<String>,
+}
+
+impl GitSource {
+    /// Understands `github:`, `gitlab:` and `git+` flake references
+    pub fn from_flake(flake: &str) -> Option<Self> {
+        let (flake, _) = split_flake_uri(flake);
+
+        let (flake, query) = match flake.split_once('?') {
+            Some((flake, query)) => (flake, query),
+            None => (flake, ""),
+        };
+
+        let query_ref = query_param(query, "ref").map(|branch| branch.to_string());
+
+        if let Some(url) = flake.strip_prefix("git+") {
+            let (host, repository) = split_repository_url(url)?;
+
+            return Some(GitSource {
+                host,
+                repository,
+                branch: query_ref,
+            });
+        }
+
+        let (default_host, path) = if let Some(path) = flake.strip_prefix("github:") {
+            ("github.com", path)
+        } else if let Some(path) = flake.strip_prefix("gitlab:") {
+            ("gitlab.com", path)
+        } else {
+            return None;
+        };
+
+        let mut segments = path.split('/');
+        let owner = segments.next()?;
+        let name = segments.next()?;
+        let path_ref = segments.next().map(|branch| branch.to_string());
+
+        Some(GitSource {
+            host: query_param(query, "host")
+                .unwrap_or(default_host)
+                .to_lowercase(),
+            repository: normalize_repository(&format!("{}/{}", owner, name)),
+            branch: path_ref.or(query_ref),
+        })
+    }
+
+    /// Whether a push to the repository and branch concerns this source
+    pub fn matches(
+        &self,
+        host: &str,
+        repository: &str,
+        branch: &str,
+        default_branch: &str,
+    ) -> bool {
+        self.host == host
+            && self.repository == repository
+            && self.branch.as_deref().unwrap_or(default_branch) == branch
+    }
+}
+
+/// Host and repository of `https://`, `ssh://` and `git@host:path` urls
+pub fn split_repository_url(url: &str) -> Option<(String, String)> {
+    let url = url.split(['?', '#']).next()?;
+
+    let (host, path) = match url.split_once("://") {
+        Some((_, rest)) => rest.split_once('/')?,
+        // scp like ssh urls
+        None => url.split_once(':')?,
+    };
+
+    let host = host.rsplit('@').next()?;
+    let host = host.split(':').next()?;
+
+    if host.is_empty() || path.is_empty() {
+        return None;
+    }
+
+    Some((host.to_lowercase(), normalize_repository(path)))
+}
+
+fn normalize_repository(path: &str) -> String {
+    let path = path.trim_matches('/');
+
+    path.strip_suffix(".git").unwrap_or(path).to_lowercase()
+}
+
+fn query_param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// The flake reference at the revision, so an evaluation builds exactly what was pushed.
+/// Returns None for flakes which do not come from git.
+pub fn pin_revision(flake_uri: &str, revision: &str) -> Option<String> {
+    let (flake, attribute) = split_flake_uri(flake_uri);
+
+    let (flake, query) = match flake.split_once('?') {
+        Some((flake, query)) => (flake, query),
+        None => (flake, ""),
+    };
+
+    let pinned = if flake.starts_with("git+") {
+        // the git fetcher takes the branch and the revision together
+        let mut params: Vec<String> = query
+            .split('&')
+            .filter(|pair| !pair.is_empty() && !pair.starts_with("rev="))
+            .map(|pair| pair.to_string())
+            .collect();
+
+        params.push(format!("rev={}", revision));
+
+        format!("{}?{}", flake, params.join("&"))
+    } else if flake.starts_with("github:") || flake.starts_with("gitlab:") {
+        // these may not have a branch and a revision at once
+        let (scheme, path) = flake.split_once(':')?;
+        let mut segments = path.split('/');
+        let owner = segments.next()?;
+        let name = segments.next()?;
+
+        let params: Vec<&str> = query
+            .split('&')
+            .filter(|pair| {
+                !pair.is_empty() && !pair.starts_with("ref=") && !pair.starts_with("rev=")
+            })
+            .collect();
+
+        match params.is_empty() {
+            true => format!("{}:{}/{}/{}", scheme, owner, name, revision),
+            false => format!(
+                "{}:{}/{}/{}?{}",
+                scheme,
+                owner,
+                name,
+                revision,
+                params.join("&")
+            ),
+        }
+    } else {
+        return None;
+    };
+
+    Some(match attribute {
+        Some(attribute) => format!("{}#{}", pinned, attribute),
+        None => pinned,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GitSource;
+
+    fn source(host: &str, repository: &str, branch: Option<&str>) -> Option<GitSource> {
+        Some(GitSource {
+            host: host.to_string(),
+            repository: repository.to_string(),
+            branch: branch.map(|branch| branch.to_string()),
+        })
+    }
+
+    #[test]
+    fn github() {
+        assert_eq!(
+            GitSource::from_flake("github:NixOS/Nixpkgs#hydraJobs"),
+            source("github.com", "nixos/nixpkgs", None)
+        );
+        assert_eq!(
+            GitSource::from_flake("github:owner/repo?ref=staging#hydraJobs"),
+            source("github.com", "owner/repo", Some("staging"))
+        );
+        assert_eq!(
+            GitSource::from_flake("github:owner/repo/release"),
+            source("github.com", "owner/repo", Some("release"))
+        );
+    }
+
+    #[test]
+    fn gitlab() {
+        assert_eq!(
+            GitSource::from_flake("gitlab:group/project?ref=main"),
+            source("gitlab.com", "group/project", Some("main"))
+        );
+        assert_eq!(
+            GitSource::from_flake("gitlab:group/project?host=git.Example.org"),
+            source("git.example.org", "group/project", None)
+        );
+    }
+
+    #[test]
+    fn git_urls() {
+        assert_eq!(
+            GitSource::from_flake("git+https://codeberg.org/owner/Repo.git?ref=dev#hydraJobs"),
+            source("codeberg.org", "owner/repo", Some("dev"))
+        );
+        assert_eq!(
+            GitSource::from_flake("git+ssh://git@example.org:2222/owner/repo"),
+            source("example.org", "owner/repo", None)
+        );
+    }
+
+    #[test]
+    fn other_flakes() {
+        assert_eq!(GitSource::from_flake("/src#hydraJobs"), None);
+        assert_eq!(GitSource::from_flake("path:/src"), None);
+        assert_eq!(GitSource::from_flake("github:owner"), None);
+    }
+}
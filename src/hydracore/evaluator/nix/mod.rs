@@ -1,3 +1,5 @@
 pub mod build;
 pub mod drv;
 pub mod eval;
+pub mod flake;
+pub mod store;
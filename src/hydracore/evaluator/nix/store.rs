@@ -0,0 +1,236 @@
+use core::{error, fmt};
+use std::{path::Path, process::Stdio};
+
+use tokio::process::{ChildStdout, Command};
+
+#[derive(Debug)]
+pub struct StoreError {
+    error: String,
+}
+
+impl StoreError {
+    pub fn new(error: String) -> Self {
+        StoreError { error }
+    }
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl error::Error for StoreError {}
+
+/// Returns the stdout lines of a `nix-store --query` call
+async fn run_query(args: &[&str], paths: &[String]) -> Result<Vec<String>, StoreError> {
+    let result = Command::new("nix-store")
+        .arg("--query")
+        .args(args)
+        .args(paths)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .map_err(|e| StoreError::new(e.to_string()))?;
+
+    if !result.status.success() {
+        return Err(StoreError::new(format!(
+            "nix-store --query {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&result.stderr)
+        )));
+    }
+
+    let stdout = String::from_utf8_lossy(&result.stdout);
+
+    Ok(stdout
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect())
+}
+
+/// The paths and everything they reference
+pub async fn query_closure(paths: &[String]) -> Result<Vec<String>, StoreError> {
+    run_query(&["--requisites"], paths).await
+}
+
+/// The hash part of a store path, which is how binary caches address it
+pub fn store_path_hash(path: &str) -> &str {
+    let name = path.rsplit('/').next().unwrap_or(path);
+
+    name.split('-').next().unwrap_or(name)
+}
+
+/// The file name of a store path, as used in narinfo files
+pub fn store_path_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// What a binary cache has to know about a store path
+pub struct PathInfo {
+    pub path: String,
+    pub nar_hash: String,
+    pub nar_size: u64,
+    pub references: Vec<String>,
+    pub deriver: Option<String>,
+}
+
+impl PathInfo {
+    pub async fn query(path: &str) -> Result<Self, StoreError> {
+        let paths = [path.to_string()];
+
+        let nar_hash = run_query(&["--hash"], &paths).await?;
+        let nar_size = run_query(&["--size"], &paths).await?;
+        let references = run_query(&["--references"], &paths).await?;
+        let deriver = run_query(&["--deriver"], &paths).await?;
+
+        let nar_hash = nar_hash
+            .first()
+            .ok_or(StoreError::new(format!("No nar hash for {}", path)))?
+            .clone();
+
+        let nar_size = nar_size
+            .first()
+            .ok_or(StoreError::new(format!("No nar size for {}", path)))?
+            .parse::<u64>()
+            .map_err(|e| StoreError::new(e.to_string()))?;
+
+        // nix prints this if the deriver is not known
+        let deriver = deriver
+            .first()
+            .filter(|deriver| deriver.as_str() != "unknown-deriver")
+            .cloned();
+
+        Ok(PathInfo {
+            path: path.to_string(),
+            nar_hash,
+            nar_size,
+            references,
+            deriver,
+        })
+    }
+}
+
+/// Starts serialising the path into a nar, which can be read from the returned stdout
+pub fn dump_path(path: &str) -> Result<ChildStdout, StoreError> {
+    let mut child = Command::new("nix-store")
+        .arg("--dump")
+        .arg(path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| StoreError::new(e.to_string()))?;
+
+    child.stdout.take().ok_or(StoreError::new(
+        "Failed to get stdout of nix-store".to_string(),
+    ))
+}
+
+/// Copies the paths and their closure to another store, like a binary cache
+pub async fn copy_to(url: &str, paths: &[String]) -> Result<(), StoreError> {
+    let result = Command::new("nix")
+        .arg("copy")
+        .arg("--to")
+        .arg(url)
+        .args(paths)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| StoreError::new(e.to_string()))?;
+
+    if !result.status.success() {
+        return Err(StoreError::new(format!(
+            "nix copy to {} failed: {}",
+            url,
+            String::from_utf8_lossy(&result.stderr).trim()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Makes `root` an indirect gc root for the paths, so the garbage collector keeps them.
+/// For more than one path nix adds `-<n>` to the names of the roots after the first one.
+pub async fn add_root(root: &Path, paths: &[String]) -> Result<(), StoreError> {
+    let result = Command::new("nix-store")
+        .arg("--add-root")
+        .arg(root)
+        .arg("--realise")
+        .args(paths)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .map_err(|e| StoreError::new(e.to_string()))?;
+
+    if !result.status.success() {
+        return Err(StoreError::new(format!(
+            "nix-store --add-root {} failed: {}",
+            root.display(),
+            String::from_utf8_lossy(&result.stderr).trim()
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::process::Command;
+
+    use super::copy_to;
+
+    /// Needs nix with the nix-command feature, like ladon itself
+    #[tokio::test]
+    async fn upload_to_file_store() {
+        if Command::new("nix-store")
+            .arg("--version")
+            .output()
+            .await
+            .is_err()
+        {
+            eprintln!("nix is not installed, skipping");
+            return;
+        }
+
+        let dir = std::env::temp_dir().join(format!("ladon-upload-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let file = dir.join("hello.txt");
+        std::fs::write(&file, format!("uploaded by {}", std::process::id())).unwrap();
+
+        let added = Command::new("nix-store")
+            .arg("--add")
+            .arg(&file)
+            .output()
+            .await
+            .unwrap();
+        assert!(added.status.success());
+
+        let path = String::from_utf8(added.stdout).unwrap().trim().to_string();
+        let cache = dir.join("cache");
+
+        copy_to(&format!("file://{}", cache.display()), &[path.clone()])
+            .await
+            .unwrap();
+
+        // the cache describes the path and holds its nar
+        let hash = &path["/nix/store/".len()..][..32];
+        let narinfo = std::fs::read_to_string(cache.join(format!("{}.narinfo", hash))).unwrap();
+
+        assert!(narinfo.contains(&format!("StorePath: {}", path)));
+
+        let nar = narinfo
+            .lines()
+            .find_map(|line| line.strip_prefix("URL: "))
+            .unwrap();
+
+        assert!(cache.join(nar).is_file());
+
+        _ = std::fs::remove_dir_all(&dir);
+    }
+}
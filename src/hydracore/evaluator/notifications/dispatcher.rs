@@ -0,0 +1,352 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use tracing::{error, info};
+
+use crate::models::{
+    Evaluation, Job, JobState, Jobset, NotificationDelivery, NotificationEvent, NotifierConfig,
+    Project,
+};
+
+use super::{
+    super::{
+        super::db::{DBError, DB},
+        deliveries::Deliverer,
+    },
+    notifier_for, Notification,
+};
+
+/// How often a delivery is tried before it is given up
+const MAX_ATTEMPTS: i32 = 5;
+
+/// Turns what the coordinator does into notifications and delivers them
+/// through the notifiers of the project, retrying failed deliveries.
+#[derive(Clone)]
+pub struct NotificationDispatcher {
+    db: DB,
+    http: reqwest::Client,
+    /// Where ladon itself is reachable, the notifications link to it
+    public_url: String,
+    deliverer: Deliverer,
+}
+
+impl NotificationDispatcher {
+    pub fn new(db: DB, public_url: String) -> Self {
+        NotificationDispatcher {
+            deliverer: Deliverer::new(db.clone(), MAX_ATTEMPTS),
+            db,
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+            public_url: public_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Called when the evaluation of the jobset starts
+    pub async fn on_evaluation_started(&self, jobset: &Jobset, revision: Option<&str>) {
+        let message = match revision {
+            Some(revision) => format!("Evaluating {} at {}", jobset.name, revision),
+            None => format!("Evaluating {}", jobset.name),
+        };
+
+        self.notify(
+            NotificationEvent::EvaluationStarted,
+            jobset,
+            None,
+            None,
+            revision.map(str::to_string),
+            message,
+        )
+        .await;
+    }
+
+    /// Called when the jobset could not be evaluated
+    pub async fn on_jobset_errored(&self, jobset: &Jobset, revision: Option<&str>, error: &str) {
+        // nix errors span many lines, the jobset page shows all of them
+        let first_line = error.lines().next().unwrap_or_default();
+
+        self.notify(
+            NotificationEvent::JobsetErrored,
+            jobset,
+            None,
+            None,
+            revision.map(str::to_string),
+            format!("Failed to evaluate {}: {}", jobset.name, first_line),
+        )
+        .await;
+    }
+
+    /// Called for every finished job, tells whether it failed, was fixed or is still failing
+    pub async fn on_job_finished(&self, job: &Job) {
+        let result = self.try_job_finished(job).await;
+
+        if result.is_err() {
+            error!(
+                "Failed to notify about job {}: {}",
+                job.attribute_name,
+                result.err().unwrap()
+            );
+        }
+    }
+
+    /// Called after a job finished, the last one finishes its evaluation
+    pub async fn on_evaluation_progress(&self, evaluation_id: i32) {
+        let result = self.try_evaluation_progress(evaluation_id).await;
+
+        if result.is_err() {
+            error!(
+                "Failed to notify about evaluation {}: {}",
+                evaluation_id,
+                result.err().unwrap()
+            );
+        }
+    }
+
+    /// Continues the deliveries a previous coordinator left unfinished
+    pub async fn resume_unfinished(&self) {
+        let deliveries = NotificationDelivery::get_unfinished(&self.db).await;
+
+        if deliveries.is_err() {
+            error!(
+                "Failed to get unfinished notification deliveries: {}",
+                deliveries.err().unwrap().to_string()
+            );
+            return;
+        }
+
+        for delivery in deliveries.unwrap() {
+            let notification = serde_json::from_str::<Notification>(&delivery.payload);
+
+            if notification.is_err() {
+                error!(
+                    "Failed to read notification of delivery {}: {}",
+                    delivery.id.unwrap(),
+                    notification.err().unwrap()
+                );
+                continue;
+            }
+
+            info!(
+                "Resuming delivery of {} to {}",
+                delivery.event, delivery.target
+            );
+
+            let dispatcher = self.clone();
+            let notification = notification.unwrap();
+
+            self.deliverer.spawn(async move {
+                dispatcher.run(delivery, notification).await;
+            });
+        }
+    }
+
+    async fn try_job_finished(&self, job: &Job) -> Result<(), DBError> {
+        let previous = Job::get_previous(&self.db, job).await?;
+
+        let event = match (&job.state, previous.map(|previous| previous.state)) {
+            (JobState::Failed, Some(JobState::Failed)) => NotificationEvent::JobStillFailing,
+            (JobState::Failed, _) => NotificationEvent::JobFailed,
+            (JobState::Done, Some(JobState::Failed)) => NotificationEvent::JobFixed,
+            _ => return Ok(()),
+        };
+
+        let (evaluation, jobset) = match self.evaluation_and_jobset(job.evaluation_id).await? {
+            Some(found) => found,
+            None => return Ok(()),
+        };
+
+        let message = match event {
+            NotificationEvent::JobStillFailing => {
+                format!("{} is still failing", job.attribute_name)
+            }
+            NotificationEvent::JobFixed => format!("{} was fixed", job.attribute_name),
+            _ => format!("{} failed", job.attribute_name),
+        };
+
+        self.notify(
+            event,
+            &jobset,
+            Some(&evaluation),
+            Some(job),
+            evaluation.revision.clone(),
+            message,
+        )
+        .await;
+
+        Ok(())
+    }
+
+    async fn try_evaluation_progress(&self, evaluation_id: i32) -> Result<(), DBError> {
+        let jobs = Job::get_by_evaluation(&self.db, evaluation_id).await?;
+
+        let unfinished = jobs
+            .iter()
+            .any(|job| !matches!(job.state, JobState::Done | JobState::Failed));
+
+        if jobs.is_empty() || unfinished {
+            return Ok(());
+        }
+
+        let failed = jobs
+            .iter()
+            .filter(|job| matches!(job.state, JobState::Failed))
+            .count();
+
+        let (evaluation, jobset) = match self.evaluation_and_jobset(evaluation_id).await? {
+            Some(found) => found,
+            None => return Ok(()),
+        };
+
+        let (event, message) = match failed {
+            0 => (
+                NotificationEvent::EvaluationFinished,
+                format!("All {} jobs of {} were built", jobs.len(), jobset.name),
+            ),
+            failed => (
+                NotificationEvent::EvaluationFailed,
+                format!(
+                    "{} of {} jobs of {} failed",
+                    failed,
+                    jobs.len(),
+                    jobset.name
+                ),
+            ),
+        };
+
+        self.notify(
+            event,
+            &jobset,
+            Some(&evaluation),
+            None,
+            evaluation.revision.clone(),
+            message,
+        )
+        .await;
+
+        Ok(())
+    }
+
+    async fn evaluation_and_jobset(
+        &self,
+        evaluation_id: i32,
+    ) -> Result<Option<(Evaluation, Jobset)>, DBError> {
+        let evaluation = match Evaluation::get_single(&self.db, evaluation_id).await? {
+            Some(evaluation) => evaluation,
+            None => return Ok(None),
+        };
+
+        let jobset = match Jobset::get_single(&self.db, evaluation.jobset_id).await? {
+            Some(jobset) => jobset,
+            None => return Ok(None),
+        };
+
+        Ok(Some((evaluation, jobset)))
+    }
+
+    /// Queues a delivery for every notifier of the jobset which wants the event
+    async fn notify(
+        &self,
+        event: NotificationEvent,
+        jobset: &Jobset,
+        evaluation: Option<&Evaluation>,
+        job: Option<&Job>,
+        revision: Option<String>,
+        message: String,
+    ) {
+        let result = self
+            .try_notify(event, jobset, evaluation, job, revision, message)
+            .await;
+
+        if result.is_err() {
+            error!(
+                "Failed to queue {} notifications of jobset {}: {}",
+                event,
+                jobset.name,
+                result.err().unwrap()
+            );
+        }
+    }
+
+    async fn try_notify(
+        &self,
+        event: NotificationEvent,
+        jobset: &Jobset,
+        evaluation: Option<&Evaluation>,
+        job: Option<&Job>,
+        revision: Option<String>,
+        message: String,
+    ) -> Result<(), DBError> {
+        let jobset_id = jobset.id.unwrap();
+
+        let notifiers: Vec<NotifierConfig> =
+            NotifierConfig::get_for_jobset(&self.db, jobset.project_id, jobset_id)
+                .await?
+                .into_iter()
+                .filter(|notifier| notifier.wants(event))
+                .collect();
+
+        if notifiers.is_empty() {
+            return Ok(());
+        }
+
+        let project = match Project::get_single(&self.db, jobset.project_id).await? {
+            Some(project) => project,
+            None => return Ok(()),
+        };
+
+        let jobset_url = format!(
+            "{}/project/{}/jobset/{}",
+            self.public_url, jobset.project_id, jobset_id
+        );
+
+        let notification = Notification {
+            event,
+            project: project.name,
+            project_id: jobset.project_id,
+            jobset: jobset.name.clone(),
+            jobset_id,
+            evaluation_id: evaluation.and_then(|evaluation| evaluation.id),
+            job: job.map(|job| job.attribute_name.clone()),
+            job_id: job.and_then(|job| job.id),
+            revision,
+            message,
+            url: match job {
+                Some(job) => format!("{}/job/{}", jobset_url, job.id.unwrap()),
+                None => jobset_url,
+            },
+            created: Utc::now(),
+        };
+
+        let payload = serde_json::to_string(&notification)
+            .map_err(|e| DBError::new(format!("Failed to serialize notification: {}", e)))?;
+
+        for notifier in notifiers {
+            let mut delivery = NotificationDelivery::new(&notifier, event, payload.clone());
+
+            delivery.add_to_db(&self.db).await?;
+
+            let dispatcher = self.clone();
+            let notification = notification.clone();
+
+            self.deliverer.spawn(async move {
+                dispatcher.run(delivery, notification).await;
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Aborts the running deliveries, see `Deliverer::stop`
+    pub fn stop(&self) {
+        self.deliverer.stop();
+    }
+
+    async fn run(&self, mut delivery: NotificationDelivery, notification: Notification) {
+        let notifier = notifier_for(delivery.kind, &delivery.target, &self.http);
+
+        self.deliverer
+            .run(&mut delivery, || notifier.send(&notification))
+            .await;
+    }
+}
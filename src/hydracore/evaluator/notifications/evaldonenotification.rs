@@ -9,6 +9,8 @@ pub struct EvalDoneNotification {
     error_msg: Option<String>,
     derivations: Option<Vec<Job>>,
     jobset_id: JobsetID,
+    flake: Option<String>,
+    revision: Option<String>,
 }
 
 impl EvalDoneNotification {
@@ -27,9 +29,25 @@ impl EvalDoneNotification {
             error_msg,
             derivations,
             jobset_id,
+            flake: None,
+            revision: None,
         }
     }
 
+    /// The locked flake which was evaluated and its git revision
+    pub fn set_flake(&mut self, flake: String, revision: Option<String>) {
+        self.flake = Some(flake);
+        self.revision = revision;
+    }
+
+    pub fn get_flake(&self) -> Option<&str> {
+        self.flake.as_deref()
+    }
+
+    pub fn get_revision(&self) -> Option<&str> {
+        self.revision.as_deref()
+    }
+
     pub fn set_success(&mut self, value: bool) {
         self.successfull = value;
     }
@@ -1,3 +1,10 @@
+pub use dispatcher::*;
 pub use evaldonenotification::*;
+pub use notification::*;
+pub use notifier::*;
 
+mod dispatcher;
 mod evaldonenotification;
+mod notification;
+mod notifier;
+mod webhook;
@@ -0,0 +1,26 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::NotificationEvent;
+
+/// What happened, as it is sent to every notifier which wants the event
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Notification {
+    pub event: NotificationEvent,
+    pub project: String,
+    pub project_id: i32,
+    pub jobset: String,
+    pub jobset_id: i32,
+    /// Not set before the jobs of the evaluation are known
+    pub evaluation_id: Option<i32>,
+    /// Attribute name of the job, only for job events
+    pub job: Option<String>,
+    pub job_id: Option<i32>,
+    /// Git revision which was evaluated, if the flake comes from git
+    pub revision: Option<String>,
+    /// One line for humans, like the reason an evaluation failed
+    pub message: String,
+    /// Page of the job or the jobset
+    pub url: String,
+    pub created: DateTime<Utc>,
+}
@@ -0,0 +1,41 @@
+use core::{error, fmt};
+
+use futures::future::BoxFuture;
+
+use crate::models::NotifierKind;
+
+use super::{webhook::WebhookNotifier, Notification};
+
+#[derive(Debug)]
+pub struct NotifyError {
+    error: String,
+}
+
+impl NotifyError {
+    pub fn new(error: String) -> Self {
+        NotifyError { error }
+    }
+}
+
+impl fmt::Display for NotifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl error::Error for NotifyError {}
+
+/// A channel notifications are delivered through. Implementations only send,
+/// queueing, retrying and recording the deliveries is left to the dispatcher.
+pub trait Notifier: Send + Sync {
+    /// An error gets the notification sent again later
+    fn send<'a>(&'a self, notification: &'a Notification)
+        -> BoxFuture<'a, Result<(), NotifyError>>;
+}
+
+/// The notifier of the kind, delivering to the target
+pub fn notifier_for(kind: NotifierKind, target: &str, http: &reqwest::Client) -> Box<dyn Notifier> {
+    match kind {
+        NotifierKind::Webhook => Box::new(WebhookNotifier::new(http.clone(), target.to_string())),
+    }
+}
@@ -0,0 +1,42 @@
+use futures::future::BoxFuture;
+
+use super::{Notification, Notifier, NotifyError};
+
+/// Posts the notification as json
+pub struct WebhookNotifier {
+    http: reqwest::Client,
+    url: String,
+}
+
+impl WebhookNotifier {
+    pub fn new(http: reqwest::Client, url: String) -> Self {
+        WebhookNotifier { http, url }
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn send<'a>(
+        &'a self,
+        notification: &'a Notification,
+    ) -> BoxFuture<'a, Result<(), NotifyError>> {
+        Box::pin(async move {
+            let response = self
+                .http
+                .post(&self.url)
+                .header("User-Agent", "ladon")
+                .json(notification)
+                .send()
+                .await
+                .map_err(|e| NotifyError::new(e.to_string()))?;
+
+            if !response.status().is_success() {
+                return Err(NotifyError::new(format!(
+                    "The webhook answered {}",
+                    response.status()
+                )));
+            }
+
+            Ok(())
+        })
+    }
+}
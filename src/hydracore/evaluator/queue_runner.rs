@@ -0,0 +1,214 @@
+use std::{collections::HashMap, time::Duration};
+
+use sqlx::postgres::PgListener;
+use tokio::{sync::mpsc::unbounded_channel, task::JoinSet};
+use tracing::{error, info, warn};
+
+use crate::models::{Jobset, QueueRequest, QueueRequestKind, QUEUE_CHANNEL};
+
+use super::{
+    super::db::{Pool, DB},
+    coordinator::Coordinator,
+};
+
+/// Picks up the requests the web frontend puts into the database and hands them to the coordinator.
+/// On postgres the web frontend notifies it about new requests, sqlite is polled.
+pub struct QueueRunner {
+    db: DB,
+    coordinator: Coordinator,
+    poll_interval: Duration,
+}
+
+impl QueueRunner {
+    pub fn new(coordinator: Coordinator, poll_interval: Duration) -> Self {
+        QueueRunner {
+            db: coordinator.get_db(),
+            coordinator,
+            poll_interval,
+        }
+    }
+
+    pub async fn run(&self) {
+        if let Pool::Postgres(pool) = self.db.pool() {
+            let listener = match PgListener::connect_with(pool).await {
+                Ok(mut listener) => listener.listen(QUEUE_CHANNEL).await.map(|_| listener),
+                Err(e) => Err(e),
+            };
+
+            match listener {
+                Ok(listener) => return self.listen(listener).await,
+                Err(e) => error!(
+                    "Failed to listen for queue requests, polling instead: {}",
+                    e.to_string()
+                ),
+            }
+        }
+
+        info!(
+            "Queue runner started, polling every {}s",
+            self.poll_interval.as_secs()
+        );
+
+        let mut interval = tokio::time::interval(self.poll_interval);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = self.coordinator.evaluation_done() => {}
+            }
+
+            self.handle_pending().await;
+        }
+    }
+
+    async fn listen(&self, mut listener: PgListener) {
+        info!("Queue runner started, listening for queue requests");
+
+        let (tx, mut rx) = unbounded_channel();
+        let poll_interval = self.poll_interval;
+
+        // receiving can't be cancelled safely, so it gets its own task which goes with the runner
+        let mut receiver = JoinSet::new();
+        receiver.spawn(async move {
+            loop {
+                match listener.try_recv().await {
+                    Ok(Some(_)) => {}
+                    Ok(None) => {
+                        warn!("Lost the connection listening for queue requests, reconnecting")
+                    }
+                    Err(e) => {
+                        error!("Failed to listen for queue requests: {}", e.to_string());
+                        tokio::time::sleep(poll_interval).await;
+                    }
+                }
+
+                if tx.send(()).is_err() {
+                    return;
+                }
+            }
+        });
+
+        loop {
+            // also picks up what was queued before or while the connection was lost
+            self.handle_pending().await;
+
+            // triggers of a busy jobset wait for its evaluation to finish
+            tokio::select! {
+                _ = rx.recv() => {}
+                _ = self.coordinator.evaluation_done() => {}
+            }
+        }
+    }
+
+    async fn handle_pending(&self) {
+        let requests = QueueRequest::get_pending(&self.db).await;
+
+        if requests.is_err() {
+            error!(
+                "Failed to get queue requests: {}",
+                requests.err().unwrap().to_string()
+            );
+            return;
+        }
+
+        let requests = requests.unwrap();
+
+        // only the latest trigger of a jobset is evaluated, it replaces the earlier ones
+        let mut latest_triggers = HashMap::new();
+        for request in requests.iter() {
+            if let (QueueRequestKind::TriggerJobset, Some(jobset_id)) =
+                (&request.kind, request.jobset_id)
+            {
+                latest_triggers.insert(jobset_id, request.id.unwrap());
+            }
+        }
+
+        for request in requests {
+            if let (QueueRequestKind::TriggerJobset, Some(jobset_id)) =
+                (&request.kind, request.jobset_id)
+            {
+                if latest_triggers.get(&jobset_id) == request.id.as_ref() {
+                    self.handle_trigger(request, jobset_id).await;
+                }
+                continue;
+            }
+
+            let taken = request.take(&self.db).await;
+
+            match taken {
+                Err(e) => {
+                    error!("Failed to take queue request: {}", e.to_string());
+                    continue;
+                }
+                Ok(false) => continue,
+                Ok(true) => {}
+            }
+
+            self.handle(request).await;
+        }
+    }
+
+    /// Schedules the jobset unless it is evaluated already, the request stays queued until then.
+    /// It is only removed once it was handled, together with the earlier triggers of the jobset.
+    async fn handle_trigger(&self, request: QueueRequest, jobset_id: i32) {
+        if self.coordinator.is_evaluating(jobset_id) {
+            return;
+        }
+
+        let jobset = Jobset::get_single(&self.db, jobset_id).await;
+
+        match jobset {
+            Err(e) => {
+                // the request stays queued, the next attempt may succeed
+                error!("Failed to get jobset: {}", e.to_string());
+                return;
+            }
+            Ok(None) => warn!("Jobset {} does not exist anymore", jobset_id),
+            Ok(Some(mut jobset)) => {
+                match &request.revision {
+                    Some(revision) => {
+                        info!("Scheduling jobset {} at revision {}", jobset_id, revision)
+                    }
+                    None => info!("Scheduling jobset: {}", jobset_id),
+                }
+
+                let result = self
+                    .coordinator
+                    .schedule_jobset(&mut jobset, request.revision.as_deref())
+                    .await;
+
+                // the jobset records why the evaluation did not start
+                if result.is_err() {
+                    error!(
+                        "Failed to schedule jobset {}: {}",
+                        jobset_id,
+                        result.err().unwrap()
+                    );
+                }
+            }
+        }
+
+        let taken = QueueRequest::take_triggers(&self.db, jobset_id, request.id.unwrap()).await;
+
+        if let Err(e) = taken {
+            error!("Failed to take queue request: {}", e.to_string());
+        }
+    }
+
+    async fn handle(&self, request: QueueRequest) {
+        match request.kind {
+            QueueRequestKind::TriggerJobset => warn!("Got trigger request without a jobset!"),
+            QueueRequestKind::CancelDeleted => {
+                self.coordinator.cancel_deleted().await;
+            }
+            QueueRequestKind::CancelJobset => match request.jobset_id {
+                Some(jobset_id) => self.coordinator.cancel_jobset(jobset_id).await,
+                None => warn!("Got cancel request without a jobset!"),
+            },
+            QueueRequestKind::RestartJob => match request.job_id {
+                Some(job_id) => self.coordinator.restart_job(job_id).await,
+                None => warn!("Got restart request without a job!"),
+            },
+        }
+    }
+}
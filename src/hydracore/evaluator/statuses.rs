@@ -0,0 +1,685 @@
+use core::{error, fmt};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use chrono::Utc;
+use serde::Serialize;
+use tracing::{error, info, warn};
+
+use crate::{
+    models::{
+        DeliveryState, Evaluation, Forge, Job, JobState, Jobset, Project, StatusDelivery,
+        StatusState,
+    },
+    server::config::StatusConfig,
+};
+
+use super::{
+    super::db::{DBError, DB},
+    deliveries::Deliverer,
+    nix::flake::GitSource,
+};
+
+/// How often a status is posted before it is given up
+const MAX_ATTEMPTS: i32 = 5;
+
+#[derive(Debug)]
+pub struct StatusError {
+    error: String,
+}
+
+impl StatusError {
+    pub fn new(error: String) -> Self {
+        StatusError { error }
+    }
+}
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl error::Error for StatusError {}
+
+/// The body of GitHub's and Gitea's status api
+#[derive(Serialize)]
+struct StatusBody<'a> {
+    state: String,
+    target_url: &'a str,
+    description: &'a str,
+    context: &'a str,
+}
+
+/// GitLab names its states differently and the context `name`
+#[derive(Serialize)]
+struct GitLabStatusBody<'a> {
+    state: &'a str,
+    target_url: &'a str,
+    description: &'a str,
+    name: &'a str,
+}
+
+/// Reports evaluations to the forge their jobset's flake comes from, retrying failed reports
+#[derive(Clone)]
+pub struct StatusReporter {
+    db: DB,
+    http: reqwest::Client,
+    deliverer: Deliverer,
+    /// Where ladon itself is reachable, the statuses link to it
+    public_url: String,
+    /// The forge credentials by project name
+    projects: Arc<HashMap<String, StatusConfig>>,
+}
+
+impl StatusReporter {
+    pub fn new(db: DB, public_url: String, statuses: Vec<StatusConfig>) -> Self {
+        StatusReporter {
+            deliverer: Deliverer::new(db.clone(), MAX_ATTEMPTS),
+            db,
+            // a forge which does not answer must not hold up the statuses of the others for long
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+            public_url: public_url.trim_end_matches('/').to_string(),
+            projects: Arc::new(
+                statuses
+                    .into_iter()
+                    .map(|config| (config.project.clone(), config))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Warns about the configured projects which do not exist, like after a project was renamed
+    pub async fn check_projects(&self) {
+        for name in self.projects.keys() {
+            match Project::get_by_name(&self.db, name).await {
+                Ok(Some(_)) => {}
+                Ok(None) => warn!(
+                    "Statuses are configured for project {}, but there is no such project",
+                    name
+                ),
+                Err(e) => error!("Failed to get project {}: {}", name, e.to_string()),
+            }
+        }
+    }
+
+    /// Continues the statuses a previous coordinator left unfinished
+    pub async fn resume_unfinished(&self) {
+        let deliveries = StatusDelivery::get_unfinished(&self.db).await;
+
+        if deliveries.is_err() {
+            error!(
+                "Failed to get unfinished status deliveries: {}",
+                deliveries.err().unwrap().to_string()
+            );
+            return;
+        }
+
+        for mut delivery in deliveries.unwrap() {
+            let target = match Jobset::get_single(&self.db, delivery.jobset_id).await {
+                Ok(Some(jobset)) => self.target(&jobset).await,
+                Ok(None) => Ok(None),
+                Err(e) => Err(StatusError::new(e.to_string())),
+            };
+
+            let (config, source) = match target {
+                Ok(Some((config, source, _))) => (config, source),
+                Ok(None) => {
+                    // the project does not report statuses anymore
+                    delivery.state = DeliveryState::Failed;
+                    delivery.error_message = Some("Nowhere to report the status to".to_string());
+                    delivery.finished = Some(Utc::now());
+
+                    if let Err(e) = delivery.update(&self.db).await {
+                        error!("Failed to update status delivery: {}", e.to_string());
+                    }
+                    continue;
+                }
+                Err(e) => {
+                    error!(
+                        "Failed to resume status of {}: {}",
+                        delivery.context,
+                        e.to_string()
+                    );
+                    continue;
+                }
+            };
+
+            info!(
+                "Resuming {} status of {} at {}",
+                delivery.status, delivery.context, delivery.revision
+            );
+
+            let reporter = self.clone();
+
+            self.deliverer.spawn(async move {
+                reporter.run(delivery, config, source).await;
+            });
+        }
+    }
+
+    /// Called once the jobs of an evaluation are queued, they are pending until built
+    pub async fn on_evaluation_created(&self, evaluation_id: i32) {
+        self.report_evaluation(evaluation_id, false).await;
+    }
+
+    /// Called for every finished job, the last one of its evaluation decides the status
+    pub async fn on_job_finished(&self, job: &Job) {
+        self.report_evaluation(job.evaluation_id, true).await;
+    }
+
+    /// Called when a failed job is built again, its evaluation is pending again
+    pub async fn on_job_restarted(&self, job: &Job) {
+        self.report_evaluation(job.evaluation_id, false).await;
+    }
+
+    /// The flake was locked but did not evaluate, so there is a revision to blame
+    pub async fn on_evaluation_failed(&self, jobset: &Jobset, revision: Option<&str>) {
+        if self.projects.is_empty() {
+            return;
+        }
+
+        let revision = match revision {
+            Some(revision) => revision,
+            None => return,
+        };
+
+        let result = self
+            .report(
+                jobset,
+                StatusState::Failure,
+                revision,
+                "The evaluation failed".to_string(),
+            )
+            .await;
+
+        if result.is_err() {
+            error!(
+                "Failed to report failed evaluation of jobset {}: {}",
+                jobset.name,
+                result.err().unwrap()
+            );
+        }
+    }
+
+    async fn report_evaluation(&self, evaluation_id: i32, only_finished: bool) {
+        if self.projects.is_empty() {
+            return;
+        }
+
+        let result = self
+            .try_report_evaluation(evaluation_id, only_finished)
+            .await;
+
+        if result.is_err() {
+            error!(
+                "Failed to report status of evaluation {}: {}",
+                evaluation_id,
+                result.err().unwrap()
+            );
+        }
+    }
+
+    async fn try_report_evaluation(
+        &self,
+        evaluation_id: i32,
+        only_finished: bool,
+    ) -> Result<(), StatusError> {
+        let db = &self.db;
+        let db_err = |e: DBError| StatusError::new(e.to_string());
+
+        let evaluation = Evaluation::get_single(db, evaluation_id)
+            .await
+            .map_err(db_err)?
+            .ok_or(StatusError::new("Failed to find evaluation".to_string()))?;
+
+        let revision = match &evaluation.revision {
+            Some(revision) => revision,
+            None => return Ok(()),
+        };
+
+        let jobs = Job::get_by_evaluation(db, evaluation_id)
+            .await
+            .map_err(db_err)?;
+
+        let (state, description) = evaluation_state(&jobs);
+
+        if only_finished && state == StatusState::Pending {
+            return Ok(());
+        }
+
+        let jobset = Jobset::get_single(db, evaluation.jobset_id)
+            .await
+            .map_err(db_err)?
+            .ok_or(StatusError::new("Failed to find jobset".to_string()))?;
+
+        self.report(&jobset, state, revision, description).await
+    }
+
+    async fn report(
+        &self,
+        jobset: &Jobset,
+        state: StatusState,
+        revision: &str,
+        description: String,
+    ) -> Result<(), StatusError> {
+        let (config, source, project) = match self.target(jobset).await? {
+            Some(target) => target,
+            None => return Ok(()),
+        };
+
+        let mut delivery = StatusDelivery::new(
+            jobset.id.unwrap(),
+            revision.to_string(),
+            state,
+            format!("ladon/{}/{}", project.name, jobset.name),
+            description,
+            format!(
+                "{}/project/{}/jobset/{}",
+                self.public_url,
+                jobset.project_id,
+                jobset.id.unwrap()
+            ),
+        );
+
+        delivery
+            .add_to_db(&self.db)
+            .await
+            .map_err(|e| StatusError::new(e.to_string()))?;
+
+        // the coordinator goes on with the next build while the forge answers
+        let reporter = self.clone();
+
+        self.deliverer.spawn(async move {
+            reporter.run(delivery, config, source).await;
+        });
+
+        Ok(())
+    }
+
+    /// The forge credentials and repository the statuses of the jobset go to, if it has any
+    async fn target(
+        &self,
+        jobset: &Jobset,
+    ) -> Result<Option<(StatusConfig, GitSource, Project)>, StatusError> {
+        let project = Project::get_single(&self.db, jobset.project_id)
+            .await
+            .map_err(|e| StatusError::new(e.to_string()))?
+            .ok_or(StatusError::new("Failed to find project".to_string()))?;
+
+        let config = match self.projects.get(&project.name) {
+            Some(config) => config.clone(),
+            None => return Ok(None),
+        };
+
+        let source = match GitSource::from_flake(&jobset.flake) {
+            Some(source) => source,
+            None => {
+                warn!(
+                    "Cannot report status of jobset {}, its flake does not come from a forge",
+                    jobset.name
+                );
+                return Ok(None);
+            }
+        };
+
+        Ok(Some((config, source, project)))
+    }
+
+    /// Aborts the running deliveries, see `Deliverer::stop`
+    pub fn stop(&self) {
+        self.deliverer.stop();
+    }
+
+    async fn run(&self, mut delivery: StatusDelivery, config: StatusConfig, source: GitSource) {
+        let status = delivery.clone();
+
+        self.deliverer
+            .run(&mut delivery, || {
+                send_status(&self.http, &config, &source, &status)
+            })
+            .await;
+    }
+}
+
+/// Pending while jobs are unfinished, a success once all of them are built
+fn evaluation_state(jobs: &[Job]) -> (StatusState, String) {
+    let done = jobs
+        .iter()
+        .filter(|job| matches!(job.state, JobState::Done))
+        .count();
+    let failed = jobs
+        .iter()
+        .filter(|job| matches!(job.state, JobState::Failed))
+        .count();
+
+    if done + failed < jobs.len() {
+        return (
+            StatusState::Pending,
+            format!("{} of {} jobs are built", done + failed, jobs.len()),
+        );
+    }
+
+    match failed {
+        0 => (
+            StatusState::Success,
+            format!("All {} jobs were built", jobs.len()),
+        ),
+        failed => (
+            StatusState::Failure,
+            format!("{} of {} jobs failed", failed, jobs.len()),
+        ),
+    }
+}
+
+/// Posts the status to the forge's api
+async fn send_status(
+    http: &reqwest::Client,
+    config: &StatusConfig,
+    source: &GitSource,
+    status: &StatusDelivery,
+) -> Result<(), StatusError> {
+    let api_url = match &config.api_url {
+        Some(api_url) => api_url.trim_end_matches('/').to_string(),
+        None => match config.forge {
+            Forge::GitHub if source.host == "github.com" => "https://api.github.com".to_string(),
+            Forge::GitHub => format!("https://{}/api/v3", source.host),
+            Forge::Gitea => format!("https://{}/api/v1", source.host),
+            Forge::GitLab => format!("https://{}/api/v4", source.host),
+        },
+    };
+
+    let request = match config.forge {
+        Forge::GitHub | Forge::Gitea => {
+            let url = format!(
+                "{}/repos/{}/statuses/{}",
+                api_url, source.repository, status.revision
+            );
+
+            let authorization = match config.forge {
+                Forge::GitHub => format!("Bearer {}", config.token),
+                _ => format!("token {}", config.token),
+            };
+
+            http.post(url)
+                .header("Authorization", authorization)
+                .header("Accept", "application/json")
+                .json(&StatusBody {
+                    state: status.status.to_string(),
+                    target_url: &status.target_url,
+                    description: &status.description,
+                    context: &status.context,
+                })
+        }
+        Forge::GitLab => {
+            // the project is named by its url encoded path
+            let url = format!(
+                "{}/projects/{}/statuses/{}",
+                api_url,
+                source.repository.replace('/', "%2F"),
+                status.revision
+            );
+
+            let state = match status.status {
+                StatusState::Pending => "running",
+                StatusState::Success => "success",
+                StatusState::Failure => "failed",
+            };
+
+            http.post(url)
+                .header("PRIVATE-TOKEN", &config.token)
+                .json(&GitLabStatusBody {
+                    state,
+                    target_url: &status.target_url,
+                    description: &status.description,
+                    name: &status.context,
+                })
+        }
+    };
+
+    // GitHub refuses requests without one
+    let response = request
+        .header("User-Agent", "ladon")
+        .send()
+        .await
+        .map_err(|e| StatusError::new(e.to_string()))?;
+
+    if !response.status().is_success() {
+        let code = response.status();
+        let body = response.text().await.unwrap_or_default();
+
+        return Err(StatusError::new(format!(
+            "The forge answered {}: {}",
+            code,
+            body.chars().take(200).collect::<String>()
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::{
+        body::Bytes,
+        http::{HeaderMap, StatusCode, Uri},
+        Router,
+    };
+    use serde_json::{json, Value};
+
+    use crate::{
+        hydracore::evaluator::nix::flake::GitSource,
+        models::{Forge, Job, JobState, StatusDelivery, StatusState},
+        server::config::StatusConfig,
+    };
+
+    use super::{evaluation_state, send_status};
+
+    /// A request the mock forge received
+    struct Received {
+        path: String,
+        headers: HeaderMap,
+        body: Value,
+    }
+
+    /// Serves a forge api which answers every request with the code, returns its url
+    async fn mock_forge(code: StatusCode) -> (String, Arc<Mutex<Vec<Received>>>) {
+        let received = Arc::new(Mutex::new(Vec::new()));
+
+        let app = {
+            let received = received.clone();
+            Router::new().fallback(
+                move |uri: Uri, headers: HeaderMap, body: Bytes| async move {
+                    received.lock().unwrap().push(Received {
+                        path: uri.path().to_string(),
+                        headers,
+                        body: serde_json::from_slice(&body).unwrap_or_default(),
+                    });
+                    code
+                },
+            )
+        };
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        (url, received)
+    }
+
+    fn config(forge: Forge, api_url: &str) -> StatusConfig {
+        StatusConfig {
+            project: "p".to_string(),
+            forge,
+            api_url: Some(api_url.to_string()),
+            token: "secret".to_string(),
+        }
+    }
+
+    fn source() -> GitSource {
+        GitSource {
+            host: "example.org".to_string(),
+            repository: "owner/repo".to_string(),
+            branch: None,
+        }
+    }
+
+    fn status(state: StatusState) -> StatusDelivery {
+        StatusDelivery::new(
+            1,
+            "0123456789abcdef0123456789abcdef01234567".to_string(),
+            state,
+            "ladon/p/j".to_string(),
+            "All 2 jobs were built".to_string(),
+            "http://ladon/project/1/jobset/1".to_string(),
+        )
+    }
+
+    fn job(state: JobState) -> Job {
+        Job {
+            state,
+            ..Job::new(1, "hello".to_string(), "/nix/store/x-hello.drv".to_string())
+        }
+    }
+
+    #[test]
+    fn pending_evaluation() {
+        let jobs = [job(JobState::Done), job(JobState::Building)];
+
+        assert_eq!(
+            evaluation_state(&jobs),
+            (StatusState::Pending, "1 of 2 jobs are built".to_string())
+        );
+    }
+
+    #[test]
+    fn successful_evaluation() {
+        let jobs = [job(JobState::Done), job(JobState::Done)];
+
+        assert_eq!(
+            evaluation_state(&jobs),
+            (StatusState::Success, "All 2 jobs were built".to_string())
+        );
+    }
+
+    #[test]
+    fn failed_evaluation() {
+        let jobs = [job(JobState::Done), job(JobState::Failed)];
+
+        assert_eq!(
+            evaluation_state(&jobs),
+            (StatusState::Failure, "1 of 2 jobs failed".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn github() {
+        let (url, received) = mock_forge(StatusCode::CREATED).await;
+
+        send_status(
+            &reqwest::Client::new(),
+            &config(Forge::GitHub, &url),
+            &source(),
+            &status(StatusState::Success),
+        )
+        .await
+        .unwrap();
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(
+            received[0].path,
+            "/repos/owner/repo/statuses/0123456789abcdef0123456789abcdef01234567"
+        );
+        assert_eq!(received[0].headers["authorization"], "Bearer secret");
+        assert_eq!(received[0].headers["user-agent"], "ladon");
+        assert_eq!(
+            received[0].body,
+            json!({
+                "state": "success",
+                "target_url": "http://ladon/project/1/jobset/1",
+                "description": "All 2 jobs were built",
+                "context": "ladon/p/j",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn gitea() {
+        let (url, received) = mock_forge(StatusCode::CREATED).await;
+
+        send_status(
+            &reqwest::Client::new(),
+            &config(Forge::Gitea, &url),
+            &source(),
+            &status(StatusState::Failure),
+        )
+        .await
+        .unwrap();
+
+        let received = received.lock().unwrap();
+        assert_eq!(
+            received[0].path,
+            "/repos/owner/repo/statuses/0123456789abcdef0123456789abcdef01234567"
+        );
+        assert_eq!(received[0].headers["authorization"], "token secret");
+        assert_eq!(received[0].body["state"], "failure");
+        assert_eq!(received[0].body["context"], "ladon/p/j");
+    }
+
+    #[tokio::test]
+    async fn gitlab() {
+        let (url, received) = mock_forge(StatusCode::CREATED).await;
+
+        send_status(
+            &reqwest::Client::new(),
+            &config(Forge::GitLab, &url),
+            &source(),
+            &status(StatusState::Pending),
+        )
+        .await
+        .unwrap();
+
+        let received = received.lock().unwrap();
+        // the mock sees the path still encoded
+        assert_eq!(
+            received[0].path,
+            "/projects/owner%2Frepo/statuses/0123456789abcdef0123456789abcdef01234567"
+        );
+        assert_eq!(received[0].headers["private-token"], "secret");
+        assert!(received[0].headers.get("authorization").is_none());
+        assert_eq!(
+            received[0].body,
+            json!({
+                "state": "running",
+                "target_url": "http://ladon/project/1/jobset/1",
+                "description": "All 2 jobs were built",
+                "name": "ladon/p/j",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn refused() {
+        let (url, _) = mock_forge(StatusCode::UNAUTHORIZED).await;
+
+        let result = send_status(
+            &reqwest::Client::new(),
+            &config(Forge::GitHub, &url),
+            &source(),
+            &status(StatusState::Success),
+        )
+        .await;
+
+        assert!(result
+            .err()
+            .unwrap()
+            .to_string()
+            .starts_with("The forge answered 401"));
+    }
+}
@@ -0,0 +1,50 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use tokio::task::AbortHandle;
+
+/// Running evaluations or builds by id, so they can be cancelled when their jobset goes away.
+/// Aborting a task drops its child process, which is spawned with `kill_on_drop`.
+#[derive(Clone, Default)]
+pub struct TaskRegistry {
+    tasks: Arc<Mutex<HashMap<i32, AbortHandle>>>,
+}
+
+impl TaskRegistry {
+    pub fn insert(&self, id: i32, handle: AbortHandle) {
+        let mut tasks = self.tasks.lock().unwrap();
+
+        tasks.retain(|_, task| !task.is_finished());
+        tasks.insert(id, handle);
+    }
+
+    /// Ids of the tasks which are still queued or running
+    pub fn ids(&self) -> Vec<i32> {
+        let mut tasks = self.tasks.lock().unwrap();
+
+        tasks.retain(|_, task| !task.is_finished());
+        tasks.keys().copied().collect()
+    }
+
+    /// Returns false if there was no such task
+    pub fn cancel(&self, id: i32) -> bool {
+        let task = self.tasks.lock().unwrap().remove(&id);
+
+        match task {
+            Some(task) => {
+                task.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Aborts every task, like when the coordinator lease was lost
+    pub fn cancel_all(&self) {
+        for (_, task) in self.tasks.lock().unwrap().drain() {
+            task.abort();
+        }
+    }
+}
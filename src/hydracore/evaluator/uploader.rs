@@ -0,0 +1,115 @@
+use tracing::{error, info, warn};
+
+use crate::models::{Cache, JobPath, Upload};
+
+use super::{super::db::DB, deliveries::Deliverer, nix::store::copy_to};
+
+/// How often an upload is tried before it is given up
+const MAX_ATTEMPTS: i32 = 3;
+
+/// Copies the outputs of successful jobs to the external caches of their project.
+#[derive(Clone)]
+pub struct Uploader {
+    db: DB,
+    deliverer: Deliverer,
+}
+
+impl Uploader {
+    pub fn new(db: DB) -> Self {
+        Uploader {
+            deliverer: Deliverer::new(db.clone(), MAX_ATTEMPTS),
+            db,
+        }
+    }
+
+    /// Starts uploading the outputs to every cache of the job's project
+    pub async fn upload_job(&self, job_id: i32, outputs: Vec<String>) {
+        let caches = Cache::get_for_job(&self.db, job_id).await;
+
+        if caches.is_err() {
+            error!(
+                "Failed to get caches: {}",
+                caches.err().unwrap().to_string()
+            );
+            return;
+        }
+
+        for cache in caches.unwrap() {
+            // caches added before their urls were checked
+            if !Cache::is_supported_url(&cache.url) {
+                warn!("Skipping unsupported cache {}", cache.url);
+                continue;
+            }
+
+            let mut upload = Upload::new(job_id, &cache);
+
+            let result = upload.add_to_db(&self.db).await;
+
+            if result.is_err() {
+                error!(
+                    "Failed to add upload: {}",
+                    result.err().unwrap().to_string()
+                );
+                continue;
+            }
+
+            let uploader = self.clone();
+            let outputs = outputs.clone();
+
+            self.deliverer.spawn(async move {
+                uploader.run(upload, outputs).await;
+            });
+        }
+    }
+
+    /// Continues the uploads a previous coordinator left unfinished
+    pub async fn resume_unfinished(&self) {
+        let uploads = Upload::get_unfinished(&self.db).await;
+
+        if uploads.is_err() {
+            error!(
+                "Failed to get unfinished uploads: {}",
+                uploads.err().unwrap().to_string()
+            );
+            return;
+        }
+
+        for upload in uploads.unwrap() {
+            let outputs = JobPath::get_outputs(&self.db, upload.job_id).await;
+
+            if outputs.is_err() {
+                error!(
+                    "Failed to get outputs of job {}: {}",
+                    upload.job_id,
+                    outputs.err().unwrap().to_string()
+                );
+                continue;
+            }
+
+            let outputs = outputs.unwrap().into_iter().map(|path| path.path).collect();
+
+            info!("Resuming upload of job {} to {}", upload.job_id, upload.url);
+
+            let uploader = self.clone();
+
+            self.deliverer.spawn(async move {
+                uploader.run(upload, outputs).await;
+            });
+        }
+    }
+
+    /// Aborts the running uploads, see `Deliverer::stop`
+    pub fn stop(&self) {
+        self.deliverer.stop();
+    }
+
+    async fn run(&self, mut upload: Upload, outputs: Vec<String>) {
+        info!("Uploading job {} to {}", upload.job_id, upload.url);
+
+        let url = upload.url.clone();
+
+        self.deliverer
+            .run(&mut upload, || copy_to(&url, &outputs))
+            .await;
+    }
+}
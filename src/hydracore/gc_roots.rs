@@ -0,0 +1,141 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+};
+
+use tracing::{error, info, warn};
+
+use crate::models::{Job, JobPath};
+
+use super::{
+    data_dir::DataDir,
+    db::{DBError, DB},
+    evaluator::add_root,
+};
+
+/// Roots are named `<job id>` or `<job id>-<suffix>`
+pub fn gc_root_job_id(name: &str) -> Option<i32> {
+    name.split('-').next()?.parse::<i32>().ok()
+}
+
+/// Keeps the gc roots in `<data dir>/gcroots` in line with the retention policy of the jobsets,
+/// so `nix-collect-garbage` does not delete outputs ladon still serves.
+#[derive(Clone)]
+pub struct GcRoots {
+    db: DB,
+    data_dir: DataDir,
+}
+
+impl GcRoots {
+    pub fn new(db: DB, data_dir: DataDir) -> Self {
+        GcRoots { db, data_dir }
+    }
+
+    /// Roots the outputs of the job
+    pub async fn add(&self, job_id: i32, outputs: &[String]) {
+        if outputs.is_empty() {
+            return;
+        }
+
+        let result = fs::create_dir_all(self.data_dir.gc_roots());
+
+        if result.is_err() {
+            error!(
+                "Failed to create gc root directory: {}",
+                result.err().unwrap()
+            );
+            return;
+        }
+
+        let result = add_root(&self.data_dir.gc_root(job_id), outputs).await;
+
+        if result.is_err() {
+            warn!(
+                "Failed to add gc root for job {}: {}",
+                job_id,
+                result.err().unwrap()
+            );
+        }
+    }
+
+    /// The existing roots by the job they belong to
+    fn existing(&self) -> HashMap<i32, Vec<PathBuf>> {
+        let mut roots: HashMap<i32, Vec<PathBuf>> = HashMap::new();
+
+        let entries = fs::read_dir(self.data_dir.gc_roots());
+
+        if entries.is_err() {
+            return roots;
+        }
+
+        for entry in entries.unwrap().flatten() {
+            let id = gc_root_job_id(&entry.file_name().to_string_lossy());
+
+            if let Some(id) = id {
+                roots.entry(id).or_default().push(entry.path());
+            }
+        }
+
+        roots
+    }
+
+    /// Removes the roots of jobs which aged out and adds the missing ones
+    pub async fn sync(&self) -> Result<(), DBError> {
+        let rooted: HashSet<i32> = Job::get_rooted(&self.db).await?.into_iter().collect();
+
+        let existing = self.existing();
+
+        let mut removed = 0;
+
+        for (job_id, roots) in existing.iter() {
+            if rooted.contains(job_id) {
+                continue;
+            }
+
+            for root in roots {
+                let result = fs::remove_file(root);
+
+                if result.is_err() {
+                    warn!(
+                        "Failed to remove gc root {}: {}",
+                        root.display(),
+                        result.err().unwrap()
+                    );
+                    continue;
+                }
+
+                removed += 1;
+            }
+        }
+
+        let mut added = 0;
+
+        for job_id in rooted.iter() {
+            if existing.contains_key(job_id) {
+                continue;
+            }
+
+            let outputs: Vec<String> = JobPath::get_outputs(&self.db, *job_id)
+                .await?
+                .into_iter()
+                .map(|path| path.path)
+                .collect();
+
+            // jobs built before ladon recorded their outputs
+            if outputs.is_empty() {
+                continue;
+            }
+
+            self.add(*job_id, &outputs).await;
+
+            added += 1;
+        }
+
+        if removed > 0 || added > 0 {
+            info!("Removed {} and added {} gc roots", removed, added);
+        }
+
+        Ok(())
+    }
+}
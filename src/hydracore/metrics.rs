@@ -0,0 +1,214 @@
+use core::{error, fmt};
+use std::time::Duration;
+
+use chrono::Utc;
+use prometheus::{
+    exponential_buckets, Histogram, HistogramOpts, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+
+use crate::models::{Job, QueueRequest};
+
+use super::db::{DBError, DB};
+
+#[derive(Debug)]
+pub struct MetricsError {
+    error: String,
+}
+
+impl MetricsError {
+    pub fn new(error: String) -> Self {
+        MetricsError { error }
+    }
+}
+
+impl fmt::Display for MetricsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl error::Error for MetricsError {}
+
+/// Prometheus metrics of one ladon process, served under `/metrics`.
+/// The build and evaluation metrics are only collected by the process running the queue runner,
+/// the ones read from the database on every scrape are available everywhere.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    build_queue_length: IntGauge,
+    running_builds: IntGaugeVec,
+    build_duration: Histogram,
+    builds: IntCounterVec,
+    evaluation_duration: Histogram,
+    evaluations: IntCounterVec,
+    unfinished_jobs: IntGauge,
+    pending_requests: IntGauge,
+    scheduler_lag: IntGauge,
+    db_connections: IntGaugeVec,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new_custom(Some("ladon".to_string()), None).unwrap();
+
+        // up to about 9 hours
+        let duration_buckets = exponential_buckets(1.0, 2.0, 16).unwrap();
+
+        let metrics = Metrics {
+            build_queue_length: IntGauge::new(
+                "build_queue_length",
+                "Builds waiting for a free builder",
+            )
+            .unwrap(),
+            running_builds: IntGaugeVec::new(
+                Opts::new("running_builds", "Builds currently running"),
+                &["machine"],
+            )
+            .unwrap(),
+            build_duration: Histogram::with_opts(
+                HistogramOpts::new("build_duration_seconds", "How long builds took")
+                    .buckets(duration_buckets.clone()),
+            )
+            .unwrap(),
+            builds: IntCounterVec::new(
+                Opts::new("builds_total", "Finished builds"),
+                &["jobset", "result"],
+            )
+            .unwrap(),
+            evaluation_duration: Histogram::with_opts(
+                HistogramOpts::new("evaluation_duration_seconds", "How long evaluations took")
+                    .buckets(duration_buckets),
+            )
+            .unwrap(),
+            evaluations: IntCounterVec::new(
+                Opts::new("evaluations_total", "Finished evaluations"),
+                &["jobset", "result"],
+            )
+            .unwrap(),
+            unfinished_jobs: IntGauge::new("unfinished_jobs", "Jobs which still have to be built")
+                .unwrap(),
+            pending_requests: IntGauge::new(
+                "pending_queue_requests",
+                "Requests the queue runner did not pick up yet",
+            )
+            .unwrap(),
+            scheduler_lag: IntGauge::new(
+                "scheduler_lag_seconds",
+                "Age of the oldest request the queue runner did not pick up yet",
+            )
+            .unwrap(),
+            db_connections: IntGaugeVec::new(
+                Opts::new("db_connections", "Connections of the database pool"),
+                &["state"],
+            )
+            .unwrap(),
+            registry,
+        };
+
+        let collectors: Vec<Box<dyn prometheus::core::Collector>> = vec![
+            Box::new(metrics.build_queue_length.clone()),
+            Box::new(metrics.running_builds.clone()),
+            Box::new(metrics.build_duration.clone()),
+            Box::new(metrics.builds.clone()),
+            Box::new(metrics.evaluation_duration.clone()),
+            Box::new(metrics.evaluations.clone()),
+            Box::new(metrics.unfinished_jobs.clone()),
+            Box::new(metrics.pending_requests.clone()),
+            Box::new(metrics.scheduler_lag.clone()),
+            Box::new(metrics.db_connections.clone()),
+        ];
+
+        for collector in collectors {
+            metrics.registry.register(collector).unwrap();
+        }
+
+        metrics
+    }
+
+    /// A build was handed to the build manager
+    pub fn build_queued(&self) {
+        self.build_queue_length.inc();
+    }
+
+    /// A build got a builder or was cancelled while waiting
+    pub fn build_dequeued(&self) {
+        self.build_queue_length.dec();
+    }
+
+    pub fn build_started(&self, machine: &str) {
+        self.running_builds.with_label_values(&[machine]).inc();
+    }
+
+    pub fn build_stopped(&self, machine: &str, duration: Duration) {
+        self.running_builds.with_label_values(&[machine]).dec();
+        self.build_duration.observe(duration.as_secs_f64());
+    }
+
+    pub fn build_finished(&self, jobset_id: i32, successful: bool) {
+        self.builds
+            .with_label_values(&[&jobset_id.to_string(), result_label(successful)])
+            .inc();
+    }
+
+    pub fn evaluation_finished(&self, jobset_id: i32, successful: bool, duration: Duration) {
+        self.evaluations
+            .with_label_values(&[&jobset_id.to_string(), result_label(successful)])
+            .inc();
+        self.evaluation_duration.observe(duration.as_secs_f64());
+    }
+
+    /// Reads the values which live in the database
+    async fn update_from_db(&self, db: &DB) -> Result<(), MetricsError> {
+        let db_err = |e: DBError| MetricsError::new(e.to_string());
+
+        self.unfinished_jobs
+            .set(Job::count_unfinished(db).await.map_err(db_err)?);
+
+        let requests = QueueRequest::get_pending(db).await.map_err(db_err)?;
+
+        self.pending_requests.set(requests.len() as i64);
+
+        let lag = requests
+            .iter()
+            .map(|request| request.created)
+            .min()
+            .map(|oldest| (Utc::now() - oldest).num_seconds().max(0))
+            .unwrap_or(0);
+
+        self.scheduler_lag.set(lag);
+
+        let (size, idle) = db.pool_stats();
+
+        self.db_connections
+            .with_label_values(&["active"])
+            .set(size as i64 - idle as i64);
+        self.db_connections
+            .with_label_values(&["idle"])
+            .set(idle as i64);
+
+        Ok(())
+    }
+
+    /// The metrics in the prometheus text format
+    pub async fn render(&self, db: &DB) -> Result<String, MetricsError> {
+        self.update_from_db(db).await?;
+
+        TextEncoder::new()
+            .encode_to_string(&self.registry.gather())
+            .map_err(|e| MetricsError::new(e.to_string()))
+    }
+}
+
+fn result_label(successful: bool) -> &'static str {
+    match successful {
+        true => "success",
+        false => "failure",
+    }
+}
@@ -1,6 +1,20 @@
+pub use auth::*;
+pub use data_dir::*;
 pub use db::*;
+pub use deletion::*;
 pub use evaluator::*;
+pub use gc_roots::*;
+pub use metrics::*;
+pub use pruning::*;
+pub use signing::*;
 
+mod auth;
+mod data_dir;
+mod deletion;
 mod evaluator;
+mod gc_roots;
+mod metrics;
+mod pruning;
+mod signing;
 
 mod db;
@@ -0,0 +1,88 @@
+use tracing::{info, warn};
+
+use crate::models::{Evaluation, Pruning};
+
+use super::{
+    data_dir::DataDir,
+    db::{DBError, DB},
+    gc_roots::GcRoots,
+};
+
+/// How many evaluations are deleted in one transaction
+const BATCH_SIZE: usize = 50;
+
+/// Deletes evaluations which exceed the retention limits of their jobset,
+/// together with their jobs, build logs and gc roots.
+#[derive(Clone)]
+pub struct Pruner {
+    db: DB,
+    data_dir: DataDir,
+    gc_roots: GcRoots,
+}
+
+impl Pruner {
+    pub fn new(db: DB, data_dir: DataDir) -> Self {
+        Pruner {
+            gc_roots: GcRoots::new(db.clone(), data_dir.clone()),
+            db,
+            data_dir,
+        }
+    }
+
+    /// Prunes in batches for as long as `is_idle` allows, then compacts the database.
+    /// Returns `None` if there was nothing to prune.
+    pub async fn run(&self, is_idle: impl Fn() -> bool) -> Result<Option<Pruning>, DBError> {
+        let mut pruning = Pruning::new(self.db.size().await?);
+
+        loop {
+            if !is_idle() {
+                info!("Pausing pruning, there is work to do");
+                break;
+            }
+
+            let evaluations = Evaluation::get_prunable(&self.db, BATCH_SIZE).await?;
+
+            if evaluations.is_empty() {
+                break;
+            }
+
+            let jobs = Evaluation::delete_many(&self.db, &evaluations).await?;
+
+            pruning.evaluations += evaluations.len() as i32;
+            pruning.jobs += jobs.len() as i32;
+
+            for job_id in jobs {
+                match self.data_dir.remove_build_log(job_id) {
+                    Ok(Some(size)) => {
+                        pruning.logs += 1;
+                        pruning.log_bytes += size as i64;
+                    }
+                    Ok(None) => (),
+                    Err(e) => warn!("Failed to remove build log of job {}: {}", job_id, e),
+                }
+            }
+        }
+
+        if pruning.evaluations == 0 {
+            return Ok(None);
+        }
+
+        self.gc_roots.sync().await?;
+
+        self.db.vacuum().await?;
+
+        pruning.db_bytes_after = self.db.size().await?;
+        pruning.finished = chrono::Utc::now();
+
+        pruning.add_to_db(&self.db).await?;
+
+        info!(
+            "Pruned {} evaluations and {} jobs, reclaimed {} bytes",
+            pruning.evaluations,
+            pruning.jobs,
+            pruning.reclaimed_bytes()
+        );
+
+        Ok(Some(pruning))
+    }
+}
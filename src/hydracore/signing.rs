@@ -0,0 +1,217 @@
+use core::{error, fmt};
+use std::{
+    fs,
+    io::Write,
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ed25519_dalek::{Signer, SigningKey as Ed25519Key};
+use rand::rngs::OsRng;
+
+use super::data_dir::DataDir;
+
+#[derive(Debug)]
+pub struct KeyError {
+    error: String,
+}
+
+impl KeyError {
+    pub fn new(error: String) -> Self {
+        KeyError { error }
+    }
+}
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl error::Error for KeyError {}
+
+/// An ed25519 key in the format of `nix key generate-secret`
+pub struct SigningKey {
+    pub name: String,
+    key: Ed25519Key,
+}
+
+impl SigningKey {
+    pub fn generate(name: &str) -> Self {
+        SigningKey {
+            name: name.to_string(),
+            key: Ed25519Key::generate(&mut OsRng),
+        }
+    }
+
+    /// Parses `<name>:<base64 of the 64 byte secret key>`
+    pub fn parse(content: &str) -> Result<Self, KeyError> {
+        let (name, key) = content
+            .trim()
+            .split_once(':')
+            .ok_or(KeyError::new("Key has no name".to_string()))?;
+
+        let bytes = STANDARD
+            .decode(key)
+            .map_err(|e| KeyError::new(e.to_string()))?;
+
+        let bytes: [u8; 64] = bytes
+            .try_into()
+            .map_err(|_| KeyError::new("Secret key has to be 64 bytes long".to_string()))?;
+
+        let key =
+            Ed25519Key::from_keypair_bytes(&bytes).map_err(|e| KeyError::new(e.to_string()))?;
+
+        Ok(SigningKey {
+            name: name.to_string(),
+            key,
+        })
+    }
+
+    pub fn secret_key(&self) -> String {
+        format!(
+            "{}:{}",
+            self.name,
+            STANDARD.encode(self.key.to_keypair_bytes())
+        )
+    }
+
+    /// The value clients put into `trusted-public-keys`
+    pub fn public_key(&self) -> String {
+        format!(
+            "{}:{}",
+            self.name,
+            STANDARD.encode(self.key.verifying_key().to_bytes())
+        )
+    }
+
+    /// Signs the fingerprint of a store path, the result goes into a `Sig:` line
+    pub fn sign(&self, fingerprint: &str) -> String {
+        let signature = self.key.sign(fingerprint.as_bytes());
+
+        format!("{}:{}", self.name, STANDARD.encode(signature.to_bytes()))
+    }
+}
+
+/// What nix signs for a store path: `1;<path>;<nar hash>;<nar size>;<references>`
+pub fn fingerprint(path: &str, nar_hash: &str, nar_size: u64, references: &[String]) -> String {
+    format!(
+        "1;{};{};{};{}",
+        path,
+        nar_hash,
+        nar_size,
+        references.join(",")
+    )
+}
+
+/// The signing keys in `<data dir>/keys`.
+/// Every active key signs, so a new key can be rolled out before the old one is retired.
+pub struct KeyStore {
+    dir: PathBuf,
+}
+
+impl KeyStore {
+    pub fn new(data_dir: &DataDir) -> Self {
+        KeyStore {
+            dir: data_dir.keys(),
+        }
+    }
+
+    fn retired_dir(&self) -> PathBuf {
+        self.dir.join("retired")
+    }
+
+    fn key_path(dir: &Path, name: &str) -> PathBuf {
+        dir.join(format!("{}.sec", name))
+    }
+
+    fn check_name(name: &str) -> Result<(), KeyError> {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || ".-_".contains(c));
+
+        if !valid {
+            return Err(KeyError::new(format!("Invalid key name: {}", name)));
+        }
+
+        Ok(())
+    }
+
+    /// Creates a new active key, fails if one with the name already exists
+    pub fn generate(&self, name: &str) -> Result<SigningKey, KeyError> {
+        KeyStore::check_name(name)?;
+
+        fs::create_dir_all(&self.dir).map_err(|e| KeyError::new(e.to_string()))?;
+
+        let key = SigningKey::generate(name);
+
+        // the secret key must only be readable by us
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(KeyStore::key_path(&self.dir, name))
+            .map_err(|e| KeyError::new(format!("Failed to create key {}: {}", name, e)))?;
+
+        file.write_all(key.secret_key().as_bytes())
+            .map_err(|e| KeyError::new(e.to_string()))?;
+
+        Ok(key)
+    }
+
+    fn read_keys(dir: &Path) -> Result<Vec<SigningKey>, KeyError> {
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let entries = fs::read_dir(dir).map_err(|e| KeyError::new(e.to_string()))?;
+
+        let mut keys = Vec::new();
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+
+            if path.extension().map(|ext| ext != "sec").unwrap_or(true) {
+                continue;
+            }
+
+            let content = fs::read_to_string(&path).map_err(|e| KeyError::new(e.to_string()))?;
+
+            let key = SigningKey::parse(&content)
+                .map_err(|e| KeyError::new(format!("{}: {}", path.display(), e)))?;
+
+            keys.push(key);
+        }
+
+        keys.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(keys)
+    }
+
+    /// The keys everything is signed with
+    pub fn active(&self) -> Result<Vec<SigningKey>, KeyError> {
+        KeyStore::read_keys(&self.dir)
+    }
+
+    pub fn retired(&self) -> Result<Vec<SigningKey>, KeyError> {
+        KeyStore::read_keys(&self.retired_dir())
+    }
+
+    /// Stops signing with the key. It is kept so it can be looked up later.
+    pub fn retire(&self, name: &str) -> Result<(), KeyError> {
+        KeyStore::check_name(name)?;
+
+        let path = KeyStore::key_path(&self.dir, name);
+
+        if !path.exists() {
+            return Err(KeyError::new(format!("No active key named {}", name)));
+        }
+
+        fs::create_dir_all(self.retired_dir()).map_err(|e| KeyError::new(e.to_string()))?;
+
+        fs::rename(path, KeyStore::key_path(&self.retired_dir(), name))
+            .map_err(|e| KeyError::new(e.to_string()))
+    }
+}
@@ -3,22 +3,135 @@ use ladon::state;
 use std::sync::Arc;
 
 #[cfg(feature = "ssr")]
-use clap::Parser;
+use {
+    clap::{Parser, Subcommand},
+    ladon::hydracore,
+};
 
 #[cfg(feature = "ssr")]
 #[derive(Parser)]
 struct Args {
-    #[arg(short, long = "data-dir", help = "The data directory to use")]
-    data_dir: std::path::PathBuf,
-    #[arg(short='v', long, action = clap::ArgAction::Count, help="Sets the verbose level. More v's more output")]
+    #[arg(
+        short,
+        long = "data-dir",
+        global = true,
+        help = "The data directory to use"
+    )]
+    data_dir: Option<std::path::PathBuf>,
+    #[arg(
+        long = "database-url",
+        global = true,
+        help = "sqlite:// or postgres:// url of the database, defaults to db.sqlite in the data directory"
+    )]
+    database_url: Option<String>,
+    #[arg(short='v', long, global = true, action = clap::ArgAction::Count, help="Sets the verbose level. More v's more output")]
     verbose: u8,
+    #[arg(
+        long = "poll-interval",
+        global = true,
+        default_value_t = 5,
+        help = "How often the queue runner checks for new requests on sqlite, in seconds. Postgres notifies it right away"
+    )]
+    poll_interval: u64,
+    #[arg(
+        long = "instance-id",
+        global = true,
+        help = "Name of this instance for leader election, defaults to <hostname>-<pid>"
+    )]
+    instance_id: Option<String>,
+    #[arg(
+        long = "lease-ttl",
+        global = true,
+        default_value_t = 30,
+        help = "Seconds after which a standby instance takes over from an unresponsive coordinator"
+    )]
+    lease_ttl: u64,
+    #[arg(
+        long = "prune-interval",
+        global = true,
+        default_value_t = 3600,
+        help = "How often old evaluations are pruned while the queue runner is idle, in seconds"
+    )]
+    prune_interval: u64,
+    #[arg(
+        long = "public-url",
+        global = true,
+        help = "Url ladon is reachable under, for the binary-cache-url of channels and the links of commit statuses and notifications, defaults to the site address"
+    )]
+    public_url: Option<String>,
+    #[arg(
+        long = "metrics-address",
+        global = true,
+        help = "Also serves /metrics on this address, for queue runners without the web frontend"
+    )]
+    metrics_address: Option<std::net::SocketAddr>,
+    #[arg(
+        long = "require-login",
+        global = true,
+        help = "Only logged in users may browse, by default anybody may look but only users may change things"
+    )]
+    require_login: bool,
+    #[arg(
+        long = "config",
+        global = true,
+        help = "Toml file with further settings, like the OpenID Connect provider or the forges to report to"
+    )]
+    config: Option<std::path::PathBuf>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[cfg(feature = "ssr")]
+#[derive(Subcommand)]
+enum Command {
+    /// Runs the web frontend and the queue runner in one process (default)
+    All,
+    /// Runs only the web frontend
+    Web,
+    /// Runs only the evaluator and queue runner
+    QueueRunner,
+    /// Manages the keys the binary cache is signed with
+    Keys {
+        #[command(subcommand)]
+        command: KeysCommand,
+    },
+    /// Manages the local accounts
+    Users {
+        #[command(subcommand)]
+        command: UsersCommand,
+    },
+}
+
+#[cfg(feature = "ssr")]
+#[derive(Subcommand)]
+enum UsersCommand {
+    /// Creates an account, asks for its password
+    Add {
+        name: String,
+        #[arg(long, help = "Makes the user an administrator")]
+        admin: bool,
+    },
+    /// Lists the accounts
+    List,
+}
+
+#[cfg(feature = "ssr")]
+#[derive(Subcommand)]
+enum KeysCommand {
+    /// Generates a new signing key, it is used alongside the existing ones
+    Generate {
+        /// Name of the key, like cache.example.com-1
+        name: String,
+    },
+    /// Lists the public keys
+    List,
+    /// Stops signing with the key
+    Retire { name: String },
 }
 
 #[cfg(feature = "ssr")]
 #[tokio::main]
 async fn main() {
-    use ladon::hydracore;
-    use tokio::sync::Mutex;
     use tracing::{error, Level};
     use tracing_subscriber;
 
@@ -34,9 +147,25 @@ async fn main() {
 
     logger.init();
 
-    let path = args.data_dir.join("db.sqlite");
+    if args.data_dir.is_none() {
+        // also with --database-url, the logs, keys, channels and gc roots are kept there
+        error!("No data directory given! Use --data-dir");
+        return;
+    }
+
+    let data_dir = hydracore::DataDir::new(args.data_dir.unwrap());
 
-    let db = hydracore::DB::new(path.to_str().unwrap()).await;
+    if let Some(Command::Keys { command }) = args.command {
+        run_keys(&data_dir, command);
+        return;
+    }
+
+    let database_url = args.database_url.unwrap_or_else(|| {
+        let path = data_dir.path().join("db.sqlite");
+        String::new() + "sqlite://" + path.to_str().unwrap()
+    });
+
+    let db = hydracore::DB::new(&database_url).await;
 
     if db.is_err() {
         error!("Failed to create database: {}", db.err().unwrap());
@@ -45,11 +174,269 @@ async fn main() {
 
     let db = db.unwrap();
 
-    let coordinator = hydracore::Coordinator::new(db);
+    if let Some(Command::Users { command }) = args.command {
+        run_users(&db, command).await;
+        return;
+    }
+
+    let config = match &args.config {
+        Some(path) => match ladon::server::config::Config::load(path) {
+            Ok(config) => config,
+            Err(e) => {
+                error!("{}", e);
+                return;
+            }
+        },
+        None => ladon::server::config::Config::default(),
+    };
+
+    let public_url = args.public_url.unwrap_or_else(default_public_url);
 
-    let state = Arc::new(state::State {
-        coordinator: Mutex::new(coordinator),
-    });
+    // shared by the web frontend and the queue runner if they run in the same process
+    let metrics = hydracore::Metrics::new();
+
+    let web_state = state::State {
+        db: db.clone(),
+        data_dir: data_dir.clone(),
+        metrics: metrics.clone(),
+        require_login: args.require_login,
+        public_url: public_url.clone(),
+        oidc: config.oidc,
+        webhooks: config.webhooks,
+    };
+
+    if let Some(address) = args.metrics_address {
+        tokio::spawn(serve_metrics(
+            address,
+            state::State {
+                db: db.clone(),
+                data_dir: data_dir.clone(),
+                metrics: metrics.clone(),
+                require_login: args.require_login,
+                public_url: public_url.clone(),
+                oidc: None,
+                webhooks: Default::default(),
+            },
+        ));
+    }
+
+    let runner_opts = RunnerOptions {
+        poll_interval: std::time::Duration::from_secs(args.poll_interval),
+        lease_ttl: std::time::Duration::from_secs(args.lease_ttl),
+        prune_interval: std::time::Duration::from_secs(args.prune_interval),
+        instance_id: args.instance_id.unwrap_or_else(default_instance_id),
+        public_url,
+        statuses: config.statuses,
+        metrics: metrics.clone(),
+    };
+
+    match args.command.unwrap_or(Command::All) {
+        Command::All => {
+            tokio::spawn(run_queue_runner(
+                db.clone(),
+                data_dir.clone(),
+                runner_opts,
+                false,
+            ));
+            run_web(web_state).await;
+        }
+        Command::Web => run_web(web_state).await,
+        Command::QueueRunner => run_queue_runner(db, data_dir, runner_opts, true).await,
+        Command::Keys { .. } | Command::Users { .. } => unreachable!(),
+    }
+}
+
+#[cfg(feature = "ssr")]
+struct RunnerOptions {
+    poll_interval: std::time::Duration,
+    lease_ttl: std::time::Duration,
+    prune_interval: std::time::Duration,
+    instance_id: String,
+    public_url: String,
+    statuses: Vec<ladon::server::config::StatusConfig>,
+    metrics: hydracore::Metrics,
+}
+
+#[cfg(feature = "ssr")]
+fn run_keys(data_dir: &hydracore::DataDir, command: KeysCommand) {
+    use tracing::error;
+
+    let store = hydracore::KeyStore::new(data_dir);
+
+    match command {
+        KeysCommand::Generate { name } => match store.generate(&name) {
+            Ok(key) => println!("{}", key.public_key()),
+            Err(e) => error!("Failed to generate key: {}", e),
+        },
+        KeysCommand::List => {
+            let active = store.active();
+            let retired = store.retired();
+
+            if active.is_err() || retired.is_err() {
+                error!("Failed to read keys");
+                return;
+            }
+
+            for key in active.unwrap() {
+                println!("{}", key.public_key());
+            }
+
+            for key in retired.unwrap() {
+                println!("{} (retired)", key.public_key());
+            }
+        }
+        KeysCommand::Retire { name } => {
+            if let Err(e) = store.retire(&name) {
+                error!("Failed to retire key: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+async fn run_users(db: &hydracore::DB, command: UsersCommand) {
+    use std::io::IsTerminal;
+    use tracing::error;
+
+    match command {
+        UsersCommand::Add { name, admin } => {
+            // scripts can pipe the password in
+            let password = if std::io::stdin().is_terminal() {
+                rpassword::prompt_password("Password: ")
+            } else {
+                std::io::stdin().lines().next().unwrap_or(Ok(String::new()))
+            };
+
+            let password = match password {
+                Ok(password) => password,
+                Err(e) => {
+                    error!("Failed to read password: {}", e);
+                    return;
+                }
+            };
+
+            match hydracore::add_user(db, &name, &password, admin).await {
+                Ok(user) => println!("Created user {}", user.name),
+                Err(e) => error!("Failed to create user: {}", e),
+            }
+        }
+        UsersCommand::List => match hydracore::get_users(db).await {
+            Ok(users) => {
+                for user in users {
+                    match user.admin {
+                        true => println!("{} (admin)", user.name),
+                        false => println!("{}", user.name),
+                    }
+                }
+            }
+            Err(e) => error!("Failed to get users: {}", e),
+        },
+    }
+}
+
+#[cfg(feature = "ssr")]
+fn default_instance_id() -> String {
+    let hostname = std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|name| name.trim().to_string())
+        .unwrap_or("ladon".to_string());
+
+    format!("{}-{}", hostname, std::process::id())
+}
+
+/// Where the web frontend listens, LEPTOS_SITE_ADDR as cargo leptos sets it from the site-addr of Cargo.toml
+#[cfg(feature = "ssr")]
+fn default_public_url() -> String {
+    use leptos::prelude::get_configuration;
+
+    let addr = get_configuration(None)
+        .map(|conf| conf.leptos_options.site_addr.to_string())
+        .unwrap_or("127.0.0.1:3598".to_string());
+
+    format!("http://{}", addr)
+}
+
+/// Runs as the coordinator while this instance holds the lease.
+/// Losing it stops the process if it only runs the queue runner, else this instance goes back to standby
+/// so the web frontend of the same process keeps running.
+#[cfg(feature = "ssr")]
+async fn run_queue_runner(
+    db: hydracore::DB,
+    data_dir: hydracore::DataDir,
+    opts: RunnerOptions,
+    exit_on_lost_lease: bool,
+) {
+    use tracing::{error, warn};
+
+    // only one instance may schedule and build at a time, the others wait in standby
+    let election = hydracore::LeaderElection::new(db.clone(), opts.instance_id, opts.lease_ttl);
+
+    loop {
+        let lease = election.wait_for_leadership().await;
+
+        let coordinator = hydracore::Coordinator::new(
+            db.clone(),
+            data_dir.clone(),
+            lease.clone(),
+            opts.public_url.clone(),
+            opts.statuses.clone(),
+            opts.metrics.clone(),
+        );
+
+        let runner = hydracore::QueueRunner::new(coordinator.clone(), opts.poll_interval);
+
+        let work = async {
+            coordinator.resume_unfinished().await;
+
+            tokio::join!(coordinator.run_pruning(opts.prune_interval), runner.run());
+        };
+
+        // the work never finishes, it ends with the lease
+        tokio::select! {
+            _ = election.keep_alive(lease) => {},
+            _ = work => {},
+        }
+
+        coordinator.stop();
+
+        if exit_on_lost_lease {
+            error!("Shutting down after losing the coordinator lease");
+            std::process::exit(1);
+        }
+
+        warn!("Stopped coordinating after losing the lease, going back to standby");
+    }
+}
+
+#[cfg(feature = "ssr")]
+async fn serve_metrics(address: std::net::SocketAddr, state: state::State) {
+    use tracing::{error, info};
+
+    let listener = tokio::net::TcpListener::bind(address).await;
+
+    if listener.is_err() {
+        error!(
+            "Failed to listen on {}: {}",
+            address,
+            listener.err().unwrap()
+        );
+        return;
+    }
+
+    info!("Serving metrics on http://{}/metrics", address);
+
+    let app = ladon::server::metrics_router(Arc::new(state));
+
+    let result = axum::serve(listener.unwrap(), app.into_make_service()).await;
+
+    if result.is_err() {
+        error!("Failed to serve metrics: {}", result.err().unwrap());
+    }
+}
+
+#[cfg(feature = "ssr")]
+async fn run_web(state: state::State) {
+    let state = Arc::new(state);
+    let server_state = state.clone();
 
     use axum::Router;
     use ladon::app::*;
@@ -67,14 +454,25 @@ async fn main() {
         .leptos_routes_with_context(
             &leptos_options,
             routes,
-            move || provide_context(state.clone()),
+            {
+                let state = state.clone();
+                move || provide_context(state.clone())
+            },
             {
                 let leptos_options = leptos_options.clone();
                 move || shell(leptos_options.clone())
             },
         )
-        .fallback(leptos_axum::file_and_error_handler(shell))
-        .with_state(leptos_options);
+        .fallback(leptos_axum::file_and_error_handler_with_context(
+            move || provide_context(state.clone()),
+            shell,
+        ))
+        .with_state(leptos_options)
+        .merge(ladon::server::router(server_state.clone()))
+        .layer(axum::middleware::from_fn_with_state(
+            server_state,
+            ladon::server::auth::require_login,
+        ));
 
     // run our app with hyper
     // `axum::Server` is a re-export of `hyper::Server`
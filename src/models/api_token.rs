@@ -0,0 +1,173 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::hydracore::{with_db, DBError, DB},
+    sqlx::{query, query_as, query_scalar},
+};
+
+/// What requests with a token may do, every scope may do what the scopes before it may.
+/// A token never allows more than the role of its user.
+#[cfg_attr(feature = "ssr", derive(sqlx::Type), sqlx(type_name = "text"))]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TokenScope {
+    /// May read projects, jobsets, evaluations and jobs
+    Read,
+    /// May also trigger and cancel jobsets and restart jobs
+    Trigger,
+    /// May also create, change and delete projects and jobsets
+    Admin,
+}
+
+impl std::fmt::Display for TokenScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            TokenScope::Read => "read",
+            TokenScope::Trigger => "trigger",
+            TokenScope::Admin => "admin",
+        })
+    }
+}
+
+/// A token for scripts, the database only keeps the hash of the token itself
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ApiToken {
+    pub id: Option<i32>,
+    pub user_id: i32,
+    /// The only project the token may be used for
+    pub project_id: Option<i32>,
+    #[cfg_attr(feature = "ssr", sqlx(default))]
+    pub project_name: Option<String>,
+    pub name: String,
+    pub scope: TokenScope,
+    pub created: DateTime<Utc>,
+    pub expires: Option<DateTime<Utc>>,
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+#[cfg(feature = "ssr")]
+impl ApiToken {
+    pub fn new(
+        user_id: i32,
+        project_id: Option<i32>,
+        name: String,
+        scope: TokenScope,
+        expires: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            id: None,
+            user_id,
+            project_id,
+            project_name: None,
+            name,
+            scope,
+            created: Utc::now(),
+            expires,
+            last_used: None,
+        }
+    }
+
+    pub async fn add_to_db(&mut self, db: &DB, token_hash: &str) -> Result<(), DBError> {
+        let result = with_db!(db, |pool| query_scalar::<_, i32>(
+            "
+                insert into ApiTokens
+                    (user_id, project_id, name, token_hash, scope, created, expires)
+                values
+                    ($1, $2, $3, $4, $5, $6, $7)
+                returning id
+            ",
+        )
+        .bind(self.user_id)
+        .bind(self.project_id)
+        .bind(&self.name)
+        .bind(token_hash)
+        .bind(self.scope)
+        .bind(self.created)
+        .bind(self.expires)
+        .fetch_one(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        self.id = Some(result);
+
+        Ok(())
+    }
+
+    pub async fn get_all(db: &DB, user_id: i32) -> Result<Vec<ApiToken>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, ApiToken>(
+            "
+                select ApiTokens.*, Projects.name as project_name
+                from ApiTokens
+                left join Projects on Projects.id = ApiTokens.project_id
+                where user_id = $1
+                order by ApiTokens.id
+            ",
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    /// The token with the hash, if it did not expire
+    pub async fn get_valid(db: &DB, token_hash: &str) -> Result<Option<ApiToken>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, ApiToken>(
+            "
+                select *
+                from ApiTokens
+                where token_hash = $1 and (expires is null or expires > $2)
+            ",
+        )
+        .bind(token_hash)
+        .bind(Utc::now())
+        .fetch_optional(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    pub async fn set_last_used(&mut self, db: &DB) -> Result<(), DBError> {
+        let now = Utc::now();
+
+        _ = with_db!(db, |pool| query(
+            "
+                update ApiTokens
+                set last_used = $1
+                where id = $2
+            ",
+        )
+        .bind(now)
+        .bind(self.id)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected()))
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        self.last_used = Some(now);
+
+        Ok(())
+    }
+
+    /// Returns false if the user has no such token
+    pub async fn delete(db: &DB, id: i32, user_id: i32) -> Result<bool, DBError> {
+        let result = with_db!(db, |pool| query(
+            "
+                delete from ApiTokens
+                where id = $1 and user_id = $2
+            ",
+        )
+        .bind(id)
+        .bind(user_id)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected()))
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result > 0)
+    }
+}
@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::hydracore::{with_db, with_tx, DBError, DB},
+    sqlx::{query_as, query_scalar},
+};
+
+/// A file or directory a job lists in `$out/nix-support/hydra-build-products`
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BuildProduct {
+    pub id: Option<i32>,
+    pub job_id: i32,
+    pub r#type: String,
+    pub subtype: String,
+    pub path: String,
+    pub name: String,
+    pub default_path: Option<String>,
+    /// Not set for directories
+    pub file_size: Option<i64>,
+    /// Hex encoded, not set for directories
+    pub sha256: Option<String>,
+}
+
+impl BuildProduct {
+    pub fn is_directory(&self) -> bool {
+        self.file_size.is_none()
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl BuildProduct {
+    /// Records the products of the job in one transaction
+    pub async fn add_many(db: &DB, products: &mut [BuildProduct]) -> Result<(), DBError> {
+        let mut tx = db.begin().await?;
+
+        for product in products.iter_mut() {
+            let result = with_tx!(&mut tx, |conn| query_scalar::<_, i32>(
+                "
+                    insert into BuildProducts
+                        (job_id, type, subtype, path, name, default_path, file_size, sha256)
+                    values
+                        ($1, $2, $3, $4, $5, $6, $7, $8)
+                    returning id
+                ",
+            )
+            .bind(product.job_id)
+            .bind(&product.r#type)
+            .bind(&product.subtype)
+            .bind(&product.path)
+            .bind(&product.name)
+            .bind(&product.default_path)
+            .bind(product.file_size)
+            .bind(&product.sha256)
+            .fetch_one(conn)
+            .await)
+            .map_err(|e| DBError::new(e.to_string()))?;
+
+            product.id = Some(result);
+        }
+
+        tx.commit().await
+    }
+
+    pub async fn get_all(db: &DB, job_id: i32) -> Result<Vec<BuildProduct>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, BuildProduct>(
+            "
+                select *
+                from BuildProducts
+                where job_id = $1
+                order by id
+            ",
+        )
+        .bind(job_id)
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    /// The product, if it belongs to the job of the project's jobset
+    pub async fn get_single(
+        db: &DB,
+        project_id: i32,
+        jobset_id: i32,
+        job_id: i32,
+        id: i32,
+    ) -> Result<Option<BuildProduct>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, BuildProduct>(
+            "
+                select BuildProducts.*
+                from BuildProducts
+                join Jobs on Jobs.id = BuildProducts.job_id
+                join Evaluations on Evaluations.id = Jobs.evaluation_id
+                join Jobsets on Jobsets.id = Evaluations.jobset_id
+                where Jobsets.project_id = $1 and Jobsets.id = $2
+                    and Jobs.id = $3 and BuildProducts.id = $4
+            ",
+        )
+        .bind(project_id)
+        .bind(jobset_id)
+        .bind(job_id)
+        .bind(id)
+        .fetch_optional(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+}
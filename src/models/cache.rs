@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::hydracore::{with_db, DBError, DB},
+    sqlx::{query, query_as, query_scalar},
+};
+
+/// An external binary cache the outputs of a project are copied to
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Cache {
+    pub id: Option<i32>,
+    pub project_id: i32,
+    /// Nix store url, query parameters like `?endpoint=` for s3 are passed on to nix
+    pub url: String,
+}
+
+/// Store settings which may be set in the url, others like `secret-key` or `ssh-key`
+/// would make nix read files or run programs of the server
+const ALLOWED_PARAMS: [&str; 13] = [
+    "endpoint",
+    "region",
+    "scheme",
+    "profile",
+    "compression",
+    "compression-level",
+    "parallel-compression",
+    "multipart-upload",
+    "write-nar-listing",
+    "want-mass-query",
+    "priority",
+    "compress",
+    "base64-ssh-public-host-key",
+];
+
+impl Cache {
+    pub fn is_supported_url(url: &str) -> bool {
+        let (store, params) = url.split_once('?').unwrap_or((url, ""));
+
+        let scheme_ok = ["file://", "s3://", "ssh-ng://"]
+            .iter()
+            .any(|scheme| store.starts_with(scheme) && store.len() > scheme.len());
+
+        let params_ok = params
+            .split('&')
+            .filter(|param| !param.is_empty())
+            .all(|param| {
+                let name = param.split_once('=').map_or(param, |(name, _)| name);
+                ALLOWED_PARAMS.contains(&name)
+            });
+
+        scheme_ok && params_ok
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl Cache {
+    pub fn new(project_id: i32, url: String) -> Self {
+        Self {
+            id: None,
+            project_id,
+            url,
+        }
+    }
+
+    pub async fn add_to_db(&mut self, db: &DB) -> Result<(), DBError> {
+        let result = with_db!(db, |pool| query_scalar::<_, i32>(
+            "
+                insert into Caches
+                    (project_id, url)
+                values
+                    ($1, $2)
+                returning id
+            ",
+        )
+        .bind(self.project_id)
+        .bind(&self.url)
+        .fetch_one(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        self.id = Some(result);
+
+        Ok(())
+    }
+
+    pub async fn get_all(db: &DB, project_id: i32) -> Result<Vec<Cache>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, Cache>(
+            "
+                select *
+                from Caches
+                where project_id = $1
+                order by id
+            ",
+        )
+        .bind(project_id)
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    /// The caches of the project the job belongs to
+    pub async fn get_for_job(db: &DB, job_id: i32) -> Result<Vec<Cache>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, Cache>(
+            "
+                select Caches.*
+                from Caches
+                join Jobsets on Jobsets.project_id = Caches.project_id
+                join Evaluations on Evaluations.jobset_id = Jobsets.id
+                join Jobs on Jobs.evaluation_id = Evaluations.id
+                where Jobs.id = $1
+                order by Caches.id
+            ",
+        )
+        .bind(job_id)
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    /// Deletes the cache of the project, returns false if there was none
+    pub async fn delete(db: &DB, project_id: i32, id: i32) -> Result<bool, DBError> {
+        let result = with_db!(db, |pool| query(
+            "
+                delete from Caches
+                where id = $1 and project_id = $2
+            ",
+        )
+        .bind(id)
+        .bind(project_id)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected()))
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result == 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cache;
+
+    #[test]
+    fn supported_urls() {
+        assert!(Cache::is_supported_url("file:///srv/cache"));
+        assert!(Cache::is_supported_url("ssh-ng://host"));
+        assert!(Cache::is_supported_url(
+            "s3://bucket?endpoint=localhost:9000&region=eu-west-1"
+        ));
+
+        assert!(!Cache::is_supported_url("file://"));
+        assert!(!Cache::is_supported_url("https://cache.example.com"));
+        assert!(!Cache::is_supported_url(
+            "s3://bucket?secret-key=/var/lib/ladon/key"
+        ));
+        assert!(!Cache::is_supported_url(
+            "ssh-ng://host?ssh-key=/root/.ssh/id_ed25519"
+        ));
+        assert!(!Cache::is_supported_url("ssh-ng://host?remote-program=sh"));
+    }
+}
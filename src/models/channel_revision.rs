@@ -0,0 +1,144 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::hydracore::{with_db, DBError, DB},
+    sqlx::{query_as, query_scalar},
+};
+
+/// A release of a jobset's channel, the newest one is the channel head
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChannelRevision {
+    pub id: Option<i32>,
+    pub jobset_id: i32,
+    /// Not set anymore once the evaluation was pruned
+    pub evaluation_id: Option<i32>,
+    pub name: String,
+    pub revision: Option<String>,
+    pub created: DateTime<Utc>,
+}
+
+#[cfg(feature = "ssr")]
+impl ChannelRevision {
+    pub fn new(jobset_id: i32, evaluation_id: i32, name: String, revision: Option<String>) -> Self {
+        Self {
+            id: None,
+            jobset_id,
+            evaluation_id: Some(evaluation_id),
+            name,
+            revision,
+            created: Utc::now(),
+        }
+    }
+
+    /// Adding a revision advances the channel
+    pub async fn add_to_db(&mut self, db: &DB) -> Result<(), DBError> {
+        let result = with_db!(db, |pool| query_scalar::<_, i32>(
+            "
+                insert into ChannelRevisions
+                    (jobset_id, evaluation_id, name, revision, created)
+                values
+                    ($1, $2, $3, $4, $5)
+                returning id
+            ",
+        )
+        .bind(self.jobset_id)
+        .bind(self.evaluation_id)
+        .bind(&self.name)
+        .bind(&self.revision)
+        .bind(self.created)
+        .fetch_one(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        self.id = Some(result);
+
+        Ok(())
+    }
+
+    pub async fn get_all(db: &DB, jobset_id: i32) -> Result<Vec<ChannelRevision>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, ChannelRevision>(
+            "
+                select *
+                from ChannelRevisions
+                where jobset_id = $1
+                order by id desc
+            ",
+        )
+        .bind(jobset_id)
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    /// The revision, if it belongs to the project's jobset
+    pub async fn get_single(
+        db: &DB,
+        project_id: i32,
+        jobset_id: i32,
+        id: i32,
+    ) -> Result<Option<ChannelRevision>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, ChannelRevision>(
+            "
+                select ChannelRevisions.*
+                from ChannelRevisions
+                join Jobsets on Jobsets.id = ChannelRevisions.jobset_id
+                where Jobsets.project_id = $1 and Jobsets.id = $2 and ChannelRevisions.id = $3
+            ",
+        )
+        .bind(project_id)
+        .bind(jobset_id)
+        .bind(id)
+        .fetch_optional(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    /// The channel head of the project's jobset
+    pub async fn get_head(
+        db: &DB,
+        project_id: i32,
+        jobset_id: i32,
+    ) -> Result<Option<ChannelRevision>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, ChannelRevision>(
+            "
+                select ChannelRevisions.*
+                from ChannelRevisions
+                join Jobsets on Jobsets.id = ChannelRevisions.jobset_id
+                where Jobsets.project_id = $1 and Jobsets.id = $2
+                order by ChannelRevisions.id desc
+                limit 1
+            ",
+        )
+        .bind(project_id)
+        .bind(jobset_id)
+        .fetch_optional(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    /// Id of the newest evaluation the jobset's channel was released from
+    pub async fn get_latest_evaluation(db: &DB, jobset_id: i32) -> Result<Option<i32>, DBError> {
+        let result = with_db!(db, |pool| query_scalar::<_, Option<i32>>(
+            "
+                select max(evaluation_id)
+                from ChannelRevisions
+                where jobset_id = $1
+            ",
+        )
+        .bind(jobset_id)
+        .fetch_one(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::hydracore::{with_tx, DBError, Transaction},
+    crate::models::JobState,
+    sqlx::{query, query_as, query_scalar},
+};
+
+/// Everything that was removed together with a project or jobset
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct DeletionReport {
+    pub jobsets: i64,
+    pub evaluations: i64,
+    pub jobs: i64,
+    /// Evaluations which were still running and got cancelled
+    pub cancelled_evaluations: i64,
+    /// Builds which were queued or running and got cancelled
+    pub cancelled_builds: i64,
+    pub logs: i64,
+    pub gc_roots: i64,
+    #[serde(skip)]
+    pub jobset_ids: Vec<i32>,
+    #[serde(skip)]
+    pub job_ids: Vec<i32>,
+}
+
+#[cfg(feature = "ssr")]
+impl DeletionReport {
+    /// Collects what deleting the jobsets selected by `jobsets` will remove
+    /// and drops their pending queue requests.
+    /// `jobsets` is a subquery returning jobset ids, it gets `id` bound as `$1`.
+    pub(crate) async fn prepare(
+        tx: &mut Transaction,
+        jobsets: &str,
+        id: i32,
+    ) -> Result<Self, DBError> {
+        let mut report = DeletionReport::default();
+
+        let (count, evaluating): (i64, i64) = with_tx!(tx, |conn| query_as(&format!(
+            "
+                select count(*), coalesce(sum(case when state = 'Evaluating' then 1 else 0 end), 0)
+                from Jobsets
+                where id in ({})
+            ",
+            jobsets
+        ))
+        .bind(id)
+        .fetch_one(conn)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        report.jobsets = count;
+        report.cancelled_evaluations = evaluating;
+
+        report.jobset_ids = with_tx!(tx, |conn| query_scalar::<_, i32>(&format!(
+            "
+                select id
+                from Jobsets
+                where id in ({})
+            ",
+            jobsets
+        ))
+        .bind(id)
+        .fetch_all(conn)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        let (count,): (i64,) = with_tx!(tx, |conn| query_as(&format!(
+            "
+                select count(*)
+                from Evaluations
+                where jobset_id in ({})
+            ",
+            jobsets
+        ))
+        .bind(id)
+        .fetch_one(conn)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        report.evaluations = count;
+
+        let jobs: Vec<(i32, Option<JobState>)> = with_tx!(tx, |conn| query_as(&format!(
+            "
+                select Jobs.id, Jobs.state
+                from Jobs
+                join Evaluations on Evaluations.id = Jobs.evaluation_id
+                where Evaluations.jobset_id in ({})
+            ",
+            jobsets
+        ))
+        .bind(id)
+        .fetch_all(conn)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        for (job_id, state) in jobs {
+            if matches!(state, Some(JobState::ToBeBuilt) | Some(JobState::Building)) {
+                report.cancelled_builds += 1;
+            }
+
+            report.job_ids.push(job_id);
+        }
+
+        report.jobs = report.job_ids.len() as i64;
+
+        // queue requests don't reference the jobset, so they are not cascaded
+        _ = with_tx!(tx, |conn| query(&format!(
+            "
+                delete from QueueRequests
+                where jobset_id in ({})
+            ",
+            jobsets
+        ))
+        .bind(id)
+        .execute(conn)
+        .await
+        .map(|result| result.rows_affected()))
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(report)
+    }
+
+    /// Whether the queue runner has to stop anything
+    pub fn has_active_work(&self) -> bool {
+        self.cancelled_evaluations > 0 || self.cancelled_builds > 0
+    }
+}
@@ -1,16 +1,55 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "ssr")]
 use {
-    crate::hydracore::{DBError, DB},
-    sqlx::query,
+    crate::hydracore::{with_db, with_tx, DBError, Transaction, DB},
+    crate::models::Job,
+    chrono::TimeDelta,
+    sqlx::{query_as, query_scalar, QueryBuilder},
 };
 
 #[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Evaluation {
     pub id: Option<i32>,
     pub jobset_id: i32,
+    pub created: Option<DateTime<Utc>>,
+    /// Locked flake reference which was evaluated
+    pub flake: Option<String>,
+    /// Git revision of the flake
+    pub revision: Option<String>,
+}
+
+/// An evaluation of a jobset with a retention limit, which nothing protects from pruning
+#[cfg(feature = "ssr")]
+#[derive(sqlx::FromRow)]
+struct PruneCandidate {
+    id: i32,
+    created: Option<DateTime<Utc>>,
+    max_evaluations: i32,
+    max_evaluation_age: i32,
+    /// 1 for the latest evaluation of the jobset
+    age: i64,
+}
+
+#[cfg(feature = "ssr")]
+impl PruneCandidate {
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        if self.max_evaluations > 0 && self.age > self.max_evaluations as i64 {
+            return true;
+        }
+
+        if self.max_evaluation_age <= 0 {
+            return false;
+        }
+
+        match self.created {
+            Some(created) => now - created > TimeDelta::days(self.max_evaluation_age as i64),
+            None => false,
+        }
+    }
 }
 
 #[cfg(feature = "ssr")]
@@ -19,25 +58,112 @@ impl Evaluation {
         Self {
             id: None,
             jobset_id,
+            created: Some(Utc::now()),
+            flake: None,
+            revision: None,
         }
     }
 
-    pub async fn add_to_db(&mut self, db: &DB) -> Result<(), DBError> {
-        let mut conn = db.get_conn().await?;
+    pub async fn get_single(db: &DB, id: i32) -> Result<Option<Evaluation>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, Evaluation>(
+            "
+                select *
+                from Evaluations
+                where id = $1
+            ",
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    /// The evaluations of the jobset, newest first, and how many there are in total
+    pub async fn get_page(
+        db: &DB,
+        jobset_id: i32,
+        offset: i64,
+        limit: i64,
+    ) -> Result<(Vec<Evaluation>, i64), DBError> {
+        let total = with_db!(db, |pool| query_scalar::<_, i64>(
+            "
+                select count(*)
+                from Evaluations
+                where jobset_id = $1
+            ",
+        )
+        .bind(jobset_id)
+        .fetch_one(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        let result = with_db!(db, |pool| query_as::<_, Evaluation>(
+            "
+                select *
+                from Evaluations
+                where jobset_id = $1
+                order by id desc
+                limit $2 offset $3
+            ",
+        )
+        .bind(jobset_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok((result, total))
+    }
+
+    /// Adds the evaluation together with its jobs in a single transaction.
+    /// `constituents` links aggregates to their constituents by their index in `jobs`.
+    pub async fn add_with_jobs(
+        &mut self,
+        db: &DB,
+        jobs: &mut [Job],
+        constituents: &[(usize, usize)],
+    ) -> Result<(), DBError> {
+        let mut tx = db.begin().await?;
+
+        self.insert(&mut tx).await?;
+
+        for job in jobs.iter_mut() {
+            job.evaluation_id = self.id.unwrap();
+            job.insert(&mut tx).await?;
+        }
+
+        for (aggregate, constituent) in constituents.iter() {
+            Job::add_constituent(
+                &mut tx,
+                jobs[*aggregate].id.unwrap(),
+                jobs[*constituent].id.unwrap(),
+            )
+            .await?;
+        }
+
+        tx.commit().await
+    }
 
-        let result = query!(
+    async fn insert(&mut self, tx: &mut Transaction) -> Result<(), DBError> {
+        let result = with_tx!(tx, |conn| query_scalar::<_, i32>(
             "
                 insert into Evaluations
-                    (jobset_id)
+                    (jobset_id, created, flake, revision)
                 values
-                    (?)
+                    ($1, $2, $3, $4)
                 returning id
                 
             ",
-            self.jobset_id
         )
-        .fetch_one(&mut *conn)
-        .await;
+        .bind(self.jobset_id)
+        .bind(self.created)
+        .bind(&self.flake)
+        .bind(&self.revision)
+        .fetch_one(conn)
+        .await);
 
         if result.is_err() {
             return Err(DBError::new(result.err().unwrap().to_string()));
@@ -45,7 +171,114 @@ impl Evaluation {
 
         let result = result.unwrap();
 
-        self.id = Some(result.id as i32);
+        self.id = Some(result);
         Ok(())
     }
+
+    /// Up to `limit` evaluations which exceed the limits of their jobset, oldest first.
+    /// The latest successful evaluation of a jobset, channel heads and evaluations with kept or unfinished jobs are never returned.
+    pub async fn get_prunable(db: &DB, limit: usize) -> Result<Vec<i32>, DBError> {
+        let candidates = with_db!(db, |pool| query_as::<_, PruneCandidate>(
+            "
+                with Ages as (
+                    select
+                        Evaluations.id,
+                        Evaluations.created,
+                        Jobsets.max_evaluations,
+                        Jobsets.max_evaluation_age,
+                        row_number() over (
+                            partition by Evaluations.jobset_id
+                            order by Evaluations.id desc
+                        ) as age
+                    from Evaluations
+                    join Jobsets on Jobsets.id = Evaluations.jobset_id
+                    where Jobsets.max_evaluations > 0 or Jobsets.max_evaluation_age > 0
+                ),
+                LatestSuccessful as (
+                    select max(Evaluations.id) as id
+                    from Evaluations
+                    where exists (
+                        select 1 from Jobs
+                        where Jobs.evaluation_id = Evaluations.id
+                    )
+                    and not exists (
+                        select 1 from Jobs
+                        where Jobs.evaluation_id = Evaluations.id and Jobs.state <> 'Done'
+                    )
+                    group by Evaluations.jobset_id
+                )
+                select Ages.*
+                from Ages
+                where Ages.id not in (select id from LatestSuccessful)
+                    and Ages.id not in (
+                        select evaluation_id
+                        from ChannelRevisions
+                        where evaluation_id is not null
+                            and id in (select max(id) from ChannelRevisions group by jobset_id)
+                    )
+                    and not exists (
+                        select 1 from Jobs
+                        where Jobs.evaluation_id = Ages.id
+                            and (Jobs.keep or Jobs.state in ('ToBeBuilt', 'Building', 'Waiting'))
+                    )
+                order by Ages.id
+            ",
+        )
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        let now = Utc::now();
+
+        Ok(candidates
+            .into_iter()
+            .filter(|candidate| candidate.is_expired(now))
+            .map(|candidate| candidate.id)
+            .take(limit)
+            .collect())
+    }
+
+    /// Deletes the evaluations together with their jobs, returns the ids of the deleted jobs
+    pub async fn delete_many(db: &DB, ids: &[i32]) -> Result<Vec<i32>, DBError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut tx = db.begin().await?;
+
+        let job_ids = with_tx!(&mut tx, |conn| {
+            let mut query = QueryBuilder::new("select id from Jobs where evaluation_id in (");
+
+            let mut separated = query.separated(", ");
+            for id in ids {
+                separated.push_bind(id);
+            }
+            query.push(")");
+
+            query.build_query_scalar::<i32>().fetch_all(conn).await
+        })
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        // jobs, their paths and uploads go with the evaluation
+        _ = with_tx!(&mut tx, |conn| {
+            let mut query = QueryBuilder::new("delete from Evaluations where id in (");
+
+            let mut separated = query.separated(", ");
+            for id in ids {
+                separated.push_bind(id);
+            }
+            query.push(")");
+
+            query
+                .build()
+                .execute(conn)
+                .await
+                .map(|result| result.rows_affected())
+        })
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        tx.commit().await?;
+
+        Ok(job_ids)
+    }
 }
@@ -3,8 +3,9 @@ use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "ssr")]
 use {
-    crate::hydracore::{DBError, DB},
-    sqlx::{query, QueryBuilder, Sqlite},
+    crate::hydracore::{with_db, with_tx, DBError, Transaction, DB},
+    crate::models::User,
+    sqlx::{query, query_as, query_scalar, QueryBuilder},
 };
 
 pub struct JobDiff {
@@ -13,6 +14,7 @@ pub struct JobDiff {
     pub derivation_path: Option<String>,
     pub state: Option<JobState>,
     pub finished: Option<DateTime<Utc>>,
+    pub keep: Option<bool>,
 }
 
 impl JobDiff {
@@ -23,20 +25,25 @@ impl JobDiff {
             derivation_path: None,
             state: None,
             finished: None,
+            keep: None,
         }
     }
 }
 
-#[cfg_attr(feature = "ssr", derive(sqlx::Type))]
+#[cfg_attr(feature = "ssr", derive(sqlx::Type), sqlx(type_name = "text"))]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub enum JobState {
     ToBeBuilt,
     Building,
     Failed,
     Done,
+    /// An aggregate which was built and waits for its constituents
+    Waiting,
 }
 
 #[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Job {
     pub id: Option<i32>,
@@ -45,6 +52,50 @@ pub struct Job {
     pub derivation_path: String,
     pub state: JobState,
     pub finished: Option<DateTime<Utc>>,
+    /// Pinned by a user, its outputs stay rooted
+    pub keep: bool,
+    /// Only succeeds if all of its constituents do
+    pub aggregate: bool,
+}
+
+/// Which jobs `Job::get_page` returns, unset fields match every job
+#[cfg(feature = "ssr")]
+#[derive(Default)]
+pub struct JobFilter {
+    pub evaluation_id: Option<i32>,
+    pub jobset_id: Option<i32>,
+    pub state: Option<JobState>,
+    /// Part of the attribute name
+    pub attribute: Option<String>,
+    /// Leaves out jobs of private projects `member_id` is no member of
+    pub hide_private: bool,
+    pub member_id: Option<i32>,
+    /// Only jobs of this project, for api tokens limited to it
+    pub project_id: Option<i32>,
+}
+
+#[cfg(feature = "ssr")]
+impl JobFilter {
+    /// Only the jobs of projects the user may see
+    pub fn visible_to(mut self, user: Option<&User>) -> Self {
+        self.hide_private = !user.is_some_and(|user| user.admin);
+        self.member_id = user.and_then(|user| user.id);
+        self
+    }
+}
+
+/// A job together with the jobset and project it was evaluated in
+#[cfg(feature = "ssr")]
+#[derive(sqlx::FromRow)]
+pub struct JobOrigin {
+    #[sqlx(flatten)]
+    pub job: Job,
+    pub project_id: i32,
+    pub project_name: String,
+    pub jobset_id: i32,
+    pub jobset_name: String,
+    /// When the evaluation which created the job was added
+    pub evaluated: Option<DateTime<Utc>>,
 }
 
 #[cfg(feature = "ssr")]
@@ -57,120 +108,561 @@ impl Job {
             derivation_path,
             state: JobState::ToBeBuilt,
             finished: None,
+            keep: false,
+            aggregate: false,
         }
     }
 
-    pub async fn add_to_db(&mut self, db: &DB) -> Result<(), DBError> {
-        let mut conn = db.get_conn().await?;
-
-        let result = query!(
+    /// Inserts the job as part of a transaction
+    pub(crate) async fn insert(&mut self, tx: &mut Transaction) -> Result<(), DBError> {
+        let result = with_tx!(tx, |conn| query_scalar::<_, i32>(
             "
                 insert into Jobs
-                    (evaluation_id, attribute_name, derivation_path, state, finished)
+                    (evaluation_id, attribute_name, derivation_path, state, finished, keep, aggregate)
                 values
-                    (?, ?, ?, ?, ?)
+                    ($1, $2, $3, $4, $5, $6, $7)
                 returning id
             ",
-            self.evaluation_id,
-            self.attribute_name,
-            self.derivation_path,
-            self.state,
-            self.finished,
         )
-        .fetch_one(&mut *conn)
-        .await
+        .bind(self.evaluation_id)
+        .bind(&self.attribute_name)
+        .bind(&self.derivation_path)
+        .bind(&self.state)
+        .bind(self.finished)
+        .bind(self.keep)
+        .bind(self.aggregate)
+        .fetch_one(conn)
+        .await)
         .map_err(|e| DBError::new(e.to_string()))?;
 
-        self.id = Some(result.id as i32);
+        self.id = Some(result);
 
         Ok(())
     }
 
-    pub async fn update_job(&mut self, db: &DB, diff: JobDiff) -> Result<(), DBError> {
-        let mut conn = db.get_conn().await?;
-        let self_id = self.id.unwrap();
+    /// Makes the job a constituent of the aggregate, as part of a transaction
+    pub(crate) async fn add_constituent(
+        tx: &mut Transaction,
+        aggregate_id: i32,
+        constituent_id: i32,
+    ) -> Result<(), DBError> {
+        _ = with_tx!(tx, |conn| query(
+            "
+                insert into Constituents
+                    (aggregate_id, constituent_id)
+                values
+                    ($1, $2)
+                on conflict do nothing
+            ",
+        )
+        .bind(aggregate_id)
+        .bind(constituent_id)
+        .execute(conn)
+        .await
+        .map(|result| result.rows_affected()))
+        .map_err(|e| DBError::new(e.to_string()))?;
 
-        let mut query: QueryBuilder<'_, Sqlite> = QueryBuilder::new("update Jobs set ");
+        Ok(())
+    }
 
-        let mut separated = query.separated(", ");
+    pub async fn get_constituents(db: &DB, aggregate_id: i32) -> Result<Vec<Job>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, Job>(
+            "
+                select Jobs.*
+                from Jobs
+                join Constituents on Constituents.constituent_id = Jobs.id
+                where Constituents.aggregate_id = $1
+                order by Jobs.attribute_name
+            ",
+        )
+        .bind(aggregate_id)
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
 
-        let mut has_updates = false;
+        Ok(result)
+    }
 
-        macro_rules! handle_field_base {
-            ($field:ident, $column:literal, $val:ident => $assign_expr:expr) => {
-                if let Some($val) = diff.$field {
-                    self.$field = $assign_expr;
-                    separated
-                        .push(concat!($column, " = "))
-                        .push_bind_unseparated(&self.$field);
-                    has_updates = true;
-                }
-            };
-        }
+    /// Aggregates of the job which wait for their constituents
+    pub async fn get_waiting_aggregates(db: &DB, constituent_id: i32) -> Result<Vec<Job>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, Job>(
+            "
+                select Jobs.*
+                from Jobs
+                join Constituents on Constituents.aggregate_id = Jobs.id
+                where Constituents.constituent_id = $1 and Jobs.state = 'Waiting'
+            ",
+        )
+        .bind(constituent_id)
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    pub async fn get_waiting(db: &DB) -> Result<Vec<Job>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, Job>(
+            "
+                select *
+                from Jobs
+                where state = 'Waiting'
+            ",
+        )
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
 
-        macro_rules! handle_field {
-            ($field:ident, $column:literal) => {
-                handle_field_base!($field, $column, value => value);
-            };
+    /// State of a built aggregate: failed as soon as one constituent failed, done once all are
+    pub fn aggregate_state(constituents: &[Job]) -> JobState {
+        if constituents
+            .iter()
+            .any(|job| matches!(job.state, JobState::Failed))
+        {
+            return JobState::Failed;
         }
 
-        macro_rules! handle_field_some {
-            ($field:ident, $column:literal) => {
-                handle_field_base!($field, $column, value => Some(value));
-            };
+        if constituents
+            .iter()
+            .all(|job| matches!(job.state, JobState::Done))
+        {
+            return JobState::Done;
         }
 
-        handle_field!(evaluation_id, "evaluation_id");
-        handle_field!(attribute_name, "attribute_name");
-        handle_field!(derivation_path, "derivation_path");
-        handle_field!(state, "state");
-        handle_field_some!(finished, "finished");
+        JobState::Waiting
+    }
 
-        if !has_updates {
-            return Ok(());
-        }
+    pub async fn update_job(&mut self, db: &DB, diff: JobDiff) -> Result<(), DBError> {
+        let self_id = self.id.unwrap();
+
+        with_db!(db, |pool| {
+            let mut query = QueryBuilder::new("update Jobs set ");
+
+            let mut separated = query.separated(", ");
+
+            let mut has_updates = false;
+
+            macro_rules! handle_field_base {
+                ($field:ident, $column:literal, $val:ident => $assign_expr:expr) => {
+                    if let Some($val) = diff.$field {
+                        self.$field = $assign_expr;
+                        separated
+                            .push(concat!($column, " = "))
+                            .push_bind_unseparated(&self.$field);
+                        has_updates = true;
+                    }
+                };
+            }
+
+            macro_rules! handle_field {
+                ($field:ident, $column:literal) => {
+                    handle_field_base!($field, $column, value => value);
+                };
+            }
+
+            macro_rules! handle_field_some {
+                ($field:ident, $column:literal) => {
+                    handle_field_base!($field, $column, value => Some(value));
+                };
+            }
+
+            handle_field!(evaluation_id, "evaluation_id");
+            handle_field!(attribute_name, "attribute_name");
+            handle_field!(derivation_path, "derivation_path");
+            handle_field!(state, "state");
+            handle_field_some!(finished, "finished");
+            handle_field!(keep, "keep");
+
+            if !has_updates {
+                return Ok(());
+            }
+
+            query.push(" where id = ").push_bind(self_id);
+            let result = query.build().execute(pool).await;
+
+            if result.is_err() {
+                return Err(DBError::new(result.err().unwrap().to_string()));
+            }
+
+            Ok(())
+        })
+    }
 
-        query.push(" where id = ").push_bind(self_id);
-        let result = query.build().execute(&mut *conn).await;
+    /// Queues the failed job for another build.
+    /// Returns false if the job did not fail.
+    pub async fn restart(&mut self, db: &DB) -> Result<bool, DBError> {
+        let result = with_db!(db, |pool| query(
+            "
+                update Jobs
+                set state = 'ToBeBuilt', finished = null
+                where id = $1 and state = 'Failed'
+            ",
+        )
+        .bind(self.id.unwrap())
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected()))
+        .map_err(|e| DBError::new(e.to_string()))?;
 
-        if result.is_err() {
-            return Err(DBError::new(result.err().unwrap().to_string()));
+        if result == 0 {
+            return Ok(false);
         }
 
-        Ok(())
+        self.state = JobState::ToBeBuilt;
+        self.finished = None;
+
+        Ok(true)
     }
 
     pub async fn get_single(db: &DB, id: i32) -> Result<Option<Job>, DBError> {
-        let mut conn = db.get_conn().await?;
+        let result = with_db!(db, |pool| query_as::<_, Job>(
+            "
+                select *
+                from Jobs
+                where id = $1
+            ",
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
 
-        let result = sqlx::query_as::<_, Job>(
+    /// Jobs of the jobset's evaluations which did not finish yet, including waiting aggregates
+    pub async fn get_unfinished_of_jobset(db: &DB, jobset_id: i32) -> Result<Vec<Job>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, Job>(
+            "
+                select Jobs.*
+                from Jobs
+                join Evaluations on Evaluations.id = Jobs.evaluation_id
+                where Evaluations.jobset_id = $1
+                    and Jobs.state in ('ToBeBuilt', 'Building', 'Waiting')
+            ",
+        )
+        .bind(jobset_id)
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    /// Jobs which were handed to the build manager but never finished
+    pub async fn get_unfinished(db: &DB) -> Result<Vec<Job>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, Job>(
+            "
+                select *
+                from Jobs
+                where state in ('ToBeBuilt', 'Building')
+            ",
+        )
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    /// How many jobs still have to be built
+    pub async fn count_unfinished(db: &DB) -> Result<i64, DBError> {
+        let result = with_db!(db, |pool| query_scalar::<_, i64>(
+            "
+                select count(*)
+                from Jobs
+                where state in ('ToBeBuilt', 'Building')
+            ",
+        )
+        .fetch_one(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    pub async fn get_by_evaluation(db: &DB, evaluation_id: i32) -> Result<Vec<Job>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, Job>(
             "
                 select *
                 from Jobs
-                where id = ?
+                where evaluation_id = $1
+            ",
+        )
+        .bind(evaluation_id)
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    /// The matching jobs, newest first, and how many there are in total
+    pub async fn get_page(
+        db: &DB,
+        filter: &JobFilter,
+        offset: i64,
+        limit: i64,
+    ) -> Result<(Vec<Job>, i64), DBError> {
+        let condition = "
+            ($1 is null or evaluation_id = $1)
+            and ($2 is null or evaluation_id in (select id from Evaluations where jobset_id = $2))
+            and ($3 is null or state = $3)
+            and ($4 is null or attribute_name like '%' || $4 || '%')
+            and (not $5 or evaluation_id in (
+                select Evaluations.id
+                from Evaluations
+                join Jobsets on Jobsets.id = Evaluations.jobset_id
+                join Projects on Projects.id = Jobsets.project_id
+                where not Projects.private
+                    or Projects.id in (select project_id from ProjectMembers where user_id = $6)
+            ))
+            and ($7 is null or evaluation_id in (
+                select Evaluations.id
+                from Evaluations
+                join Jobsets on Jobsets.id = Evaluations.jobset_id
+                where Jobsets.project_id = $7
+            ))
+        ";
+
+        let total = with_db!(db, |pool| query_scalar::<_, i64>(&format!(
+            "
+                select count(*)
+                from Jobs
+                where {}
+            ",
+            condition
+        ))
+        .bind(filter.evaluation_id)
+        .bind(filter.jobset_id)
+        .bind(&filter.state)
+        .bind(&filter.attribute)
+        .bind(filter.hide_private)
+        .bind(filter.member_id)
+        .bind(filter.project_id)
+        .fetch_one(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        let result = with_db!(db, |pool| query_as::<_, Job>(&format!(
+            "
+                select *
+                from Jobs
+                where {}
+                order by id desc
+                limit $8 offset $9
+            ",
+            condition
+        ))
+        .bind(filter.evaluation_id)
+        .bind(filter.jobset_id)
+        .bind(&filter.state)
+        .bind(&filter.attribute)
+        .bind(filter.hide_private)
+        .bind(filter.member_id)
+        .bind(filter.project_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok((result, total))
+    }
+
+    pub async fn get_origin(db: &DB, id: i32) -> Result<Option<JobOrigin>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, JobOrigin>(
+            "
+                select
+                    Jobs.*,
+                    Projects.id as project_id,
+                    Projects.name as project_name,
+                    Jobsets.id as jobset_id,
+                    Jobsets.name as jobset_name,
+                    Evaluations.created as evaluated
+                from Jobs
+                join Evaluations on Evaluations.id = Jobs.evaluation_id
+                join Jobsets on Jobsets.id = Evaluations.jobset_id
+                join Projects on Projects.id = Jobsets.project_id
+                where Jobs.id = $1
             ",
         )
         .bind(id)
-        .fetch_optional(&mut *conn)
-        .await
+        .fetch_optional(pool)
+        .await)
         .map_err(|e| DBError::new(e.to_string()))?;
 
         Ok(result)
     }
 
-    pub async fn get_all(db: &DB, jobset_id: i32) -> Result<Vec<Job>, DBError> {
-        let mut conn = db.get_conn().await?;
+    /// The newest finished jobs, optionally only those of a project, jobset or attribute.
+    /// Flake jobs name their system last, like `hello.x86_64-linux`, which `system` matches.
+    pub async fn get_latest_finished(
+        db: &DB,
+        project_name: Option<&str>,
+        jobset_name: Option<&str>,
+        attribute_name: Option<&str>,
+        system: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<JobOrigin>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, JobOrigin>(
+            "
+                select
+                    Jobs.*,
+                    Projects.id as project_id,
+                    Projects.name as project_name,
+                    Jobsets.id as jobset_id,
+                    Jobsets.name as jobset_name,
+                    Evaluations.created as evaluated
+                from Jobs
+                join Evaluations on Evaluations.id = Jobs.evaluation_id
+                join Jobsets on Jobsets.id = Evaluations.jobset_id
+                join Projects on Projects.id = Jobsets.project_id
+                where Jobs.state in ('Done', 'Failed')
+                    and ($1 is null or Projects.name = $1)
+                    and ($2 is null or Jobsets.name = $2)
+                    and ($3 is null or Jobs.attribute_name = $3)
+                    and ($4 is null or Jobs.attribute_name like '%.' || $4)
+                order by Jobs.id desc
+                limit $5
+            ",
+        )
+        .bind(project_name)
+        .bind(jobset_name)
+        .bind(attribute_name)
+        .bind(system)
+        .bind(limit)
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
 
-        let result = sqlx::query_as::<_, Job>(
+    /// The finished job of the same attribute in the latest earlier evaluation of its jobset
+    pub async fn get_previous(db: &DB, job: &Job) -> Result<Option<Job>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, Job>(
+            "
+                select Jobs.*
+                from Jobs
+                join Evaluations on Evaluations.id = Jobs.evaluation_id
+                where Evaluations.jobset_id = (
+                        select jobset_id
+                        from Evaluations
+                        where id = $1
+                    )
+                    and Jobs.evaluation_id < $1
+                    and Jobs.attribute_name = $2
+                    and Jobs.state in ('Done', 'Failed')
+                order by Jobs.evaluation_id desc
+                limit 1
+            ",
+        )
+        .bind(job.evaluation_id)
+        .bind(&job.attribute_name)
+        .fetch_optional(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    /// Returns the jobs of the latest evaluation of the jobset
+    pub async fn get_all(db: &DB, jobset_id: i32) -> Result<Vec<Job>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, Job>(
             "
                 select *
                 from Jobs
-                where evaluation_id = ?
+                where evaluation_id = (
+                    select max(id)
+                    from Evaluations
+                    where jobset_id = $1
+                )
             ",
         )
         .bind(jobset_id)
-        .fetch_all(&mut *conn)
-        .await
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    /// Successful jobs whose outputs have to stay rooted: the kept ones, those of the
+    /// last `keep_evaluations` evaluations of their jobset and those of channel heads
+    pub async fn get_rooted(db: &DB) -> Result<Vec<i32>, DBError> {
+        let result = with_db!(db, |pool| query_scalar::<_, i32>(
+            "
+                with Ages as (
+                    select
+                        Evaluations.id,
+                        Jobsets.keep_evaluations,
+                        row_number() over (
+                            partition by Evaluations.jobset_id
+                            order by Evaluations.id desc
+                        ) as age
+                    from Evaluations
+                    join Jobsets on Jobsets.id = Evaluations.jobset_id
+                )
+                select Jobs.id
+                from Jobs
+                join Ages on Ages.id = Jobs.evaluation_id
+                where Jobs.state = 'Done'
+                    and (
+                        Jobs.keep
+                        or Ages.age <= Ages.keep_evaluations
+                        or Jobs.evaluation_id in (
+                            select evaluation_id
+                            from ChannelRevisions
+                            where id in (select max(id) from ChannelRevisions group by jobset_id)
+                        )
+                    )
+            ",
+        )
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    /// The newest successful build of the attribute in the project's jobset.
+    /// With `finished_evaluation` only evaluations whose jobs have all finished are considered.
+    pub async fn get_latest_successful(
+        db: &DB,
+        project_id: i32,
+        jobset_id: i32,
+        attribute_name: &str,
+        finished_evaluation: bool,
+    ) -> Result<Option<Job>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, Job>(
+            "
+                select Jobs.*
+                from Jobs
+                join Evaluations on Evaluations.id = Jobs.evaluation_id
+                join Jobsets on Jobsets.id = Evaluations.jobset_id
+                where Jobsets.project_id = $1
+                    and Jobsets.id = $2
+                    and Jobs.attribute_name = $3
+                    and Jobs.state = 'Done'
+                    and (not $4 or not exists (
+                        select 1 from Jobs as Others
+                        where Others.evaluation_id = Jobs.evaluation_id
+                            and Others.state in ('ToBeBuilt', 'Building', 'Waiting')
+                    ))
+                order by Jobs.id desc
+                limit 1
+            ",
+        )
+        .bind(project_id)
+        .bind(jobset_id)
+        .bind(attribute_name)
+        .bind(finished_evaluation)
+        .fetch_optional(pool)
+        .await)
         .map_err(|e| DBError::new(e.to_string()))?;
 
         Ok(result)
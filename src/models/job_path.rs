@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::hydracore::{store_path_hash, with_db, with_tx, DBError, DB},
+    sqlx::{query, query_as},
+};
+
+/// A store path in the closure of the outputs of a successful job
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JobPath {
+    pub job_id: i32,
+    pub hash: String,
+    pub path: String,
+    pub output: bool,
+}
+
+#[cfg(feature = "ssr")]
+impl JobPath {
+    /// Records the closure of the job's outputs in one transaction
+    pub async fn add_closure(
+        db: &DB,
+        job_id: i32,
+        outputs: &[String],
+        closure: &[String],
+    ) -> Result<(), DBError> {
+        let mut tx = db.begin().await?;
+
+        for path in closure.iter() {
+            _ = with_tx!(&mut tx, |conn| query(
+                "
+                    insert into JobPaths
+                        (job_id, hash, path, output)
+                    values
+                        ($1, $2, $3, $4)
+                    on conflict do nothing
+                ",
+            )
+            .bind(job_id)
+            .bind(store_path_hash(path))
+            .bind(path)
+            .bind(outputs.contains(path))
+            .execute(conn)
+            .await
+            .map(|result| result.rows_affected()))
+            .map_err(|e| DBError::new(e.to_string()))?;
+        }
+
+        tx.commit().await
+    }
+
+    /// Finds the path by its hash, if it belongs to a project that serves a binary cache
+    pub async fn get_cached(db: &DB, hash: &str) -> Result<Option<JobPath>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, JobPath>(
+            "
+                select JobPaths.*
+                from JobPaths
+                join Jobs on Jobs.id = JobPaths.job_id
+                join Evaluations on Evaluations.id = Jobs.evaluation_id
+                join Jobsets on Jobsets.id = Evaluations.jobset_id
+                join Projects on Projects.id = Jobsets.project_id
+                where JobPaths.hash = $1 and Projects.binary_cache and not Projects.private
+                limit 1
+            ",
+        )
+        .bind(hash)
+        .fetch_optional(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    /// The output paths of the job
+    pub async fn get_outputs(db: &DB, job_id: i32) -> Result<Vec<JobPath>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, JobPath>(
+            "
+                select *
+                from JobPaths
+                where job_id = $1 and output
+                order by path
+            ",
+        )
+        .bind(job_id)
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+}
@@ -10,6 +10,11 @@ pub struct JobsetDiff {
     pub flake: Option<String>,
     pub description: Option<String>,
     pub check_interval: Option<i32>,
+    pub keep_evaluations: Option<i32>,
+    pub max_evaluations: Option<i32>,
+    pub max_evaluation_age: Option<i32>,
+    pub channel: Option<bool>,
+    pub channel_gate: Option<Option<String>>,
     pub last_checked: Option<DateTime<Utc>>,
     pub last_evaluated: Option<DateTime<Utc>>,
     pub evaluation_took: Option<i32>,
@@ -24,6 +29,11 @@ impl JobsetDiff {
             flake: None,
             description: None,
             check_interval: None,
+            keep_evaluations: None,
+            max_evaluations: None,
+            max_evaluation_age: None,
+            channel: None,
+            channel_gate: None,
             last_evaluated: None,
             last_checked: None,
             evaluation_took: None,
@@ -49,6 +59,23 @@ impl JobsetDiff {
         self.check_interval = Some(check_interval);
         self
     }
+    pub fn set_keep_evaluations(&mut self, keep_evaluations: i32) -> &mut Self {
+        self.keep_evaluations = Some(keep_evaluations);
+        self
+    }
+    pub fn set_max_evaluations(&mut self, max_evaluations: i32) -> &mut Self {
+        self.max_evaluations = Some(max_evaluations);
+        self
+    }
+    pub fn set_max_evaluation_age(&mut self, max_evaluation_age: i32) -> &mut Self {
+        self.max_evaluation_age = Some(max_evaluation_age);
+        self
+    }
+    pub fn set_channel(&mut self, channel: bool, channel_gate: Option<String>) -> &mut Self {
+        self.channel = Some(channel);
+        self.channel_gate = Some(channel_gate);
+        self
+    }
     pub fn set_last_checked(&mut self, last_checked: DateTime<Utc>) -> &mut Self {
         self.last_checked = Some(last_checked);
         self
@@ -75,7 +102,8 @@ impl JobsetDiff {
     }
 }
 
-#[cfg_attr(feature = "ssr", derive(sqlx::Type))]
+#[cfg_attr(feature = "ssr", derive(sqlx::Type), sqlx(type_name = "text"))]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub enum JobsetState {
     Unknown,
@@ -99,6 +127,7 @@ impl JobsetState {
 }
 
 #[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Jobset {
     pub id: Option<JobsetID>,
@@ -107,6 +136,17 @@ pub struct Jobset {
     pub flake: String,
     pub description: String,
     pub check_interval: i32,
+    /// How many of the latest evaluations keep their outputs rooted
+    pub keep_evaluations: i32,
+    /// Older evaluations get pruned, 0 keeps all of them
+    pub max_evaluations: i32,
+    /// Evaluations older than this many days get pruned, 0 keeps all of them
+    pub max_evaluation_age: i32,
+    /// Whether evaluations which pass the gate are published as a channel
+    #[serde(default)]
+    pub channel: bool,
+    /// Attribute which has to build for a channel release, all jobs have to if it is not set
+    pub channel_gate: Option<String>,
     pub last_checked: Option<DateTime<Utc>>,
     pub last_evaluated: Option<DateTime<Utc>>,
     pub evaluation_took: Option<i32>,
@@ -116,25 +156,24 @@ pub struct Jobset {
 
 #[cfg(feature = "ssr")]
 use {
-    crate::hydracore::{DBError, DB},
-    sqlx::{query, QueryBuilder, Sqlite},
+    crate::hydracore::{with_db, with_tx, DBError, DB},
+    crate::models::DeletionReport,
+    sqlx::{query, query_as, query_scalar, QueryBuilder},
     tracing::trace,
 };
 
 #[cfg(feature = "ssr")]
 impl Jobset {
     pub async fn get_all(db: &DB, project_id: i32) -> Result<Vec<Jobset>, DBError> {
-        let mut conn = db.get_conn().await?;
-
-        let result = sqlx::query_as::<_, Jobset>(
+        let result = with_db!(db, |pool| query_as::<_, Jobset>(
             "
                 select * from Jobsets
-                where project_id = ?
+                where project_id = $1
             ",
         )
         .bind(project_id)
-        .fetch_all(&mut *conn)
-        .await;
+        .fetch_all(pool)
+        .await);
 
         if result.is_err() {
             return Err(DBError::new(result.err().unwrap().to_string()));
@@ -144,17 +183,15 @@ impl Jobset {
     }
 
     pub async fn get_single(db: &DB, jobset_id: i32) -> Result<Option<Jobset>, DBError> {
-        let mut conn = db.get_conn().await?;
-
-        let result = sqlx::query_as::<_, Jobset>(
+        let result = with_db!(db, |pool| query_as::<_, Jobset>(
             "
                 select * from Jobsets
-                where id = ?
+                where id = $1
             ",
         )
         .bind(jobset_id)
-        .fetch_optional(&mut *conn)
-        .await;
+        .fetch_optional(pool)
+        .await);
 
         if result.is_err() {
             return Err(DBError::new(result.err().unwrap().to_string()));
@@ -163,39 +200,101 @@ impl Jobset {
         Ok(result.unwrap())
     }
 
-    pub async fn add_to_db(&mut self, db: &DB) -> Result<(), DBError> {
-        let mut conn = db.get_conn().await?;
+    /// The first jobset of the project with the name
+    pub async fn get_by_name(
+        db: &DB,
+        project_id: i32,
+        name: &str,
+    ) -> Result<Option<Jobset>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, Jobset>(
+            "
+                select * from Jobsets
+                where project_id = $1 and name = $2
+                order by id
+                limit 1
+            ",
+        )
+        .bind(project_id)
+        .bind(name)
+        .fetch_optional(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
 
+        Ok(result)
+    }
+
+    /// The jobsets of every project
+    pub async fn get_every(db: &DB) -> Result<Vec<Jobset>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, Jobset>(
+            "
+                select * from Jobsets
+            ",
+        )
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    pub async fn get_by_state(db: &DB, state: JobsetState) -> Result<Vec<Jobset>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, Jobset>(
+            "
+                select * from Jobsets
+                where state = $1
+            ",
+        )
+        .bind(state)
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    pub async fn add_to_db(&mut self, db: &DB) -> Result<(), DBError> {
         let name = &self.name;
         let desc = &self.description;
         let flake = &self.flake;
         let interval = self.check_interval;
+        let keep_evaluations = self.keep_evaluations;
+        let max_evaluations = self.max_evaluations;
+        let max_evaluation_age = self.max_evaluation_age;
+        let channel = self.channel;
+        let channel_gate = &self.channel_gate;
         let state = self.state.clone().unwrap_or(JobsetState::Unknown);
         let proj_id = self.project_id;
 
-        let result = query!(
+        let result = with_db!(db, |pool| {
+            query_scalar::<_, i32>(
             "
                 insert into Jobsets
-                    (project_id, flake, name, description, state, check_interval)
+                    (project_id, flake, name, description, state, check_interval, keep_evaluations, max_evaluations, max_evaluation_age, channel, channel_gate)
                 values
-                    (?, ?, ?, ?, ?, ?)
+                    ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
                 returning id
             ",
-            proj_id,
-            flake,
-            name,
-            desc,
-            state,
-            interval,
         )
-        .fetch_one(&mut *conn)
-        .await;
+        .bind(proj_id)
+        .bind(flake)
+        .bind(name)
+        .bind(desc)
+        .bind(&state)
+        .bind(interval)
+        .bind(keep_evaluations)
+        .bind(max_evaluations)
+        .bind(max_evaluation_age)
+        .bind(channel)
+        .bind(channel_gate)
+        .fetch_one(pool)
+        .await
+        });
 
         if result.is_err() {
             return Err(DBError::new(result.err().unwrap().to_string()));
         }
 
-        self.id = Some(result.unwrap().id as i32);
+        self.id = Some(result.unwrap());
 
         Ok(())
     }
@@ -226,84 +325,97 @@ impl Jobset {
     }
 
     pub async fn update_jobset(&mut self, db: &DB, diff: JobsetDiff) -> Result<(), DBError> {
-        let mut conn = db.get_conn().await?;
         let self_id = self.id.unwrap();
 
-        let mut query: QueryBuilder<'_, Sqlite> = QueryBuilder::new("update Jobsets set ");
+        with_db!(db, |pool| {
+            let mut query = QueryBuilder::new("update Jobsets set ");
 
-        let mut separated = query.separated(", ");
+            let mut separated = query.separated(", ");
 
-        let mut has_updates = false;
+            let mut has_updates = false;
 
-        macro_rules! handle_field_base {
-            ($field:ident, $column:literal, $val:ident => $assign_expr:expr) => {
-                if let Some($val) = diff.$field {
-                    self.$field = $assign_expr;
-                    separated
-                        .push(concat!($column, " = "))
-                        .push_bind_unseparated(&self.$field);
-                    has_updates = true;
-                }
-            };
-        }
+            macro_rules! handle_field_base {
+                ($field:ident, $column:literal, $val:ident => $assign_expr:expr) => {
+                    if let Some($val) = diff.$field {
+                        self.$field = $assign_expr;
+                        separated
+                            .push(concat!($column, " = "))
+                            .push_bind_unseparated(&self.$field);
+                        has_updates = true;
+                    }
+                };
+            }
 
-        macro_rules! handle_field {
-            ($field:ident, $column:literal) => {
-                handle_field_base!($field, $column, value => value);
-            };
-        }
+            macro_rules! handle_field {
+                ($field:ident, $column:literal) => {
+                    handle_field_base!($field, $column, value => value);
+                };
+            }
 
-        macro_rules! handle_field_some {
-            ($field:ident, $column:literal) => {
-                handle_field_base!($field, $column, value => Some(value));
-            };
-        }
+            macro_rules! handle_field_some {
+                ($field:ident, $column:literal) => {
+                    handle_field_base!($field, $column, value => Some(value));
+                };
+            }
 
-        handle_field!(name, "name");
-        handle_field!(description, "description");
-        handle_field!(flake, "flake");
-        handle_field!(check_interval, "check_interval");
+            handle_field!(name, "name");
+            handle_field!(description, "description");
+            handle_field!(flake, "flake");
+            handle_field!(check_interval, "check_interval");
+            handle_field!(keep_evaluations, "keep_evaluations");
+            handle_field!(max_evaluations, "max_evaluations");
+            handle_field!(max_evaluation_age, "max_evaluation_age");
+            handle_field!(channel, "channel");
+            handle_field!(channel_gate, "channel_gate");
 
-        handle_field_some!(last_checked, "last_checked");
+            handle_field_some!(last_checked, "last_checked");
 
-        handle_field_some!(evaluation_took, "evaluation_took");
+            handle_field_some!(evaluation_took, "evaluation_took");
 
-        handle_field_some!(state, "state");
+            handle_field_some!(state, "state");
 
-        handle_field_some!(last_evaluated, "last_evaluated");
+            handle_field_some!(last_evaluated, "last_evaluated");
 
-        handle_field_some!(error_message, "error_message");
+            handle_field_some!(error_message, "error_message");
 
-        if !has_updates {
-            return Ok(());
-        }
+            if !has_updates {
+                return Ok(());
+            }
 
-        query.push(" where id = ").push_bind(self_id);
-        let result = query.build().execute(&mut *conn).await;
+            query.push(" where id = ").push_bind(self_id);
+            let result = query.build().execute(pool).await;
 
-        if result.is_err() {
-            return Err(DBError::new(result.err().unwrap().to_string()));
-        }
+            if result.is_err() {
+                return Err(DBError::new(result.err().unwrap().to_string()));
+            }
 
-        Ok(())
+            Ok(())
+        })
     }
 
-    pub async fn delete(&mut self, db: &DB) -> Result<(), DBError> {
-        let mut conn = db.get_conn().await?;
-
+    /// Deletes the jobset with its evaluations and jobs
+    pub async fn delete(&mut self, db: &DB) -> Result<DeletionReport, DBError> {
         let id = self.id.unwrap();
 
-        _ = query!(
+        let mut tx = db.begin().await?;
+
+        let report =
+            DeletionReport::prepare(&mut tx, "select id from Jobsets where id = $1", id).await?;
+
+        _ = with_tx!(&mut tx, |conn| query(
             "
                 delete from Jobsets
-                where id = ?
+                where id = $1
            ",
-            id
         )
-        .execute(&mut *conn)
+        .bind(id)
+        .execute(conn)
         .await
+        .map(|result| result.rows_affected()))
         .map_err(|e| DBError::new(e.to_string()))?;
 
-        Ok(())
+        tx.commit().await?;
+
+        Ok(report)
     }
 }
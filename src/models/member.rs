@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::hydracore::{with_db, DBError, DB},
+    sqlx::{query, query_as, query_scalar},
+};
+
+/// What a member may do in a project, every role may do what the roles before it may.
+/// Admins may do everything in every project.
+#[cfg_attr(feature = "ssr", derive(sqlx::Type), sqlx(type_name = "text"))]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ProjectRole {
+    /// May see the project if it is private
+    Viewer,
+    /// May create, change and trigger jobsets and keep or restart jobs
+    Maintainer,
+    /// May change and delete the project, its jobsets, caches and members
+    Owner,
+}
+
+impl std::fmt::Display for ProjectRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ProjectRole::Viewer => "viewer",
+            ProjectRole::Maintainer => "maintainer",
+            ProjectRole::Owner => "owner",
+        })
+    }
+}
+
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ProjectMember {
+    pub project_id: i32,
+    pub user_id: i32,
+    pub user_name: String,
+    pub role: ProjectRole,
+}
+
+#[cfg(feature = "ssr")]
+impl ProjectMember {
+    pub async fn get_all(db: &DB, project_id: i32) -> Result<Vec<ProjectMember>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, ProjectMember>(
+            "
+                select ProjectMembers.*, Users.name as user_name
+                from ProjectMembers
+                join Users on Users.id = ProjectMembers.user_id
+                where project_id = $1
+                order by Users.name
+            ",
+        )
+        .bind(project_id)
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    pub async fn get_role(
+        db: &DB,
+        project_id: i32,
+        user_id: i32,
+    ) -> Result<Option<ProjectRole>, DBError> {
+        let result = with_db!(db, |pool| query_scalar::<_, ProjectRole>(
+            "
+                select role
+                from ProjectMembers
+                where project_id = $1 and user_id = $2
+            ",
+        )
+        .bind(project_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    /// Adds the user to the project or changes its role
+    pub async fn set(
+        db: &DB,
+        project_id: i32,
+        user_id: i32,
+        role: ProjectRole,
+    ) -> Result<(), DBError> {
+        _ = with_db!(db, |pool| query(
+            "
+                insert into ProjectMembers
+                    (project_id, user_id, role)
+                values
+                    ($1, $2, $3)
+                on conflict (project_id, user_id) do update
+                set role = excluded.role
+            ",
+        )
+        .bind(project_id)
+        .bind(user_id)
+        .bind(role)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected()))
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Returns false if the user was no member
+    pub async fn delete(db: &DB, project_id: i32, user_id: i32) -> Result<bool, DBError> {
+        let result = with_db!(db, |pool| query(
+            "
+                delete from ProjectMembers
+                where project_id = $1 and user_id = $2
+            ",
+        )
+        .bind(project_id)
+        .bind(user_id)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected()))
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result > 0)
+    }
+}
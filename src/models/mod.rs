@@ -1,9 +1,47 @@
+pub use api_token::*;
+pub use build_product::*;
+pub use cache::*;
+pub use channel_revision::*;
+pub use deletion::*;
 pub use evaluation::*;
 pub use job::*;
+pub use job_path::*;
 pub use jobset::*;
+pub use member::*;
+pub use notification_delivery::*;
+pub use notifier::*;
 pub use project::*;
+pub use pruning::*;
+#[cfg(feature = "ssr")]
+pub use queue::*;
+#[cfg(feature = "ssr")]
+pub use session::*;
+#[cfg(feature = "ssr")]
+pub use status_delivery::*;
+pub use upload::*;
+pub use user::*;
+pub use webhook_delivery::*;
 
+mod api_token;
+mod build_product;
+mod cache;
+mod channel_revision;
+mod deletion;
 mod evaluation;
 mod job;
+mod job_path;
 mod jobset;
+mod member;
+mod notification_delivery;
+mod notifier;
 mod project;
+mod pruning;
+#[cfg(feature = "ssr")]
+mod queue;
+#[cfg(feature = "ssr")]
+mod session;
+#[cfg(feature = "ssr")]
+mod status_delivery;
+mod upload;
+mod user;
+mod webhook_delivery;
@@ -0,0 +1,164 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{NotificationEvent, NotifierKind};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::hydracore::{with_db, DBError, DB},
+    crate::models::NotifierConfig,
+    chrono::TimeDelta,
+    sqlx::{query, query_as, query_scalar},
+};
+
+#[cfg_attr(feature = "ssr", derive(sqlx::Type), sqlx(type_name = "text"))]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum DeliveryState {
+    Queued,
+    Sending,
+    /// Failed, will be retried
+    Retrying,
+    Delivered,
+    Failed,
+}
+
+/// Sending one notification through one notifier
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NotificationDelivery {
+    pub id: Option<i32>,
+    pub notifier_id: i32,
+    /// Kind and target of the notifier, not stored in the NotificationDeliveries table
+    pub kind: NotifierKind,
+    pub target: String,
+    pub event: NotificationEvent,
+    /// The notification as json, every attempt sends the same
+    pub payload: String,
+    pub state: DeliveryState,
+    pub attempts: i32,
+    pub error_message: Option<String>,
+    pub created: DateTime<Utc>,
+    pub finished: Option<DateTime<Utc>>,
+}
+
+#[cfg(feature = "ssr")]
+impl NotificationDelivery {
+    pub fn new(notifier: &NotifierConfig, event: NotificationEvent, payload: String) -> Self {
+        Self {
+            id: None,
+            notifier_id: notifier.id.unwrap(),
+            kind: notifier.kind,
+            target: notifier.target.clone(),
+            event,
+            payload,
+            state: DeliveryState::Queued,
+            attempts: 0,
+            error_message: None,
+            created: Utc::now(),
+            finished: None,
+        }
+    }
+
+    /// Deliveries are only kept for a month
+    pub async fn add_to_db(&mut self, db: &DB) -> Result<(), DBError> {
+        _ = with_db!(db, |pool| query(
+            "
+                delete from NotificationDeliveries
+                where created < $1 and state in ('Delivered', 'Failed')
+            ",
+        )
+        .bind(Utc::now() - TimeDelta::days(30))
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected()))
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        let result = with_db!(db, |pool| query_scalar::<_, i32>(
+            "
+                insert into NotificationDeliveries
+                    (notifier_id, event, payload, state, attempts, created)
+                values
+                    ($1, $2, $3, $4, $5, $6)
+                returning id
+            ",
+        )
+        .bind(self.notifier_id)
+        .bind(self.event)
+        .bind(&self.payload)
+        .bind(&self.state)
+        .bind(self.attempts)
+        .bind(self.created)
+        .fetch_one(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        self.id = Some(result);
+
+        Ok(())
+    }
+
+    /// Writes state, attempts, error message and finished
+    pub async fn update(&self, db: &DB) -> Result<(), DBError> {
+        _ = with_db!(db, |pool| query(
+            "
+                update NotificationDeliveries
+                set state = $1, attempts = $2, error_message = $3, finished = $4
+                where id = $5
+            ",
+        )
+        .bind(&self.state)
+        .bind(self.attempts)
+        .bind(&self.error_message)
+        .bind(self.finished)
+        .bind(self.id.unwrap())
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected()))
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// The newest deliveries of the project's notifiers first
+    pub async fn get_latest(
+        db: &DB,
+        project_id: i32,
+        count: i64,
+    ) -> Result<Vec<NotificationDelivery>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, NotificationDelivery>(
+            "
+                select NotificationDeliveries.*, Notifiers.kind, Notifiers.target
+                from NotificationDeliveries
+                join Notifiers on Notifiers.id = NotificationDeliveries.notifier_id
+                where Notifiers.project_id = $1
+                order by NotificationDeliveries.id desc
+                limit $2
+            ",
+        )
+        .bind(project_id)
+        .bind(count)
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    /// Deliveries a previous coordinator left unfinished
+    pub async fn get_unfinished(db: &DB) -> Result<Vec<NotificationDelivery>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, NotificationDelivery>(
+            "
+                select NotificationDeliveries.*, Notifiers.kind, Notifiers.target
+                from NotificationDeliveries
+                join Notifiers on Notifiers.id = NotificationDeliveries.notifier_id
+                where NotificationDeliveries.state in ('Queued', 'Sending', 'Retrying')
+                order by NotificationDeliveries.id
+            ",
+        )
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+}
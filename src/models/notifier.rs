@@ -0,0 +1,218 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::hydracore::{with_db, DBError, DB},
+    sqlx::{query, query_as, query_scalar},
+};
+
+#[cfg_attr(feature = "ssr", derive(sqlx::Type), sqlx(type_name = "text"))]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NotificationEvent {
+    /// The jobset evaluated, its jobs are queued
+    EvaluationStarted,
+    /// Every job of the evaluation was built
+    EvaluationFinished,
+    /// Every job of the evaluation is finished, at least one failed
+    EvaluationFailed,
+    /// The job failed, its previous build succeeded or there was none
+    JobFailed,
+    /// The job succeeded, its previous build failed
+    JobFixed,
+    /// The job failed again
+    JobStillFailing,
+    /// The jobset could not be evaluated
+    JobsetErrored,
+}
+
+impl NotificationEvent {
+    pub const ALL: [NotificationEvent; 7] = [
+        NotificationEvent::EvaluationStarted,
+        NotificationEvent::EvaluationFinished,
+        NotificationEvent::EvaluationFailed,
+        NotificationEvent::JobFailed,
+        NotificationEvent::JobFixed,
+        NotificationEvent::JobStillFailing,
+        NotificationEvent::JobsetErrored,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        NotificationEvent::ALL
+            .into_iter()
+            .find(|event| event.to_string() == name)
+    }
+}
+
+impl std::fmt::Display for NotificationEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            NotificationEvent::EvaluationStarted => "EvaluationStarted",
+            NotificationEvent::EvaluationFinished => "EvaluationFinished",
+            NotificationEvent::EvaluationFailed => "EvaluationFailed",
+            NotificationEvent::JobFailed => "JobFailed",
+            NotificationEvent::JobFixed => "JobFixed",
+            NotificationEvent::JobStillFailing => "JobStillFailing",
+            NotificationEvent::JobsetErrored => "JobsetErrored",
+        })
+    }
+}
+
+/// How a notifier delivers, every kind is a `Notifier` of the queue runner
+#[cfg_attr(feature = "ssr", derive(sqlx::Type), sqlx(type_name = "text"))]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NotifierKind {
+    /// Posts the notification as json to the target url
+    Webhook,
+}
+
+impl std::fmt::Display for NotifierKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            NotifierKind::Webhook => "Webhook",
+        })
+    }
+}
+
+/// Where the events of a project, or of one of its jobsets, are sent to
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NotifierConfig {
+    pub id: Option<i32>,
+    pub project_id: i32,
+    /// Only events of this jobset, of every jobset of the project if not set
+    pub jobset_id: Option<i32>,
+    /// Not stored in the Notifiers table
+    #[cfg_attr(feature = "ssr", sqlx(default))]
+    pub jobset_name: Option<String>,
+    pub kind: NotifierKind,
+    /// What the kind delivers to, like the url of a webhook
+    pub target: String,
+    /// Comma separated names of the events which are sent
+    pub events: String,
+    pub created: DateTime<Utc>,
+}
+
+impl NotifierConfig {
+    pub fn get_events(&self) -> Vec<NotificationEvent> {
+        self.events
+            .split(',')
+            .filter_map(NotificationEvent::from_name)
+            .collect()
+    }
+
+    pub fn wants(&self, event: NotificationEvent) -> bool {
+        self.get_events().contains(&event)
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl NotifierConfig {
+    pub fn new(
+        project_id: i32,
+        jobset_id: Option<i32>,
+        kind: NotifierKind,
+        target: String,
+        events: &[NotificationEvent],
+    ) -> Self {
+        Self {
+            id: None,
+            project_id,
+            jobset_id,
+            jobset_name: None,
+            kind,
+            target,
+            events: events
+                .iter()
+                .map(|event| event.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            created: Utc::now(),
+        }
+    }
+
+    pub async fn add_to_db(&mut self, db: &DB) -> Result<(), DBError> {
+        let result = with_db!(db, |pool| query_scalar::<_, i32>(
+            "
+                insert into Notifiers
+                    (project_id, jobset_id, kind, target, events, created)
+                values
+                    ($1, $2, $3, $4, $5, $6)
+                returning id
+            ",
+        )
+        .bind(self.project_id)
+        .bind(self.jobset_id)
+        .bind(self.kind)
+        .bind(&self.target)
+        .bind(&self.events)
+        .bind(self.created)
+        .fetch_one(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        self.id = Some(result);
+
+        Ok(())
+    }
+
+    pub async fn get_all(db: &DB, project_id: i32) -> Result<Vec<NotifierConfig>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, NotifierConfig>(
+            "
+                select Notifiers.*, Jobsets.name as jobset_name
+                from Notifiers
+                left join Jobsets on Jobsets.id = Notifiers.jobset_id
+                where Notifiers.project_id = $1
+                order by Notifiers.id
+            ",
+        )
+        .bind(project_id)
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    /// The notifiers of the jobset and those of its whole project
+    pub async fn get_for_jobset(
+        db: &DB,
+        project_id: i32,
+        jobset_id: i32,
+    ) -> Result<Vec<NotifierConfig>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, NotifierConfig>(
+            "
+                select *
+                from Notifiers
+                where project_id = $1
+                    and (jobset_id is null or jobset_id = $2)
+                order by id
+            ",
+        )
+        .bind(project_id)
+        .bind(jobset_id)
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    /// Deletes the notifier of the project and its deliveries, returns false if there was none
+    pub async fn delete(db: &DB, project_id: i32, id: i32) -> Result<bool, DBError> {
+        let result = with_db!(db, |pool| query(
+            "
+                delete from Notifiers
+                where id = $1 and project_id = $2
+            ",
+        )
+        .bind(id)
+        .bind(project_id)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected()))
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result == 1)
+    }
+}
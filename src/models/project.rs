@@ -2,118 +2,172 @@ use serde::{Deserialize, Serialize};
 pub type ProjectID = i32;
 
 #[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Project {
     pub id: Option<ProjectID>,
     pub name: String,
     pub description: String,
+    /// Serve the outputs of the project's jobs from the binary cache
+    #[serde(default)]
+    pub binary_cache: bool,
+    /// Only members and admins can see the project
+    #[serde(default)]
+    pub private: bool,
 }
 
 #[cfg(feature = "ssr")]
 use {
-    crate::hydracore::{DBError, DB},
-    sqlx::{query, query_as},
+    crate::hydracore::{with_db, with_tx, DBError, DB},
+    crate::models::{DeletionReport, User},
+    sqlx::{query, query_as, query_scalar},
     tracing::trace,
 };
 
 #[cfg(feature = "ssr")]
 impl Project {
     pub async fn get_single(db: &DB, id: i32) -> Result<Option<Project>, DBError> {
-        let mut conn = db.get_conn().await?;
-
-        let project = query_as::<_, Project>(
+        let project = with_db!(db, |pool| query_as::<_, Project>(
             "
                 select * 
                 from Projects
-                where id = ?
+                where id = $1
             ",
         )
         .bind(id)
-        .fetch_optional(&mut *conn)
-        .await;
+        .fetch_optional(pool)
+        .await);
 
         let project = project.map_err(|e| DBError::new(e.to_string()))?;
 
         Ok(project)
     }
 
-    pub async fn get_all(db: &DB) -> Result<Vec<Project>, DBError> {
-        let mut conn = db.get_conn().await?;
+    /// The first project with the name, names are what Hydra's urls use
+    pub async fn get_by_name(db: &DB, name: &str) -> Result<Option<Project>, DBError> {
+        let project = with_db!(db, |pool| query_as::<_, Project>(
+            "
+                select *
+                from Projects
+                where name = $1
+                order by id
+                limit 1
+            ",
+        )
+        .bind(name)
+        .fetch_optional(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
 
-        let projects = query_as::<_, Project>("select * from Projects")
-            .fetch_all(&mut *conn)
-            .await;
+        Ok(project)
+    }
+
+    pub async fn get_all(db: &DB) -> Result<Vec<Project>, DBError> {
+        let projects = with_db!(db, |pool| query_as::<_, Project>("select * from Projects")
+            .fetch_all(pool)
+            .await);
 
         let projects = projects.map_err(|e| DBError::new(e.to_string()))?;
 
         Ok(projects)
     }
 
+    /// The projects the user may see, private ones only if it is a member or an admin
+    pub async fn get_visible(db: &DB, user: Option<&User>) -> Result<Vec<Project>, DBError> {
+        let (user_id, admin) = user.map_or((None, false), |user| (user.id, user.admin));
+
+        let projects = with_db!(db, |pool| query_as::<_, Project>(
+            "
+                select *
+                from Projects
+                where not private
+                    or $1
+                    or id in (select project_id from ProjectMembers where user_id = $2)
+            ",
+        )
+        .bind(admin)
+        .bind(user_id)
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(projects)
+    }
+
     pub async fn add_to_db(&mut self, db: &DB) -> Result<(), DBError> {
         let name = &self.name;
         let desc = &self.description;
 
-        let mut conn = db.get_conn().await?;
-
-        let result = query!(
+        let result = with_db!(db, |pool| query_scalar::<_, i32>(
             "
                 insert into Projects 
-                    (name, description)
+                    (name, description, binary_cache, private)
                 values
-                    (?, ?)
+                    ($1, $2, $3, $4)
                 returning id
             ",
-            name,
-            desc
         )
-        .fetch_one(&mut *conn)
-        .await;
+        .bind(name)
+        .bind(desc)
+        .bind(self.binary_cache)
+        .bind(self.private)
+        .fetch_one(pool)
+        .await);
 
         if result.is_err() {
             return Err(DBError::new(result.err().unwrap().to_string()));
         }
 
-        self.id = Some(result.unwrap().id as i32);
+        self.id = Some(result.unwrap());
 
         Ok(())
     }
 
-    pub async fn delete(&self, db: &DB) -> Result<(), DBError> {
-        let mut conn = db.get_conn().await?;
-
+    /// Deletes the project with all of its jobsets
+    pub async fn delete(&self, db: &DB) -> Result<DeletionReport, DBError> {
         let id = self.id.unwrap();
 
-        _ = query!(
+        let mut tx = db.begin().await?;
+
+        let report =
+            DeletionReport::prepare(&mut tx, "select id from Jobsets where project_id = $1", id)
+                .await?;
+
+        _ = with_tx!(&mut tx, |conn| query(
             "
                 delete from Projects
-                where id = ?
+                where id = $1
             ",
-            id
         )
-        .execute(&mut *conn)
+        .bind(id)
+        .execute(conn)
         .await
+        .map(|result| result.rows_affected()))
         .map_err(|e| DBError::new(e.to_string()))?;
 
-        Ok(())
+        tx.commit().await?;
+
+        Ok(report)
     }
 
     pub async fn update(&self, db: &DB) -> Result<(), DBError> {
-        let mut conn = db.get_conn().await?;
-
         let id = self.id.unwrap();
 
-        _ = query!(
+        _ = with_db!(db, |pool| query(
             "
                 update Projects
-                set name = ?, description = ?
-                where id = ?
+                set name = $1, description = $2, binary_cache = $3, private = $4
+                where id = $5
             ",
-            self.name,
-            self.description,
-            id
         )
-        .execute(&mut *conn)
+        .bind(&self.name)
+        .bind(&self.description)
+        .bind(self.binary_cache)
+        .bind(self.private)
+        .bind(id)
+        .execute(pool)
         .await
+        .map(|result| result.rows_affected()))
         .map_err(|e| DBError::new(e.to_string()))?;
 
         Ok(())
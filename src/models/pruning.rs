@@ -0,0 +1,95 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::hydracore::{with_db, DBError, DB},
+    sqlx::{query_as, query_scalar},
+};
+
+/// What one pruning run removed and how much space that reclaimed
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Pruning {
+    pub id: Option<i32>,
+    pub started: DateTime<Utc>,
+    pub finished: DateTime<Utc>,
+    pub evaluations: i32,
+    pub jobs: i32,
+    pub logs: i32,
+    /// Size of the removed build logs
+    pub log_bytes: i64,
+    pub db_bytes_before: i64,
+    pub db_bytes_after: i64,
+}
+
+impl Pruning {
+    /// Space reclaimed in the database and the data directory
+    pub fn reclaimed_bytes(&self) -> i64 {
+        self.log_bytes + (self.db_bytes_before - self.db_bytes_after).max(0)
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl Pruning {
+    pub fn new(db_bytes_before: i64) -> Self {
+        let now = Utc::now();
+
+        Self {
+            id: None,
+            started: now,
+            finished: now,
+            evaluations: 0,
+            jobs: 0,
+            logs: 0,
+            log_bytes: 0,
+            db_bytes_before,
+            db_bytes_after: db_bytes_before,
+        }
+    }
+
+    pub async fn add_to_db(&mut self, db: &DB) -> Result<(), DBError> {
+        let result = with_db!(db, |pool| query_scalar::<_, i32>(
+            "
+                insert into Prunings
+                    (started, finished, evaluations, jobs, logs, log_bytes, db_bytes_before, db_bytes_after)
+                values
+                    ($1, $2, $3, $4, $5, $6, $7, $8)
+                returning id
+            ",
+        )
+        .bind(self.started)
+        .bind(self.finished)
+        .bind(self.evaluations)
+        .bind(self.jobs)
+        .bind(self.logs)
+        .bind(self.log_bytes)
+        .bind(self.db_bytes_before)
+        .bind(self.db_bytes_after)
+        .fetch_one(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        self.id = Some(result);
+
+        Ok(())
+    }
+
+    /// The latest runs, newest first
+    pub async fn get_latest(db: &DB, limit: i64) -> Result<Vec<Pruning>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, Pruning>(
+            "
+                select *
+                from Prunings
+                order by id desc
+                limit $1
+            ",
+        )
+        .bind(limit)
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+}
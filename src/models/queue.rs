@@ -0,0 +1,159 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::JobsetID;
+
+#[cfg(feature = "ssr")]
+use {
+    crate::hydracore::{with_db, DBError, Pool, DB},
+    sqlx::{query, query_as, query_scalar},
+    tracing::error,
+};
+
+/// Postgres channel the queue runner listens on for new requests
+#[cfg(feature = "ssr")]
+pub const QUEUE_CHANNEL: &str = "ladon_queue_requests";
+
+#[cfg_attr(feature = "ssr", derive(sqlx::Type), sqlx(type_name = "text"))]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum QueueRequestKind {
+    TriggerJobset,
+    /// Something was deleted, stop its evaluations and builds
+    CancelDeleted,
+    /// Stop the evaluation and the unfinished builds of the jobset
+    CancelJobset,
+    /// Build the failed job again
+    RestartJob,
+}
+
+/// A request from the web frontend to the queue runner.
+/// The queue runner may live in another process, so these are passed through the database.
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "ssr", derive(utoipa::ToSchema))]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct QueueRequest {
+    pub id: Option<i32>,
+    pub kind: QueueRequestKind,
+    pub jobset_id: Option<JobsetID>,
+    pub created: DateTime<Utc>,
+    pub job_id: Option<i32>,
+    /// The revision a push asked to evaluate, the latest one otherwise
+    pub revision: Option<String>,
+}
+
+#[cfg(feature = "ssr")]
+impl QueueRequest {
+    pub fn new(kind: QueueRequestKind, jobset_id: Option<JobsetID>) -> Self {
+        Self {
+            id: None,
+            kind,
+            jobset_id,
+            created: Utc::now(),
+            job_id: None,
+            revision: None,
+        }
+    }
+
+    pub fn for_job(kind: QueueRequestKind, job_id: i32) -> Self {
+        Self {
+            job_id: Some(job_id),
+            ..Self::new(kind, None)
+        }
+    }
+
+    pub async fn add_to_db(&mut self, db: &DB) -> Result<(), DBError> {
+        let result = with_db!(db, |pool| query_scalar::<_, i32>(
+            "
+                insert into QueueRequests
+                    (kind, jobset_id, created, job_id, revision)
+                values
+                    ($1, $2, $3, $4, $5)
+                returning id
+            ",
+        )
+        .bind(&self.kind)
+        .bind(self.jobset_id)
+        .bind(self.created)
+        .bind(self.job_id)
+        .bind(&self.revision)
+        .fetch_one(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        self.id = Some(result);
+
+        // sqlite has nothing alike, the queue runner polls it instead
+        if let Pool::Postgres(pool) = db.pool() {
+            let result = query("select pg_notify($1, '')")
+                .bind(QUEUE_CHANNEL)
+                .execute(pool)
+                .await;
+
+            // the request is queued nevertheless, it is picked up with the next one
+            if result.is_err() {
+                error!(
+                    "Failed to notify the queue runner: {}",
+                    result.err().unwrap().to_string()
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_pending(db: &DB) -> Result<Vec<QueueRequest>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, QueueRequest>(
+            "
+                select *
+                from QueueRequests
+                order by id
+            ",
+        )
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    /// Removes the request from the queue.
+    /// Returns false if somebody else already took it.
+    pub async fn take(&self, db: &DB) -> Result<bool, DBError> {
+        let id = self.id.unwrap();
+
+        let result = with_db!(db, |pool| query(
+            "
+                delete from QueueRequests
+                where id = $1
+            ",
+        )
+        .bind(id)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected()))
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result == 1)
+    }
+
+    /// Removes the trigger requests of the jobset up to the given one, a later push replaces earlier ones.
+    /// Returns false if somebody else already took them.
+    pub async fn take_triggers(db: &DB, jobset_id: JobsetID, up_to: i32) -> Result<bool, DBError> {
+        let result = with_db!(db, |pool| query(
+            "
+                delete from QueueRequests
+                where kind = $1 and jobset_id = $2 and id <= $3
+            ",
+        )
+        .bind(QueueRequestKind::TriggerJobset)
+        .bind(jobset_id)
+        .bind(up_to)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected()))
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result > 0)
+    }
+}
@@ -0,0 +1,103 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::hydracore::{with_db, DBError, DB},
+    crate::models::User,
+    sqlx::{query, query_as},
+};
+
+/// A login, identified by the hash of the token in its cookie
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Session {
+    pub id: String,
+    pub user_id: i32,
+    pub created: DateTime<Utc>,
+    pub expires: DateTime<Utc>,
+}
+
+#[cfg(feature = "ssr")]
+impl Session {
+    pub fn new(id: String, user_id: i32, expires: DateTime<Utc>) -> Self {
+        Self {
+            id,
+            user_id,
+            created: Utc::now(),
+            expires,
+        }
+    }
+
+    pub async fn add_to_db(&self, db: &DB) -> Result<(), DBError> {
+        _ = with_db!(db, |pool| query(
+            "
+                insert into Sessions
+                    (id, user_id, created, expires)
+                values
+                    ($1, $2, $3, $4)
+            ",
+        )
+        .bind(&self.id)
+        .bind(self.user_id)
+        .bind(self.created)
+        .bind(self.expires)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected()))
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// The user the session belongs to, if it did not expire
+    pub async fn get_user(db: &DB, id: &str) -> Result<Option<User>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, User>(
+            "
+                select Users.id, Users.name, Users.admin, Users.created
+                from Sessions
+                join Users on Users.id = Sessions.user_id
+                where Sessions.id = $1 and Sessions.expires > $2
+            ",
+        )
+        .bind(id)
+        .bind(Utc::now())
+        .fetch_optional(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    pub async fn delete(db: &DB, id: &str) -> Result<(), DBError> {
+        _ = with_db!(db, |pool| query(
+            "
+                delete from Sessions
+                where id = $1
+            ",
+        )
+        .bind(id)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected()))
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(())
+    }
+
+    pub async fn delete_expired(db: &DB) -> Result<(), DBError> {
+        _ = with_db!(db, |pool| query(
+            "
+                delete from Sessions
+                where expires <= $1
+            ",
+        )
+        .bind(Utc::now())
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected()))
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::DeliveryState;
+
+#[cfg(feature = "ssr")]
+use {
+    crate::hydracore::{with_db, DBError, DB},
+    chrono::TimeDelta,
+    sqlx::{query, query_as, query_scalar},
+};
+
+#[cfg_attr(feature = "ssr", derive(sqlx::Type), sqlx(type_name = "text"))]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatusState {
+    Pending,
+    Success,
+    Failure,
+}
+
+impl fmt::Display for StatusState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            StatusState::Pending => "pending",
+            StatusState::Success => "success",
+            StatusState::Failure => "failure",
+        })
+    }
+}
+
+/// Reporting one commit status of a jobset to its forge
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StatusDelivery {
+    pub id: Option<i32>,
+    pub jobset_id: i32,
+    pub revision: String,
+    pub status: StatusState,
+    /// Tells the statuses of several jobsets of the same commit apart
+    pub context: String,
+    pub description: String,
+    /// Page of the jobset the status links to
+    pub target_url: String,
+    pub state: DeliveryState,
+    pub attempts: i32,
+    pub error_message: Option<String>,
+    pub created: DateTime<Utc>,
+    pub finished: Option<DateTime<Utc>>,
+}
+
+#[cfg(feature = "ssr")]
+impl StatusDelivery {
+    pub fn new(
+        jobset_id: i32,
+        revision: String,
+        status: StatusState,
+        context: String,
+        description: String,
+        target_url: String,
+    ) -> Self {
+        Self {
+            id: None,
+            jobset_id,
+            revision,
+            status,
+            context,
+            description,
+            target_url,
+            state: DeliveryState::Queued,
+            attempts: 0,
+            error_message: None,
+            created: Utc::now(),
+            finished: None,
+        }
+    }
+
+    /// Deliveries are only kept for a month
+    pub async fn add_to_db(&mut self, db: &DB) -> Result<(), DBError> {
+        _ = with_db!(db, |pool| query(
+            "
+                delete from StatusDeliveries
+                where created < $1 and state in ('Delivered', 'Failed')
+            ",
+        )
+        .bind(Utc::now() - TimeDelta::days(30))
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected()))
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        let result = with_db!(db, |pool| query_scalar::<_, i32>(
+            "
+                insert into StatusDeliveries
+                    (jobset_id, revision, status, context, description, target_url, state, attempts, created)
+                values
+                    ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                returning id
+            ",
+        )
+        .bind(self.jobset_id)
+        .bind(&self.revision)
+        .bind(self.status)
+        .bind(&self.context)
+        .bind(&self.description)
+        .bind(&self.target_url)
+        .bind(&self.state)
+        .bind(self.attempts)
+        .bind(self.created)
+        .fetch_one(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        self.id = Some(result);
+
+        Ok(())
+    }
+
+    /// Writes state, attempts, error message and finished
+    pub async fn update(&self, db: &DB) -> Result<(), DBError> {
+        _ = with_db!(db, |pool| query(
+            "
+                update StatusDeliveries
+                set state = $1, attempts = $2, error_message = $3, finished = $4
+                where id = $5
+            ",
+        )
+        .bind(&self.state)
+        .bind(self.attempts)
+        .bind(&self.error_message)
+        .bind(self.finished)
+        .bind(self.id.unwrap())
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected()))
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Whether a later status of the same commit and context was queued, the forge would show this one instead
+    pub async fn is_replaced(&self, db: &DB) -> Result<bool, DBError> {
+        let result = with_db!(db, |pool| query_scalar::<_, i64>(
+            "
+                select count(*)
+                from StatusDeliveries
+                where jobset_id = $1 and revision = $2 and context = $3 and id > $4
+            ",
+        )
+        .bind(self.jobset_id)
+        .bind(&self.revision)
+        .bind(&self.context)
+        .bind(self.id.unwrap())
+        .fetch_one(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result > 0)
+    }
+
+    /// Deliveries a previous coordinator left unfinished
+    pub async fn get_unfinished(db: &DB) -> Result<Vec<StatusDelivery>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, StatusDelivery>(
+            "
+                select *
+                from StatusDeliveries
+                where state in ('Queued', 'Sending', 'Retrying')
+                order by id
+            ",
+        )
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+}
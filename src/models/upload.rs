@@ -0,0 +1,132 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::hydracore::{with_db, DBError, DB},
+    crate::models::Cache,
+    sqlx::{query, query_as, query_scalar},
+};
+
+#[cfg_attr(feature = "ssr", derive(sqlx::Type), sqlx(type_name = "text"))]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum UploadState {
+    Queued,
+    Uploading,
+    /// Failed, will be retried
+    Retrying,
+    Done,
+    Failed,
+}
+
+/// Copying the outputs of a job to one external cache
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Upload {
+    pub id: Option<i32>,
+    pub job_id: i32,
+    pub cache_id: i32,
+    /// Url of the cache, not stored in the Uploads table
+    pub url: String,
+    pub state: UploadState,
+    pub attempts: i32,
+    pub error_message: Option<String>,
+    pub finished: Option<DateTime<Utc>>,
+}
+
+#[cfg(feature = "ssr")]
+impl Upload {
+    pub fn new(job_id: i32, cache: &Cache) -> Self {
+        Self {
+            id: None,
+            job_id,
+            cache_id: cache.id.unwrap(),
+            url: cache.url.clone(),
+            state: UploadState::Queued,
+            attempts: 0,
+            error_message: None,
+            finished: None,
+        }
+    }
+
+    pub async fn add_to_db(&mut self, db: &DB) -> Result<(), DBError> {
+        let result = with_db!(db, |pool| query_scalar::<_, i32>(
+            "
+                insert into Uploads
+                    (job_id, cache_id, state, attempts)
+                values
+                    ($1, $2, $3, $4)
+                returning id
+            ",
+        )
+        .bind(self.job_id)
+        .bind(self.cache_id)
+        .bind(&self.state)
+        .bind(self.attempts)
+        .fetch_one(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        self.id = Some(result);
+
+        Ok(())
+    }
+
+    /// Writes state, attempts, error message and finished
+    pub async fn update(&self, db: &DB) -> Result<(), DBError> {
+        _ = with_db!(db, |pool| query(
+            "
+                update Uploads
+                set state = $1, attempts = $2, error_message = $3, finished = $4
+                where id = $5
+            ",
+        )
+        .bind(&self.state)
+        .bind(self.attempts)
+        .bind(&self.error_message)
+        .bind(self.finished)
+        .bind(self.id.unwrap())
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected()))
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(())
+    }
+
+    pub async fn get_all(db: &DB, job_id: i32) -> Result<Vec<Upload>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, Upload>(
+            "
+                select Uploads.*, Caches.url
+                from Uploads
+                join Caches on Caches.id = Uploads.cache_id
+                where Uploads.job_id = $1
+                order by Uploads.id
+            ",
+        )
+        .bind(job_id)
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    /// Uploads a previous coordinator left unfinished
+    pub async fn get_unfinished(db: &DB) -> Result<Vec<Upload>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, Upload>(
+            "
+                select Uploads.*, Caches.url
+                from Uploads
+                join Caches on Caches.id = Uploads.cache_id
+                where Uploads.state in ('Queued', 'Uploading', 'Retrying')
+                order by Uploads.id
+            ",
+        )
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,185 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::hydracore::{with_db, DBError, DB},
+    sqlx::{query, query_as, query_scalar},
+};
+
+/// A local account, the password hash never leaves the database
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct User {
+    pub id: Option<i32>,
+    pub name: String,
+    pub admin: bool,
+    pub created: DateTime<Utc>,
+}
+
+#[cfg(feature = "ssr")]
+#[derive(sqlx::FromRow)]
+struct UserWithPassword {
+    #[sqlx(flatten)]
+    user: User,
+    password_hash: String,
+}
+
+#[cfg(feature = "ssr")]
+impl User {
+    pub fn new(name: String, admin: bool) -> Self {
+        Self {
+            id: None,
+            name,
+            admin,
+            created: Utc::now(),
+        }
+    }
+
+    pub async fn add_to_db(&mut self, db: &DB, password_hash: &str) -> Result<(), DBError> {
+        let result = with_db!(db, |pool| query_scalar::<_, i32>(
+            "
+                insert into Users
+                    (name, password_hash, admin, created)
+                values
+                    ($1, $2, $3, $4)
+                returning id
+            ",
+        )
+        .bind(&self.name)
+        .bind(password_hash)
+        .bind(self.admin)
+        .bind(self.created)
+        .fetch_one(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        self.id = Some(result);
+
+        Ok(())
+    }
+
+    /// Adds an account of the OpenID Connect provider, it has no password
+    pub async fn add_oidc_to_db(&mut self, db: &DB, subject: &str) -> Result<(), DBError> {
+        let result = with_db!(db, |pool| query_scalar::<_, i32>(
+            "
+                insert into Users
+                    (name, password_hash, admin, created, oidc_subject)
+                values
+                    ($1, '', $2, $3, $4)
+                returning id
+            ",
+        )
+        .bind(&self.name)
+        .bind(self.admin)
+        .bind(self.created)
+        .bind(subject)
+        .fetch_one(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        self.id = Some(result);
+
+        Ok(())
+    }
+
+    pub async fn set_admin(&mut self, db: &DB, admin: bool) -> Result<(), DBError> {
+        _ = with_db!(db, |pool| query(
+            "
+                update Users
+                set admin = $1
+                where id = $2
+            ",
+        )
+        .bind(admin)
+        .bind(self.id)
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected()))
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        self.admin = admin;
+
+        Ok(())
+    }
+
+    pub async fn get_all(db: &DB) -> Result<Vec<User>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, User>(
+            "
+                select id, name, admin, created
+                from Users
+                order by name
+            ",
+        )
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    pub async fn get_single(db: &DB, id: i32) -> Result<Option<User>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, User>(
+            "
+                select id, name, admin, created
+                from Users
+                where id = $1
+            ",
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    pub async fn get_by_oidc_subject(db: &DB, subject: &str) -> Result<Option<User>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, User>(
+            "
+                select id, name, admin, created
+                from Users
+                where oidc_subject = $1
+            ",
+        )
+        .bind(subject)
+        .fetch_optional(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    pub async fn get_by_name(db: &DB, name: &str) -> Result<Option<User>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, User>(
+            "
+                select id, name, admin, created
+                from Users
+                where name = $1
+            ",
+        )
+        .bind(name)
+        .fetch_optional(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+
+    /// The user with its password hash, to check a login against
+    pub async fn get_with_password(db: &DB, name: &str) -> Result<Option<(User, String)>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, UserWithPassword>(
+            "
+                select *
+                from Users
+                where name = $1
+            ",
+        )
+        .bind(name)
+        .fetch_optional(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result.map(|row| (row.user, row.password_hash)))
+    }
+}
@@ -0,0 +1,135 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::hydracore::{with_db, DBError, DB},
+    chrono::TimeDelta,
+    sqlx::{query, query_as, query_scalar},
+};
+
+#[cfg_attr(feature = "ssr", derive(sqlx::Type), sqlx(type_name = "text"))]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Forge {
+    GitHub,
+    /// Also Forgejo, it has the same events and api
+    Gitea,
+    GitLab,
+}
+
+impl std::fmt::Display for Forge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Forge::GitHub => "GitHub",
+            Forge::Gitea => "Gitea",
+            Forge::GitLab => "GitLab",
+        })
+    }
+}
+
+#[cfg_attr(feature = "ssr", derive(sqlx::Type), sqlx(type_name = "text"))]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeliveryOutcome {
+    /// At least one jobset is queued for evaluation at the pushed revision
+    Triggered,
+    /// Verified, but no jobset follows the branch or it is no push
+    Ignored,
+    /// The payload could not be read or ladon failed to handle it.
+    /// Deliveries with a wrong signature or token are not recorded at all.
+    Rejected,
+}
+
+/// A webhook request of a forge and what ladon did with it
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WebhookDelivery {
+    pub id: Option<i32>,
+    pub forge: Forge,
+    pub event: Option<String>,
+    pub received: DateTime<Utc>,
+    pub repository: Option<String>,
+    pub branch: Option<String>,
+    pub revision: Option<String>,
+    pub outcome: DeliveryOutcome,
+    pub message: String,
+}
+
+#[cfg(feature = "ssr")]
+impl WebhookDelivery {
+    pub fn new(forge: Forge, event: Option<String>) -> Self {
+        Self {
+            id: None,
+            forge,
+            event,
+            received: Utc::now(),
+            repository: None,
+            branch: None,
+            revision: None,
+            outcome: DeliveryOutcome::Ignored,
+            message: String::new(),
+        }
+    }
+
+    pub fn set_outcome(&mut self, outcome: DeliveryOutcome, message: String) {
+        self.outcome = outcome;
+        self.message = message;
+    }
+
+    /// Deliveries are only kept for a month, forges retry failed ones within hours
+    pub async fn add_to_db(&mut self, db: &DB) -> Result<(), DBError> {
+        _ = with_db!(db, |pool| query(
+            "
+                delete from WebhookDeliveries
+                where received < $1
+            ",
+        )
+        .bind(Utc::now() - TimeDelta::days(30))
+        .execute(pool)
+        .await
+        .map(|result| result.rows_affected()))
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        let result = with_db!(db, |pool| query_scalar::<_, i32>(
+            "
+                insert into WebhookDeliveries
+                    (forge, event, received, repository, branch, revision, outcome, message)
+                values
+                    ($1, $2, $3, $4, $5, $6, $7, $8)
+                returning id
+            ",
+        )
+        .bind(self.forge)
+        .bind(&self.event)
+        .bind(self.received)
+        .bind(&self.repository)
+        .bind(&self.branch)
+        .bind(&self.revision)
+        .bind(self.outcome)
+        .bind(&self.message)
+        .fetch_one(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        self.id = Some(result);
+
+        Ok(())
+    }
+
+    /// The newest deliveries first
+    pub async fn get_latest(db: &DB, count: i64) -> Result<Vec<WebhookDelivery>, DBError> {
+        let result = with_db!(db, |pool| query_as::<_, WebhookDelivery>(
+            "
+                select *
+                from WebhookDeliveries
+                order by id desc
+                limit $1
+            ",
+        )
+        .bind(count)
+        .fetch_all(pool)
+        .await)
+        .map_err(|e| DBError::new(e.to_string()))?;
+
+        Ok(result)
+    }
+}
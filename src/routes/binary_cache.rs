@@ -0,0 +1,69 @@
+use leptos::prelude::*;
+
+#[server]
+pub async fn get_public_keys() -> Result<Vec<String>, ServerFnError> {
+    use crate::{hydracore::KeyStore, state::State};
+    use std::sync::Arc;
+    use tracing::error;
+
+    let state: Arc<State> = expect_context();
+
+    let keys = KeyStore::new(&state.data_dir).active().map_err(|e| {
+        error!("Failed to load signing keys: {}", e);
+        ServerFnError::new("Failed to load signing keys!")
+    })?;
+
+    Ok(keys.iter().map(|key| key.public_key()).collect())
+}
+
+#[component]
+pub fn BinaryCache() -> impl IntoView {
+    let keys = OnceResource::new(get_public_keys());
+
+    view! {
+        <div class="generic_table">
+            <h3>"Binary cache"</h3>
+            <div class="description">
+                <p>"Projects with the binary cache enabled serve their build outputs under /cache."</p>
+                <p>"Add the following keys to trusted-public-keys to use it as a substituter:"</p>
+            </div>
+            <Suspense fallback=move || view! {<p>"Loading keys..."</p>}>
+                {move || {
+                    let keys = keys.get();
+
+                    if keys.is_none() {
+                        return view! {<p class="left error">"Failed to load keys"</p>}.into_any();
+                    }
+
+                    let keys = keys.unwrap();
+
+                    if keys.is_err() {
+                        let err = keys.err().unwrap().to_string();
+                        return view! {<p class="left error">"Failed to load keys: "{err}</p>}.into_any();
+                    }
+
+                    let keys = keys.unwrap();
+
+                    if keys.is_empty() {
+                        return view! {<p class="left">"There are no signing keys yet, generate one with: ladon keys generate <name>"</p>}.into_any();
+                    }
+
+                    view! {
+                        <table>
+                            <tbody>
+                                <tr>
+                                    <th>"Public key"</th>
+                                </tr>
+                                {keys.into_iter().map(|key| view! {
+                                    <tr>
+                                        <td><code>{key}</code></td>
+                                    </tr>
+                                }).collect_view()}
+                            </tbody>
+                        </table>
+                    }.into_any()
+                }}
+            </Suspense>
+        </div>
+    }
+}
@@ -0,0 +1,3 @@
+pub use show::*;
+
+mod show;
@@ -0,0 +1,402 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use leptos::prelude::*;
+use leptos_router::hooks::use_params_map;
+
+use crate::{
+    components::go_back::GoBack,
+    models::{BuildProduct, Job, JobPath, ProjectRole, Upload},
+    routes::{format_bytes, project::get_project_role},
+};
+
+stylance::import_crate_style!(
+    #[allow(dead_code)]
+    style,
+    "style/jobset.module.scss"
+);
+
+#[cfg(feature = "ssr")]
+use {
+    crate::{server::auth::require_view, state::State},
+    axum::http::StatusCode,
+    leptos_axum::ResponseOptions,
+    std::sync::Arc,
+    tracing::error,
+};
+
+#[cfg(feature = "ssr")]
+fn parse_job_id(id: &str) -> Result<i32, ServerFnError> {
+    let response_opts: ResponseOptions = expect_context();
+
+    id.parse::<i32>().map_err(|_| {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        ServerFnError::new("Invalid job id!")
+    })
+}
+
+/// Parses the id, fails like for a missing project if the user may not see the job's project
+#[cfg(feature = "ssr")]
+async fn visible_job_id(id: &str) -> Result<i32, ServerFnError> {
+    let state: Arc<State> = expect_context();
+
+    let id = parse_job_id(id)?;
+
+    let origin = Job::get_origin(&state.db, id).await.map_err(|e| {
+        error!("Failed to fetch job: {}", e.to_string());
+        ServerFnError::new("Failed to fetch job!")
+    })?;
+
+    if let Some(origin) = origin {
+        require_view(origin.project_id).await?;
+    }
+
+    Ok(id)
+}
+
+#[server]
+pub async fn get_job(id: String) -> Result<Option<Job>, ServerFnError> {
+    let state: Arc<State> = expect_context();
+
+    let job = Job::get_single(&state.db, visible_job_id(&id).await?)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch job: {}", e.to_string());
+            ServerFnError::new("Failed to fetch job!")
+        })?;
+
+    Ok(job)
+}
+
+/// Keeps the job's outputs rooted regardless of the jobset's retention policy, or stops doing so
+#[server]
+pub async fn set_job_keep(id: String, keep: bool) -> Result<(), ServerFnError> {
+    use crate::{
+        hydracore::GcRoots,
+        models::{JobDiff, ProjectRole},
+        server::auth::require_role,
+    };
+
+    let state: Arc<State> = expect_context();
+    let response_opts: ResponseOptions = expect_context();
+
+    let origin = Job::get_origin(&state.db, parse_job_id(&id)?)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch job: {}", e.to_string());
+            ServerFnError::new("Failed to fetch job!")
+        })?;
+
+    if origin.is_none() {
+        response_opts.set_status(StatusCode::NOT_FOUND);
+        return Err(ServerFnError::new("Failed to find job!"));
+    }
+
+    let origin = origin.unwrap();
+
+    require_role(origin.project_id, ProjectRole::Maintainer).await?;
+
+    let mut job = origin.job;
+
+    let mut diff = JobDiff::new();
+    diff.keep = Some(keep);
+
+    job.update_job(&state.db, diff).await.map_err(|e| {
+        error!("Failed to update job: {}", e.to_string());
+        ServerFnError::new("Failed to update job!")
+    })?;
+
+    let result = GcRoots::new(state.db.clone(), state.data_dir.clone())
+        .sync()
+        .await;
+
+    if result.is_err() {
+        error!("Failed to sync gc roots: {}", result.err().unwrap());
+    }
+
+    Ok(())
+}
+
+#[server]
+pub async fn get_job_outputs(id: String) -> Result<Vec<JobPath>, ServerFnError> {
+    let state: Arc<State> = expect_context();
+
+    let outputs = JobPath::get_outputs(&state.db, visible_job_id(&id).await?)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch job outputs: {}", e.to_string());
+            ServerFnError::new("Failed to fetch job outputs!")
+        })?;
+
+    Ok(outputs)
+}
+
+#[server]
+pub async fn get_build_products(id: String) -> Result<Vec<BuildProduct>, ServerFnError> {
+    let state: Arc<State> = expect_context();
+
+    let products = BuildProduct::get_all(&state.db, visible_job_id(&id).await?)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch build products: {}", e.to_string());
+            ServerFnError::new("Failed to fetch build products!")
+        })?;
+
+    Ok(products)
+}
+
+#[server]
+pub async fn get_constituents(id: String) -> Result<Vec<Job>, ServerFnError> {
+    let state: Arc<State> = expect_context();
+
+    let constituents = Job::get_constituents(&state.db, visible_job_id(&id).await?)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch constituents: {}", e.to_string());
+            ServerFnError::new("Failed to fetch constituents!")
+        })?;
+
+    Ok(constituents)
+}
+
+#[server]
+pub async fn get_uploads(id: String) -> Result<Vec<Upload>, ServerFnError> {
+    let state: Arc<State> = expect_context();
+
+    let uploads = Upload::get_all(&state.db, visible_job_id(&id).await?)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch uploads: {}", e.to_string());
+            ServerFnError::new("Failed to fetch uploads!")
+        })?;
+
+    Ok(uploads)
+}
+
+fn convert_date_to_string(date: Option<DateTime<Utc>>) -> String {
+    match date {
+        None => "-".to_string(),
+        Some(value) => value.format("%H:%M:%S %d.%m.%Y").to_string(),
+    }
+}
+
+fn mk_job_entry(key: &str, value: String) -> impl IntoView {
+    view! {
+        <div class=style::key>
+            <p>{key.to_string()}</p>
+        </div>
+        <div class=style::value>
+            <p>{value}</p>
+        </div>
+    }
+}
+
+#[component]
+pub fn Job() -> impl IntoView {
+    let params = use_params_map();
+
+    let project_id = params.read_untracked().get("proj-id").unwrap_or_default();
+    let jobset_id = params.read_untracked().get("jobset-id").unwrap_or_default();
+    let job_id = params.read_untracked().get("job-id").unwrap_or_default();
+
+    let jobset_url = format!("/project/{}/jobset/{}", project_id, jobset_id);
+    let download_url = format!("{}/job/{}/download", jobset_url, job_id);
+
+    let keep_action = ServerAction::<SetJobKeep>::new();
+
+    let role_data = OnceResource::new(get_project_role(project_id.clone()));
+
+    let job_data = Resource::new(move || keep_action.version().get(), {
+        let job_id = job_id.clone();
+        move |_| get_job(job_id.clone())
+    });
+    let outputs_data = OnceResource::new(get_job_outputs(job_id.clone()));
+    let constituents_data = OnceResource::new(get_constituents(job_id.clone()));
+    let products_data = OnceResource::new(get_build_products(job_id.clone()));
+
+    let uploads_data = Resource::new(|| (), move |_| get_uploads(job_id.clone()));
+
+    // uploads run in the background, keep their progress up to date
+    Effect::new(move |_| {
+        _ = set_interval_with_handle(move || uploads_data.refetch(), Duration::from_secs(5));
+    });
+
+    view! {
+        <GoBack url=jobset_url.clone() text="jobset".to_string()/>
+        <div class=style::view>
+            <Suspense fallback=move || view! {<p>"Loading job..."</p>}>
+                {move || {
+                    let job = job_data.get();
+
+                    if job.is_none() {
+                        return view! {<p class="error">"Error: Failed to load job!"</p>}.into_any();
+                    }
+
+                    let job = job.unwrap();
+
+                    if job.is_err() {
+                        let msg = job.err().unwrap().to_string();
+                        return view! {<p class="error">"Error: Failed to load job: "{msg}</p>}.into_any();
+                    }
+
+                    let job = job.unwrap();
+
+                    if job.is_none() {
+                        return view! {<p class="error">"Error: Failed to find job!"</p>}.into_any();
+                    }
+
+                    let job = job.unwrap();
+
+                    let keep_text = match job.keep {
+                        true => "Stop keeping",
+                        false => "Keep",
+                    };
+
+                    let role = role_data.get().and_then(Result::ok).flatten();
+
+                    view! {
+                        <div class=style::action>
+                            {(role >= Some(ProjectRole::Maintainer)).then(|| view! {
+                                <ActionForm action=keep_action>
+                                    <input type="hidden" name="id" value=job.id.unwrap()/>
+                                    <input type="hidden" name="keep" value=(!job.keep).to_string()/>
+                                    <input type="submit" value=keep_text/>
+                                </ActionForm>
+                            })}
+                        </div>
+                        <div class=style::statistics>
+                            {mk_job_entry("Attribute: ", job.attribute_name)}
+                            {mk_job_entry("Derivation: ", job.derivation_path)}
+                            {mk_job_entry("State: ", format!("{:?}", job.state))}
+                            {mk_job_entry("Finished: ", convert_date_to_string(job.finished))}
+                            {mk_job_entry("Kept: ", match job.keep { true => "yes", false => "no" }.to_string())}
+                            {mk_job_entry("Aggregate: ", match job.aggregate { true => "yes", false => "no" }.to_string())}
+                        </div>
+                    }.into_any()
+                }}
+            </Suspense>
+            <h4>"Outputs"</h4>
+            <Suspense fallback=move || view! {<p>"Loading outputs..."</p>}>
+                {move || {
+                    let outputs = outputs_data.get();
+
+                    match outputs {
+                        None => view! {<p class="left error">"Failed to load outputs"</p>}.into_any(),
+                        Some(Err(e)) => view! {<p class="left error">"Failed to load outputs: "{e.to_string()}</p>}.into_any(),
+                        Some(Ok(outputs)) if outputs.is_empty() => view! {<p class="left">"No outputs yet"</p>}.into_any(),
+                        Some(Ok(outputs)) => view! {
+                            <ul>
+                                {outputs.into_iter().map(|output| view! {<li><code>{output.path}</code></li>}).collect_view()}
+                            </ul>
+                        }.into_any(),
+                    }
+                }}
+            </Suspense>
+            <h4>"Build products"</h4>
+            <Suspense fallback=move || view! {<p>"Loading build products..."</p>}>
+                {move || {
+                    let products = products_data.get();
+                    let download_url = download_url.clone();
+
+                    match products {
+                        None => view! {<p class="left error">"Failed to load build products"</p>}.into_any(),
+                        Some(Err(e)) => view! {<p class="left error">"Failed to load build products: "{e.to_string()}</p>}.into_any(),
+                        Some(Ok(products)) if products.is_empty() => view! {<p class="left">"No build products"</p>}.into_any(),
+                        Some(Ok(products)) => view! {
+                            <table class="generic-table">
+                                <tbody>
+                                    <tr>
+                                        <th>"Type"</th>
+                                        <th>"Name"</th>
+                                        <th>"Size"</th>
+                                        <th>"SHA-256"</th>
+                                    </tr>
+                                    {products.into_iter().map(|product| {
+                                        let url = match product.is_directory() {
+                                            true => format!("{}/{}", download_url, product.id.unwrap()),
+                                            false => format!("{}/{}/{}", download_url, product.id.unwrap(), product.name),
+                                        };
+
+                                        view! {
+                                            <tr>
+                                                <td>{format!("{} ({})", product.r#type, product.subtype)}</td>
+                                                <td><a href=url rel="external">{product.name}</a></td>
+                                                <td>{product.file_size.map(format_bytes).unwrap_or("-".to_string())}</td>
+                                                <td><code>{product.sha256.unwrap_or_default()}</code></td>
+                                            </tr>
+                                        }
+                                    }).collect_view()}
+                                </tbody>
+                            </table>
+                        }.into_any(),
+                    }
+                }}
+            </Suspense>
+            <h4>"Constituents"</h4>
+            <Suspense fallback=move || view! {<p>"Loading constituents..."</p>}>
+                {move || {
+                    let constituents = constituents_data.get();
+                    let jobset_url = jobset_url.clone();
+
+                    match constituents {
+                        None => view! {<p class="left error">"Failed to load constituents"</p>}.into_any(),
+                        Some(Err(e)) => view! {<p class="left error">"Failed to load constituents: "{e.to_string()}</p>}.into_any(),
+                        Some(Ok(constituents)) if constituents.is_empty() => view! {<p class="left">"No constituents"</p>}.into_any(),
+                        Some(Ok(constituents)) => view! {
+                            <table class="generic-table">
+                                <tbody>
+                                    <tr>
+                                        <th>"Attribute"</th>
+                                        <th>"State"</th>
+                                        <th>"Finished"</th>
+                                    </tr>
+                                    {constituents.into_iter().map(|job| view! {
+                                        <tr>
+                                            <td><a href=format!("{}/job/{}", jobset_url, job.id.unwrap())>{job.attribute_name}</a></td>
+                                            <td>{format!("{:?}", job.state)}</td>
+                                            <td>{convert_date_to_string(job.finished)}</td>
+                                        </tr>
+                                    }).collect_view()}
+                                </tbody>
+                            </table>
+                        }.into_any(),
+                    }
+                }}
+            </Suspense>
+            <h4>"Uploads"</h4>
+            <Transition fallback=move || view! {<p>"Loading uploads..."</p>}>
+                {move || {
+                    let uploads = uploads_data.get();
+
+                    match uploads {
+                        None => view! {<p class="left error">"Failed to load uploads"</p>}.into_any(),
+                        Some(Err(e)) => view! {<p class="left error">"Failed to load uploads: "{e.to_string()}</p>}.into_any(),
+                        Some(Ok(uploads)) if uploads.is_empty() => view! {<p class="left">"No uploads"</p>}.into_any(),
+                        Some(Ok(uploads)) => view! {
+                            <table class="generic-table">
+                                <tbody>
+                                    <tr>
+                                        <th>"Cache"</th>
+                                        <th>"State"</th>
+                                        <th>"Attempts"</th>
+                                        <th>"Finished"</th>
+                                        <th>"Error"</th>
+                                    </tr>
+                                    {uploads.into_iter().map(|upload| view! {
+                                        <tr>
+                                            <td>{upload.url}</td>
+                                            <td>{format!("{:?}", upload.state)}</td>
+                                            <td>{upload.attempts}</td>
+                                            <td>{convert_date_to_string(upload.finished)}</td>
+                                            <td>{upload.error_message.unwrap_or_default()}</td>
+                                        </tr>
+                                    }).collect_view()}
+                                </tbody>
+                            </table>
+                        }.into_any(),
+                    }
+                }}
+            </Transition>
+        </div>
+    }
+}
@@ -0,0 +1,123 @@
+use chrono::{DateTime, Utc};
+use leptos::prelude::*;
+use leptos_router::hooks::use_params_map;
+
+use crate::{components::go_back::GoBack, models::ChannelRevision};
+
+#[server]
+pub async fn get_channel_revisions(
+    jobset_id: String,
+) -> Result<Vec<ChannelRevision>, ServerFnError> {
+    use crate::{models::Jobset, state::State};
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+    use std::sync::Arc;
+    use tracing::error;
+
+    let state: Arc<State> = expect_context();
+    let response_opts: ResponseOptions = expect_context();
+
+    let jobset_id = jobset_id.parse::<i32>().map_err(|_| {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        ServerFnError::new("Invalid jobset id!")
+    })?;
+
+    let jobset = Jobset::get_single(&state.db, jobset_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch jobset: {}", e.to_string());
+            ServerFnError::new("Failed to fetch jobset!")
+        })?;
+
+    if let Some(jobset) = jobset {
+        crate::server::auth::require_view(jobset.project_id).await?;
+    }
+
+    let revisions = ChannelRevision::get_all(&state.db, jobset_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch channel revisions: {}", e.to_string());
+            ServerFnError::new("Failed to fetch channel revisions!")
+        })?;
+
+    Ok(revisions)
+}
+
+fn convert_date_to_string(date: DateTime<Utc>) -> String {
+    date.format("%H:%M:%S %d.%m.%Y").to_string()
+}
+
+#[component]
+pub fn JobsetChannel() -> impl IntoView {
+    let params = use_params_map();
+
+    let project_id = params.read_untracked().get("proj-id").unwrap_or_default();
+    let jobset_id = params.read_untracked().get("jobset-id").unwrap_or_default();
+
+    let revisions = OnceResource::new(get_channel_revisions(jobset_id.clone()));
+
+    let channel_url = format!("/channel/{}/{}", project_id, jobset_id);
+
+    view! {
+        <GoBack url=format!("/project/{}/jobset/{}", project_id, jobset_id) text="jobset".to_string()/>
+        <div class="generic_table">
+            <h3>"Channel"</h3>
+            <div class="description">
+                <p>"Subscribe to the channel head with: nix-channel --add <ladon url>"{channel_url.clone()}</p>
+            </div>
+            <Suspense fallback=move || view! {<p>"Loading revisions..."</p>}>
+                {move || {
+                    let revisions = revisions.get();
+
+                    if revisions.is_none() {
+                        return view! {<p class="left error">"Failed to load revisions"</p>}.into_any();
+                    }
+
+                    let revisions = revisions.unwrap();
+
+                    if revisions.is_err() {
+                        let err = revisions.err().unwrap().to_string();
+                        return view! {<p class="left error">"Failed to load revisions: "{err}</p>}.into_any();
+                    }
+
+                    let revisions = revisions.unwrap();
+
+                    if revisions.is_empty() {
+                        return view! {<p class="left">"No evaluation was released yet"</p>}.into_any();
+                    }
+
+                    let channel_url = channel_url.clone();
+
+                    view! {
+                        <table>
+                            <tbody>
+                                <tr>
+                                    <th>"Release"</th>
+                                    <th>"Revision"</th>
+                                    <th>"Released"</th>
+                                    <th>"Files"</th>
+                                </tr>
+                                {revisions.into_iter().map(|revision| {
+                                    let url = format!("{}/revision/{}", channel_url, revision.id.unwrap());
+
+                                    view! {
+                                        <tr>
+                                            <td>{revision.name}</td>
+                                            <td><code>{revision.revision.unwrap_or("-".to_string())}</code></td>
+                                            <td>{convert_date_to_string(revision.created)}</td>
+                                            <td>
+                                                <a href=format!("{}/nixexprs.tar.xz", url) rel="external">"nixexprs.tar.xz"</a>
+                                                " "
+                                                <a href=format!("{}/binary-cache-url", url) rel="external">"binary-cache-url"</a>
+                                            </td>
+                                        </tr>
+                                    }
+                                }).collect_view()}
+                            </tbody>
+                        </table>
+                    }.into_any()
+                }}
+            </Suspense>
+        </div>
+    }
+}
@@ -4,12 +4,14 @@ use leptos_router::hooks::use_params_map;
 
 #[server]
 pub async fn create_jobset(jobset: Jobset) -> Result<(), ServerFnError> {
-    use crate::state::State;
+    use crate::{models::ProjectRole, server::auth::require_role, state::State};
     use axum::http::StatusCode;
     use leptos_axum::ResponseOptions;
     use std::sync::Arc;
     use tracing::{error, info, warn};
 
+    require_role(jobset.project_id, ProjectRole::Maintainer).await?;
+
     let response_opts: ResponseOptions = expect_context();
 
     if jobset.name == "" {
@@ -25,15 +27,27 @@ pub async fn create_jobset(jobset: Jobset) -> Result<(), ServerFnError> {
         response_opts.set_status(StatusCode::BAD_REQUEST);
         return Err(ServerFnError::new(err));
     }
+    if jobset.keep_evaluations < 0 {
+        let err = "The number of kept evaluations may not be negative!";
+        warn!("{}", err);
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new(err));
+    }
+    if jobset.max_evaluations < 0 || jobset.max_evaluation_age < 0 {
+        let err = "Retention limits may not be negative!";
+        warn!("{}", err);
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new(err));
+    }
     info!("Creating new jobset on project {}", jobset.project_id);
 
     let state: Arc<State> = expect_context();
 
     let mut jobset = jobset;
 
-    let result = jobset
-        .add_to_db(&*state.coordinator.lock().await.get_db().await.lock().await)
-        .await;
+    jobset.channel_gate = jobset.channel_gate.filter(|gate| !gate.is_empty());
+
+    let result = jobset.add_to_db(&state.db).await;
 
     if result.is_err() {
         let err = result.err().unwrap().to_string();
@@ -70,6 +84,17 @@ pub fn CreateJobset() -> impl IntoView {
                     <input type="text" name="jobset[flake]" id="jobset_flake_uri" placeholder="Jobset Flake Uri"/>
                     <label for="jobset_check_interval">"Jobset check interval"</label>
                     <input type="number" name="jobset[check_interval]" id="jobset_check_interval" placeholder="Jobset check interval" value=0/>
+                    <label for="jobset_keep_evaluations">"Evaluations to keep outputs of"</label>
+                    <input type="number" name="jobset[keep_evaluations]" id="jobset_keep_evaluations" placeholder="Evaluations to keep outputs of" min=0 value=3/>
+                    <label for="jobset_max_evaluations">"Evaluations to keep, 0 for all"</label>
+                    <input type="number" name="jobset[max_evaluations]" id="jobset_max_evaluations" placeholder="Evaluations to keep" min=0 value=0/>
+                    <label for="jobset_max_evaluation_age">"Days to keep evaluations for, 0 for ever"</label>
+                    <input type="number" name="jobset[max_evaluation_age]" id="jobset_max_evaluation_age" placeholder="Days to keep evaluations for" min=0 value=0/>
+                    <label>
+                        <input type="checkbox" name="jobset[channel]" value="true"/>
+                        "Publish a channel"
+                    </label>
+                    <input type="text" name="jobset[channel_gate]" id="jobset_channel_gate" placeholder="Channel gate attribute, all jobs if empty"/>
                     <input type="submit" value="Create jobset"/>
                 </div>
             </ActionForm>
@@ -9,7 +9,7 @@ use crate::{
 
 #[cfg(feature = "ssr")]
 use {
-    crate::state::State,
+    crate::{models::ProjectRole, server::auth::require_role, state::State},
     leptos_axum::redirect,
     std::sync::Arc,
     tracing::{error, info},
@@ -20,17 +20,12 @@ pub async fn update_jobset(jobset: Jobset) -> Result<(), ServerFnError> {
     info!("Received update");
     let state: Arc<State> = expect_context();
 
-    let coordinator = state.coordinator.lock().await;
-
-    let server_jobset = Jobset::get_single(
-        &*coordinator.get_db().await.lock().await,
-        jobset.id.unwrap(),
-    )
-    .await
-    .map_err(|e| {
-        error!("Failed to get jobset: {}", e.to_string());
-        ServerFnError::new(format!("Failed to get jobset: {}", e.to_string()))
-    })?;
+    let server_jobset = Jobset::get_single(&state.db, jobset.id.unwrap())
+        .await
+        .map_err(|e| {
+            error!("Failed to get jobset: {}", e.to_string());
+            ServerFnError::new(format!("Failed to get jobset: {}", e))
+        })?;
 
     if server_jobset.is_none() {
         return Err(ServerFnError::new(format!("Failed to find the jobset")));
@@ -38,6 +33,8 @@ pub async fn update_jobset(jobset: Jobset) -> Result<(), ServerFnError> {
 
     let mut server_jobset = server_jobset.unwrap();
 
+    require_role(server_jobset.project_id, ProjectRole::Maintainer).await?;
+
     let mut diff = JobsetDiff::new();
 
     diff.set_name(jobset.name);
@@ -45,8 +42,27 @@ pub async fn update_jobset(jobset: Jobset) -> Result<(), ServerFnError> {
     diff.set_description(jobset.description);
     diff.set_check_interval(jobset.check_interval);
 
+    if jobset.keep_evaluations < 0 {
+        return Err(ServerFnError::new(
+            "The number of kept evaluations may not be negative!",
+        ));
+    }
+
+    diff.set_keep_evaluations(jobset.keep_evaluations);
+
+    if jobset.max_evaluations < 0 || jobset.max_evaluation_age < 0 {
+        return Err(ServerFnError::new("Retention limits may not be negative!"));
+    }
+
+    diff.set_max_evaluations(jobset.max_evaluations);
+    diff.set_max_evaluation_age(jobset.max_evaluation_age);
+    diff.set_channel(
+        jobset.channel,
+        jobset.channel_gate.filter(|gate| !gate.is_empty()),
+    );
+
     _ = server_jobset
-        .update_jobset(&*coordinator.get_db().await.lock().await, diff)
+        .update_jobset(&state.db, diff)
         .await
         .map_err(|e| ServerFnError::new(e.to_string()))?;
 
@@ -103,6 +119,17 @@ pub fn EditJobset() -> impl IntoView {
                                 <input type="text" name="jobset[flake]" id="jobset_flake_uri" placeholder="Jobset Flake Uri" value=jobset.flake/>
                                 <label for="jobset_check_interval">"Jobset check interval"</label>
                                 <input type="number" name="jobset[check_interval]" id="jobset_check_interval" placeholder="Jobset check interval" value=jobset.check_interval/>
+                                <label for="jobset_keep_evaluations">"Evaluations to keep outputs of"</label>
+                                <input type="number" name="jobset[keep_evaluations]" id="jobset_keep_evaluations" placeholder="Evaluations to keep outputs of" min=0 value=jobset.keep_evaluations/>
+                                <label for="jobset_max_evaluations">"Evaluations to keep, 0 for all"</label>
+                                <input type="number" name="jobset[max_evaluations]" id="jobset_max_evaluations" placeholder="Evaluations to keep" min=0 value=jobset.max_evaluations/>
+                                <label for="jobset_max_evaluation_age">"Days to keep evaluations for, 0 for ever"</label>
+                                <input type="number" name="jobset[max_evaluation_age]" id="jobset_max_evaluation_age" placeholder="Days to keep evaluations for" min=0 value=jobset.max_evaluation_age/>
+                                <label>
+                                    <input type="checkbox" name="jobset[channel]" value="true" checked=jobset.channel/>
+                                    "Publish a channel"
+                                </label>
+                                <input type="text" name="jobset[channel_gate]" id="jobset_channel_gate" placeholder="Channel gate attribute, all jobs if empty" value=jobset.channel_gate.unwrap_or_default()/>
                                 <input type="submit" value="Update jobset"/>
                             </div>
                         </ActionForm>
@@ -1,5 +1,6 @@
-pub use {create::*, edit::*, show::*};
+pub use {channel::*, create::*, edit::*, show::*};
 
+mod channel;
 mod create;
 mod edit;
 mod show;
@@ -4,8 +4,9 @@ use leptos_router::hooks::use_params_map;
 use serde::{de::DeserializeOwned, Deserialize};
 
 use crate::{
-    components::go_back::GoBack,
-    models::{Job, Jobset, JobsetState},
+    components::{deletion_report::DeletionReportView, go_back::GoBack},
+    models::{DeletionReport, Job, Jobset, JobsetState, ProjectRole},
+    routes::project::get_project_role,
 };
 
 stylance::import_crate_style!(
@@ -15,10 +16,13 @@ stylance::import_crate_style!(
 );
 
 #[server]
-pub async fn delete_jobset(project_id: String, jobset_id: String) -> Result<(), ServerFnError> {
-    use crate::state::State;
+pub async fn delete_jobset(
+    project_id: String,
+    jobset_id: String,
+) -> Result<DeletionReport, ServerFnError> {
+    use crate::{hydracore::delete_jobset, server::auth::require_role, state::State};
     use axum::http::StatusCode;
-    use leptos_axum::{redirect, ResponseOptions};
+    use leptos_axum::ResponseOptions;
     use std::sync::Arc;
     use tracing::error;
 
@@ -34,17 +38,21 @@ pub async fn delete_jobset(project_id: String, jobset_id: String) -> Result<(),
 
     let mut jobset = jobset.unwrap();
 
-    _ = jobset
-        .delete(&*state.coordinator.lock().await.get_db().await.lock().await)
+    if jobset.project_id.to_string() != project_id {
+        response_opts.set_status(StatusCode::NOT_FOUND);
+        return Err(ServerFnError::new("Failed to find jobset!"));
+    }
+
+    require_role(jobset.project_id, ProjectRole::Owner).await?;
+
+    let report = delete_jobset(&state.db, &state.data_dir, &mut jobset)
         .await
         .map_err(|e| {
             error!("Failed to delete jobset: {}", e.to_string());
             ServerFnError::new("Failed to delete jobset!")
         })?;
 
-    redirect(&format!("/project/{}", project_id));
-
-    Ok(())
+    Ok(report)
 }
 
 #[server]
@@ -68,11 +76,9 @@ pub async fn get_jobsets(id: String) -> Result<Vec<Jobset>, ServerFnError> {
 
     let number = number.unwrap();
 
-    let jobsets = Jobset::get_all(
-        &*state.coordinator.lock().await.get_db().await.lock().await,
-        number,
-    )
-    .await;
+    crate::server::auth::require_view(number).await?;
+
+    let jobsets = Jobset::get_all(&state.db, number).await;
 
     if jobsets.is_err() {
         error!(
@@ -108,22 +114,26 @@ pub async fn get_jobset(id: String) -> Result<Option<Jobset>, ServerFnError> {
 
     let jobset_id = jobset_id.unwrap();
 
-    let jobset = Jobset::get_single(
-        &*state.coordinator.lock().await.get_db().await.lock().await,
-        jobset_id,
-    )
-    .await;
+    let jobset = Jobset::get_single(&state.db, jobset_id).await;
 
     if jobset.is_err() {
         error!("Failed to fetch jobset: {}", jobset.err().unwrap());
         return Err(ServerFnError::new("Failed to fetch jobset!"));
     }
 
-    Ok(jobset.unwrap())
+    let jobset = jobset.unwrap();
+
+    if let Some(jobset) = &jobset {
+        crate::server::auth::require_view(jobset.project_id).await?;
+    }
+
+    Ok(jobset)
 }
 
 #[server]
 pub async fn trigger_jobset(project_id: String, jobset_id: String) -> Result<(), ServerFnError> {
+    use crate::models::{QueueRequest, QueueRequestKind};
+    use crate::server::auth::require_role;
     use crate::state::State;
     use axum::http::StatusCode;
     use leptos_axum::{redirect, ResponseOptions};
@@ -139,23 +149,24 @@ pub async fn trigger_jobset(project_id: String, jobset_id: String) -> Result<(),
         return Err(ServerFnError::new("Failed to find jobset!"));
     }
 
-    let mut jobset = jobset.unwrap();
+    let jobset = jobset.unwrap();
+
+    let user = require_role(jobset.project_id, ProjectRole::Maintainer).await?;
 
     let state: Arc<State> = expect_context();
 
-    info!("Triggered jobset: {}", jobset_id);
+    info!("{} triggered jobset: {}", user.name, jobset_id);
 
-    let result = state
-        .coordinator
-        .lock()
-        .await
-        .schedule_jobset(&mut jobset)
-        .await;
+    // the queue runner picks this up, it may live in another process.
+    // A jobset which is being evaluated keeps the request until that evaluation is done.
+    let mut request = QueueRequest::new(QueueRequestKind::TriggerJobset, jobset.id);
+
+    let result = request.add_to_db(&state.db).await;
 
     if result.is_err() {
         let err = result.err().unwrap().to_string();
-        error!("Failed to schedule jobset: {}", err);
-        return Err(ServerFnError::new(err));
+        error!("Failed to queue jobset: {}", err);
+        return Err(ServerFnError::new("Failed to queue jobset!"));
     }
 
     Ok(())
@@ -172,11 +183,15 @@ pub async fn get_jobs(jobset_id: String) -> Result<Vec<Job>, ServerFnError> {
 
     let state: Arc<State> = expect_context();
 
-    let db = state.coordinator.lock().await.get_db().await;
+    let jobset = get_jobset(jobset_id).await?;
 
-    let db_locked = db.lock().await;
+    if jobset.is_none() {
+        let response_opts: ResponseOptions = expect_context();
+        response_opts.set_status(StatusCode::NOT_FOUND);
+        return Err(ServerFnError::new("Failed to find jobset!"));
+    }
 
-    let jobs = Job::get_all(&*db_locked, jobset_id.parse().unwrap()).await;
+    let jobs = Job::get_all(&state.db, jobset.unwrap().id.unwrap()).await;
 
     let jobs = jobs.map_err(|e| {
         error!("Failed to get jobs: {}", e.to_string());
@@ -204,6 +219,7 @@ pub fn Jobset() -> impl IntoView {
     let delete_jobset_action = ServerAction::<DeleteJobset>::new();
 
     let jobs_data = OnceResource::new(get_jobs(jobset_id.clone()));
+    let role_data = OnceResource::new(get_project_role(project_id.clone()));
 
     Effect::new(move |_| {
         if let Some(Ok(_)) = trigger_jobset_action.value().get() {
@@ -215,6 +231,17 @@ pub fn Jobset() -> impl IntoView {
         <GoBack url=format!("/project/{}", project_id) text="project".to_string()/>
         <Suspense fallback=move || view! {<p>"Loading jobset data..."</p>}>
             {move || {
+                if let Some(Ok(report)) = delete_jobset_action.value().get() {
+                    return view! {
+                        <DeletionReportView
+                            report=report
+                            what="jobset".to_string()
+                            url=format!("/project/{}", project_id)
+                            text="project".to_string()
+                        />
+                    }.into_any();
+                }
+
                 let jobset = jobset_data.get();
 
                 if jobset.is_none() {
@@ -240,6 +267,12 @@ pub fn Jobset() -> impl IntoView {
 
                 let jobset = jobset.unwrap();
 
+                let job_url = format!("/project/{}/jobset/{}/job", project_id, jobset_id);
+
+                let role = role_data.get().and_then(Result::ok).flatten();
+                let maintainer = role >= Some(ProjectRole::Maintainer);
+                let owner = role >= Some(ProjectRole::Owner);
+
                 view! {
                     <div class=style::view>
                         <div class=style::action>
@@ -252,9 +285,10 @@ pub fn Jobset() -> impl IntoView {
                                 </div>
                                 <div class="dropdown_content">
                                     <div class="dropdown_group">
-                                        <a href=format!("/project/{}/jobset/{}/edit", project_id, jobset_id)>"Edit jobset"</a>
+                                        <a href=format!("/project/{}/jobset/{}/edit", project_id, jobset_id) style:display=if maintainer { "block" } else { "none" }>"Edit jobset"</a>
+                                        <a href=format!("/project/{}/jobset/{}/channel", project_id, jobset_id) style:display=if jobset.channel { "block" } else { "none" }>"Channel"</a>
                                     </div>
-                                    <div class="dropdown_group">
+                                    <div class="dropdown_group" style:display=if maintainer { "block" } else { "none" }>
                                         <div class="generic_input_form">
                                             <ActionForm action=trigger_jobset_action>
                                                 <div class="inputs">
@@ -265,7 +299,7 @@ pub fn Jobset() -> impl IntoView {
                                             </ActionForm>
                                         </div>
                                     </div>
-                                    <div class="dropdown_group">
+                                    <div class="dropdown_group" style:display=if owner { "block" } else { "none" }>
                                         <div class="generic_input_form">
                                            <ActionForm action=delete_jobset_action>
                                                 <div class="inputs">
@@ -304,7 +338,8 @@ pub fn Jobset() -> impl IntoView {
                                        }.into_any();
                                     }
                                 }
-
+                            }}
+                            {move || {
                                 match delete_jobset_action.value().get() {
                                     Some(Err(e)) => {
                                         let msg = match e {
@@ -330,6 +365,9 @@ pub fn Jobset() -> impl IntoView {
                             {mk_jobset_entry("Last checked: ", convert_date_to_string(jobset.last_checked))}
                             {mk_jobset_entry("Last evaluated: ", convert_date_to_string(jobset.last_evaluated))}
                             {mk_jobset_entry("Check interval (every): ", convert_seconds_to_minutes(jobset.check_interval))}
+                            {mk_jobset_entry("Keeps outputs of: ", format!("{} evaluations", jobset.keep_evaluations))}
+                            {mk_jobset_entry("Channel: ", channel_to_string(jobset.channel, jobset.channel_gate.clone()))}
+                            {mk_jobset_entry("Keeps evaluations: ", retention_to_string(jobset.max_evaluations, jobset.max_evaluation_age))}
                             {mk_jobset_entry("Evaluation took: ", convert_seconds_to_minutes(jobset.evaluation_took.unwrap_or(-1)))}
                             {mk_jobset_entry("State: ", jobset.state.clone().unwrap_or(JobsetState::Unknown).to_string())}
                             {
@@ -363,10 +401,10 @@ pub fn Jobset() -> impl IntoView {
                                             <th>"Success"</th>
                                         </tr>
                                         {jobs.iter().map(|job| {
-                                            let id = job.id;
+                                            let url = format!("{}/{}", job_url, job.id.unwrap());
                                             view! {
                                                 <tr>
-                                                    <td>{job.attribute_name.clone()}</td>
+                                                    <td><a href=url>{job.attribute_name.clone()}</a></td>
                                                     <td>{format!("{:#?}", job.state)}</td>
                                                 </tr>
                                             }
@@ -405,6 +443,23 @@ fn convert_seconds_to_minutes(seconds: i32) -> String {
     return format!("{} minute(s) {} seconds", minutes, seconds);
 }
 
+fn channel_to_string(channel: bool, channel_gate: Option<String>) -> String {
+    match (channel, channel_gate) {
+        (false, _) => "-".to_string(),
+        (true, None) => "released when all jobs succeed".to_string(),
+        (true, Some(gate)) => format!("released when {} succeeds", gate),
+    }
+}
+
+fn retention_to_string(max_evaluations: i32, max_evaluation_age: i32) -> String {
+    match (max_evaluations, max_evaluation_age) {
+        (0, 0) => "all".to_string(),
+        (count, 0) => format!("the last {}", count),
+        (0, days) => format!("for {} days", days),
+        (count, days) => format!("the last {} for up to {} days", count, days),
+    }
+}
+
 fn mk_jobset_entry(key: &str, value: String) -> impl IntoView {
     view! {
         <div class=style::key>
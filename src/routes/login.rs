@@ -0,0 +1,155 @@
+use leptos::prelude::*;
+
+use crate::models::User;
+
+#[server(endpoint = "current_user")]
+pub async fn get_current_user() -> Result<Option<User>, ServerFnError> {
+    crate::server::auth::current_user().await
+}
+
+/// The name of the OpenID Connect provider, if users may log in through it
+#[server(endpoint = "login_options")]
+pub async fn get_login_options() -> Result<Option<String>, ServerFnError> {
+    use crate::state::State;
+    use std::sync::Arc;
+
+    let state: Arc<State> = expect_context();
+
+    Ok(state.oidc.as_ref().map(|oidc| oidc.name.clone()))
+}
+
+#[server(endpoint = "login")]
+pub async fn login(name: String, password: String) -> Result<(), ServerFnError> {
+    use crate::{hydracore::verify_password, server::auth::start_session, state::State};
+    use axum::http::{header::SET_COOKIE, HeaderValue, StatusCode};
+    use leptos_axum::ResponseOptions;
+    use std::sync::Arc;
+    use tracing::{error, info, warn};
+
+    let state: Arc<State> = expect_context();
+    let response_opts: ResponseOptions = expect_context();
+
+    let user = User::get_with_password(&state.db, &name)
+        .await
+        .map_err(|e| {
+            error!("Failed to get user: {}", e.to_string());
+            ServerFnError::new("Failed to log in!")
+        })?;
+
+    let user = match user {
+        Some((user, hash)) if verify_password(&password, &hash) => user,
+        _ => {
+            warn!("Failed login of {}", name);
+            response_opts.set_status(StatusCode::UNAUTHORIZED);
+            return Err(ServerFnError::new("Wrong name or password!"));
+        }
+    };
+
+    let cookie = start_session(&state, &user).await.map_err(|e| {
+        error!("Failed to create session: {}", e.to_string());
+        ServerFnError::new("Failed to log in!")
+    })?;
+
+    response_opts.insert_header(SET_COOKIE, HeaderValue::from_str(&cookie).unwrap());
+
+    info!("{} logged in", user.name);
+    leptos_axum::redirect("/");
+    Ok(())
+}
+
+#[server(endpoint = "logout")]
+pub async fn logout() -> Result<(), ServerFnError> {
+    use crate::{
+        hydracore::hash_token,
+        models::Session,
+        server::auth::{removed_session_cookie, session_token},
+        state::State,
+    };
+    use axum::http::{header::SET_COOKIE, HeaderMap, HeaderValue};
+    use leptos_axum::ResponseOptions;
+    use std::sync::Arc;
+    use tracing::error;
+
+    let state: Arc<State> = expect_context();
+    let response_opts: ResponseOptions = expect_context();
+    let headers: HeaderMap = leptos_axum::extract().await?;
+
+    if let Some(token) = session_token(&headers) {
+        if let Err(e) = Session::delete(&state.db, &hash_token(&token)).await {
+            error!("Failed to delete session: {}", e.to_string());
+        }
+    }
+
+    response_opts.insert_header(
+        SET_COOKIE,
+        HeaderValue::from_str(&removed_session_cookie()).unwrap(),
+    );
+
+    leptos_axum::redirect("/");
+    Ok(())
+}
+
+#[component]
+pub fn Login() -> impl IntoView {
+    let login_action = ServerAction::<Login>::new();
+
+    let resp = login_action.value();
+
+    let login_options = OnceResource::new(get_login_options());
+
+    view! {
+        <div class="generic_input_form">
+            <Suspense fallback=|| ()>
+                {move || match login_options.get() {
+                    Some(Ok(Some(provider))) => view! {
+                        <h3>Log in</h3>
+                        <div class="inputs">
+                            <a href="/oidc/login" rel="external">"Log in with "{provider}</a>
+                        </div>
+                    }.into_any(),
+                    _ => ().into_any(),
+                }}
+            </Suspense>
+            <ActionForm action=login_action>
+                <h3>Log in</h3>
+                <div class="inputs">
+                    <input type="text" name="name" placeholder="Name" autocomplete="username"/>
+                    <input type="password" name="password" placeholder="Password" autocomplete="current-password"/>
+                    <input type="submit" value="Log in"/>
+                </div>
+            </ActionForm>
+        </div>
+        <div class="generic_input_form_response">
+            {move || match resp.get() {
+                Some(Err(e)) => {
+                    let msg = match e {
+                        ServerFnError::ServerError(msg) => msg,
+                        _ => e.to_string(),
+                    };
+                    view! {<p class="error">"Failed to log in: "{msg}</p>}.into_any()
+                },
+                _ => ().into_any(),
+             }}
+        </div>
+    }
+}
+
+/// The logged in user and a logout button, or a link to the login page
+#[component]
+pub fn UserMenu(user: OnceResource<Result<Option<User>, ServerFnError>>) -> impl IntoView {
+    let logout_action = ServerAction::<Logout>::new();
+
+    view! {
+        <Suspense fallback=|| ()>
+            {move || match user.get() {
+                Some(Ok(Some(user))) => view! {
+                    <ActionForm action=logout_action>
+                        <a href="/settings">{user.name}</a>
+                        <input type="submit" value="Log out"/>
+                    </ActionForm>
+                }.into_any(),
+                _ => view! {<a href="/login">Log in</a>}.into_any(),
+            }}
+        </Suspense>
+    }
+}
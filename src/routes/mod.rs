@@ -1,8 +1,19 @@
+pub use binary_cache::*;
 pub use home::*;
+pub use login::*;
 pub use notfound::*;
+pub use pruning::*;
+pub use settings::*;
+pub use webhooks::*;
 
+mod binary_cache;
 mod home;
+mod login;
 mod notfound;
+mod pruning;
+mod settings;
+mod webhooks;
 
+pub mod job;
 pub mod jobset;
 pub mod project;
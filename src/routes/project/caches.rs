@@ -0,0 +1,178 @@
+use leptos::prelude::*;
+
+use crate::models::Cache;
+
+#[cfg(feature = "ssr")]
+use {
+    crate::{
+        models::ProjectRole,
+        server::auth::{require_admin, require_role},
+        state::State,
+    },
+    axum::http::StatusCode,
+    leptos_axum::ResponseOptions,
+    std::sync::Arc,
+    tracing::{error, info},
+};
+
+#[server]
+pub async fn get_caches(project_id: String) -> Result<Vec<Cache>, ServerFnError> {
+    let state: Arc<State> = expect_context();
+
+    let project_id = project_id
+        .parse::<i32>()
+        .map_err(|_| ServerFnError::new("Invalid project id!"))?;
+
+    require_role(project_id, ProjectRole::Owner).await?;
+
+    let caches = Cache::get_all(&state.db, project_id).await.map_err(|e| {
+        error!("Failed to fetch caches: {}", e.to_string());
+        ServerFnError::new("Failed to fetch caches!")
+    })?;
+
+    Ok(caches)
+}
+
+/// Only admins may add caches, nix copies to them with the files and credentials of the server
+#[server]
+pub async fn add_cache(project_id: String, url: String) -> Result<(), ServerFnError> {
+    let state: Arc<State> = expect_context();
+    let response_opts: ResponseOptions = expect_context();
+
+    let project_id = project_id
+        .parse::<i32>()
+        .map_err(|_| ServerFnError::new("Invalid project id!"))?;
+
+    require_admin().await?;
+
+    let url = url.trim().to_string();
+
+    if !Cache::is_supported_url(&url) {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new(
+            "Only file://, s3:// and ssh-ng:// caches without settings naming files are supported!",
+        ));
+    }
+
+    let mut cache = Cache::new(project_id, url);
+
+    cache.add_to_db(&state.db).await.map_err(|e| {
+        error!("Failed to add cache: {}", e.to_string());
+        ServerFnError::new("Failed to add cache!")
+    })?;
+
+    info!("Added cache {} to project {}", cache.url, project_id);
+
+    Ok(())
+}
+
+#[server]
+pub async fn delete_cache(project_id: String, cache_id: String) -> Result<(), ServerFnError> {
+    let state: Arc<State> = expect_context();
+    let response_opts: ResponseOptions = expect_context();
+
+    let (project_id, cache_id) = match (project_id.parse::<i32>(), cache_id.parse::<i32>()) {
+        (Ok(project_id), Ok(cache_id)) => (project_id, cache_id),
+        _ => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new("Invalid id!"));
+        }
+    };
+
+    require_role(project_id, ProjectRole::Owner).await?;
+
+    let deleted = Cache::delete(&state.db, project_id, cache_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to delete cache: {}", e.to_string());
+            ServerFnError::new("Failed to delete cache!")
+        })?;
+
+    if !deleted {
+        response_opts.set_status(StatusCode::NOT_FOUND);
+        return Err(ServerFnError::new("Failed to find cache!"));
+    }
+
+    Ok(())
+}
+
+/// The external caches the outputs of the project are copied to
+#[component]
+pub fn ProjectCaches(project_id: String) -> impl IntoView {
+    let add_cache_action = ServerAction::<AddCache>::new();
+    let delete_cache_action = ServerAction::<DeleteCache>::new();
+
+    let caches = {
+        let project_id = project_id.clone();
+        Resource::new(
+            move || {
+                (
+                    add_cache_action.version().get(),
+                    delete_cache_action.version().get(),
+                )
+            },
+            move |_| get_caches(project_id.clone()),
+        )
+    };
+
+    let list_project_id = project_id.clone();
+
+    view! {
+        <div class="generic_input_form">
+            <h3>"Binary caches"</h3>
+            <p>"Successful outputs are copied to these caches, like file:///srv/cache, s3://bucket?endpoint=localhost:9000 or ssh-ng://host. Only admins may add caches."</p>
+            <Transition fallback=move || view! {<p>"Loading caches..."</p>}>
+                {move || {
+                    let caches = caches.get();
+
+                    if caches.is_none() {
+                        return view! {<p class="error">"Failed to load caches"</p>}.into_any();
+                    }
+
+                    let caches = caches.unwrap();
+
+                    if caches.is_err() {
+                        let err = caches.err().unwrap().to_string();
+                        return view! {<p class="error">"Failed to load caches: "{err}</p>}.into_any();
+                    }
+
+                    caches.unwrap().into_iter().map(|cache| {
+                        let project_id = list_project_id.clone();
+                        view! {
+                            <ActionForm action=delete_cache_action>
+                                <div class="inputs">
+                                    <input type="text" value=cache.url disabled/>
+                                    <input type="hidden" name="project_id" value=project_id/>
+                                    <input type="hidden" name="cache_id" value=cache.id.unwrap()/>
+                                    <input type="submit" value="Remove"/>
+                                </div>
+                            </ActionForm>
+                        }
+                    }).collect_view().into_any()
+                }}
+            </Transition>
+            <ActionForm action=add_cache_action>
+                <div class="inputs">
+                    <input type="text" name="url" placeholder="Store url"/>
+                    <input type="hidden" name="project_id" value=project_id/>
+                    <input type="submit" value="Add cache"/>
+                </div>
+            </ActionForm>
+        </div>
+        <div class="generic_input_form_response">
+            {move || {
+                let error = match (add_cache_action.value().get(), delete_cache_action.value().get()) {
+                    (Some(Err(e)), _) | (_, Some(Err(e))) => e,
+                    _ => return ().into_any(),
+                };
+
+                let msg = match error {
+                    ServerFnError::ServerError(msg) => msg,
+                    e => e.to_string(),
+                };
+
+                view! {<p class="error">"Failed to update caches: "{msg}</p>}.into_any()
+            }}
+        </div>
+    }
+}
@@ -10,6 +10,8 @@ pub async fn create_project_server(project: Project) -> Result<(), ServerFnError
     use leptos_axum::ResponseOptions;
     use std::sync::Arc;
 
+    crate::server::auth::require_admin().await?;
+
     let response_opts: ResponseOptions = expect_context();
 
     if project.name == "" {
@@ -28,9 +30,7 @@ pub async fn create_project_server(project: Project) -> Result<(), ServerFnError
 
     let mut project = project;
 
-    let result = project
-        .add_to_db(&*state.coordinator.lock().await.get_db().await.lock().await)
-        .await;
+    let result = project.add_to_db(&state.db).await;
 
     if result.is_err() {
         error!("Failed to create project: {}", result.err().unwrap());
@@ -57,6 +57,14 @@ pub fn CreateProject() -> impl IntoView {
                 <div class="inputs">
                     <input type="text" name="project[name]" id="proj_name" placeholder="Project Name"/>
                     <input type="text" name="project[description]" id="proj_desc" placeholder="Project Description"/>
+                    <label>
+                        <input type="checkbox" name="project[binary_cache]" value="true"/>
+                        "Serve outputs from the binary cache"
+                    </label>
+                    <label>
+                        <input type="checkbox" name="project[private]" value="true"/>
+                        "Only visible to members"
+                    </label>
                     <input type="submit" value="Create project"/>
                 </div>
             </ActionForm>
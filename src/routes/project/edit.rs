@@ -3,24 +3,24 @@ use leptos_router::hooks::use_params_map;
 
 use crate::models::Project;
 
-use super::get_project;
+use super::{get_project, ProjectCaches, ProjectMembers, ProjectNotifiers};
 
 #[cfg(feature = "ssr")]
-use {crate::state::State, leptos_axum::redirect, std::sync::Arc, tracing::info};
+use {
+    crate::{models::ProjectRole, server::auth::require_role, state::State},
+    leptos_axum::redirect,
+    std::sync::Arc,
+    tracing::info,
+};
 
 #[server]
 pub async fn update_project(project: Project) -> Result<(), ServerFnError> {
     info!("Received update");
     let state: Arc<State> = expect_context();
 
-    let coordinator = state.coordinator.lock().await;
-
-    let server_project = Project::get_single(
-        &*coordinator.get_db().await.lock().await,
-        project.id.unwrap(),
-    )
-    .await
-    .map_err(|e| ServerFnError::new(format!("Failed to update project: {}", e.to_string())))?;
+    let server_project = Project::get_single(&state.db, project.id.unwrap())
+        .await
+        .map_err(|e| ServerFnError::new(format!("Failed to update project: {}", e)))?;
 
     if server_project.is_none() {
         return Err(ServerFnError::new("Failed to find project!"));
@@ -28,13 +28,17 @@ pub async fn update_project(project: Project) -> Result<(), ServerFnError> {
 
     let mut server_project = server_project.unwrap();
 
+    require_role(server_project.id.unwrap(), ProjectRole::Owner).await?;
+
     server_project.name = project.name;
     server_project.description = project.description;
+    server_project.binary_cache = project.binary_cache;
+    server_project.private = project.private;
 
     _ = server_project
-        .update(&*coordinator.get_db().await.lock().await)
+        .update(&state.db)
         .await
-        .map_err(|e| ServerFnError::new(format!("Failed to update project: {}", e.to_string())))?;
+        .map_err(|e| ServerFnError::new(format!("Failed to update project: {}", e)))?;
 
     redirect("/");
 
@@ -80,6 +84,14 @@ pub fn EditProject() -> impl IntoView {
                             <div class="inputs">
                                 <input type="text" name="project[name]" id="proj_name" placeholder="Project Name" value=project.name/>
                                 <input type="text" name="project[description]" id="proj_desc" placeholder="Project Description" value=project.description/>
+                                <label>
+                                    <input type="checkbox" name="project[binary_cache]" value="true" checked=project.binary_cache/>
+                                    "Serve outputs from the binary cache"
+                                </label>
+                                <label>
+                                    <input type="checkbox" name="project[private]" value="true" checked=project.private/>
+                                    "Only visible to members"
+                                </label>
                                 <input type="hidden" name="project[id]" value=project.id.unwrap()/>
                                 <input type="submit" value="Update project"/>
                             </div>
@@ -97,6 +109,9 @@ pub fn EditProject() -> impl IntoView {
                             _ => {view! {}.into_any()},
                          }}
                     </div>
+                    <ProjectMembers project_id=project_id_str.clone()/>
+                    <ProjectCaches project_id=project_id_str.clone()/>
+                    <ProjectNotifiers project_id=project_id_str.clone()/>
                 }.into_any()
             }}
         </Suspense>
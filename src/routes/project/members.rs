@@ -0,0 +1,208 @@
+use leptos::prelude::*;
+
+use crate::models::{ProjectMember, ProjectRole};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::{
+        models::User,
+        server::auth::{current_role, require_role},
+        state::State,
+    },
+    axum::http::StatusCode,
+    leptos_axum::ResponseOptions,
+    std::sync::Arc,
+    tracing::{error, info},
+};
+
+/// The role of the logged in user, to hide what it may not do
+#[server]
+pub async fn get_project_role(project_id: String) -> Result<Option<ProjectRole>, ServerFnError> {
+    let project_id = project_id
+        .parse::<i32>()
+        .map_err(|_| ServerFnError::new("Invalid project id!"))?;
+
+    current_role(project_id).await
+}
+
+#[server]
+pub async fn get_members(project_id: String) -> Result<Vec<ProjectMember>, ServerFnError> {
+    let state: Arc<State> = expect_context();
+
+    let project_id = project_id
+        .parse::<i32>()
+        .map_err(|_| ServerFnError::new("Invalid project id!"))?;
+
+    require_role(project_id, ProjectRole::Owner).await?;
+
+    let members = ProjectMember::get_all(&state.db, project_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch members: {}", e.to_string());
+            ServerFnError::new("Failed to fetch members!")
+        })?;
+
+    Ok(members)
+}
+
+/// Adds the user to the project, or changes its role if it already is a member
+#[server]
+pub async fn set_member(
+    project_id: String,
+    name: String,
+    role: ProjectRole,
+) -> Result<(), ServerFnError> {
+    let state: Arc<State> = expect_context();
+    let response_opts: ResponseOptions = expect_context();
+
+    let project_id = project_id
+        .parse::<i32>()
+        .map_err(|_| ServerFnError::new("Invalid project id!"))?;
+
+    let owner = require_role(project_id, ProjectRole::Owner).await?;
+
+    let user = User::get_by_name(&state.db, name.trim())
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch user: {}", e.to_string());
+            ServerFnError::new("Failed to add member!")
+        })?;
+
+    let user = match user {
+        Some(user) => user,
+        None => {
+            response_opts.set_status(StatusCode::NOT_FOUND);
+            return Err(ServerFnError::new("There is no such user!"));
+        }
+    };
+
+    ProjectMember::set(&state.db, project_id, user.id.unwrap(), role)
+        .await
+        .map_err(|e| {
+            error!("Failed to add member: {}", e.to_string());
+            ServerFnError::new("Failed to add member!")
+        })?;
+
+    info!(
+        "{} made {} {} of project {}",
+        owner.name, user.name, role, project_id
+    );
+
+    Ok(())
+}
+
+#[server]
+pub async fn remove_member(project_id: String, user_id: String) -> Result<(), ServerFnError> {
+    let state: Arc<State> = expect_context();
+    let response_opts: ResponseOptions = expect_context();
+
+    let (project_id, user_id) = match (project_id.parse::<i32>(), user_id.parse::<i32>()) {
+        (Ok(project_id), Ok(user_id)) => (project_id, user_id),
+        _ => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new("Invalid id!"));
+        }
+    };
+
+    require_role(project_id, ProjectRole::Owner).await?;
+
+    let removed = ProjectMember::delete(&state.db, project_id, user_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to remove member: {}", e.to_string());
+            ServerFnError::new("Failed to remove member!")
+        })?;
+
+    if !removed {
+        response_opts.set_status(StatusCode::NOT_FOUND);
+        return Err(ServerFnError::new("Failed to find member!"));
+    }
+
+    Ok(())
+}
+
+/// The users which may see or change the project
+#[component]
+pub fn ProjectMembers(project_id: String) -> impl IntoView {
+    let set_member_action = ServerAction::<SetMember>::new();
+    let remove_member_action = ServerAction::<RemoveMember>::new();
+
+    let members = {
+        let project_id = project_id.clone();
+        Resource::new(
+            move || {
+                (
+                    set_member_action.version().get(),
+                    remove_member_action.version().get(),
+                )
+            },
+            move |_| get_members(project_id.clone()),
+        )
+    };
+
+    let list_project_id = project_id.clone();
+
+    view! {
+        <div class="generic_input_form">
+            <h3>"Members"</h3>
+            <p>"Viewers may see the project if it is private, maintainers may also change and trigger jobsets, owners may also change the project"</p>
+            <Transition fallback=move || view! {<p>"Loading members..."</p>}>
+                {move || {
+                    let members = members.get();
+
+                    if members.is_none() {
+                        return view! {<p class="error">"Failed to load members"</p>}.into_any();
+                    }
+
+                    let members = members.unwrap();
+
+                    if members.is_err() {
+                        let err = members.err().unwrap().to_string();
+                        return view! {<p class="error">"Failed to load members: "{err}</p>}.into_any();
+                    }
+
+                    members.unwrap().into_iter().map(|member| {
+                        let project_id = list_project_id.clone();
+                        view! {
+                            <ActionForm action=remove_member_action>
+                                <div class="inputs">
+                                    <input type="text" value=format!("{} ({})", member.user_name, member.role) disabled/>
+                                    <input type="hidden" name="project_id" value=project_id/>
+                                    <input type="hidden" name="user_id" value=member.user_id/>
+                                    <input type="submit" value="Remove"/>
+                                </div>
+                            </ActionForm>
+                        }
+                    }).collect_view().into_any()
+                }}
+            </Transition>
+            <ActionForm action=set_member_action>
+                <div class="inputs">
+                    <input type="text" name="name" placeholder="User name"/>
+                    <select name="role">
+                        <option value="Viewer">"Viewer"</option>
+                        <option value="Maintainer">"Maintainer"</option>
+                        <option value="Owner">"Owner"</option>
+                    </select>
+                    <input type="hidden" name="project_id" value=project_id/>
+                    <input type="submit" value="Add member"/>
+                </div>
+            </ActionForm>
+        </div>
+        <div class="generic_input_form_response">
+            {move || {
+                let error = match (set_member_action.value().get(), remove_member_action.value().get()) {
+                    (Some(Err(e)), _) | (_, Some(Err(e))) => e,
+                    _ => return ().into_any(),
+                };
+
+                let msg = match error {
+                    ServerFnError::ServerError(msg) => msg,
+                    e => e.to_string(),
+                };
+
+                view! {<p class="error">"Failed to update members: "{msg}</p>}.into_any()
+            }}
+        </div>
+    }
+}
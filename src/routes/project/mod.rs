@@ -1,5 +1,8 @@
-pub use {create::*, edit::*, show::*};
+pub use {caches::*, create::*, edit::*, members::*, notifiers::*, show::*};
 
+mod caches;
 mod create;
 mod edit;
+mod members;
+mod notifiers;
 mod show;
@@ -0,0 +1,343 @@
+use chrono::{DateTime, Utc};
+use leptos::prelude::*;
+
+use crate::{
+    models::{NotificationDelivery, NotificationEvent, NotifierConfig, NotifierKind},
+    routes::jobset::get_jobsets,
+};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::{
+        models::{Jobset, ProjectRole},
+        server::auth::require_role,
+        state::State,
+    },
+    axum::http::StatusCode,
+    leptos_axum::ResponseOptions,
+    std::sync::Arc,
+    tracing::{error, info},
+};
+
+#[server]
+pub async fn get_notifiers(project_id: String) -> Result<Vec<NotifierConfig>, ServerFnError> {
+    let state: Arc<State> = expect_context();
+
+    let project_id = project_id
+        .parse::<i32>()
+        .map_err(|_| ServerFnError::new("Invalid project id!"))?;
+
+    require_role(project_id, ProjectRole::Owner).await?;
+
+    let notifiers = NotifierConfig::get_all(&state.db, project_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch notifiers: {}", e.to_string());
+            ServerFnError::new("Failed to fetch notifiers!")
+        })?;
+
+    Ok(notifiers)
+}
+
+#[server]
+pub async fn get_notification_deliveries(
+    project_id: String,
+) -> Result<Vec<NotificationDelivery>, ServerFnError> {
+    let state: Arc<State> = expect_context();
+
+    let project_id = project_id
+        .parse::<i32>()
+        .map_err(|_| ServerFnError::new("Invalid project id!"))?;
+
+    require_role(project_id, ProjectRole::Owner).await?;
+
+    let deliveries = NotificationDelivery::get_latest(&state.db, project_id, 50)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch notification deliveries: {}", e.to_string());
+            ServerFnError::new("Failed to fetch notification deliveries!")
+        })?;
+
+    Ok(deliveries)
+}
+
+#[server]
+pub async fn add_notifier(
+    project_id: String,
+    jobset_id: String,
+    kind: NotifierKind,
+    target: String,
+    #[server(default)] events: Vec<NotificationEvent>,
+) -> Result<(), ServerFnError> {
+    let state: Arc<State> = expect_context();
+    let response_opts: ResponseOptions = expect_context();
+
+    let project_id = project_id
+        .parse::<i32>()
+        .map_err(|_| ServerFnError::new("Invalid project id!"))?;
+
+    require_role(project_id, ProjectRole::Owner).await?;
+
+    let jobset_id = match jobset_id.as_str() {
+        "" => None,
+        jobset_id => {
+            let jobset_id = jobset_id
+                .parse::<i32>()
+                .map_err(|_| ServerFnError::new("Invalid jobset id!"))?;
+
+            let jobset = Jobset::get_single(&state.db, jobset_id)
+                .await
+                .map_err(|e| {
+                    error!("Failed to fetch jobset: {}", e.to_string());
+                    ServerFnError::new("Failed to add notifier!")
+                })?;
+
+            if jobset.is_none_or(|jobset| jobset.project_id != project_id) {
+                response_opts.set_status(StatusCode::NOT_FOUND);
+                return Err(ServerFnError::new("Failed to find jobset!"));
+            }
+
+            Some(jobset_id)
+        }
+    };
+
+    let target = target.trim().to_string();
+
+    let valid_target = match kind {
+        NotifierKind::Webhook => target.starts_with("http://") || target.starts_with("https://"),
+    };
+
+    if !valid_target {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new(
+            "Webhooks need an http:// or https:// url!",
+        ));
+    }
+
+    if events.is_empty() {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new("Pick at least one event!"));
+    }
+
+    let mut notifier = NotifierConfig::new(project_id, jobset_id, kind, target, &events);
+
+    notifier.add_to_db(&state.db).await.map_err(|e| {
+        error!("Failed to add notifier: {}", e.to_string());
+        ServerFnError::new("Failed to add notifier!")
+    })?;
+
+    info!(
+        "Added {} notifier {} to project {}",
+        kind, notifier.target, project_id
+    );
+
+    Ok(())
+}
+
+#[server]
+pub async fn delete_notifier(project_id: String, notifier_id: String) -> Result<(), ServerFnError> {
+    let state: Arc<State> = expect_context();
+    let response_opts: ResponseOptions = expect_context();
+
+    let (project_id, notifier_id) = match (project_id.parse::<i32>(), notifier_id.parse::<i32>()) {
+        (Ok(project_id), Ok(notifier_id)) => (project_id, notifier_id),
+        _ => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new("Invalid id!"));
+        }
+    };
+
+    require_role(project_id, ProjectRole::Owner).await?;
+
+    let deleted = NotifierConfig::delete(&state.db, project_id, notifier_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to delete notifier: {}", e.to_string());
+            ServerFnError::new("Failed to delete notifier!")
+        })?;
+
+    if !deleted {
+        response_opts.set_status(StatusCode::NOT_FOUND);
+        return Err(ServerFnError::new("Failed to find notifier!"));
+    }
+
+    Ok(())
+}
+
+fn convert_date_to_string(date: Option<DateTime<Utc>>) -> String {
+    match date {
+        Some(date) => date.format("%H:%M:%S %d.%m.%Y").to_string(),
+        None => "-".to_string(),
+    }
+}
+
+/// Where the events of the project are sent to, and how that went
+#[component]
+pub fn ProjectNotifiers(project_id: String) -> impl IntoView {
+    let add_notifier_action = ServerAction::<AddNotifier>::new();
+    let delete_notifier_action = ServerAction::<DeleteNotifier>::new();
+
+    let notifiers = {
+        let project_id = project_id.clone();
+        Resource::new(
+            move || {
+                (
+                    add_notifier_action.version().get(),
+                    delete_notifier_action.version().get(),
+                )
+            },
+            move |_| get_notifiers(project_id.clone()),
+        )
+    };
+
+    let deliveries = {
+        let project_id = project_id.clone();
+        Resource::new(
+            move || delete_notifier_action.version().get(),
+            move |_| get_notification_deliveries(project_id.clone()),
+        )
+    };
+
+    let jobsets = OnceResource::new(get_jobsets(project_id.clone()));
+
+    let list_project_id = project_id.clone();
+
+    view! {
+        <div class="generic_input_form">
+            <h3>"Notifiers"</h3>
+            <p>"Events of the project, or of one of its jobsets, are posted as json to these webhooks. Failed deliveries are tried again a few times."</p>
+            <Transition fallback=move || view! {<p>"Loading notifiers..."</p>}>
+                {move || {
+                    let notifiers = notifiers.get();
+
+                    if notifiers.is_none() {
+                        return view! {<p class="error">"Failed to load notifiers"</p>}.into_any();
+                    }
+
+                    let notifiers = notifiers.unwrap();
+
+                    if notifiers.is_err() {
+                        let err = notifiers.err().unwrap().to_string();
+                        return view! {<p class="error">"Failed to load notifiers: "{err}</p>}.into_any();
+                    }
+
+                    notifiers.unwrap().into_iter().map(|notifier| {
+                        let project_id = list_project_id.clone();
+                        let description = format!(
+                            "{} {} ({}: {})",
+                            notifier.kind,
+                            notifier.target,
+                            notifier.jobset_name.clone().unwrap_or("All jobsets".to_string()),
+                            notifier.events.replace(',', ", ")
+                        );
+                        view! {
+                            <ActionForm action=delete_notifier_action>
+                                <div class="inputs">
+                                    <input type="text" value=description disabled/>
+                                    <input type="hidden" name="project_id" value=project_id/>
+                                    <input type="hidden" name="notifier_id" value=notifier.id.unwrap()/>
+                                    <input type="submit" value="Remove"/>
+                                </div>
+                            </ActionForm>
+                        }
+                    }).collect_view().into_any()
+                }}
+            </Transition>
+            <ActionForm action=add_notifier_action>
+                <div class="inputs">
+                    <select name="kind">
+                        <option value="Webhook">"Webhook"</option>
+                    </select>
+                    <input type="text" name="target" placeholder="https://example.com/hook"/>
+                    <select name="jobset_id">
+                        <option value="">"All jobsets"</option>
+                        <Suspense fallback=|| ()>
+                            {move || jobsets.get().and_then(|jobsets| jobsets.ok()).unwrap_or_default().into_iter().map(|jobset| view! {
+                                <option value=jobset.id>{jobset.name}</option>
+                            }).collect_view()}
+                        </Suspense>
+                    </select>
+                </div>
+                <div class="inputs">
+                    {NotificationEvent::ALL.into_iter().map(|event| view! {
+                        <label>
+                            <input type="checkbox" name="events[]" value=event.to_string()/>
+                            {event.to_string()}
+                        </label>
+                    }).collect_view()}
+                </div>
+                <div class="inputs">
+                    <input type="hidden" name="project_id" value=project_id/>
+                    <input type="submit" value="Add notifier"/>
+                </div>
+            </ActionForm>
+        </div>
+        <div class="generic_input_form_response">
+            {move || {
+                let error = match (add_notifier_action.value().get(), delete_notifier_action.value().get()) {
+                    (Some(Err(e)), _) | (_, Some(Err(e))) => e,
+                    _ => return ().into_any(),
+                };
+
+                let msg = match error {
+                    ServerFnError::ServerError(msg) => msg,
+                    e => e.to_string(),
+                };
+
+                view! {<p class="error">"Failed to update notifiers: "{msg}</p>}.into_any()
+            }}
+        </div>
+        <div class="generic_table">
+            <h3>"Notification deliveries"</h3>
+            <Transition fallback=move || view! {<p>"Loading deliveries..."</p>}>
+                {move || {
+                    let deliveries = deliveries.get();
+
+                    if deliveries.is_none() {
+                        return view! {<p class="left error">"Failed to load deliveries"</p>}.into_any();
+                    }
+
+                    let deliveries = deliveries.unwrap();
+
+                    if deliveries.is_err() {
+                        let err = deliveries.err().unwrap().to_string();
+                        return view! {<p class="left error">"Failed to load deliveries: "{err}</p>}.into_any();
+                    }
+
+                    let deliveries = deliveries.unwrap();
+
+                    if deliveries.is_empty() {
+                        return view! {<p class="left">"Nothing was sent yet"</p>}.into_any();
+                    }
+
+                    view! {
+                        <table>
+                            <tbody>
+                                <tr>
+                                    <th>"Created"</th>
+                                    <th>"Event"</th>
+                                    <th>"Notifier"</th>
+                                    <th>"State"</th>
+                                    <th>"Attempts"</th>
+                                    <th>"Finished"</th>
+                                    <th>"Error"</th>
+                                </tr>
+                                {deliveries.into_iter().map(|delivery| view! {
+                                    <tr>
+                                        <td>{convert_date_to_string(Some(delivery.created))}</td>
+                                        <td>{delivery.event.to_string()}</td>
+                                        <td>{format!("{} {}", delivery.kind, delivery.target)}</td>
+                                        <td>{format!("{:?}", delivery.state)}</td>
+                                        <td>{delivery.attempts}</td>
+                                        <td>{convert_date_to_string(delivery.finished)}</td>
+                                        <td>{delivery.error_message.unwrap_or("-".to_string())}</td>
+                                    </tr>
+                                }).collect_view()}
+                            </tbody>
+                        </table>
+                    }.into_any()
+                }}
+            </Transition>
+        </div>
+    }
+}
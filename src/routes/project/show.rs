@@ -1,10 +1,13 @@
 use leptos::prelude::*;
 use leptos_router::{hooks::use_params_map, params::Params};
 
-use crate::models::{Jobset, Project};
+use crate::components::deletion_report::DeletionReportView;
+use crate::models::{DeletionReport, Project, ProjectRole};
 
 use crate::routes::jobset::get_jobsets;
 
+use super::get_project_role;
+
 stylance::import_crate_style!(my_style, "style/project.module.scss");
 
 #[derive(Params, PartialEq)]
@@ -14,9 +17,13 @@ struct ProjectParams {
 
 #[cfg(feature = "ssr")]
 use {
-    crate::state::State,
+    crate::{
+        hydracore::delete_project as delete_project_data,
+        server::auth::{can_view, current_user, require_role},
+        state::State,
+    },
     axum::http::StatusCode,
-    leptos_axum::{redirect, ResponseOptions},
+    leptos_axum::ResponseOptions,
     std::sync::Arc,
     tracing::error,
 };
@@ -24,10 +31,9 @@ use {
 #[server]
 pub async fn get_projects() -> Result<Vec<Project>, ServerFnError> {
     let state: Arc<State> = expect_context();
+    let user = current_user().await?;
 
-    let coordinator = state.coordinator.lock().await;
-
-    let projects = Project::get_all(&*coordinator.get_db().await.lock().await).await;
+    let projects = Project::get_visible(&state.db, user.as_ref()).await;
 
     let projects = projects.map_err(|e| ServerFnError::new(e.to_string()))?;
 
@@ -48,11 +54,7 @@ pub async fn get_project(id: String) -> Result<Option<Project>, ServerFnError> {
 
     let number = number.unwrap();
 
-    let result = Project::get_single(
-        &*state.coordinator.lock().await.get_db().await.lock().await,
-        number,
-    )
-    .await;
+    let result = Project::get_single(&state.db, number).await;
 
     if result.is_err() {
         error!("Failed to fetch project: {}", result.err().unwrap());
@@ -61,11 +63,14 @@ pub async fn get_project(id: String) -> Result<Option<Project>, ServerFnError> {
         ));
     }
 
-    Ok(result.unwrap())
+    match result.unwrap() {
+        Some(project) if can_view(&project).await? => Ok(Some(project)),
+        _ => Ok(None),
+    }
 }
 
 #[server]
-pub async fn delete_project(project_id: String) -> Result<(), ServerFnError> {
+pub async fn delete_project(project_id: String) -> Result<DeletionReport, ServerFnError> {
     let state: Arc<State> = expect_context();
 
     let project = get_project(project_id.clone()).await?;
@@ -80,17 +85,16 @@ pub async fn delete_project(project_id: String) -> Result<(), ServerFnError> {
 
     let project = project.unwrap();
 
-    _ = project
-        .delete(&*state.coordinator.lock().await.get_db().await.lock().await)
+    require_role(project.id.unwrap(), ProjectRole::Owner).await?;
+
+    let report = delete_project_data(&state.db, &state.data_dir, &project)
         .await
         .map_err(|e| {
             error!("Failed to delete project: {}", e.to_string());
             ServerFnError::new("Failed to delete project!")
         })?;
 
-    redirect("/");
-
-    Ok(())
+    Ok(report)
 }
 
 fn make_td_entry(proj_id: &str, id: &i32, string: &str) -> impl IntoView {
@@ -109,11 +113,29 @@ pub fn Project() -> impl IntoView {
     let delete_project_action = ServerAction::<DeleteProject>::new();
 
     let jobsets = OnceResource::new(get_jobsets(project.clone()));
+    let role_data = OnceResource::new(get_project_role(project.clone()));
 
     //println!("Style is: {}", my_style::project);
 
+    let deleted = move || matches!(delete_project_action.value().get(), Some(Ok(_)));
+
     view! {
         <div class=my_style::project>
+        {move || match delete_project_action.value().get() {
+            Some(Ok(report)) => view! {
+                <DeletionReportView
+                    report=report
+                    what="project".to_string()
+                    url="/".to_string()
+                    text="projects".to_string()
+                />
+            }.into_any(),
+            Some(Err(e)) => view! {
+                <p class="error">"Failed to delete project: "{e.to_string()}</p>
+            }.into_any(),
+            None => ().into_any(),
+        }}
+        <div style:display=move || if deleted() { "none" } else { "block" }>
         <Await
                 future=get_project(project.clone())
             let:data
@@ -130,32 +152,42 @@ pub fn Project() -> impl IntoView {
                     let proj_id = project.clone();
                     view!{
                         <h4 class="title">"Project " {data.name.clone()}</h4>
-                        <div class="dropdown">
-                            <div class="title">
-                                <span>Actions</span>
-                                <svg xmlns="http://www.w3.org/2000/svg" width="16" height="16" fill="currentColor" class="bi bi-caret-down" viewBox="0 0 16 16">
-                                  <path d="M3.204 5h9.592L8 10.481zm-.753.659 4.796 5.48a1 1 0 0 0 1.506 0l4.796-5.48c.566-.647.106-1.659-.753-1.659H3.204a1 1 0 0 0-.753 1.659"/>
-                                </svg>
-                            </div>
-                            <div class="dropdown_content">
-                                <div class="dropdown_group">
-                                    <a href=format!("{}/create-jobset", proj_id)>"Create jobset"</a>
-                                </div>
-                                <div class="dropdown_group">
-                                    <a href=format!("{}/edit", proj_id)>"Edit project"</a>
-                                </div>
-                                <div class="dropdown_group">
-                                    <div class="generic_input_form">
-                                        <ActionForm action=delete_project_action>
-                                            <div class="inputs">
-                                                <input type="hidden" name="project_id" value=proj_id/>
-                                                <input type="submit" value="Delete project"/>
+                        <Suspense fallback=|| ()>
+                            {move || {
+                                let role = role_data.get().and_then(Result::ok).flatten();
+                                let owner = role >= Some(ProjectRole::Owner);
+                                let proj_id = proj_id.clone();
+
+                                (role >= Some(ProjectRole::Maintainer)).then(move || view! {
+                                    <div class="dropdown">
+                                        <div class="title">
+                                            <span>Actions</span>
+                                            <svg xmlns="http://www.w3.org/2000/svg" width="16" height="16" fill="currentColor" class="bi bi-caret-down" viewBox="0 0 16 16">
+                                              <path d="M3.204 5h9.592L8 10.481zm-.753.659 4.796 5.48a1 1 0 0 0 1.506 0l4.796-5.48c.566-.647.106-1.659-.753-1.659H3.204a1 1 0 0 0-.753 1.659"/>
+                                            </svg>
+                                        </div>
+                                        <div class="dropdown_content">
+                                            <div class="dropdown_group">
+                                                <a href=format!("{}/create-jobset", proj_id)>"Create jobset"</a>
+                                            </div>
+                                            <div class="dropdown_group" style:display=if owner { "block" } else { "none" }>
+                                                <a href=format!("{}/edit", proj_id)>"Edit project"</a>
+                                            </div>
+                                            <div class="dropdown_group" style:display=if owner { "block" } else { "none" }>
+                                                <div class="generic_input_form">
+                                                    <ActionForm action=delete_project_action>
+                                                        <div class="inputs">
+                                                            <input type="hidden" name="project_id" value=proj_id/>
+                                                            <input type="submit" value="Delete project"/>
+                                                        </div>
+                                                    </ActionForm>
+                                                </div>
                                             </div>
-                                        </ActionForm>
+                                        </div>
                                     </div>
-                                </div>
-                            </div>
-                        </div>
+                                })
+                            }}
+                        </Suspense>
                         <Suspense fallback=move || view! { <p>"Loading jobsets..."</p>}>
 
                             {move || {
@@ -206,6 +238,7 @@ pub fn Project() -> impl IntoView {
                 }
              }
         </Await>
+        </div>
     </div>
     }
 }
@@ -0,0 +1,105 @@
+use chrono::{DateTime, Utc};
+use leptos::prelude::*;
+
+use crate::models::Pruning;
+
+#[server]
+pub async fn get_prunings() -> Result<Vec<Pruning>, ServerFnError> {
+    use crate::state::State;
+    use std::sync::Arc;
+    use tracing::error;
+
+    crate::server::auth::require_admin().await?;
+
+    let state: Arc<State> = expect_context();
+
+    let prunings = Pruning::get_latest(&state.db, 50).await.map_err(|e| {
+        error!("Failed to fetch prunings: {}", e.to_string());
+        ServerFnError::new("Failed to fetch prunings!")
+    })?;
+
+    Ok(prunings)
+}
+
+pub fn format_bytes(bytes: i64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+
+    while value.abs() >= 1024.0 && unit < units.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", value, units[unit])
+}
+
+fn convert_date_to_string(date: DateTime<Utc>) -> String {
+    date.format("%H:%M:%S %d.%m.%Y").to_string()
+}
+
+#[component]
+pub fn Pruning() -> impl IntoView {
+    let prunings = OnceResource::new(get_prunings());
+
+    view! {
+        <div class="generic_table">
+            <h3>"Pruning"</h3>
+            <div class="description">
+                <p>"Evaluations beyond the limits of their jobset are pruned while the queue runner is idle."</p>
+                <p>"The latest successful evaluation of a jobset and evaluations with kept jobs are never pruned."</p>
+            </div>
+            <Suspense fallback=move || view! {<p>"Loading prunings..."</p>}>
+                {move || {
+                    let prunings = prunings.get();
+
+                    if prunings.is_none() {
+                        return view! {<p class="left error">"Failed to load prunings"</p>}.into_any();
+                    }
+
+                    let prunings = prunings.unwrap();
+
+                    if prunings.is_err() {
+                        let err = prunings.err().unwrap().to_string();
+                        return view! {<p class="left error">"Failed to load prunings: "{err}</p>}.into_any();
+                    }
+
+                    let prunings = prunings.unwrap();
+
+                    if prunings.is_empty() {
+                        return view! {<p class="left">"Nothing was pruned yet"</p>}.into_any();
+                    }
+
+                    let total: i64 = prunings.iter().map(|pruning| pruning.reclaimed_bytes()).sum();
+
+                    view! {
+                        <p class="left">"Reclaimed "{format_bytes(total)}" in the last "{prunings.len()}" runs"</p>
+                        <table>
+                            <tbody>
+                                <tr>
+                                    <th>"Finished"</th>
+                                    <th>"Evaluations"</th>
+                                    <th>"Jobs"</th>
+                                    <th>"Logs"</th>
+                                    <th>"Database"</th>
+                                    <th>"Reclaimed"</th>
+                                </tr>
+                                {prunings.into_iter().map(|pruning| view! {
+                                    <tr>
+                                        <td>{convert_date_to_string(pruning.finished)}</td>
+                                        <td>{pruning.evaluations}</td>
+                                        <td>{pruning.jobs}</td>
+                                        <td>{format!("{} ({})", pruning.logs, format_bytes(pruning.log_bytes))}</td>
+                                        <td>{format!("{} → {}", format_bytes(pruning.db_bytes_before), format_bytes(pruning.db_bytes_after))}</td>
+                                        <td>{format_bytes(pruning.reclaimed_bytes())}</td>
+                                    </tr>
+                                }).collect_view()}
+                            </tbody>
+                        </table>
+                    }.into_any()
+                }}
+            </Suspense>
+        </div>
+    }
+}
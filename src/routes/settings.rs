@@ -0,0 +1,261 @@
+use chrono::{DateTime, Utc};
+use leptos::prelude::*;
+
+use crate::{
+    models::{ApiToken, TokenScope},
+    routes::project::get_projects,
+};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::{
+        hydracore::{hash_token, new_token},
+        server::auth::{require_user, require_view},
+        state::State,
+    },
+    axum::http::StatusCode,
+    chrono::TimeDelta,
+    leptos_axum::ResponseOptions,
+    std::sync::Arc,
+    tracing::{error, info},
+};
+
+#[server]
+pub async fn get_api_tokens() -> Result<Vec<ApiToken>, ServerFnError> {
+    let state: Arc<State> = expect_context();
+
+    let user = require_user().await?;
+
+    let tokens = ApiToken::get_all(&state.db, user.id.unwrap())
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch api tokens: {}", e.to_string());
+            ServerFnError::new("Failed to fetch api tokens!")
+        })?;
+
+    Ok(tokens)
+}
+
+/// Returns the token, it is only shown this once
+#[server]
+pub async fn create_api_token(
+    name: String,
+    scope: TokenScope,
+    project_id: String,
+    expires_in_days: String,
+) -> Result<String, ServerFnError> {
+    let state: Arc<State> = expect_context();
+    let response_opts: ResponseOptions = expect_context();
+
+    let user = require_user().await?;
+
+    if name.trim().is_empty() {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new("Name cannot be empty!"));
+    }
+
+    let project_id = match project_id.as_str() {
+        "" => None,
+        project_id => {
+            let project_id = project_id
+                .parse::<i32>()
+                .map_err(|_| ServerFnError::new("Invalid project id!"))?;
+
+            require_view(project_id).await?;
+
+            Some(project_id)
+        }
+    };
+
+    let expires = match expires_in_days.trim() {
+        "" => None,
+        days => match days.parse::<i64>() {
+            Ok(days) if days > 0 => Some(Utc::now() + TimeDelta::days(days)),
+            _ => {
+                response_opts.set_status(StatusCode::BAD_REQUEST);
+                return Err(ServerFnError::new(
+                    "The expiry has to be a positive number of days!",
+                ));
+            }
+        },
+    };
+
+    let token = new_token();
+
+    let mut api_token = ApiToken::new(
+        user.id.unwrap(),
+        project_id,
+        name.trim().to_string(),
+        scope,
+        expires,
+    );
+
+    api_token
+        .add_to_db(&state.db, &hash_token(&token))
+        .await
+        .map_err(|e| {
+            error!("Failed to create api token: {}", e.to_string());
+            ServerFnError::new("Failed to create api token!")
+        })?;
+
+    info!(
+        "{} created api token {} with {} scope",
+        user.name, api_token.name, scope
+    );
+
+    Ok(token)
+}
+
+#[server]
+pub async fn revoke_api_token(id: String) -> Result<(), ServerFnError> {
+    let state: Arc<State> = expect_context();
+    let response_opts: ResponseOptions = expect_context();
+
+    let user = require_user().await?;
+
+    let id = id
+        .parse::<i32>()
+        .map_err(|_| ServerFnError::new("Invalid token id!"))?;
+
+    let revoked = ApiToken::delete(&state.db, id, user.id.unwrap())
+        .await
+        .map_err(|e| {
+            error!("Failed to revoke api token: {}", e.to_string());
+            ServerFnError::new("Failed to revoke api token!")
+        })?;
+
+    if !revoked {
+        response_opts.set_status(StatusCode::NOT_FOUND);
+        return Err(ServerFnError::new("Failed to find api token!"));
+    }
+
+    info!("{} revoked api token {}", user.name, id);
+
+    Ok(())
+}
+
+fn convert_date_to_string(date: Option<DateTime<Utc>>) -> String {
+    match date {
+        Some(date) => date.format("%H:%M:%S %d.%m.%Y").to_string(),
+        None => "-".to_string(),
+    }
+}
+
+/// The api tokens of the logged in user
+#[component]
+pub fn Settings() -> impl IntoView {
+    let create_action = ServerAction::<CreateApiToken>::new();
+    let revoke_action = ServerAction::<RevokeApiToken>::new();
+
+    let tokens = Resource::new(
+        move || (create_action.version().get(), revoke_action.version().get()),
+        |_| get_api_tokens(),
+    );
+
+    let projects = OnceResource::new(get_projects());
+
+    view! {
+        <div class="generic_table">
+            <h3>"API tokens"</h3>
+            <div class="description">
+                <p>"Scripts send a token as "<code>"Authorization: Bearer <token>"</code>" header instead of logging in."</p>
+                <p>"A token may read, also trigger, or do everything your role allows. It can be limited to one project."</p>
+            </div>
+            <Transition fallback=move || view! {<p>"Loading tokens..."</p>}>
+                {move || {
+                    let tokens = tokens.get();
+
+                    if tokens.is_none() {
+                        return view! {<p class="left error">"Failed to load tokens"</p>}.into_any();
+                    }
+
+                    let tokens = tokens.unwrap();
+
+                    if tokens.is_err() {
+                        let err = tokens.err().unwrap().to_string();
+                        return view! {<p class="left error">"Failed to load tokens: "{err}</p>}.into_any();
+                    }
+
+                    let tokens = tokens.unwrap();
+
+                    if tokens.is_empty() {
+                        return view! {<p class="left">"You have no tokens yet"</p>}.into_any();
+                    }
+
+                    view! {
+                        <table>
+                            <tbody>
+                                <tr>
+                                    <th>"Name"</th>
+                                    <th>"Scope"</th>
+                                    <th>"Project"</th>
+                                    <th>"Created"</th>
+                                    <th>"Expires"</th>
+                                    <th>"Last used"</th>
+                                    <th></th>
+                                </tr>
+                                {tokens.into_iter().map(|token| view! {
+                                    <tr>
+                                        <td>{token.name}</td>
+                                        <td>{token.scope.to_string()}</td>
+                                        <td>{token.project_name.unwrap_or("All".to_string())}</td>
+                                        <td>{convert_date_to_string(Some(token.created))}</td>
+                                        <td>{convert_date_to_string(token.expires)}</td>
+                                        <td>{convert_date_to_string(token.last_used)}</td>
+                                        <td>
+                                            <ActionForm action=revoke_action>
+                                                <input type="hidden" name="id" value=token.id/>
+                                                <input type="submit" value="Revoke"/>
+                                            </ActionForm>
+                                        </td>
+                                    </tr>
+                                }).collect_view()}
+                            </tbody>
+                        </table>
+                    }.into_any()
+                }}
+            </Transition>
+        </div>
+        <div class="generic_input_form">
+            <ActionForm action=create_action>
+                <h3>"New token"</h3>
+                <div class="inputs">
+                    <input type="text" name="name" placeholder="Name"/>
+                    <select name="scope">
+                        <option value="Read">"Read"</option>
+                        <option value="Trigger">"Trigger"</option>
+                        <option value="Admin">"Admin"</option>
+                    </select>
+                    <select name="project_id">
+                        <option value="">"All projects"</option>
+                        <Suspense fallback=|| ()>
+                            {move || projects.get().and_then(|projects| projects.ok()).unwrap_or_default().into_iter().map(|project| view! {
+                                <option value=project.id>{project.name}</option>
+                            }).collect_view()}
+                        </Suspense>
+                    </select>
+                    <input type="text" name="expires_in_days" placeholder="Expires in days, empty for never"/>
+                    <input type="submit" value="Create token"/>
+                </div>
+            </ActionForm>
+        </div>
+        <div class="generic_input_form_response">
+            {move || {
+                match (create_action.value().get(), revoke_action.value().get()) {
+                    (Some(Err(e)), _) | (_, Some(Err(e))) => {
+                        let msg = match e {
+                            ServerFnError::ServerError(msg) => msg,
+                            e => e.to_string(),
+                        };
+                        view! {<p class="error">"Failed: "{msg}</p>}.into_any()
+                    },
+                    (Some(Ok(token)), _) => view! {
+                        <p>"Copy the token now, it is not shown again:"</p>
+                        <code>{token}</code>
+                    }.into_any(),
+                    _ => ().into_any(),
+                }
+            }}
+        </div>
+    }
+}
@@ -0,0 +1,94 @@
+use chrono::{DateTime, Utc};
+use leptos::prelude::*;
+
+use crate::models::WebhookDelivery;
+
+#[server]
+pub async fn get_webhook_deliveries() -> Result<Vec<WebhookDelivery>, ServerFnError> {
+    use crate::state::State;
+    use std::sync::Arc;
+    use tracing::error;
+
+    crate::server::auth::require_admin().await?;
+
+    let state: Arc<State> = expect_context();
+
+    let deliveries = WebhookDelivery::get_latest(&state.db, 100)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch webhook deliveries: {}", e.to_string());
+            ServerFnError::new("Failed to fetch webhook deliveries!")
+        })?;
+
+    Ok(deliveries)
+}
+
+fn convert_date_to_string(date: DateTime<Utc>) -> String {
+    date.format("%H:%M:%S %d.%m.%Y").to_string()
+}
+
+#[component]
+pub fn Webhooks() -> impl IntoView {
+    let deliveries = OnceResource::new(get_webhook_deliveries());
+
+    view! {
+        <div class="generic_table">
+            <h3>"Webhooks"</h3>
+            <div class="description">
+                <p>"Pushes to a branch evaluate every jobset whose flake follows it, at the pushed revision."</p>
+                <p>"Deliveries are kept for 30 days."</p>
+            </div>
+            <Suspense fallback=move || view! {<p>"Loading deliveries..."</p>}>
+                {move || {
+                    let deliveries = deliveries.get();
+
+                    if deliveries.is_none() {
+                        return view! {<p class="left error">"Failed to load deliveries"</p>}.into_any();
+                    }
+
+                    let deliveries = deliveries.unwrap();
+
+                    if deliveries.is_err() {
+                        let err = deliveries.err().unwrap().to_string();
+                        return view! {<p class="left error">"Failed to load deliveries: "{err}</p>}.into_any();
+                    }
+
+                    let deliveries = deliveries.unwrap();
+
+                    if deliveries.is_empty() {
+                        return view! {<p class="left">"No webhook was delivered yet"</p>}.into_any();
+                    }
+
+                    view! {
+                        <table>
+                            <tbody>
+                                <tr>
+                                    <th>"Received"</th>
+                                    <th>"Forge"</th>
+                                    <th>"Event"</th>
+                                    <th>"Repository"</th>
+                                    <th>"Branch"</th>
+                                    <th>"Revision"</th>
+                                    <th>"Outcome"</th>
+                                    <th>"Message"</th>
+                                </tr>
+                                {deliveries.into_iter().map(|delivery| view! {
+                                    <tr>
+                                        <td>{convert_date_to_string(delivery.received)}</td>
+                                        <td>{delivery.forge.to_string()}</td>
+                                        <td>{delivery.event.unwrap_or("-".to_string())}</td>
+                                        <td>{delivery.repository.unwrap_or("-".to_string())}</td>
+                                        <td>{delivery.branch.unwrap_or("-".to_string())}</td>
+                                        <td>{delivery.revision.map(|revision| revision.chars().take(12).collect()).unwrap_or("-".to_string())}</td>
+                                        <td>{format!("{:?}", delivery.outcome)}</td>
+                                        <td>{delivery.message}</td>
+                                    </tr>
+                                }).collect_view()}
+                            </tbody>
+                        </table>
+                    }.into_any()
+                }}
+            </Suspense>
+        </div>
+    }
+}
@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use crate::{
+    models::{Evaluation, Job, JobFilter, Jobset},
+    server::auth::Caller,
+    state::State as AppState,
+};
+use axum::{
+    extract::{Path, Query, State},
+    routing::get,
+    Json, Router,
+};
+
+use super::{
+    jobs::JobQuery, jobsets::find_jobset, projects::find_project, ApiError, ApiResult, CurrentUser,
+    ErrorBody, Page, Pagination,
+};
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            "/projects/:project_id/jobsets/:jobset_id/evaluations",
+            get(list_evaluations),
+        )
+        .route("/evaluations/:evaluation_id", get(get_evaluation))
+        .route(
+            "/evaluations/:evaluation_id/jobs",
+            get(list_evaluation_jobs),
+        )
+}
+
+/// The evaluation, if the user may see its project
+async fn find_evaluation(
+    state: &AppState,
+    caller: Option<&Caller>,
+    evaluation_id: i32,
+) -> ApiResult<Evaluation> {
+    let evaluation = Evaluation::get_single(&state.db, evaluation_id)
+        .await?
+        .ok_or(ApiError::not_found("Failed to find evaluation!"))?;
+
+    let jobset = Jobset::get_single(&state.db, evaluation.jobset_id)
+        .await?
+        .ok_or(ApiError::not_found("Failed to find evaluation!"))?;
+
+    find_project(state, caller, jobset.project_id).await?;
+
+    Ok(evaluation)
+}
+
+#[utoipa::path(
+    get,
+    path = "/projects/{project_id}/jobsets/{jobset_id}/evaluations",
+    tag = "evaluations",
+    params(("project_id" = i32, Path), ("jobset_id" = i32, Path), Pagination),
+    responses(
+        (status = 200, description = "Newest first", body = Page<Evaluation>),
+        (status = 404, body = ErrorBody),
+    )
+)]
+pub async fn list_evaluations(
+    State(state): State<Arc<AppState>>,
+    CurrentUser(caller): CurrentUser,
+    Path((project_id, jobset_id)): Path<(i32, i32)>,
+    Query(pagination): Query<Pagination>,
+) -> ApiResult<Json<Page<Evaluation>>> {
+    find_jobset(&state, caller.as_ref(), project_id, jobset_id).await?;
+
+    let (evaluations, total) = Evaluation::get_page(
+        &state.db,
+        jobset_id,
+        pagination.offset(),
+        pagination.limit(),
+    )
+    .await?;
+
+    Ok(Json(Page::new(evaluations, total, &pagination)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/evaluations/{evaluation_id}",
+    tag = "evaluations",
+    params(("evaluation_id" = i32, Path)),
+    responses(
+        (status = 200, body = Evaluation),
+        (status = 404, body = ErrorBody),
+    )
+)]
+pub async fn get_evaluation(
+    State(state): State<Arc<AppState>>,
+    CurrentUser(caller): CurrentUser,
+    Path(evaluation_id): Path<i32>,
+) -> ApiResult<Json<Evaluation>> {
+    Ok(Json(
+        find_evaluation(&state, caller.as_ref(), evaluation_id).await?,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/evaluations/{evaluation_id}/jobs",
+    tag = "evaluations",
+    params(("evaluation_id" = i32, Path), Pagination, JobQuery),
+    responses(
+        (status = 200, body = Page<Job>),
+        (status = 404, body = ErrorBody),
+    )
+)]
+pub async fn list_evaluation_jobs(
+    State(state): State<Arc<AppState>>,
+    CurrentUser(caller): CurrentUser,
+    Path(evaluation_id): Path<i32>,
+    Query(pagination): Query<Pagination>,
+    Query(query): Query<JobQuery>,
+) -> ApiResult<Json<Page<Job>>> {
+    find_evaluation(&state, caller.as_ref(), evaluation_id).await?;
+
+    let filter = JobFilter {
+        evaluation_id: Some(evaluation_id),
+        state: query.state,
+        attribute: query.attribute,
+        ..Default::default()
+    };
+
+    let (jobs, total) =
+        Job::get_page(&state.db, &filter, pagination.offset(), pagination.limit()).await?;
+
+    Ok(Json(Page::new(jobs, total, &pagination)))
+}
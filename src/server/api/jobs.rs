@@ -0,0 +1,197 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::Deserialize;
+use tokio::io::AsyncSeekExt;
+use tokio_util::io::ReaderStream;
+use utoipa::IntoParams;
+
+use crate::{
+    models::{
+        Job, JobFilter, JobOrigin, JobState, ProjectRole, QueueRequest, QueueRequestKind,
+        TokenScope,
+    },
+    server::auth::Caller,
+    state::State as AppState,
+};
+
+use super::{
+    check_role, projects::find_project, ApiError, ApiResult, Authenticated, CurrentUser, ErrorBody,
+    Page, Pagination,
+};
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/jobs", get(list_jobs))
+        .route("/jobs/:job_id", get(get_job))
+        .route("/jobs/:job_id/log", get(get_job_log))
+        .route("/jobs/:job_id/restart", post(restart_job))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct JobQuery {
+    pub state: Option<JobState>,
+    /// Part of the attribute name
+    pub attribute: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct JobsetQuery {
+    /// Only jobs of the jobset's evaluations
+    jobset_id: Option<i32>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LogQuery {
+    /// Skip this many bytes, to follow a log which is still being written
+    offset: Option<u64>,
+}
+
+/// The job with its project, if the user may see the project
+async fn find_job(state: &AppState, caller: Option<&Caller>, job_id: i32) -> ApiResult<JobOrigin> {
+    let origin = Job::get_origin(&state.db, job_id)
+        .await?
+        .ok_or(ApiError::not_found("Failed to find job!"))?;
+
+    find_project(state, caller, origin.project_id).await?;
+
+    Ok(origin)
+}
+
+#[utoipa::path(
+    get,
+    path = "/jobs",
+    tag = "jobs",
+    params(Pagination, JobsetQuery, JobQuery),
+    responses((status = 200, description = "Newest first", body = Page<Job>))
+)]
+pub async fn list_jobs(
+    State(state): State<Arc<AppState>>,
+    CurrentUser(caller): CurrentUser,
+    Query(pagination): Query<Pagination>,
+    Query(jobset): Query<JobsetQuery>,
+    Query(query): Query<JobQuery>,
+) -> ApiResult<Json<Page<Job>>> {
+    let mut filter = JobFilter {
+        jobset_id: jobset.jobset_id,
+        state: query.state,
+        attribute: query.attribute,
+        ..Default::default()
+    }
+    .visible_to(caller.as_ref().map(|caller| &caller.user));
+
+    filter.project_id = caller.as_ref().and_then(Caller::project_id);
+
+    let (jobs, total) =
+        Job::get_page(&state.db, &filter, pagination.offset(), pagination.limit()).await?;
+
+    Ok(Json(Page::new(jobs, total, &pagination)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/jobs/{job_id}",
+    tag = "jobs",
+    params(("job_id" = i32, Path)),
+    responses(
+        (status = 200, body = Job),
+        (status = 404, body = ErrorBody),
+    )
+)]
+pub async fn get_job(
+    State(state): State<Arc<AppState>>,
+    CurrentUser(caller): CurrentUser,
+    Path(job_id): Path<i32>,
+) -> ApiResult<Json<Job>> {
+    Ok(Json(find_job(&state, caller.as_ref(), job_id).await?.job))
+}
+
+#[utoipa::path(
+    get,
+    path = "/jobs/{job_id}/log",
+    tag = "jobs",
+    params(("job_id" = i32, Path), LogQuery),
+    responses(
+        (status = 200, description = "The build log, while building the part written so far", content_type = "text/plain", body = String),
+        (status = 404, body = ErrorBody),
+    )
+)]
+pub async fn get_job_log(
+    State(state): State<Arc<AppState>>,
+    CurrentUser(caller): CurrentUser,
+    Path(job_id): Path<i32>,
+    Query(query): Query<LogQuery>,
+) -> ApiResult<Response> {
+    find_job(&state, caller.as_ref(), job_id).await?;
+
+    let mut file = tokio::fs::File::open(state.data_dir.build_log(job_id))
+        .await
+        .map_err(|_| ApiError::not_found("The job has no build log"))?;
+
+    if let Some(offset) = query.offset {
+        file.seek(std::io::SeekFrom::Start(offset))
+            .await
+            .map_err(|_| ApiError::bad_request("Invalid offset"))?;
+    }
+
+    Ok((
+        [(CONTENT_TYPE, "text/plain; charset=utf-8")],
+        Body::from_stream(ReaderStream::new(file)),
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/jobs/{job_id}/restart",
+    tag = "jobs",
+    params(("job_id" = i32, Path)),
+    responses(
+        (status = 202, description = "The queue runner builds the job again soon", body = QueueRequest),
+        (status = 404, body = ErrorBody),
+        (status = 409, description = "Only failed jobs can be restarted", body = ErrorBody),
+        (status = 401, description = "Nobody is logged in", body = ErrorBody),
+        (status = 403, description = "The user's role in the project or the token's scope is too low", body = ErrorBody),
+    )
+)]
+pub async fn restart_job(
+    State(state): State<Arc<AppState>>,
+    Authenticated(caller): Authenticated,
+    Path(job_id): Path<i32>,
+) -> ApiResult<(StatusCode, Json<QueueRequest>)> {
+    let JobOrigin {
+        job, project_id, ..
+    } = find_job(&state, Some(&caller), job_id).await?;
+
+    check_role(
+        &state,
+        &caller,
+        project_id,
+        ProjectRole::Maintainer,
+        TokenScope::Trigger,
+    )
+    .await?;
+
+    if !matches!(job.state, JobState::Failed) {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            "Only failed jobs can be restarted",
+        ));
+    }
+
+    let mut request = QueueRequest::for_job(QueueRequestKind::RestartJob, job_id);
+
+    request.add_to_db(&state.db).await?;
+
+    Ok((StatusCode::ACCEPTED, Json(request)))
+}
@@ -0,0 +1,394 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::Deserialize;
+use tracing::info;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    hydracore::delete_jobset as delete_jobset_data,
+    models::{
+        DeletionReport, Jobset, JobsetDiff, JobsetState, ProjectRole, QueueRequest,
+        QueueRequestKind, TokenScope,
+    },
+    server::auth::Caller,
+    state::State as AppState,
+};
+
+use super::{
+    check_role, projects::find_project, ApiError, ApiResult, Authenticated, CurrentUser, ErrorBody,
+    Page, Pagination,
+};
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            "/projects/:project_id/jobsets",
+            get(list_jobsets).post(create_jobset),
+        )
+        .route(
+            "/projects/:project_id/jobsets/:jobset_id",
+            get(get_jobset).put(update_jobset).delete(delete_jobset),
+        )
+        .route(
+            "/projects/:project_id/jobsets/:jobset_id/trigger",
+            post(trigger_jobset),
+        )
+        .route(
+            "/projects/:project_id/jobsets/:jobset_id/cancel",
+            post(cancel_jobset),
+        )
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct JobsetFilter {
+    state: Option<JobsetState>,
+}
+
+fn default_keep_evaluations() -> i32 {
+    3
+}
+
+/// What clients send to create or change a jobset
+#[derive(Deserialize, ToSchema)]
+pub struct JobsetInput {
+    name: String,
+    flake: String,
+    description: String,
+    /// Seconds between evaluations, 0 only evaluates when triggered
+    check_interval: i32,
+    /// How many of the latest evaluations keep their outputs rooted
+    #[serde(default = "default_keep_evaluations")]
+    keep_evaluations: i32,
+    /// Older evaluations get pruned, 0 keeps all of them
+    #[serde(default)]
+    max_evaluations: i32,
+    /// Evaluations older than this many days get pruned, 0 keeps all of them
+    #[serde(default)]
+    max_evaluation_age: i32,
+    /// Whether evaluations which pass the gate are published as a channel
+    #[serde(default)]
+    channel: bool,
+    /// Attribute which has to build for a channel release, all jobs have to if it is not set
+    #[serde(default)]
+    channel_gate: Option<String>,
+}
+
+impl JobsetInput {
+    fn validate(&self) -> ApiResult<()> {
+        if self.name.is_empty() {
+            return Err(ApiError::bad_request("Name may not be empty!"));
+        }
+
+        if self.flake.is_empty() {
+            return Err(ApiError::bad_request("Flake URI may not be empty!"));
+        }
+
+        if self.keep_evaluations < 0 {
+            return Err(ApiError::bad_request(
+                "The number of kept evaluations may not be negative!",
+            ));
+        }
+
+        if self.max_evaluations < 0 || self.max_evaluation_age < 0 {
+            return Err(ApiError::bad_request(
+                "Retention limits may not be negative!",
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn channel_gate(&self) -> Option<String> {
+        self.channel_gate.clone().filter(|gate| !gate.is_empty())
+    }
+}
+
+/// The jobset, if it belongs to the project and the user may see the project
+pub async fn find_jobset(
+    state: &AppState,
+    caller: Option<&Caller>,
+    project_id: i32,
+    jobset_id: i32,
+) -> ApiResult<Jobset> {
+    find_project(state, caller, project_id).await?;
+
+    Jobset::get_single(&state.db, jobset_id)
+        .await?
+        .filter(|jobset| jobset.project_id == project_id)
+        .ok_or(ApiError::not_found("Failed to find jobset!"))
+}
+
+#[utoipa::path(
+    get,
+    path = "/projects/{project_id}/jobsets",
+    tag = "jobsets",
+    params(("project_id" = i32, Path), Pagination, JobsetFilter),
+    responses(
+        (status = 200, body = Page<Jobset>),
+        (status = 404, body = ErrorBody),
+    )
+)]
+pub async fn list_jobsets(
+    State(state): State<Arc<AppState>>,
+    CurrentUser(caller): CurrentUser,
+    Path(project_id): Path<i32>,
+    Query(pagination): Query<Pagination>,
+    Query(filter): Query<JobsetFilter>,
+) -> ApiResult<Json<Page<Jobset>>> {
+    find_project(&state, caller.as_ref(), project_id).await?;
+
+    let mut jobsets = Jobset::get_all(&state.db, project_id).await?;
+
+    if let Some(jobset_state) = filter.state {
+        jobsets.retain(|jobset| jobset.state.as_ref() == Some(&jobset_state));
+    }
+
+    Ok(Json(Page::from_all(jobsets, &pagination)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/projects/{project_id}/jobsets",
+    tag = "jobsets",
+    params(("project_id" = i32, Path)),
+    request_body = JobsetInput,
+    responses(
+        (status = 201, body = Jobset),
+        (status = 400, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 401, description = "Nobody is logged in", body = ErrorBody),
+        (status = 403, description = "The user's role in the project or the token's scope is too low", body = ErrorBody),
+    )
+)]
+pub async fn create_jobset(
+    State(state): State<Arc<AppState>>,
+    Authenticated(caller): Authenticated,
+    Path(project_id): Path<i32>,
+    Json(input): Json<JobsetInput>,
+) -> ApiResult<(StatusCode, Json<Jobset>)> {
+    find_project(&state, Some(&caller), project_id).await?;
+
+    check_role(
+        &state,
+        &caller,
+        project_id,
+        ProjectRole::Maintainer,
+        TokenScope::Admin,
+    )
+    .await?;
+
+    input.validate()?;
+
+    let mut jobset = Jobset {
+        id: None,
+        project_id,
+        channel_gate: input.channel_gate(),
+        name: input.name,
+        flake: input.flake,
+        description: input.description,
+        check_interval: input.check_interval,
+        keep_evaluations: input.keep_evaluations,
+        max_evaluations: input.max_evaluations,
+        max_evaluation_age: input.max_evaluation_age,
+        channel: input.channel,
+        last_checked: None,
+        last_evaluated: None,
+        evaluation_took: None,
+        state: Some(JobsetState::Unknown),
+        error_message: None,
+    };
+
+    jobset.add_to_db(&state.db).await?;
+
+    info!(
+        "{} created jobset {} on project {}",
+        caller.user.name, jobset.name, project_id
+    );
+
+    Ok((StatusCode::CREATED, Json(jobset)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/projects/{project_id}/jobsets/{jobset_id}",
+    tag = "jobsets",
+    params(("project_id" = i32, Path), ("jobset_id" = i32, Path)),
+    responses(
+        (status = 200, body = Jobset),
+        (status = 404, body = ErrorBody),
+    )
+)]
+pub async fn get_jobset(
+    State(state): State<Arc<AppState>>,
+    CurrentUser(caller): CurrentUser,
+    Path((project_id, jobset_id)): Path<(i32, i32)>,
+) -> ApiResult<Json<Jobset>> {
+    Ok(Json(
+        find_jobset(&state, caller.as_ref(), project_id, jobset_id).await?,
+    ))
+}
+
+#[utoipa::path(
+    put,
+    path = "/projects/{project_id}/jobsets/{jobset_id}",
+    tag = "jobsets",
+    params(("project_id" = i32, Path), ("jobset_id" = i32, Path)),
+    request_body = JobsetInput,
+    responses(
+        (status = 200, body = Jobset),
+        (status = 400, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 401, description = "Nobody is logged in", body = ErrorBody),
+        (status = 403, description = "The user's role in the project or the token's scope is too low", body = ErrorBody),
+    )
+)]
+pub async fn update_jobset(
+    State(state): State<Arc<AppState>>,
+    Authenticated(caller): Authenticated,
+    Path((project_id, jobset_id)): Path<(i32, i32)>,
+    Json(input): Json<JobsetInput>,
+) -> ApiResult<Json<Jobset>> {
+    let mut jobset = find_jobset(&state, Some(&caller), project_id, jobset_id).await?;
+
+    check_role(
+        &state,
+        &caller,
+        project_id,
+        ProjectRole::Maintainer,
+        TokenScope::Admin,
+    )
+    .await?;
+
+    input.validate()?;
+
+    let mut diff = JobsetDiff::new();
+
+    diff.set_channel(input.channel, input.channel_gate());
+    diff.set_name(input.name);
+    diff.set_flake(input.flake);
+    diff.set_description(input.description);
+    diff.set_check_interval(input.check_interval);
+    diff.set_keep_evaluations(input.keep_evaluations);
+    diff.set_max_evaluations(input.max_evaluations);
+    diff.set_max_evaluation_age(input.max_evaluation_age);
+
+    jobset.update_jobset(&state.db, diff).await?;
+
+    Ok(Json(jobset))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/projects/{project_id}/jobsets/{jobset_id}",
+    tag = "jobsets",
+    params(("project_id" = i32, Path), ("jobset_id" = i32, Path)),
+    responses(
+        (status = 200, description = "What was deleted with the jobset", body = DeletionReport),
+        (status = 404, body = ErrorBody),
+        (status = 401, description = "Nobody is logged in", body = ErrorBody),
+        (status = 403, description = "The user's role in the project or the token's scope is too low", body = ErrorBody),
+    )
+)]
+pub async fn delete_jobset(
+    State(state): State<Arc<AppState>>,
+    Authenticated(caller): Authenticated,
+    Path((project_id, jobset_id)): Path<(i32, i32)>,
+) -> ApiResult<Json<DeletionReport>> {
+    let mut jobset = find_jobset(&state, Some(&caller), project_id, jobset_id).await?;
+
+    check_role(
+        &state,
+        &caller,
+        project_id,
+        ProjectRole::Owner,
+        TokenScope::Admin,
+    )
+    .await?;
+
+    let report = delete_jobset_data(&state.db, &state.data_dir, &mut jobset).await?;
+
+    Ok(Json(report))
+}
+
+#[utoipa::path(
+    post,
+    path = "/projects/{project_id}/jobsets/{jobset_id}/trigger",
+    tag = "jobsets",
+    params(("project_id" = i32, Path), ("jobset_id" = i32, Path)),
+    responses(
+        (status = 202, description = "The queue runner evaluates the jobset soon, or after the running evaluation of it", body = QueueRequest),
+        (status = 404, body = ErrorBody),
+        (status = 401, description = "Nobody is logged in", body = ErrorBody),
+        (status = 403, description = "The user's role in the project or the token's scope is too low", body = ErrorBody),
+    )
+)]
+pub async fn trigger_jobset(
+    State(state): State<Arc<AppState>>,
+    Authenticated(caller): Authenticated,
+    Path((project_id, jobset_id)): Path<(i32, i32)>,
+) -> ApiResult<(StatusCode, Json<QueueRequest>)> {
+    let jobset = find_jobset(&state, Some(&caller), project_id, jobset_id).await?;
+
+    check_role(
+        &state,
+        &caller,
+        project_id,
+        ProjectRole::Maintainer,
+        TokenScope::Trigger,
+    )
+    .await?;
+
+    // the queue runner picks this up, it may live in another process.
+    // A jobset which is being evaluated keeps the request until that evaluation is done.
+    let mut request = QueueRequest::new(QueueRequestKind::TriggerJobset, jobset.id);
+
+    request.add_to_db(&state.db).await?;
+
+    info!("{} triggered jobset: {}", caller.user.name, jobset_id);
+
+    Ok((StatusCode::ACCEPTED, Json(request)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/projects/{project_id}/jobsets/{jobset_id}/cancel",
+    tag = "jobsets",
+    params(("project_id" = i32, Path), ("jobset_id" = i32, Path)),
+    responses(
+        (status = 202, description = "The queue runner stops the evaluation and the unfinished builds soon, they count as failed", body = QueueRequest),
+        (status = 404, body = ErrorBody),
+        (status = 401, description = "Nobody is logged in", body = ErrorBody),
+        (status = 403, description = "The user's role in the project or the token's scope is too low", body = ErrorBody),
+    )
+)]
+pub async fn cancel_jobset(
+    State(state): State<Arc<AppState>>,
+    Authenticated(caller): Authenticated,
+    Path((project_id, jobset_id)): Path<(i32, i32)>,
+) -> ApiResult<(StatusCode, Json<QueueRequest>)> {
+    let jobset = find_jobset(&state, Some(&caller), project_id, jobset_id).await?;
+
+    check_role(
+        &state,
+        &caller,
+        project_id,
+        ProjectRole::Maintainer,
+        TokenScope::Trigger,
+    )
+    .await?;
+
+    let mut request = QueueRequest::new(QueueRequestKind::CancelJobset, jobset.id);
+
+    request.add_to_db(&state.db).await?;
+
+    info!("{} cancelled jobset: {}", caller.user.name, jobset_id);
+
+    Ok((StatusCode::ACCEPTED, Json(request)))
+}
@@ -0,0 +1,246 @@
+use std::sync::Arc;
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+use crate::{
+    hydracore::{project_role, DBError},
+    models::{Project, ProjectRole, TokenScope},
+    state::State as AppState,
+};
+
+use super::auth::{caller_can_view, request_caller, Caller};
+
+mod evaluations;
+mod jobs;
+mod jobsets;
+mod projects;
+mod queue;
+
+/// How many items a page has if the client does not ask for a limit
+const DEFAULT_LIMIT: i64 = 50;
+const MAX_LIMIT: i64 = 500;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "ladon",
+        version = "1",
+        description = "Projects, jobsets and what their evaluations built"
+    ),
+    servers((url = "/api/v1")),
+    paths(
+        projects::list_projects,
+        projects::create_project,
+        projects::get_project,
+        projects::update_project,
+        projects::delete_project,
+        jobsets::list_jobsets,
+        jobsets::create_jobset,
+        jobsets::get_jobset,
+        jobsets::update_jobset,
+        jobsets::delete_jobset,
+        jobsets::trigger_jobset,
+        jobsets::cancel_jobset,
+        evaluations::list_evaluations,
+        evaluations::get_evaluation,
+        evaluations::list_evaluation_jobs,
+        jobs::list_jobs,
+        jobs::get_job,
+        jobs::get_job_log,
+        jobs::restart_job,
+        queue::get_queue,
+    )
+)]
+struct ApiDoc;
+
+/// The versioned json api. Paths are documented in `/api/v1/openapi.json`.
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/openapi.json", get(openapi))
+        .merge(projects::router())
+        .merge(jobsets::router())
+        .merge(evaluations::router())
+        .merge(jobs::router())
+        .merge(queue::router())
+        .fallback(|| async { ApiError::not_found("No such endpoint") })
+}
+
+async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// What every failed request returns
+#[derive(Serialize, ToSchema)]
+pub struct ErrorBody {
+    error: String,
+}
+
+pub struct ApiError {
+    status: StatusCode,
+    error: String,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, error: &str) -> Self {
+        ApiError {
+            status,
+            error: error.to_string(),
+        }
+    }
+
+    pub fn bad_request(error: &str) -> Self {
+        ApiError::new(StatusCode::BAD_REQUEST, error)
+    }
+
+    pub fn not_found(error: &str) -> Self {
+        ApiError::new(StatusCode::NOT_FOUND, error)
+    }
+}
+
+impl From<DBError> for ApiError {
+    fn from(e: DBError) -> Self {
+        error!("Database error in api request: {}", e);
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Database error")
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(ErrorBody { error: self.error })).into_response()
+    }
+}
+
+pub type ApiResult<T> = Result<T, ApiError>;
+
+/// The logged in user or the user of the api token, handlers which change something take it to turn away anonymous requests
+pub struct Authenticated(pub Caller);
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for Authenticated {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        match request_caller(state, &parts.headers).await? {
+            Some(caller) => Ok(Authenticated(caller)),
+            None => Err(ApiError::new(StatusCode::UNAUTHORIZED, "Login required")),
+        }
+    }
+}
+
+/// The logged in user, if there is one. Handlers which only read take it to hide private projects.
+pub struct CurrentUser(pub Option<Caller>);
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for CurrentUser {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(CurrentUser(request_caller(state, &parts.headers).await?))
+    }
+}
+
+/// Answers like for a missing project if the caller may not see it, so private projects cannot be found by trying ids
+pub async fn check_view(
+    state: &AppState,
+    caller: Option<&Caller>,
+    project: &Project,
+) -> ApiResult<()> {
+    if !caller_can_view(state, caller, project).await? {
+        return Err(ApiError::not_found("Failed to find project!"));
+    }
+
+    Ok(())
+}
+
+/// Turns away callers with a lower role in the project or a token which does not allow the scope
+pub async fn check_role(
+    state: &AppState,
+    caller: &Caller,
+    project_id: i32,
+    role: ProjectRole,
+    scope: TokenScope,
+) -> ApiResult<()> {
+    if !caller.allows(scope, Some(project_id)) {
+        return Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            &format!("The token does not have the {} scope!", scope),
+        ));
+    }
+
+    if project_role(&state.db, Some(&caller.user), project_id).await? < Some(role) {
+        return Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            &format!("You have to be {} of the project!", role),
+        ));
+    }
+
+    Ok(())
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct Pagination {
+    /// How many items to skip
+    offset: Option<i64>,
+    /// How many items to return, at most 500
+    limit: Option<i64>,
+}
+
+impl Pagination {
+    pub fn offset(&self) -> i64 {
+        self.offset.unwrap_or(0).max(0)
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+}
+
+/// A part of a list, `total` is the length of the whole list
+#[derive(Serialize, ToSchema)]
+pub struct Page<T> {
+    items: Vec<T>,
+    total: i64,
+    offset: i64,
+    limit: i64,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>, total: i64, pagination: &Pagination) -> Self {
+        Page {
+            items,
+            total,
+            offset: pagination.offset(),
+            limit: pagination.limit(),
+        }
+    }
+
+    /// Pages through a list which was loaded entirely
+    pub fn from_all(all: Vec<T>, pagination: &Pagination) -> Self {
+        let total = all.len() as i64;
+
+        let items = all
+            .into_iter()
+            .skip(pagination.offset() as usize)
+            .take(pagination.limit() as usize)
+            .collect();
+
+        Page::new(items, total, pagination)
+    }
+}
@@ -0,0 +1,250 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    hydracore::delete_project as delete_project_data,
+    models::{DeletionReport, Project, ProjectRole, TokenScope},
+    server::auth::Caller,
+    state::State as AppState,
+};
+
+use super::{
+    check_role, check_view, ApiError, ApiResult, Authenticated, CurrentUser, ErrorBody, Page,
+    Pagination,
+};
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/projects", get(list_projects).post(create_project))
+        .route(
+            "/projects/:project_id",
+            get(get_project).put(update_project).delete(delete_project),
+        )
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ProjectFilter {
+    /// Part of the project name, case insensitive
+    name: Option<String>,
+}
+
+/// What clients send to create or change a project
+#[derive(Deserialize, ToSchema)]
+pub struct ProjectInput {
+    name: String,
+    description: String,
+    /// Serve the outputs of the project's jobs from the binary cache
+    #[serde(default)]
+    binary_cache: bool,
+    /// Only members and admins can see the project
+    #[serde(default)]
+    private: bool,
+}
+
+impl ProjectInput {
+    fn validate(&self) -> ApiResult<()> {
+        if self.name.is_empty() {
+            return Err(ApiError::bad_request("Name cannot be empty!"));
+        }
+
+        if self.description.is_empty() {
+            return Err(ApiError::bad_request("Description cannot be empty!"));
+        }
+
+        Ok(())
+    }
+}
+
+/// The project, if the user may see it
+pub async fn find_project(
+    state: &AppState,
+    caller: Option<&Caller>,
+    project_id: i32,
+) -> ApiResult<Project> {
+    let project = Project::get_single(&state.db, project_id)
+        .await?
+        .ok_or(ApiError::not_found("Failed to find project!"))?;
+
+    check_view(state, caller, &project).await?;
+
+    Ok(project)
+}
+
+#[utoipa::path(
+    get,
+    path = "/projects",
+    tag = "projects",
+    params(Pagination, ProjectFilter),
+    responses((status = 200, body = Page<Project>))
+)]
+pub async fn list_projects(
+    State(state): State<Arc<AppState>>,
+    CurrentUser(caller): CurrentUser,
+    Query(pagination): Query<Pagination>,
+    Query(filter): Query<ProjectFilter>,
+) -> ApiResult<Json<Page<Project>>> {
+    let mut projects =
+        Project::get_visible(&state.db, caller.as_ref().map(|caller| &caller.user)).await?;
+
+    if let Some(project_id) = caller.as_ref().and_then(Caller::project_id) {
+        projects.retain(|project| project.id == Some(project_id));
+    }
+
+    if let Some(name) = filter.name {
+        let name = name.to_lowercase();
+        projects.retain(|project| project.name.to_lowercase().contains(&name));
+    }
+
+    Ok(Json(Page::from_all(projects, &pagination)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/projects",
+    tag = "projects",
+    request_body = ProjectInput,
+    responses(
+        (status = 201, body = Project),
+        (status = 400, body = ErrorBody),
+        (status = 401, description = "Nobody is logged in", body = ErrorBody),
+        (status = 403, description = "Only admins may create projects", body = ErrorBody),
+    )
+)]
+pub async fn create_project(
+    State(state): State<Arc<AppState>>,
+    Authenticated(caller): Authenticated,
+    Json(input): Json<ProjectInput>,
+) -> ApiResult<(StatusCode, Json<Project>)> {
+    if !caller.user.admin {
+        return Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            "Only admins may create projects!",
+        ));
+    }
+
+    if !caller.allows(TokenScope::Admin, None) {
+        return Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            "The token may not create projects!",
+        ));
+    }
+
+    input.validate()?;
+
+    let mut project = Project {
+        id: None,
+        name: input.name,
+        description: input.description,
+        binary_cache: input.binary_cache,
+        private: input.private,
+    };
+
+    project.add_to_db(&state.db).await?;
+
+    Ok((StatusCode::CREATED, Json(project)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/projects/{project_id}",
+    tag = "projects",
+    params(("project_id" = i32, Path)),
+    responses(
+        (status = 200, body = Project),
+        (status = 404, body = ErrorBody),
+    )
+)]
+pub async fn get_project(
+    State(state): State<Arc<AppState>>,
+    CurrentUser(caller): CurrentUser,
+    Path(project_id): Path<i32>,
+) -> ApiResult<Json<Project>> {
+    Ok(Json(
+        find_project(&state, caller.as_ref(), project_id).await?,
+    ))
+}
+
+#[utoipa::path(
+    put,
+    path = "/projects/{project_id}",
+    tag = "projects",
+    params(("project_id" = i32, Path)),
+    request_body = ProjectInput,
+    responses(
+        (status = 200, body = Project),
+        (status = 400, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 401, description = "Nobody is logged in", body = ErrorBody),
+        (status = 403, description = "The user's role in the project or the token's scope is too low", body = ErrorBody),
+    )
+)]
+pub async fn update_project(
+    State(state): State<Arc<AppState>>,
+    Authenticated(caller): Authenticated,
+    Path(project_id): Path<i32>,
+    Json(input): Json<ProjectInput>,
+) -> ApiResult<Json<Project>> {
+    let mut project = find_project(&state, Some(&caller), project_id).await?;
+
+    check_role(
+        &state,
+        &caller,
+        project_id,
+        ProjectRole::Owner,
+        TokenScope::Admin,
+    )
+    .await?;
+
+    input.validate()?;
+
+    project.name = input.name;
+    project.description = input.description;
+    project.binary_cache = input.binary_cache;
+    project.private = input.private;
+
+    project.update(&state.db).await?;
+
+    Ok(Json(project))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/projects/{project_id}",
+    tag = "projects",
+    params(("project_id" = i32, Path)),
+    responses(
+        (status = 200, description = "What was deleted with the project", body = DeletionReport),
+        (status = 404, body = ErrorBody),
+        (status = 401, description = "Nobody is logged in", body = ErrorBody),
+        (status = 403, description = "The user's role in the project or the token's scope is too low", body = ErrorBody),
+    )
+)]
+pub async fn delete_project(
+    State(state): State<Arc<AppState>>,
+    Authenticated(caller): Authenticated,
+    Path(project_id): Path<i32>,
+) -> ApiResult<Json<DeletionReport>> {
+    let project = find_project(&state, Some(&caller), project_id).await?;
+
+    check_role(
+        &state,
+        &caller,
+        project_id,
+        ProjectRole::Owner,
+        TokenScope::Admin,
+    )
+    .await?;
+
+    let report = delete_project_data(&state.db, &state.data_dir, &project).await?;
+
+    Ok(Json(report))
+}
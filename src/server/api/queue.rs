@@ -0,0 +1,95 @@
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    sync::Arc,
+};
+
+use axum::{extract::State, routing::get, Json, Router};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    models::{Evaluation, Job, Jobset, Project, QueueRequest},
+    server::auth::Caller,
+    state::State as AppState,
+};
+
+use super::{ApiResult, CurrentUser};
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new().route("/queue", get(get_queue))
+}
+
+/// What the queue runner still has to do
+#[derive(Serialize, ToSchema)]
+pub struct Queue {
+    /// Requests the queue runner did not pick up yet
+    requests: Vec<QueueRequest>,
+    /// Jobs which are queued or being built
+    builds: Vec<Job>,
+}
+
+/// The jobsets of the projects the caller may see
+async fn visible_jobsets(state: &AppState, caller: Option<&Caller>) -> ApiResult<HashSet<i32>> {
+    let mut jobsets = HashSet::new();
+
+    let mut projects = Project::get_visible(&state.db, caller.map(|caller| &caller.user)).await?;
+
+    if let Some(project_id) = caller.and_then(Caller::project_id) {
+        projects.retain(|project| project.id == Some(project_id));
+    }
+
+    for project in projects {
+        let project_jobsets = Jobset::get_all(&state.db, project.id.unwrap()).await?;
+
+        jobsets.extend(project_jobsets.into_iter().filter_map(|jobset| jobset.id));
+    }
+
+    Ok(jobsets)
+}
+
+#[utoipa::path(
+    get,
+    path = "/queue",
+    tag = "queue",
+    responses((status = 200, description = "Only what belongs to projects the user may see", body = Queue))
+)]
+pub async fn get_queue(
+    State(state): State<Arc<AppState>>,
+    CurrentUser(caller): CurrentUser,
+) -> ApiResult<Json<Queue>> {
+    let mut requests = QueueRequest::get_pending(&state.db).await?;
+    let mut builds = Job::get_unfinished(&state.db).await?;
+
+    let sees_all = caller
+        .as_ref()
+        .is_some_and(|caller| caller.user.admin && caller.project_id().is_none());
+
+    if !sees_all {
+        let visible = visible_jobsets(&state, caller.as_ref()).await?;
+
+        requests.retain(|request| request.jobset_id.is_none_or(|id| visible.contains(&id)));
+
+        // jobs only know their evaluation, most of the queue shares a few of them
+        let mut evaluation_jobsets = HashMap::new();
+        let mut visible_builds = Vec::new();
+
+        for build in builds {
+            let jobset_id = match evaluation_jobsets.entry(build.evaluation_id) {
+                Entry::Occupied(entry) => *entry.get(),
+                Entry::Vacant(entry) => {
+                    let evaluation = Evaluation::get_single(&state.db, build.evaluation_id).await?;
+
+                    *entry.insert(evaluation.map(|evaluation| evaluation.jobset_id))
+                }
+            };
+
+            if jobset_id.is_some_and(|id| visible.contains(&id)) {
+                visible_builds.push(build);
+            }
+        }
+
+        builds = visible_builds;
+    }
+
+    Ok(Json(Queue { requests, builds }))
+}
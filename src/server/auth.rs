@@ -0,0 +1,325 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{
+        header::{AUTHORIZATION, COOKIE},
+        HeaderMap, StatusCode,
+    },
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+    Json,
+};
+use chrono::{TimeDelta, Utc};
+use leptos::prelude::*;
+use leptos_axum::ResponseOptions;
+use serde_json::json;
+use tracing::error;
+
+use crate::{
+    hydracore::{can_view_project, hash_token, new_token, project_role, DBError},
+    models::{ApiToken, Project, ProjectRole, Session, TokenScope, User},
+    state::State as AppState,
+};
+
+pub const SESSION_COOKIE: &str = "ladon_session";
+
+pub fn session_lifetime() -> TimeDelta {
+    TimeDelta::days(30)
+}
+
+/// Paths which have to work without logging in, even with `--require-login`.
+/// Nix clients cannot log in, projects and jobsets opt into the binary cache and channels.
+const PUBLIC_PATHS: &[&str] = &[
+    "/login",
+    "/api/login",
+    "/api/current_user",
+    "/api/login_options",
+    "/oidc/",
+    "/webhooks/",
+    "/pkg/",
+    "/favicon.ico",
+    "/cache/",
+    "/channel/",
+    "/metrics",
+];
+
+/// The token of the session cookie, if the request has one
+pub fn session_token(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|cookie| cookie.to_str().ok())
+        .flat_map(|cookie| cookie.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, token)| token.to_string())
+}
+
+/// `SameSite=Lax` keeps other sites from sending forms with the session
+pub fn session_cookie(token: &str) -> String {
+    format!(
+        "{}={}; Path=/; HttpOnly; SameSite=Lax; Max-Age={}",
+        SESSION_COOKIE,
+        token,
+        session_lifetime().num_seconds()
+    )
+}
+
+pub fn removed_session_cookie() -> String {
+    format!(
+        "{}=; Path=/; HttpOnly; SameSite=Lax; Max-Age=0",
+        SESSION_COOKIE
+    )
+}
+
+/// Logs the user in, returns the `Set-Cookie` header of the new session
+pub async fn start_session(state: &AppState, user: &User) -> Result<String, DBError> {
+    if let Err(e) = Session::delete_expired(&state.db).await {
+        error!("Failed to delete expired sessions: {}", e.to_string());
+    }
+
+    let token = new_token();
+
+    let session = Session::new(
+        hash_token(&token),
+        user.id.unwrap(),
+        Utc::now() + session_lifetime(),
+    );
+
+    session.add_to_db(&state.db).await?;
+
+    Ok(session_cookie(&token))
+}
+
+/// The user whose session cookie the request carries
+pub async fn session_user(state: &AppState, headers: &HeaderMap) -> Result<Option<User>, DBError> {
+    match session_token(headers) {
+        Some(token) => Session::get_user(&state.db, &hash_token(&token)).await,
+        None => Ok(None),
+    }
+}
+
+/// The token of the `Authorization: Bearer` header, if the request has one
+pub fn bearer_token(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string())
+}
+
+/// Who sent a request which is no server function, with the api token it used instead of logging in
+pub struct Caller {
+    pub user: User,
+    pub token: Option<ApiToken>,
+}
+
+impl Caller {
+    /// Whether the token lets the request do something in the project, or outside of any project.
+    /// Requests with a session cookie may do everything their user's role allows.
+    pub fn allows(&self, scope: TokenScope, project_id: Option<i32>) -> bool {
+        match &self.token {
+            None => true,
+            Some(token) => {
+                token.scope >= scope && token.project_id.is_none_or(|id| Some(id) == project_id)
+            }
+        }
+    }
+
+    /// The only project the request may see, if its token is limited to one
+    pub fn project_id(&self) -> Option<i32> {
+        self.token.as_ref().and_then(|token| token.project_id)
+    }
+}
+
+/// The user of the api token or session cookie the request carries
+pub async fn request_caller(
+    state: &AppState,
+    headers: &HeaderMap,
+) -> Result<Option<Caller>, DBError> {
+    let token = match bearer_token(headers) {
+        Some(token) => token,
+        None => {
+            return Ok(session_user(state, headers)
+                .await?
+                .map(|user| Caller { user, token: None }))
+        }
+    };
+
+    let mut token = match ApiToken::get_valid(&state.db, &hash_token(&token)).await? {
+        Some(token) => token,
+        None => return Ok(None),
+    };
+
+    let user = match User::get_single(&state.db, token.user_id).await? {
+        Some(user) => user,
+        None => return Ok(None),
+    };
+
+    token.set_last_used(&state.db).await?;
+
+    Ok(Some(Caller {
+        user,
+        token: Some(token),
+    }))
+}
+
+/// Whether the caller may see the project, private projects only show up for their members
+pub async fn caller_can_view(
+    state: &AppState,
+    caller: Option<&Caller>,
+    project: &Project,
+) -> Result<bool, DBError> {
+    if caller.is_some_and(|caller| !caller.allows(TokenScope::Read, project.id)) {
+        return Ok(false);
+    }
+
+    can_view_project(&state.db, caller.map(|caller| &caller.user), project).await
+}
+
+/// Whether the caller of the request may see the project, for handlers which are no server functions
+pub async fn request_can_view(
+    state: &AppState,
+    headers: &HeaderMap,
+    project_id: i32,
+) -> Result<bool, DBError> {
+    let project = match Project::get_single(&state.db, project_id).await? {
+        Some(project) => project,
+        None => return Ok(true),
+    };
+
+    let caller = request_caller(state, headers).await?;
+
+    caller_can_view(state, caller.as_ref(), &project).await
+}
+
+/// Turns away anonymous requests if the instance was started with `--require-login`
+pub async fn require_login(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path();
+
+    if !state.require_login || PUBLIC_PATHS.iter().any(|public| path.starts_with(public)) {
+        return next.run(request).await;
+    }
+
+    let caller = request_caller(&state, request.headers()).await;
+
+    match caller {
+        Ok(Some(_)) => next.run(request).await,
+        Ok(None) if path.starts_with("/api/") => (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "error": "Login required" })),
+        )
+            .into_response(),
+        Ok(None) => Redirect::to("/login").into_response(),
+        Err(e) => {
+            error!("Failed to check session: {}", e.to_string());
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// The logged in user of the request a server function answers
+pub async fn current_user() -> Result<Option<User>, ServerFnError> {
+    let state: Arc<AppState> = expect_context();
+    let headers: HeaderMap = leptos_axum::extract().await?;
+
+    session_user(&state, &headers).await.map_err(|e| {
+        error!("Failed to check session: {}", e.to_string());
+        ServerFnError::new("Failed to check session!")
+    })
+}
+
+/// Fails server functions which change something if nobody is logged in
+pub async fn require_user() -> Result<User, ServerFnError> {
+    let user = current_user().await?;
+
+    if user.is_none() {
+        let response_opts: ResponseOptions = expect_context();
+        response_opts.set_status(StatusCode::UNAUTHORIZED);
+        return Err(ServerFnError::new("You have to log in first!"));
+    }
+
+    Ok(user.unwrap())
+}
+
+/// Fails server functions which only admins may call
+pub async fn require_admin() -> Result<User, ServerFnError> {
+    let user = require_user().await?;
+
+    if !user.admin {
+        let response_opts: ResponseOptions = expect_context();
+        response_opts.set_status(StatusCode::FORBIDDEN);
+        return Err(ServerFnError::new("Only admins may do this!"));
+    }
+
+    Ok(user)
+}
+
+/// The role of the logged in user in the project
+pub async fn current_role(project_id: i32) -> Result<Option<ProjectRole>, ServerFnError> {
+    let state: Arc<AppState> = expect_context();
+    let user = current_user().await?;
+
+    project_role(&state.db, user.as_ref(), project_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to get role: {}", e.to_string());
+            ServerFnError::new("Failed to check permissions!")
+        })
+}
+
+/// Fails server functions if the logged in user has a lower role in the project
+pub async fn require_role(project_id: i32, role: ProjectRole) -> Result<User, ServerFnError> {
+    let user = require_user().await?;
+
+    if current_role(project_id).await? < Some(role) {
+        let response_opts: ResponseOptions = expect_context();
+        response_opts.set_status(StatusCode::FORBIDDEN);
+        return Err(ServerFnError::new(format!(
+            "You have to be {} of the project!",
+            role
+        )));
+    }
+
+    Ok(user)
+}
+
+/// Whether the logged in user may see the project
+pub async fn can_view(project: &Project) -> Result<bool, ServerFnError> {
+    let state: Arc<AppState> = expect_context();
+    let user = current_user().await?;
+
+    can_view_project(&state.db, user.as_ref(), project)
+        .await
+        .map_err(|e| {
+            error!("Failed to get role: {}", e.to_string());
+            ServerFnError::new("Failed to check permissions!")
+        })
+}
+
+/// Fails server functions reading the project if the logged in user may not see it.
+/// Answers like for a missing project, so private projects cannot be found by trying ids.
+pub async fn require_view(project_id: i32) -> Result<(), ServerFnError> {
+    let state: Arc<AppState> = expect_context();
+
+    let project = Project::get_single(&state.db, project_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch project: {}", e.to_string());
+            ServerFnError::new("Failed to check permissions!")
+        })?;
+
+    match project {
+        Some(project) if !can_view(&project).await? => {
+            let response_opts: ResponseOptions = expect_context();
+            response_opts.set_status(StatusCode::NOT_FOUND);
+            Err(ServerFnError::new("Failed to find project!"))
+        }
+        _ => Ok(()),
+    }
+}
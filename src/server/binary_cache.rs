@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use async_compression::tokio::bufread::ZstdEncoder;
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use tokio::io::BufReader;
+use tokio_util::io::ReaderStream;
+use tracing::{debug, error};
+
+use crate::{
+    hydracore::{dump_path, fingerprint, store_path_hash, store_path_name, KeyStore, PathInfo},
+    models::JobPath,
+    state::State as AppState,
+};
+
+/// Nix binary cache protocol, serving the closures of successful jobs
+/// of the projects which have the binary cache enabled.
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/nix-cache-info", get(cache_info))
+        .route("/:file", get(narinfo))
+        .route("/nar/:file", get(nar))
+}
+
+async fn cache_info() -> impl IntoResponse {
+    (
+        [(CONTENT_TYPE, "text/x-nix-cache-info")],
+        "StoreDir: /nix/store\nWantMassQuery: 1\nPriority: 40\n",
+    )
+}
+
+/// Looks up a path by its hash, only paths known to the binary cache are found
+async fn find_path(state: &AppState, hash: &str) -> Result<JobPath, StatusCode> {
+    // store path hashes are 32 characters of nix base32
+    if hash.len() != 32 || !hash.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let path = JobPath::get_cached(&state.db, hash).await.map_err(|e| {
+        error!("Failed to look up store path: {}", e.to_string());
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    path.ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn narinfo(
+    State(state): State<Arc<AppState>>,
+    Path(file): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let hash = file.strip_suffix(".narinfo").ok_or(StatusCode::NOT_FOUND)?;
+
+    let path = find_path(&state, hash).await?;
+
+    debug!("Serving narinfo of {}", path.path);
+
+    let info = PathInfo::query(&path.path).await.map_err(|e| {
+        error!("Failed to query path info of {}: {}", path.path, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let mut narinfo = render_narinfo(&info);
+
+    // keys are read on every request, so rotating them doesn't need a restart
+    let keys = KeyStore::new(&state.data_dir).active().map_err(|e| {
+        error!("Failed to load signing keys: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let fingerprint = fingerprint(&info.path, &info.nar_hash, info.nar_size, &info.references);
+
+    for key in keys.iter() {
+        narinfo += &format!("Sig: {}\n", key.sign(&fingerprint));
+    }
+
+    Ok(([(CONTENT_TYPE, "text/x-nix-narinfo")], narinfo))
+}
+
+fn render_narinfo(info: &PathInfo) -> String {
+    let references: Vec<&str> = info
+        .references
+        .iter()
+        .map(|reference| store_path_name(reference))
+        .collect();
+
+    let mut narinfo = format!(
+        "StorePath: {}\nURL: nar/{}.nar.zst\nCompression: zstd\nNarHash: {}\nNarSize: {}\nReferences: {}\n",
+        info.path,
+        store_path_hash(&info.path),
+        info.nar_hash,
+        info.nar_size,
+        references.join(" "),
+    );
+
+    if let Some(deriver) = &info.deriver {
+        narinfo += &format!("Deriver: {}\n", store_path_name(deriver));
+    }
+
+    narinfo
+}
+
+/// Streams the nar of the path, compressed on the fly
+async fn nar(
+    State(state): State<Arc<AppState>>,
+    Path(file): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let hash = file.strip_suffix(".nar.zst").ok_or(StatusCode::NOT_FOUND)?;
+
+    let path = find_path(&state, hash).await?;
+
+    debug!("Serving nar of {}", path.path);
+
+    let stdout = dump_path(&path.path).map_err(|e| {
+        error!("Failed to dump {}: {}", path.path, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let stream = ReaderStream::new(ZstdEncoder::new(BufReader::new(stdout)));
+
+    Ok((
+        [(CONTENT_TYPE, "application/x-nix-nar")],
+        Body::from_stream(stream),
+    ))
+}
@@ -0,0 +1,164 @@
+use std::{path::PathBuf, sync::Arc};
+
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{
+        header::{CONTENT_LENGTH, CONTENT_SECURITY_POLICY, CONTENT_TYPE, X_CONTENT_TYPE_OPTIONS},
+        HeaderMap, StatusCode,
+    },
+    response::{IntoResponse, Redirect, Response},
+    routing::get,
+    Router,
+};
+use tokio_util::io::ReaderStream;
+use tracing::error;
+
+use crate::{models::BuildProduct, state::State as AppState};
+
+use super::auth::request_can_view;
+
+/// Downloads of the build products of a job, streamed from the store.
+/// The parameter names have to match the ones of the leptos routes.
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            "/project/:proj-id/jobset/:jobset-id/job/:job-id/download/:product-id",
+            get(product),
+        )
+        .route(
+            "/project/:proj-id/jobset/:jobset-id/job/:job-id/download/:product-id/*path",
+            get(product_file),
+        )
+}
+
+fn parse_id(id: &str) -> Result<i32, StatusCode> {
+    id.parse::<i32>().map_err(|_| StatusCode::BAD_REQUEST)
+}
+
+/// The product, if the user of the request may see its project
+async fn find_product(
+    state: &AppState,
+    headers: &HeaderMap,
+    (project_id, jobset_id, job_id, product_id): (String, String, String, String),
+) -> Result<BuildProduct, StatusCode> {
+    let project_id = parse_id(&project_id)?;
+
+    let visible = request_can_view(state, headers, project_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to check session: {}", e.to_string());
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    if !visible {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    BuildProduct::get_single(
+        &state.db,
+        project_id,
+        parse_id(&jobset_id)?,
+        parse_id(&job_id)?,
+        parse_id(&product_id)?,
+    )
+    .await
+    .map_err(|e| {
+        error!("Failed to get build product: {}", e.to_string());
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// A file product, or the default path of a directory product
+async fn product(
+    State(state): State<Arc<AppState>>,
+    Path(params): Path<(String, String, String, String)>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let product = find_product(&state, &headers, params.clone()).await?;
+
+    if !product.is_directory() {
+        return serve_file(PathBuf::from(&product.path)).await;
+    }
+
+    let default_path = product.default_path.ok_or(StatusCode::NOT_FOUND)?;
+
+    // relative links inside the directory only work below the product's url
+    Ok(Redirect::temporary(&format!(
+        "/project/{}/jobset/{}/job/{}/download/{}/{}",
+        params.0, params.1, params.2, params.3, default_path
+    ))
+    .into_response())
+}
+
+/// A file inside a directory product. For file products the path is only the file name,
+/// so browsers save the download under it.
+async fn product_file(
+    State(state): State<Arc<AppState>>,
+    Path((project_id, jobset_id, job_id, product_id, path)): Path<(
+        String,
+        String,
+        String,
+        String,
+        String,
+    )>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let product = find_product(
+        &state,
+        &headers,
+        (project_id, jobset_id, job_id, product_id),
+    )
+    .await?;
+
+    if !product.is_directory() {
+        return serve_file(PathBuf::from(&product.path)).await;
+    }
+
+    let dir = PathBuf::from(&product.path);
+
+    let file = dir
+        .join(path.trim_start_matches('/'))
+        .canonicalize()
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    // neither `..` nor symlinks may lead out of the product
+    if !file.starts_with(&dir) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    serve_file(file).await
+}
+
+async fn serve_file(path: PathBuf) -> Result<Response, StatusCode> {
+    // the product is gone if its outputs were garbage collected
+    let file = tokio::fs::File::open(&path)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    let metadata = file.metadata().await.map_err(|e| {
+        error!("Failed to stat {}: {}", path.display(), e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    if !metadata.is_file() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let content_type = mime_guess::from_path(&path)
+        .first_or_octet_stream()
+        .to_string();
+
+    // build outputs are untrusted, html and svg must not run scripts on the origin of the session cookie
+    Ok((
+        [
+            (CONTENT_TYPE, content_type),
+            (CONTENT_LENGTH, metadata.len().to_string()),
+            (CONTENT_SECURITY_POLICY, "sandbox".to_string()),
+            (X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+        ],
+        Body::from_stream(ReaderStream::new(file)),
+    )
+        .into_response())
+}
@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header::CONTENT_TYPE, HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use tokio_util::io::ReaderStream;
+use tracing::error;
+
+use crate::{models::ChannelRevision, state::State as AppState};
+
+use super::auth::request_can_view;
+
+/// The files `nix-channel` downloads, for the channel head and for every older release.
+/// Subscribe with `nix-channel --add <url>/channel/<project id>/<jobset id>`.
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/:proj-id/:jobset-id/:file", get(head_file))
+        .route(
+            "/:proj-id/:jobset-id/revision/:revision-id/:file",
+            get(revision_file),
+        )
+}
+
+fn parse_id(id: &str) -> Result<i32, StatusCode> {
+    id.parse::<i32>().map_err(|_| StatusCode::BAD_REQUEST)
+}
+
+/// Channels of private projects look like missing ones to those who may not see them
+async fn check_visible(
+    state: &AppState,
+    headers: &HeaderMap,
+    project_id: i32,
+) -> Result<(), StatusCode> {
+    let visible = request_can_view(state, headers, project_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to check session: {}", e.to_string());
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    if !visible {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(())
+}
+
+async fn head_file(
+    State(state): State<Arc<AppState>>,
+    Path((project_id, jobset_id, file)): Path<(String, String, String)>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, StatusCode> {
+    let project_id = parse_id(&project_id)?;
+
+    check_visible(&state, &headers, project_id).await?;
+
+    let revision = ChannelRevision::get_head(&state.db, project_id, parse_id(&jobset_id)?)
+        .await
+        .map_err(|e| {
+            error!("Failed to get channel head: {}", e.to_string());
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    serve_file(&state, &revision, &file).await
+}
+
+async fn revision_file(
+    State(state): State<Arc<AppState>>,
+    Path((project_id, jobset_id, revision_id, file)): Path<(String, String, String, String)>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, StatusCode> {
+    let project_id = parse_id(&project_id)?;
+
+    check_visible(&state, &headers, project_id).await?;
+
+    let revision = ChannelRevision::get_single(
+        &state.db,
+        project_id,
+        parse_id(&jobset_id)?,
+        parse_id(&revision_id)?,
+    )
+    .await
+    .map_err(|e| {
+        error!("Failed to get channel revision: {}", e.to_string());
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    serve_file(&state, &revision, &file).await
+}
+
+async fn serve_file(
+    state: &AppState,
+    revision: &ChannelRevision,
+    file: &str,
+) -> Result<impl IntoResponse, StatusCode> {
+    let content_type = match file {
+        "nixexprs.tar.xz" => "application/x-xz",
+        "binary-cache-url" => "text/plain",
+        _ => return Err(StatusCode::NOT_FOUND),
+    };
+
+    let path = state
+        .data_dir
+        .channel_revision(revision.jobset_id, &revision.name)
+        .join(file);
+
+    let file = tokio::fs::File::open(&path).await.map_err(|e| {
+        error!("Failed to open {}: {}", path.display(), e);
+        StatusCode::NOT_FOUND
+    })?;
+
+    Ok((
+        [(CONTENT_TYPE, content_type)],
+        Body::from_stream(ReaderStream::new(file)),
+    ))
+}
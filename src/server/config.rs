@@ -0,0 +1,114 @@
+use std::{fmt, path::Path};
+
+use serde::Deserialize;
+
+use crate::models::{Forge, ProjectRole};
+
+/// The `--config` file
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Login through an OpenID Connect provider
+    pub oidc: Option<OidcConfig>,
+    /// Secrets of the push webhooks, forges without one cannot trigger anything
+    #[serde(default)]
+    pub webhooks: WebhookConfig,
+    /// Forges the queue runner posts commit statuses to, per project
+    #[serde(default)]
+    pub statuses: Vec<StatusConfig>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct OidcConfig {
+    /// Shown on the login button
+    #[serde(default = "default_provider_name")]
+    pub name: String,
+    /// The provider's issuer url, its configuration is discovered under `/.well-known/openid-configuration`
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    /// Defaults to `/oidc/callback` under `--public-url`
+    pub redirect_url: Option<String>,
+    /// Requested besides `openid`
+    #[serde(default = "default_scopes")]
+    pub scopes: Vec<String>,
+    /// The claim of the id token which lists the user's groups
+    #[serde(default = "default_groups_claim")]
+    pub groups_claim: String,
+    /// Members of these groups are admins
+    #[serde(default)]
+    pub admin_groups: Vec<String>,
+    /// Project roles of group members, set again on every login
+    #[serde(default)]
+    pub roles: Vec<OidcRole>,
+}
+
+#[derive(Deserialize, Default, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    /// Signs the payloads of `/webhooks/github`
+    pub github_secret: Option<String>,
+    /// Signs the payloads of `/webhooks/gitea`, for Gitea and Forgejo
+    pub gitea_secret: Option<String>,
+    /// Sent as `X-Gitlab-Token` to `/webhooks/gitlab`
+    pub gitlab_token: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct OidcRole {
+    pub group: String,
+    /// Name of the project
+    pub project: String,
+    pub role: ProjectRole,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct StatusConfig {
+    /// Name of the project
+    pub project: String,
+    pub forge: Forge,
+    /// Root of the forge's api, defaults to the one on the host of the jobset's flake
+    pub api_url: Option<String>,
+    /// Needs to be allowed to set commit statuses of the repositories
+    pub token: String,
+}
+
+fn default_provider_name() -> String {
+    "single sign-on".to_string()
+}
+
+fn default_scopes() -> Vec<String> {
+    vec!["profile".to_string(), "email".to_string()]
+}
+
+fn default_groups_claim() -> String {
+    "groups".to_string()
+}
+
+#[derive(Debug)]
+pub struct ConfigError {
+    error: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config, ConfigError> {
+        let content = std::fs::read_to_string(path).map_err(|e| ConfigError {
+            error: format!("Failed to read {}: {}", path.display(), e),
+        })?;
+
+        toml::from_str(&content).map_err(|e| ConfigError {
+            error: format!("Failed to parse {}: {}", path.display(), e),
+        })
+    }
+}
@@ -0,0 +1,436 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::Arc,
+};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    routing::get,
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::error;
+
+use crate::{
+    hydracore::{store_path_name, DBError},
+    models::{
+        BuildProduct, Evaluation, Job, JobOrigin, JobPath, JobState, Jobset, Project, TokenScope,
+    },
+    state::State as AppState,
+};
+
+use super::{
+    auth::{request_caller, request_can_view},
+    wants_json,
+};
+
+/// Hydra shows this many evaluations per page
+const EVALS_PER_PAGE: i64 = 20;
+const MAX_LATEST_BUILDS: i64 = 500;
+
+/// Hydra's json endpoints, so tools written for Hydra keep working.
+/// Without `Accept: application/json` they redirect to the matching ladon page.
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/build/:id", get(build))
+        .route("/eval/:id", get(eval))
+        .route("/jobset/:project/:jobset/evals", get(evals))
+        .route("/api/latestbuilds", get(latest_builds))
+}
+
+fn db_error(e: DBError) -> StatusCode {
+    error!("Failed to answer hydra api request: {}", e.to_string());
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+fn timestamp(time: Option<DateTime<Utc>>) -> Option<i64> {
+    time.map(|time| time.timestamp())
+}
+
+/// The name of the derivation without its hash and `.drv`, like `hello-2.12`
+fn nix_name(derivation_path: &str) -> &str {
+    let name = store_path_name(derivation_path);
+    let name = name.strip_suffix(".drv").unwrap_or(name);
+
+    name.split_once('-').map(|(_, name)| name).unwrap_or(name)
+}
+
+/// Outputs other than `out` append their name to the one of the derivation
+fn output_name(nix_name: &str, output_path: &str) -> String {
+    let name = store_path_name(output_path);
+    let name = name.split_once('-').map(|(_, name)| name).unwrap_or(name);
+
+    if name == nix_name {
+        return "out".to_string();
+    }
+
+    name.strip_prefix(nix_name)
+        .and_then(|name| name.strip_prefix('-'))
+        .unwrap_or(name)
+        .to_string()
+}
+
+/// Flake jobs name their system last, like `hello.x86_64-linux`
+fn system(attribute_name: &str) -> Option<String> {
+    attribute_name
+        .rsplit_once('.')
+        .map(|(_, system)| system)
+        .filter(|system| system.contains('-'))
+        .map(|system| system.to_string())
+}
+
+#[derive(Serialize)]
+struct HydraOutput {
+    path: String,
+}
+
+#[derive(Serialize)]
+struct HydraProduct {
+    r#type: String,
+    subtype: String,
+    name: String,
+    path: String,
+    defaultpath: Option<String>,
+    filesize: Option<i64>,
+    sha256hash: Option<String>,
+}
+
+impl From<BuildProduct> for HydraProduct {
+    fn from(product: BuildProduct) -> Self {
+        HydraProduct {
+            r#type: product.r#type,
+            subtype: product.subtype,
+            name: product.name,
+            path: product.path,
+            defaultpath: product.default_path,
+            filesize: product.file_size,
+            sha256hash: product.sha256,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct HydraBuild {
+    id: i32,
+    project: String,
+    jobset: String,
+    job: String,
+    nixname: String,
+    system: Option<String>,
+    drvpath: String,
+    priority: i32,
+    finished: i32,
+    /// 0 for success and 1 for failure, not set while the build is unfinished
+    buildstatus: Option<i32>,
+    timestamp: Option<i64>,
+    /// ladon does not record when builds start
+    starttime: Option<i64>,
+    stoptime: Option<i64>,
+    releasename: Option<String>,
+    jobsetevals: Vec<i32>,
+    buildoutputs: BTreeMap<String, HydraOutput>,
+    buildproducts: BTreeMap<String, HydraProduct>,
+    buildmetrics: BTreeMap<String, Value>,
+}
+
+impl HydraBuild {
+    async fn load(state: &AppState, origin: JobOrigin) -> Result<Self, DBError> {
+        let job = origin.job;
+        let id = job.id.unwrap();
+        let nixname = nix_name(&job.derivation_path).to_string();
+
+        let buildoutputs = JobPath::get_outputs(&state.db, id)
+            .await?
+            .into_iter()
+            .map(|output| {
+                (
+                    output_name(&nixname, &output.path),
+                    HydraOutput { path: output.path },
+                )
+            })
+            .collect();
+
+        let buildproducts = BuildProduct::get_all(&state.db, id)
+            .await?
+            .into_iter()
+            .map(|product| (product.id.unwrap().to_string(), product.into()))
+            .collect();
+
+        let buildstatus = match job.state {
+            JobState::Done => Some(0),
+            JobState::Failed => Some(1),
+            JobState::ToBeBuilt | JobState::Building | JobState::Waiting => None,
+        };
+
+        Ok(HydraBuild {
+            id,
+            project: origin.project_name,
+            jobset: origin.jobset_name,
+            system: system(&job.attribute_name),
+            job: job.attribute_name,
+            nixname,
+            drvpath: job.derivation_path,
+            priority: 100,
+            finished: buildstatus.is_some() as i32,
+            buildstatus,
+            timestamp: timestamp(origin.evaluated),
+            starttime: None,
+            stoptime: timestamp(job.finished),
+            releasename: None,
+            jobsetevals: vec![job.evaluation_id],
+            buildoutputs,
+            buildproducts,
+            buildmetrics: BTreeMap::new(),
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct HydraEval {
+    id: i32,
+    timestamp: Option<i64>,
+    /// ladon does not record how long single evaluations took
+    checkouttime: i32,
+    evaltime: i32,
+    hasnewbuilds: i32,
+    builds: Vec<i32>,
+    /// Flake evaluations have no inputs, the locked flake is in `flake`
+    jobsetevalinputs: BTreeMap<String, Value>,
+    flake: Option<String>,
+}
+
+impl HydraEval {
+    async fn load(state: &AppState, evaluation: Evaluation) -> Result<Self, DBError> {
+        let id = evaluation.id.unwrap();
+
+        let builds: Vec<i32> = Job::get_by_evaluation(&state.db, id)
+            .await?
+            .into_iter()
+            .filter_map(|job| job.id)
+            .collect();
+
+        Ok(HydraEval {
+            id,
+            timestamp: timestamp(evaluation.created),
+            checkouttime: 0,
+            evaltime: 0,
+            hasnewbuilds: !builds.is_empty() as i32,
+            builds,
+            jobsetevalinputs: BTreeMap::new(),
+            flake: evaluation.flake,
+        })
+    }
+}
+
+async fn build(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let origin = Job::get_origin(&state.db, id)
+        .await
+        .map_err(db_error)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    if !request_can_view(&state, &headers, origin.project_id)
+        .await
+        .map_err(db_error)?
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    if !wants_json(&headers) {
+        return Ok(Redirect::temporary(&format!(
+            "/project/{}/jobset/{}/job/{}",
+            origin.project_id, origin.jobset_id, id
+        ))
+        .into_response());
+    }
+
+    let build = HydraBuild::load(&state, origin).await.map_err(db_error)?;
+
+    Ok(Json(build).into_response())
+}
+
+async fn eval(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let evaluation = Evaluation::get_single(&state.db, id)
+        .await
+        .map_err(db_error)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let jobset = Jobset::get_single(&state.db, evaluation.jobset_id)
+        .await
+        .map_err(db_error)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    if !request_can_view(&state, &headers, jobset.project_id)
+        .await
+        .map_err(db_error)?
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    if !wants_json(&headers) {
+        // there is no page for single evaluations
+        return Ok(Redirect::temporary(&format!(
+            "/project/{}/jobset/{}",
+            jobset.project_id, evaluation.jobset_id
+        ))
+        .into_response());
+    }
+
+    let eval = HydraEval::load(&state, evaluation)
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(eval).into_response())
+}
+
+#[derive(Deserialize)]
+struct EvalsQuery {
+    page: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct HydraEvals {
+    evals: Vec<HydraEval>,
+    first: String,
+    last: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prev: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next: Option<String>,
+}
+
+/// The evaluations of a jobset, addressed by the names of the project and jobset like Hydra does
+async fn evals(
+    State(state): State<Arc<AppState>>,
+    Path((project_name, jobset_name)): Path<(String, String)>,
+    Query(query): Query<EvalsQuery>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let project = Project::get_by_name(&state.db, &project_name)
+        .await
+        .map_err(db_error)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let project_id = project.id.unwrap();
+
+    if !request_can_view(&state, &headers, project_id)
+        .await
+        .map_err(db_error)?
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let jobset = Jobset::get_by_name(&state.db, project_id, &jobset_name)
+        .await
+        .map_err(db_error)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let jobset_id = jobset.id.unwrap();
+
+    if !wants_json(&headers) {
+        return Ok(
+            Redirect::temporary(&format!("/project/{}/jobset/{}", project_id, jobset_id))
+                .into_response(),
+        );
+    }
+
+    let page = query.page.unwrap_or(1).max(1);
+
+    let (evaluations, total) = Evaluation::get_page(
+        &state.db,
+        jobset_id,
+        (page - 1) * EVALS_PER_PAGE,
+        EVALS_PER_PAGE,
+    )
+    .await
+    .map_err(db_error)?;
+
+    let mut evals = Vec::new();
+
+    for evaluation in evaluations {
+        evals.push(
+            HydraEval::load(&state, evaluation)
+                .await
+                .map_err(db_error)?,
+        );
+    }
+
+    let last_page = ((total + EVALS_PER_PAGE - 1) / EVALS_PER_PAGE).max(1);
+
+    Ok(Json(HydraEvals {
+        evals,
+        first: "?page=1".to_string(),
+        last: format!("?page={}", last_page),
+        prev: (page > 1).then(|| format!("?page={}", page - 1)),
+        next: (page < last_page).then(|| format!("?page={}", page + 1)),
+    })
+    .into_response())
+}
+
+#[derive(Deserialize)]
+struct LatestBuildsQuery {
+    nr: Option<i64>,
+    project: Option<String>,
+    jobset: Option<String>,
+    job: Option<String>,
+    system: Option<String>,
+}
+
+/// The newest `nr` finished builds, always json like in Hydra.
+/// Builds of private projects the user may not see are left out afterwards, so there may be fewer.
+async fn latest_builds(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<LatestBuildsQuery>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<HydraBuild>>, StatusCode> {
+    let nr = query.nr.ok_or(StatusCode::BAD_REQUEST)?;
+
+    let origins = Job::get_latest_finished(
+        &state.db,
+        query.project.as_deref(),
+        query.jobset.as_deref(),
+        query.job.as_deref(),
+        query.system.as_deref(),
+        nr.clamp(0, MAX_LATEST_BUILDS),
+    )
+    .await
+    .map_err(db_error)?;
+
+    let caller = request_caller(&state, &headers).await.map_err(db_error)?;
+
+    let visible: HashSet<i32> =
+        Project::get_visible(&state.db, caller.as_ref().map(|caller| &caller.user))
+            .await
+            .map_err(db_error)?
+            .into_iter()
+            .filter_map(|project| project.id)
+            .filter(|id| {
+                caller
+                    .as_ref()
+                    .is_none_or(|caller| caller.allows(TokenScope::Read, Some(*id)))
+            })
+            .collect();
+
+    let mut builds = Vec::new();
+
+    for origin in origins {
+        if !visible.contains(&origin.project_id) {
+            continue;
+        }
+
+        builds.push(HydraBuild::load(&state, origin).await.map_err(db_error)?);
+    }
+
+    Ok(Json(builds))
+}
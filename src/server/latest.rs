@@ -0,0 +1,128 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    routing::get,
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tracing::error;
+
+use crate::{
+    models::{Job, JobPath},
+    state::State as AppState,
+};
+
+use super::{auth::request_can_view, wants_json};
+
+/// Stable urls for the newest successful build of an attribute, like Hydra's `latest` links.
+/// The parameter names have to match the ones of the leptos routes.
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            "/project/:proj-id/jobset/:jobset-id/latest/:attr",
+            get(latest),
+        )
+        .route(
+            "/project/:proj-id/jobset/:jobset-id/latest-finished/:attr",
+            get(latest_finished),
+        )
+}
+
+#[derive(Serialize)]
+struct LatestBuild {
+    id: i32,
+    project_id: i32,
+    jobset_id: i32,
+    evaluation_id: i32,
+    attribute_name: String,
+    derivation_path: String,
+    finished: Option<DateTime<Utc>>,
+    outputs: Vec<String>,
+    url: String,
+}
+
+/// The newest successful build of the attribute
+async fn latest(
+    State(state): State<Arc<AppState>>,
+    Path(params): Path<(String, String, String)>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    respond(&state, params, &headers, false).await
+}
+
+/// Like `latest`, but only from evaluations which finished building entirely
+async fn latest_finished(
+    State(state): State<Arc<AppState>>,
+    Path(params): Path<(String, String, String)>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    respond(&state, params, &headers, true).await
+}
+
+/// Redirects to the job page, or returns the build as json if the client asks for it
+async fn respond(
+    state: &AppState,
+    (project_id, jobset_id, attr): (String, String, String),
+    headers: &HeaderMap,
+    finished_evaluation: bool,
+) -> Result<Response, StatusCode> {
+    let project_id = project_id
+        .parse::<i32>()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let jobset_id = jobset_id
+        .parse::<i32>()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let visible = request_can_view(state, headers, project_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to check session: {}", e.to_string());
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    if !visible {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let job =
+        Job::get_latest_successful(&state.db, project_id, jobset_id, &attr, finished_evaluation)
+            .await
+            .map_err(|e| {
+                error!("Failed to find latest build of {}: {}", attr, e.to_string());
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?
+            .ok_or(StatusCode::NOT_FOUND)?;
+
+    let job_id = job.id.unwrap();
+
+    let url = format!(
+        "/project/{}/jobset/{}/job/{}",
+        project_id, jobset_id, job_id
+    );
+
+    if !wants_json(headers) {
+        return Ok(Redirect::temporary(&url).into_response());
+    }
+
+    let outputs = JobPath::get_outputs(&state.db, job_id).await.map_err(|e| {
+        error!("Failed to get outputs of job {}: {}", job_id, e.to_string());
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(LatestBuild {
+        id: job_id,
+        project_id,
+        jobset_id,
+        evaluation_id: job.evaluation_id,
+        attribute_name: job.attribute_name,
+        derivation_path: job.derivation_path,
+        finished: job.finished,
+        outputs: outputs.into_iter().map(|output| output.path).collect(),
+        url,
+    })
+    .into_response())
+}
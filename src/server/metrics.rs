@@ -0,0 +1,26 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use tracing::error;
+
+use crate::state::State as AppState;
+
+/// Prometheus metrics of this process
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new().route("/metrics", get(metrics))
+}
+
+async fn metrics(State(state): State<Arc<AppState>>) -> Result<impl IntoResponse, StatusCode> {
+    let metrics = state.metrics.render(&state.db).await.map_err(|e| {
+        error!("Failed to render metrics: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(([(CONTENT_TYPE, "text/plain; version=0.0.4")], metrics))
+}
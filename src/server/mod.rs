@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use axum::{
+    http::{header::ACCEPT, HeaderMap},
+    Router,
+};
+
+use crate::state::State;
+
+pub mod auth;
+pub mod config;
+
+mod api;
+mod binary_cache;
+mod build_products;
+mod channels;
+mod hydra;
+mod latest;
+mod metrics;
+mod oidc;
+mod webhooks;
+
+/// Plain http endpoints which live next to the leptos routes
+pub fn router(state: Arc<State>) -> Router {
+    Router::new()
+        .nest("/api/v1", api::router())
+        .nest("/cache", binary_cache::router())
+        .nest("/channel", channels::router())
+        .merge(hydra::router())
+        .merge(latest::router())
+        .merge(build_products::router())
+        .merge(metrics::router())
+        .merge(oidc::router())
+        .merge(webhooks::router())
+        .with_state(state)
+}
+
+/// Only the metrics, for processes without the web frontend
+pub fn metrics_router(state: Arc<State>) -> Router {
+    metrics::router().with_state(state)
+}
+
+/// Whether the client asked for json instead of a page
+fn wants_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .map(|accept| accept.contains("application/json"))
+        .unwrap_or(false)
+}
@@ -0,0 +1,571 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::{Query, State},
+    http::{
+        header::{COOKIE, SET_COOKIE},
+        HeaderMap, StatusCode,
+    },
+    response::{AppendHeaders, IntoResponse, Redirect, Response},
+    routing::get,
+    Router,
+};
+use openidconnect::{
+    core::{
+        CoreAuthDisplay, CoreAuthPrompt, CoreAuthenticationFlow, CoreErrorResponseType,
+        CoreGenderClaim, CoreJsonWebKey, CoreJweContentEncryptionAlgorithm,
+        CoreJwsSigningAlgorithm, CoreProviderMetadata, CoreRevocableToken,
+        CoreRevocationErrorResponse, CoreTokenIntrospectionResponse, CoreTokenType,
+    },
+    reqwest, AdditionalClaims, AuthorizationCode, Client, ClientId, ClientSecret, CsrfToken,
+    EmptyExtraTokenFields, EndpointMaybeSet, EndpointNotSet, EndpointSet, IdTokenFields, IssuerUrl,
+    Nonce, PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, Scope, StandardErrorResponse,
+    StandardTokenResponse, TokenResponse,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::{
+    hydracore::{DBError, DB},
+    models::{Project, ProjectMember, ProjectRole, User},
+    state::State as AppState,
+};
+
+use super::{auth::start_session, config::OidcConfig};
+
+/// Keeps the state of a login between the redirect to the provider and the callback
+const FLOW_COOKIE: &str = "ladon_oidc";
+
+/// Every claim of the id token, the groups claim is configurable
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct ExtraClaims(HashMap<String, serde_json::Value>);
+
+impl AdditionalClaims for ExtraClaims {}
+
+type OidcTokenResponse = StandardTokenResponse<
+    IdTokenFields<
+        ExtraClaims,
+        EmptyExtraTokenFields,
+        CoreGenderClaim,
+        CoreJweContentEncryptionAlgorithm,
+        CoreJwsSigningAlgorithm,
+    >,
+    CoreTokenType,
+>;
+
+type OidcClient = Client<
+    ExtraClaims,
+    CoreAuthDisplay,
+    CoreGenderClaim,
+    CoreJweContentEncryptionAlgorithm,
+    CoreJsonWebKey,
+    CoreAuthPrompt,
+    StandardErrorResponse<CoreErrorResponseType>,
+    OidcTokenResponse,
+    CoreTokenIntrospectionResponse,
+    CoreRevocableToken,
+    CoreRevocationErrorResponse,
+    EndpointSet,
+    EndpointNotSet,
+    EndpointNotSet,
+    EndpointNotSet,
+    EndpointMaybeSet,
+    EndpointMaybeSet,
+>;
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/oidc/login", get(login))
+        .route("/oidc/callback", get(callback))
+}
+
+/// Failed logins show the reason as plain text, the provider's page is gone by then
+#[derive(Debug)]
+struct OidcError {
+    status: StatusCode,
+    error: String,
+}
+
+impl OidcError {
+    fn new(status: StatusCode, error: &str) -> Self {
+        OidcError {
+            status,
+            error: error.to_string(),
+        }
+    }
+
+    fn provider(error: impl std::fmt::Display) -> Self {
+        error!("OpenID Connect provider failed: {}", error);
+        OidcError::new(
+            StatusCode::BAD_GATEWAY,
+            "Failed to talk to the login provider",
+        )
+    }
+}
+
+impl From<DBError> for OidcError {
+    fn from(e: DBError) -> Self {
+        error!("Database error in OpenID Connect login: {}", e);
+        OidcError::new(StatusCode::INTERNAL_SERVER_ERROR, "Database error")
+    }
+}
+
+impl IntoResponse for OidcError {
+    fn into_response(self) -> Response {
+        (self.status, self.error).into_response()
+    }
+}
+
+fn config(state: &AppState) -> Result<&OidcConfig, OidcError> {
+    state.oidc.as_ref().ok_or(OidcError::new(
+        StatusCode::NOT_FOUND,
+        "Single sign-on is not configured",
+    ))
+}
+
+fn http_client() -> Result<reqwest::Client, OidcError> {
+    // the provider's redirects are never followed, against SSRF
+    reqwest::ClientBuilder::new()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(OidcError::provider)
+}
+
+/// Discovers the provider's endpoints and keys, on every login so a restarted provider may rotate them
+async fn client(
+    state: &AppState,
+    config: &OidcConfig,
+    http_client: &reqwest::Client,
+) -> Result<OidcClient, OidcError> {
+    let issuer = IssuerUrl::new(config.issuer.clone()).map_err(OidcError::provider)?;
+
+    let metadata = CoreProviderMetadata::discover_async(issuer, http_client)
+        .await
+        .map_err(OidcError::provider)?;
+
+    let redirect_url = config.redirect_url.clone().unwrap_or(format!(
+        "{}/oidc/callback",
+        state.public_url.trim_end_matches('/')
+    ));
+
+    let client = OidcClient::from_provider_metadata(
+        metadata,
+        ClientId::new(config.client_id.clone()),
+        config.client_secret.clone().map(ClientSecret::new),
+    )
+    .set_redirect_uri(RedirectUrl::new(redirect_url).map_err(OidcError::provider)?);
+
+    Ok(client)
+}
+
+/// Sends the browser to the provider
+async fn login(State(state): State<Arc<AppState>>) -> Result<Response, OidcError> {
+    let config = config(&state)?;
+    let http_client = http_client()?;
+    let client = client(&state, config, &http_client).await?;
+
+    let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+
+    let mut request = client
+        .authorize_url(
+            CoreAuthenticationFlow::AuthorizationCode,
+            CsrfToken::new_random,
+            Nonce::new_random,
+        )
+        .set_pkce_challenge(pkce_challenge);
+
+    for scope in &config.scopes {
+        request = request.add_scope(Scope::new(scope.clone()));
+    }
+
+    let (url, csrf_token, nonce) = request.url();
+
+    // all three are url safe base64, `.` never shows up in them
+    let cookie = format!(
+        "{}={}.{}.{}; Path=/oidc; HttpOnly; SameSite=Lax; Max-Age=600",
+        FLOW_COOKIE,
+        csrf_token.secret(),
+        nonce.secret(),
+        pkce_verifier.secret()
+    );
+
+    Ok((
+        AppendHeaders([(SET_COOKIE, cookie)]),
+        Redirect::to(url.as_str()),
+    )
+        .into_response())
+}
+
+#[derive(Deserialize)]
+struct CallbackQuery {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+/// The csrf token, nonce and pkce verifier of the login the browser started
+fn flow_state(headers: &HeaderMap) -> Option<(String, String, String)> {
+    let value = headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|cookie| cookie.to_str().ok())
+        .flat_map(|cookie| cookie.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == FLOW_COOKIE)
+        .map(|(_, value)| value.to_string())?;
+
+    let mut parts = value.split('.');
+
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(csrf), Some(nonce), Some(verifier)) => {
+            Some((csrf.to_string(), nonce.to_string(), verifier.to_string()))
+        }
+        _ => None,
+    }
+}
+
+/// The provider sends the browser back with a code, which is exchanged for the id token
+async fn callback(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<CallbackQuery>,
+    headers: HeaderMap,
+) -> Result<Response, OidcError> {
+    let config = config(&state)?;
+
+    if let Some(error) = query.error {
+        warn!(
+            "OpenID Connect provider refused the login: {} {}",
+            error,
+            query.error_description.as_deref().unwrap_or("")
+        );
+        return Err(OidcError::new(
+            StatusCode::UNAUTHORIZED,
+            "The login provider refused the login",
+        ));
+    }
+
+    let (csrf, nonce, verifier) = flow_state(&headers).ok_or(OidcError::new(
+        StatusCode::BAD_REQUEST,
+        "The login took too long, try again",
+    ))?;
+
+    if query.state.as_deref() != Some(csrf.as_str()) {
+        return Err(OidcError::new(
+            StatusCode::BAD_REQUEST,
+            "The login does not belong to this browser",
+        ));
+    }
+
+    let code = query.code.ok_or(OidcError::new(
+        StatusCode::BAD_REQUEST,
+        "The login provider sent no code",
+    ))?;
+
+    let http_client = http_client()?;
+    let client = client(&state, config, &http_client).await?;
+
+    let token_response = client
+        .exchange_code(AuthorizationCode::new(code))
+        .map_err(OidcError::provider)?
+        .set_pkce_verifier(PkceCodeVerifier::new(verifier))
+        .request_async(&http_client)
+        .await
+        .map_err(OidcError::provider)?;
+
+    let id_token = token_response
+        .id_token()
+        .ok_or_else(|| OidcError::provider("No id token in the token response"))?;
+
+    let claims = id_token
+        .claims(&client.id_token_verifier(), &Nonce::new(nonce))
+        .map_err(|e| {
+            warn!("Invalid id token: {}", e);
+            OidcError::new(StatusCode::UNAUTHORIZED, "The id token is invalid")
+        })?;
+
+    let subject = claims.subject().as_str();
+
+    let name = claims
+        .preferred_username()
+        .map(|name| name.as_str().to_string())
+        .or(claims.email().map(|email| email.as_str().to_string()))
+        .unwrap_or(subject.to_string());
+
+    let groups: Vec<String> = match claims.additional_claims().0.get(&config.groups_claim) {
+        Some(serde_json::Value::Array(groups)) => groups
+            .iter()
+            .filter_map(|group| group.as_str().map(|group| group.to_string()))
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let user = provision_user(&state.db, config, subject, &name, &groups).await?;
+
+    let session_cookie = start_session(&state, &user).await?;
+
+    let removed_flow_cookie = format!(
+        "{}=; Path=/oidc; HttpOnly; SameSite=Lax; Max-Age=0",
+        FLOW_COOKIE
+    );
+
+    info!("{} logged in through {}", user.name, config.name);
+
+    Ok((
+        AppendHeaders([
+            (SET_COOKIE, session_cookie),
+            (SET_COOKIE, removed_flow_cookie),
+        ]),
+        Redirect::to("/"),
+    )
+        .into_response())
+}
+
+/// Creates the account on the first login, and sets the admin flag and project roles from the groups on every login
+async fn provision_user(
+    db: &DB,
+    config: &OidcConfig,
+    subject: &str,
+    name: &str,
+    groups: &[String],
+) -> Result<User, OidcError> {
+    let admin = config
+        .admin_groups
+        .iter()
+        .any(|group| groups.contains(group));
+
+    let mut user = match User::get_by_oidc_subject(db, subject).await? {
+        Some(user) => user,
+        None => {
+            // local accounts are never taken over, their name may belong to somebody else
+            if User::get_by_name(db, name).await?.is_some() {
+                warn!(
+                    "OpenID Connect user {} has the name of an existing account: {}",
+                    subject, name
+                );
+                return Err(OidcError::new(
+                    StatusCode::CONFLICT,
+                    "There already is an account with your name",
+                ));
+            }
+
+            let mut user = User::new(name.to_string(), admin);
+
+            user.add_oidc_to_db(db, subject).await?;
+
+            info!(
+                "Created account {} for OpenID Connect user {}",
+                name, subject
+            );
+
+            user
+        }
+    };
+
+    if user.admin != admin {
+        user.set_admin(db, admin).await?;
+    }
+
+    sync_roles(db, config, &user, groups).await?;
+
+    Ok(user)
+}
+
+/// Projects which show up in the role mappings are managed by the groups,
+/// the user gets the highest mapped role or is removed from the project
+async fn sync_roles(
+    db: &DB,
+    config: &OidcConfig,
+    user: &User,
+    groups: &[String],
+) -> Result<(), OidcError> {
+    let mut roles: HashMap<&str, Option<ProjectRole>> = HashMap::new();
+
+    for mapping in &config.roles {
+        let role = roles.entry(mapping.project.as_str()).or_default();
+
+        if groups.contains(&mapping.group) {
+            *role = (*role).max(Some(mapping.role));
+        }
+    }
+
+    for (project_name, role) in roles {
+        let project = match Project::get_by_name(db, project_name).await? {
+            Some(project) => project,
+            None => {
+                warn!(
+                    "OpenID Connect role mapping names an unknown project: {}",
+                    project_name
+                );
+                continue;
+            }
+        };
+
+        match role {
+            Some(role) => {
+                ProjectMember::set(db, project.id.unwrap(), user.id.unwrap(), role).await?
+            }
+            None => {
+                ProjectMember::delete(db, project.id.unwrap(), user.id.unwrap()).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use axum::http::StatusCode;
+
+    use crate::{
+        hydracore::DB,
+        models::{Project, ProjectMember, ProjectRole, User},
+        server::config::OidcConfig,
+    };
+
+    use super::{provision_user, sync_roles};
+
+    const CONFIG: &str = r#"
+        issuer = "https://idp.example.org"
+        client_id = "ladon"
+        admin_groups = ["admins"]
+        roles = [
+            { group = "developers", project = "a", role = "Maintainer" },
+            { group = "leads", project = "a", role = "Owner" },
+            { group = "developers", project = "b", role = "Viewer" },
+            { group = "developers", project = "missing", role = "Viewer" },
+        ]
+    "#;
+
+    /// A fresh sqlite database with the projects a, b and c
+    async fn database(test: &str) -> (DB, PathBuf) {
+        let path =
+            std::env::temp_dir().join(format!("ladon-oidc-{}-{}.sqlite", test, std::process::id()));
+
+        let db = DB::new(&format!("sqlite://{}", path.display()))
+            .await
+            .unwrap();
+
+        for name in ["a", "b", "c"] {
+            let mut project = Project {
+                id: None,
+                name: name.to_string(),
+                description: String::new(),
+                binary_cache: false,
+                private: false,
+            };
+
+            project.add_to_db(&db).await.unwrap();
+        }
+
+        (db, path)
+    }
+
+    fn remove(path: PathBuf) {
+        for suffix in ["", "-wal", "-shm"] {
+            _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+
+    fn config() -> OidcConfig {
+        toml::from_str(CONFIG).unwrap()
+    }
+
+    fn groups(groups: &[&str]) -> Vec<String> {
+        groups.iter().map(|group| group.to_string()).collect()
+    }
+
+    async fn role(db: &DB, project: &str, user: &User) -> Option<ProjectRole> {
+        let project = Project::get_by_name(db, project).await.unwrap().unwrap();
+
+        ProjectMember::get_role(db, project.id.unwrap(), user.id.unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn provisions_users() {
+        let (db, path) = database("provision").await;
+        let config = config();
+
+        let user = provision_user(&db, &config, "sub-1", "alice", &groups(&["admins"]))
+            .await
+            .unwrap();
+        assert!(user.admin);
+
+        // the subject finds the account again, the groups decide the admin flag every time
+        let again = provision_user(&db, &config, "sub-1", "alice", &groups(&[]))
+            .await
+            .unwrap();
+        assert_eq!(again.id, user.id);
+        assert!(!again.admin);
+        assert!(
+            !User::get_single(&db, user.id.unwrap())
+                .await
+                .unwrap()
+                .unwrap()
+                .admin
+        );
+
+        remove(path);
+    }
+
+    #[tokio::test]
+    async fn keeps_local_accounts() {
+        let (db, path) = database("local").await;
+
+        let mut local = User::new("bob".to_string(), false);
+        local.add_to_db(&db, "hash").await.unwrap();
+
+        let result = provision_user(&db, &config(), "sub-2", "bob", &groups(&["admins"])).await;
+
+        assert_eq!(result.err().unwrap().status, StatusCode::CONFLICT);
+        assert!(User::get_by_oidc_subject(&db, "sub-2")
+            .await
+            .unwrap()
+            .is_none());
+
+        remove(path);
+    }
+
+    #[tokio::test]
+    async fn maps_groups_to_roles() {
+        let (db, path) = database("roles").await;
+        let config = config();
+
+        let user = provision_user(&db, &config, "sub-3", "carol", &groups(&[]))
+            .await
+            .unwrap();
+
+        // projects without a mapping are left to their owners
+        let c = Project::get_by_name(&db, "c").await.unwrap().unwrap();
+        ProjectMember::set(&db, c.id.unwrap(), user.id.unwrap(), ProjectRole::Owner)
+            .await
+            .unwrap();
+
+        // the highest role of the user's groups wins
+        sync_roles(&db, &config, &user, &groups(&["developers", "leads"]))
+            .await
+            .unwrap();
+        assert_eq!(role(&db, "a", &user).await, Some(ProjectRole::Owner));
+        assert_eq!(role(&db, "b", &user).await, Some(ProjectRole::Viewer));
+
+        sync_roles(&db, &config, &user, &groups(&["developers"]))
+            .await
+            .unwrap();
+        assert_eq!(role(&db, "a", &user).await, Some(ProjectRole::Maintainer));
+
+        // leaving the groups removes the user from the mapped projects
+        sync_roles(&db, &config, &user, &groups(&["others"]))
+            .await
+            .unwrap();
+        assert_eq!(role(&db, "a", &user).await, None);
+        assert_eq!(role(&db, "b", &user).await, None);
+        assert_eq!(role(&db, "c", &user).await, Some(ProjectRole::Owner));
+
+        remove(path);
+    }
+}
@@ -0,0 +1,462 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Router,
+};
+use hmac::{Hmac, Mac};
+use serde::{de::DeserializeOwned, Deserialize};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+use tracing::{error, info, warn};
+
+use crate::{
+    hydracore::{split_repository_url, GitSource},
+    models::{DeliveryOutcome, Forge, Jobset, QueueRequest, QueueRequestKind, WebhookDelivery},
+    state::State as AppState,
+};
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/webhooks/github", post(github))
+        .route("/webhooks/gitea", post(gitea))
+        .route("/webhooks/gitlab", post(gitlab))
+}
+
+/// What became of a delivery, it is recorded and sent back to the forge
+struct Reply {
+    status: StatusCode,
+    outcome: DeliveryOutcome,
+    message: String,
+}
+
+impl Reply {
+    fn triggered(message: String) -> Self {
+        Reply {
+            status: StatusCode::ACCEPTED,
+            outcome: DeliveryOutcome::Triggered,
+            message,
+        }
+    }
+
+    fn ignored(message: &str) -> Self {
+        Reply {
+            status: StatusCode::OK,
+            outcome: DeliveryOutcome::Ignored,
+            message: message.to_string(),
+        }
+    }
+
+    fn rejected(status: StatusCode, message: &str) -> Self {
+        Reply {
+            status,
+            outcome: DeliveryOutcome::Rejected,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// GitHub, Gitea and Forgejo describe the repository the same way
+#[derive(Deserialize)]
+struct RepositoryPush {
+    #[serde(rename = "ref")]
+    reference: String,
+    after: String,
+    repository: Repository,
+}
+
+#[derive(Deserialize)]
+struct Repository {
+    html_url: Option<String>,
+    clone_url: Option<String>,
+    ssh_url: Option<String>,
+    default_branch: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GitLabPush {
+    #[serde(rename = "ref")]
+    reference: String,
+    after: String,
+    project: GitLabProject,
+}
+
+#[derive(Deserialize)]
+struct GitLabProject {
+    web_url: Option<String>,
+    git_http_url: Option<String>,
+    git_ssh_url: Option<String>,
+    default_branch: Option<String>,
+}
+
+/// The parts of a push every forge sends
+struct Push {
+    reference: String,
+    revision: String,
+    /// The repository's urls, jobsets may use any of them
+    urls: Vec<String>,
+    default_branch: String,
+}
+
+impl From<RepositoryPush> for Push {
+    fn from(push: RepositoryPush) -> Self {
+        let repository = push.repository;
+
+        Push {
+            reference: push.reference,
+            revision: push.after,
+            urls: [
+                repository.html_url,
+                repository.clone_url,
+                repository.ssh_url,
+            ]
+            .into_iter()
+            .flatten()
+            .collect(),
+            default_branch: repository.default_branch.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<GitLabPush> for Push {
+    fn from(push: GitLabPush) -> Self {
+        let project = push.project;
+
+        Push {
+            reference: push.reference,
+            revision: push.after,
+            urls: [project.web_url, project.git_http_url, project.git_ssh_url]
+                .into_iter()
+                .flatten()
+                .collect(),
+            default_branch: project.default_branch.unwrap_or_default(),
+        }
+    }
+}
+
+fn header(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+}
+
+/// Checks the hex encoded hmac of the body, as GitHub, Gitea and Forgejo send it
+fn verify_signature(
+    secret: Option<&str>,
+    signature: Option<&str>,
+    body: &[u8],
+) -> Result<(), Reply> {
+    let secret = secret.ok_or_else(|| {
+        Reply::rejected(
+            StatusCode::NOT_FOUND,
+            "No secret is configured for this forge",
+        )
+    })?;
+
+    let signature = signature
+        .ok_or_else(|| Reply::rejected(StatusCode::UNAUTHORIZED, "The signature is missing"))?;
+
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac takes keys of any length");
+    mac.update(body);
+
+    let expected = format!("{:x}", mac.finalize().into_bytes());
+
+    match bool::from(
+        expected
+            .as_bytes()
+            .ct_eq(signature.to_lowercase().as_bytes()),
+    ) {
+        true => Ok(()),
+        false => Err(Reply::rejected(
+            StatusCode::UNAUTHORIZED,
+            "The signature is wrong",
+        )),
+    }
+}
+
+/// GitLab sends the secret itself instead of signing the body
+fn verify_token(secret: Option<&str>, token: Option<&str>) -> Result<(), Reply> {
+    let secret = secret.ok_or_else(|| {
+        Reply::rejected(
+            StatusCode::NOT_FOUND,
+            "No secret token is configured for GitLab",
+        )
+    })?;
+
+    let token =
+        token.ok_or_else(|| Reply::rejected(StatusCode::UNAUTHORIZED, "The token is missing"))?;
+
+    match bool::from(secret.as_bytes().ct_eq(token.as_bytes())) {
+        true => Ok(()),
+        false => Err(Reply::rejected(
+            StatusCode::UNAUTHORIZED,
+            "The token is wrong",
+        )),
+    }
+}
+
+fn parse<T: DeserializeOwned>(body: &[u8]) -> Result<T, Reply> {
+    serde_json::from_slice(body).map_err(|e| {
+        warn!("Failed to parse webhook payload: {}", e);
+        Reply::rejected(StatusCode::BAD_REQUEST, "Failed to parse the payload")
+    })
+}
+
+async fn github(State(state): State<Arc<AppState>>, headers: HeaderMap, body: Bytes) -> Response {
+    let mut delivery = WebhookDelivery::new(Forge::GitHub, header(&headers, "x-github-event"));
+
+    let signature = header(&headers, "x-hub-signature-256");
+
+    let reply = match verify_signature(
+        state.webhooks.github_secret.as_deref(),
+        signature
+            .as_deref()
+            .and_then(|signature| signature.strip_prefix("sha256=")),
+        &body,
+    ) {
+        Err(reply) => return refuse(&delivery, reply),
+        Ok(()) => match delivery.event.as_deref() {
+            Some("push") => match parse::<RepositoryPush>(&body) {
+                Ok(push) => trigger(&state, &mut delivery, push.into()).await,
+                Err(reply) => reply,
+            },
+            Some("ping") => Reply::ignored("Pong"),
+            _ => Reply::ignored("Only pushes trigger evaluations"),
+        },
+    };
+
+    finish(&state, delivery, reply).await
+}
+
+/// Forgejo sends its own headers, besides or instead of the Gitea ones
+async fn gitea(State(state): State<Arc<AppState>>, headers: HeaderMap, body: Bytes) -> Response {
+    let event = header(&headers, "x-gitea-event").or(header(&headers, "x-forgejo-event"));
+
+    let mut delivery = WebhookDelivery::new(Forge::Gitea, event);
+
+    let signature =
+        header(&headers, "x-gitea-signature").or(header(&headers, "x-forgejo-signature"));
+
+    let reply = match verify_signature(
+        state.webhooks.gitea_secret.as_deref(),
+        signature.as_deref(),
+        &body,
+    ) {
+        Err(reply) => return refuse(&delivery, reply),
+        Ok(()) => match delivery.event.as_deref() {
+            Some("push") => match parse::<RepositoryPush>(&body) {
+                Ok(push) => trigger(&state, &mut delivery, push.into()).await,
+                Err(reply) => reply,
+            },
+            _ => Reply::ignored("Only pushes trigger evaluations"),
+        },
+    };
+
+    finish(&state, delivery, reply).await
+}
+
+async fn gitlab(State(state): State<Arc<AppState>>, headers: HeaderMap, body: Bytes) -> Response {
+    let mut delivery = WebhookDelivery::new(Forge::GitLab, header(&headers, "x-gitlab-event"));
+
+    let token = header(&headers, "x-gitlab-token");
+
+    let reply = match verify_token(state.webhooks.gitlab_token.as_deref(), token.as_deref()) {
+        Err(reply) => return refuse(&delivery, reply),
+        Ok(()) => match delivery.event.as_deref() {
+            Some("Push Hook") => match parse::<GitLabPush>(&body) {
+                Ok(push) => trigger(&state, &mut delivery, push.into()).await,
+                Err(reply) => reply,
+            },
+            _ => Reply::ignored("Only pushes trigger evaluations"),
+        },
+    };
+
+    finish(&state, delivery, reply).await
+}
+
+/// Queues an evaluation at the pushed revision for every jobset which follows the branch
+async fn trigger(state: &AppState, delivery: &mut WebhookDelivery, push: Push) -> Reply {
+    let repositories: Vec<(String, String)> = push
+        .urls
+        .iter()
+        .filter_map(|url| split_repository_url(url))
+        .collect();
+
+    delivery.repository = repositories
+        .first()
+        .map(|(host, repository)| format!("{}/{}", host, repository));
+    delivery.revision = Some(push.revision.clone());
+
+    let branch = match push.reference.strip_prefix("refs/heads/") {
+        Some(branch) => branch,
+        None => return Reply::ignored("Only branch pushes trigger evaluations"),
+    };
+
+    delivery.branch = Some(branch.to_string());
+
+    // the revision ends up in the flake reference, so it has to be a plain commit hash
+    if !matches!(push.revision.len(), 40 | 64)
+        || !push.revision.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        return Reply::rejected(StatusCode::BAD_REQUEST, "The revision is no commit hash");
+    }
+
+    if push.revision.chars().all(|c| c == '0') {
+        return Reply::ignored("The branch was deleted");
+    }
+
+    if repositories.is_empty() {
+        return Reply::rejected(StatusCode::BAD_REQUEST, "The payload has no repository url");
+    }
+
+    let jobsets = match Jobset::get_every(&state.db).await {
+        Ok(jobsets) => jobsets,
+        Err(e) => {
+            error!("Failed to get jobsets for webhook: {}", e);
+            return Reply::rejected(StatusCode::INTERNAL_SERVER_ERROR, "Database error");
+        }
+    };
+
+    let mut triggered = Vec::new();
+
+    for jobset in jobsets {
+        let source = match GitSource::from_flake(&jobset.flake) {
+            Some(source) => source,
+            None => continue,
+        };
+
+        if !repositories.iter().any(|(host, repository)| {
+            source.matches(host, repository, branch, &push.default_branch)
+        }) {
+            continue;
+        }
+
+        // the queue runner picks this up, it may live in another process
+        let mut request = QueueRequest::new(QueueRequestKind::TriggerJobset, jobset.id);
+        request.revision = Some(push.revision.clone());
+
+        if let Err(e) = request.add_to_db(&state.db).await {
+            error!("Failed to queue jobset {}: {}", jobset.name, e);
+            return Reply::rejected(StatusCode::INTERNAL_SERVER_ERROR, "Database error");
+        }
+
+        triggered.push(jobset.name);
+    }
+
+    if triggered.is_empty() {
+        return Reply::ignored("No jobset follows the branch");
+    }
+
+    Reply::triggered(format!("Queued {}", triggered.join(", ")))
+}
+
+/// Anybody may send deliveries which fail verification, so they are only logged and not recorded
+fn refuse(delivery: &WebhookDelivery, reply: Reply) -> Response {
+    warn!("Refused {} webhook: {}", delivery.forge, reply.message);
+
+    (reply.status, reply.message).into_response()
+}
+
+async fn finish(state: &AppState, mut delivery: WebhookDelivery, reply: Reply) -> Response {
+    match reply.outcome {
+        DeliveryOutcome::Rejected => {
+            warn!("Rejected {} webhook: {}", delivery.forge, reply.message)
+        }
+        _ => info!(
+            "{} webhook for {} {}: {}",
+            delivery.forge,
+            delivery.repository.as_deref().unwrap_or("-"),
+            delivery.branch.as_deref().unwrap_or("-"),
+            reply.message
+        ),
+    }
+
+    delivery.set_outcome(reply.outcome, reply.message.clone());
+
+    if let Err(e) = delivery.add_to_db(&state.db).await {
+        error!("Failed to record webhook delivery: {}", e);
+    }
+
+    (reply.status, reply.message).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    use super::{verify_signature, verify_token};
+
+    const BODY: &[u8] = br#"{"ref":"refs/heads/main"}"#;
+
+    fn sign(secret: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(BODY);
+
+        format!("{:x}", mac.finalize().into_bytes())
+    }
+
+    fn status<T>(result: Result<T, super::Reply>) -> Option<StatusCode> {
+        result.err().map(|reply| reply.status)
+    }
+
+    #[test]
+    fn valid_signature() {
+        assert!(verify_signature(Some("secret"), Some(&sign("secret")), BODY).is_ok());
+        assert!(
+            verify_signature(Some("secret"), Some(&sign("secret").to_uppercase()), BODY).is_ok()
+        );
+    }
+
+    #[test]
+    fn invalid_signature() {
+        assert_eq!(
+            status(verify_signature(Some("secret"), Some(&sign("other")), BODY)),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            status(verify_signature(
+                Some("secret"),
+                Some(&sign("secret")),
+                b"{}"
+            )),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+    }
+
+    #[test]
+    fn missing_signature() {
+        assert_eq!(
+            status(verify_signature(Some("secret"), None, BODY)),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            status(verify_signature(None, Some(&sign("secret")), BODY)),
+            Some(StatusCode::NOT_FOUND)
+        );
+    }
+
+    #[test]
+    fn tokens() {
+        assert!(verify_token(Some("token"), Some("token")).is_ok());
+        assert_eq!(
+            status(verify_token(Some("token"), Some("tokem"))),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            status(verify_token(Some("token"), None)),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            status(verify_token(None, Some("token"))),
+            Some(StatusCode::NOT_FOUND)
+        );
+    }
+}
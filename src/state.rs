@@ -1,10 +1,21 @@
 #[cfg(feature = "ssr")]
-use tokio::sync::Mutex;
-
-#[cfg(feature = "ssr")]
-use crate::hydracore::Coordinator;
+use crate::{
+    hydracore::{DataDir, Metrics, DB},
+    server::config::{OidcConfig, WebhookConfig},
+};
 
+/// Shared state of the web frontend.
+/// The coordinator is not part of it, it may run in a separate queue runner process.
 #[cfg(feature = "ssr")]
 pub struct State {
-    pub coordinator: Mutex<Coordinator>,
+    pub db: DB,
+    pub data_dir: DataDir,
+    pub metrics: Metrics,
+    /// Anonymous visitors may not even look at anything
+    pub require_login: bool,
+    /// Url ladon is reachable under, for links which leave the site
+    pub public_url: String,
+    /// Login through an OpenID Connect provider, if it is configured
+    pub oidc: Option<OidcConfig>,
+    pub webhooks: WebhookConfig,
 }